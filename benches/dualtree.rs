@@ -0,0 +1,41 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use gaussian_kde::GaussianKDE;
+use ndarray::prelude::*;
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+use rand_pcg::Pcg64Mcg;
+
+/// Evaluate a 2D KDE of `n` points on a grid of about `n` points with the dual-tree algorithm and the exact batch
+/// evaluation, which scales quadratically in `n`.
+fn dualtree_scaling_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("2D dual-tree eval");
+    group.sample_size(10);
+    for n in [5_000, 10_000, 20_000, 40_000] {
+        let mut rng = Pcg64Mcg::seed_from_u64(79);
+        let data = Array2::<f64>::from_shape_simple_fn((n, 2), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let n_grid = (n as f64).sqrt() as usize;
+        let grid = Array1::linspace(-4., 4., n_grid);
+        let x = Array2::from_shape_fn((n_grid * n_grid, 2), |(i, k)| {
+            if k == 0 {
+                grid[i / n_grid]
+            } else {
+                grid[i % n_grid]
+            }
+        });
+        // Build the cached tree of the dataset beforehand
+        kde.eval_approx(x.row(0), 1e-6).unwrap();
+        group.bench_with_input(BenchmarkId::new("rtol 1e-6", n), &x, |b, x| {
+            b.iter(|| kde.eval_batch_dualtree(x.view(), 1e-6))
+        });
+        if n <= 20_000 {
+            group.bench_with_input(BenchmarkId::new("exact", n), &x, |b, x| {
+                b.iter(|| kde.eval_batch(x.view()))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, dualtree_scaling_bench);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use gaussian_kde::GaussianKDE;
+use ndarray::prelude::*;
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+use rand_pcg::Pcg64Mcg;
+
+/// Kernel sum at `x` with a freshly allocated whitened distance $z = L^{-1}(x_i - x)$ for each data point, as
+/// evaluated before the single-point evaluation avoided the per-kernel allocations.
+fn allocating_kernel_sum(kde: &GaussianKDE<f64>, x: ArrayView1<f64>) -> f64 {
+    kde.data()
+        .rows()
+        .into_iter()
+        .map(|xi| {
+            let z = kde.inv_cholesky().dot(&(&xi - &x));
+            (-0.5 * z.dot(&z)).exp()
+        })
+        .sum()
+}
+
+/// Evaluate KDEs of `10^5` points in two and five dimensions at a single point, with the cached whitened dataset,
+/// with the whitened distances computed from the triangular Cholesky factor and with an allocation per kernel.
+fn single_eval_bench(c: &mut Criterion) {
+    let n = 100_000;
+    let mut rng = Pcg64Mcg::seed_from_u64(85);
+    for dim in [2, 5] {
+        let mut group = c.benchmark_group(format!("{dim}D single eval {n} points"));
+        let data = Array2::<f64>::from_shape_simple_fn((n, dim), || rng.sample(StandardNormal));
+        let x = Array1::<f64>::from_shape_simple_fn(dim, || rng.sample(StandardNormal));
+        let mut kde = GaussianKDE::new(data, None).unwrap();
+        group.bench_with_input(BenchmarkId::new("cached", dim), &x, |b, x| {
+            b.iter(|| kde.eval(x.view()))
+        });
+        kde.set_whitened_data_cache(false);
+        group.bench_with_input(BenchmarkId::new("triangular", dim), &x, |b, x| {
+            b.iter(|| kde.eval(x.view()))
+        });
+        group.bench_with_input(BenchmarkId::new("allocating", dim), &x, |b, x| {
+            b.iter(|| allocating_kernel_sum(&kde, x.view()))
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, single_eval_bench);
+criterion_main!(benches);
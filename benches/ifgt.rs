@@ -0,0 +1,29 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use gaussian_kde::GaussianKDE;
+use ndarray::prelude::*;
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+use rand_pcg::Pcg64Mcg;
+
+/// Evaluate a 5D KDE of `10^5` points at as many points with the IFGT and the exact batch evaluation.
+fn ifgt_5d_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("5D IFGT eval");
+    group.sample_size(10);
+    let n = 100_000;
+    let mut rng = Pcg64Mcg::seed_from_u64(82);
+    let data = Array2::<f64>::from_shape_simple_fn((n, 5), || rng.sample(StandardNormal));
+    let x = Array2::<f64>::from_shape_simple_fn((n, 5), || rng.sample(StandardNormal));
+    let kde = GaussianKDE::new(data, None).unwrap();
+    for epsilon in [1e-3, 1e-6] {
+        group.bench_with_input(BenchmarkId::new("epsilon", epsilon), &x, |b, x| {
+            b.iter(|| kde.eval_batch_ifgt(x.view(), epsilon).unwrap())
+        });
+    }
+    group.bench_with_input(BenchmarkId::new("exact", n), &x, |b, x| {
+        b.iter(|| kde.eval_batch(x.view()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, ifgt_5d_bench);
+criterion_main!(benches);
@@ -26,6 +26,52 @@ fn tth_eval_bench(c: &mut Criterion) {
     });
 }
 
+fn tth_grid_bench(c: &mut Criterion) {
+    let pwd = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let npy: Array2<f64> = read_npy(pwd.join("tth.npy")).unwrap();
+    let weights: Array1<f64> = npy.slice(s![.., -1]).to_owned();
+    let data: Array2<f64> = npy.slice(s![.., ..-1]).to_owned();
+    let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+    let kde_margin = kde.marginalize_to(&[2, 3]).unwrap();
+    let n_grid = 200;
+    let x = Array1::linspace(0., 1., n_grid);
+    let grid = Array3::from_shape_fn(
+        (n_grid, n_grid, 2),
+        |(i, j, k)| if k == 0 { x[i] } else { x[j] },
+    )
+    .into_shape_with_order((n_grid * n_grid, 2))
+    .unwrap();
+
+    c.bench_function("2D grid eval bench tth 10k points", |b| {
+        b.iter(|| kde_margin.eval_batch(grid.view()))
+    });
+    c.bench_function("2D FFT grid eval bench tth 10k points", |b| {
+        b.iter(|| {
+            kde_margin
+                .eval_grid_fft(&[(0., 1.), (0., 1.)], &[n_grid, n_grid])
+                .unwrap()
+        })
+    });
+}
+
+fn tth_single_eval_bench(c: &mut Criterion) {
+    let pwd = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let npy: Array2<f64> = read_npy(pwd.join("tth.npy")).unwrap();
+    let weights: Array1<f64> = npy.slice(s![.., -1]).to_owned();
+    let data: Array2<f64> = npy.slice(s![.., ..-1]).to_owned();
+    let x = data.slice(s![..100, ..]).to_owned();
+    let mut kde = GaussianKDE::new(data, Some(weights)).unwrap();
+
+    c.bench_function("single-point eval bench tth 10k points", |b| {
+        b.iter(|| x.rows().into_iter().map(|xi| kde.eval(xi)).sum::<f64>())
+    });
+    kde.set_whitened_data_cache(false);
+    c.bench_function(
+        "single-point eval bench tth 10k points without cache",
+        |b| b.iter(|| x.rows().into_iter().map(|xi| kde.eval(xi)).sum::<f64>()),
+    );
+}
+
 fn tth_sample_bench(c: &mut Criterion) {
     let pwd = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples");
     let npy: Array2<f64> = read_npy(pwd.join("tth.npy")).unwrap();
@@ -38,5 +84,24 @@ fn tth_sample_bench(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, tth_eval_bench, tth_sample_bench);
+fn tth_marginalize_bench(c: &mut Criterion) {
+    let pwd = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let npy: Array2<f64> = read_npy(pwd.join("tth.npy")).unwrap();
+    let weights: Array1<f64> = npy.slice(s![.., -1]).to_owned();
+    let data: Array2<f64> = npy.slice(s![.., ..-1]).to_owned();
+    let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+
+    c.bench_function("2D marginalize bench tth 10k points", |b| {
+        b.iter(|| kde.marginalize_to(&[2, 3]).unwrap())
+    });
+}
+
+criterion_group!(
+    benches,
+    tth_eval_bench,
+    tth_grid_bench,
+    tth_single_eval_bench,
+    tth_sample_bench,
+    tth_marginalize_bench
+);
 criterion_main!(benches);
@@ -0,0 +1,17 @@
+//! A Python extension module exposing [`PyGaussianKDE`] as `gaussian_kde.gaussian_kde`, a drop-in for
+//! `scipy.stats.gaussian_kde`.
+//!
+//! The module is built as shared library with
+//! ```sh
+//! cargo build --release --example python_module --features python,pyo3/extension-module
+//! ```
+//! and copied to `gaussian_kde.so` (`gaussian_kde.pyd` on Windows) in the Python path, e.g. in `examples`, where the
+//! tests in `test_python_module.py` compare it to scipy with `pytest examples/test_python_module.py`.
+
+use ::gaussian_kde::PyGaussianKDE;
+use pyo3::prelude::*;
+
+#[pymodule]
+fn gaussian_kde(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGaussianKDE>()
+}
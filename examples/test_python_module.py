@@ -0,0 +1,64 @@
+"""Tests of the Python bindings against `scipy.stats.gaussian_kde`, see `python_module.rs`."""
+
+import numpy as np
+import pytest
+import scipy.stats
+
+import gaussian_kde
+
+
+@pytest.fixture
+def dataset():
+    rng = np.random.default_rng(106)
+    return rng.normal(size=(3, 500)), rng.uniform(0.5, 1.5, size=500)
+
+
+@pytest.mark.parametrize("bw_method", [None, "scott", "silverman", 0.3])
+def test_evaluate(dataset, bw_method):
+    data, weights = dataset
+    points = np.random.default_rng(7).normal(size=(3, 40))
+    kde = gaussian_kde.gaussian_kde(data, bw_method=bw_method, weights=weights)
+    expected = scipy.stats.gaussian_kde(data, bw_method=bw_method, weights=weights)
+    assert kde.factor == pytest.approx(expected.factor, rel=1e-12)
+    assert kde.neff == pytest.approx(expected.neff, rel=1e-12)
+    np.testing.assert_allclose(kde.covariance, expected.covariance, rtol=1e-10)
+    np.testing.assert_allclose(kde.evaluate(points), expected.evaluate(points), rtol=1e-10)
+    np.testing.assert_allclose(kde(points[:, 0]), expected(points[:, 0]), rtol=1e-10)
+    np.testing.assert_allclose(kde.logpdf(points), expected.logpdf(points), rtol=1e-10)
+
+
+def test_marginalize_and_integrate(dataset):
+    data, weights = dataset
+    kde = gaussian_kde.gaussian_kde(data, weights=weights)
+    expected = scipy.stats.gaussian_kde(data, weights=weights)
+    marginal = kde.marginalize([0, -1])
+    np.testing.assert_allclose(marginal.dataset, expected.marginalize([0, -1]).dataset)
+    np.testing.assert_allclose(
+        marginal.evaluate(data[[0, 2], :10]),
+        expected.marginalize([0, -1]).evaluate(data[[0, 2], :10]),
+        rtol=1e-10,
+    )
+    one_d = kde.marginalize(1)
+    assert one_d.integrate_box_1d(-0.5, 1.0) == pytest.approx(
+        expected.marginalize(1).integrate_box_1d(-0.5, 1.0), rel=1e-10
+    )
+
+
+def test_resample(dataset):
+    data, weights = dataset
+    kde = gaussian_kde.gaussian_kde(data, weights=weights)
+    assert kde.resample().shape == (3, 500)
+    np.testing.assert_array_equal(kde.resample(100, seed=1), kde.resample(100, seed=1))
+
+
+def test_errors(dataset):
+    data, _ = dataset
+    kde = gaussian_kde.gaussian_kde(data)
+    with pytest.raises(ValueError):
+        kde.evaluate(np.zeros((2, 5)))
+    with pytest.raises(IndexError):
+        kde.marginalize([3])
+    with pytest.raises(np.linalg.LinAlgError):
+        gaussian_kde.gaussian_kde(np.ones((2, 10)))
+    with pytest.raises(TypeError):
+        gaussian_kde.gaussian_kde(data, bw_method=lambda kde: 0.5)
@@ -0,0 +1,55 @@
+//! A `no_std` crate using `gaussian_kde` without its default features, i.e. without `std`. Since features are unified
+//! across the packages of a build, this is only checked when the crate is built on its own, e.g. with
+//! ```sh
+//! cargo test -p gaussian_kde_no_std
+//! ```
+#![no_std]
+#![allow(clippy::needless_return)]
+
+extern crate alloc;
+
+use gaussian_kde::{GaussianKDE, KDEError};
+use ndarray::prelude::*;
+
+/// Evaluate a KDE shipped as its dataset and bandwidth matrix, e.g. obtained from
+/// `GaussianKDE::scaled_covariance` of a KDE fitted elsewhere, at the rows of `x`.
+pub fn eval_prefitted(
+    data: Array2<f64>,
+    bandwidth_matrix: Array2<f64>,
+    x: ArrayView2<f64>,
+) -> Result<Array1<f64>, KDEError> {
+    let kde = GaussianKDE::with_bandwidth_matrix(data, None, bandwidth_matrix)?;
+    return Ok(kde.eval_batch(x));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{format, vec};
+
+    #[test]
+    fn no_std_test() {
+        let data = array![
+            [0.15, 0.4],
+            [0.2, 0.3],
+            [0.21, 0.29],
+            [0.31, 0.74],
+            [0.72, 0.9],
+            [0.74, 0.84],
+            [0.6, 0.3]
+        ];
+        let x = array![[0.3, 0.5], [0.7, 0.8], [2.0, -1.0]];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let expected = kde.eval_batch(x.view());
+        let close = |a: f64, b: f64| a - b <= 1e-12 * b && b - a <= 1e-12 * b;
+        assert!(expected.iter().all(|p| *p > 0.0 && *p < f64::INFINITY));
+        assert!(close(kde.eval(x.row(0)), expected[0]));
+
+        let res = eval_prefitted(data.clone(), kde.scaled_covariance(), x.view()).unwrap();
+        assert!(res.iter().zip(expected.iter()).all(|(r, e)| close(*r, *e)));
+
+        // The errors carry their message without `std`
+        let err = eval_prefitted(data, Array2::zeros((2, 2)), x.view()).unwrap_err();
+        assert!(!format!("{err}").is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::summation::ln_sum_exp;
+use crate::{Bandwidth, CowGaussianKDE, ErrorKind, KDEError, ScottBandwidth};
+use alloc::format;
+use ndarray::{Zip, prelude::*};
+use num_traits::{Float, FloatConst, FromPrimitive};
+#[cfg(feature = "sample")]
+use rand::prelude::*;
+#[cfg(feature = "sample")]
+use rand_distr::{StandardNormal, uniform::SampleUniform, weighted::Weight};
+#[cfg(feature = "os-rng")]
+use rand_pcg::Pcg64Mcg;
+
+/// Adaptive (sample-point) kernel density estimation with Gaussian kernels and optionally weighed data points.
+///
+/// In contrast to [`GaussianKDE`], the kernel centered at the data point $x_i$ uses the local bandwidth matrix
+/// $\lambda_i^2 H$, where $H = h^2 V$ is the bandwidth matrix of a fixed-bandwidth pilot estimate $\tilde f$ and
+/// \\[ \lambda_i = \left(\frac{\tilde f(x_i)}{g}\right)^{-\alpha}, \quad \ln g = \frac{1}{\sum_i w_i} \sum_i w_i \ln \tilde f(x_i), \\]
+/// following [[1](#ref-adaptive)]. The density estimation then reads
+/// \\[ f_\mathrm{KDE}(x) = \frac{1}{h^d \sqrt{(2\pi)^d \det V} \sum_i w_i} \sum_{i=1}^n \frac{w_i}{\lambda_i^d} \\, \exp\left(- \frac{1}{2 \lambda_i^2 h^2}(\bm{x} - \bm{x}_i)^\top V^{-1}(\bm{x} - \bm{x}_i)\right). \\]
+/// Kernels in sparsely populated regions, e.g. the tails of the density, are widened, while kernels in densely
+/// populated regions are narrowed. The sensitivity $\alpha$ is usually chosen as $\alpha = 1/2$ [[2](#ref-abramson)],
+/// $\alpha = 0$ recovers the pilot estimate.
+///
+/// ---
+///
+/// <a name = "ref-adaptive"></a> \[1\] [Silverman, Bernard W. Density Estimation for Statistics and Data Analysis. Chapman and Hall, 1986, Section 5.3.](https://doi.org/10.1201/9781315140919)
+///
+/// <a name = "ref-abramson"></a> \[2\] [Abramson, Ian S. "On bandwidth variation in kernel estimates-a square root law." The Annals of Statistics 10.4 (1982): 1217-1223.](https://doi.org/10.1214/aos/1176345986)
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize, B: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>, B: serde::Deserialize<'de>"
+    ))
+)]
+pub struct AdaptiveGaussianKDE<'a, F, B = ScottBandwidth>
+where
+    F: Float + FloatConst + FromPrimitive,
+    B: Bandwidth<F>,
+{
+    pilot: CowGaussianKDE<'a, F, B>,
+    alpha: F,
+    local_factors: Array1<F>,
+    // Logarithm of the kernel weights $w_i / \lambda_i^d$, absorbing the per-point normalization of the kernels.
+    ln_kernel_weights: Array1<F>,
+}
+
+impl<'a, F> AdaptiveGaussianKDE<'a, F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Create a new adaptive kernel density estimator from the given dataset and (optionally) weights with the
+    /// sensitivity `alpha`, using a pilot estimate with the default bandwidth choice [`ScottBandwidth`]. If no
+    /// weights are given, all points are weighed equally. Returns a `DomainError` if `alpha` lies outside of
+    /// $[0, 1]$.
+    ///
+    /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    pub fn new(data: Array2<F>, weights: Option<Array1<F>>, alpha: F) -> Result<Self, KDEError> {
+        return Self::from_pilot(CowGaussianKDE::new(data, weights)?, alpha);
+    }
+}
+
+impl<'a, F, B> AdaptiveGaussianKDE<'a, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Create a new adaptive kernel density estimator with the sensitivity `alpha` from the fixed-bandwidth `pilot`
+    /// estimate, whose dataset, weights and bandwidth matrix are reused.
+    ///
+    /// Returns a `DomainError` if `alpha` lies outside of $[0, 1]$.
+    pub fn from_pilot(pilot: CowGaussianKDE<'a, F, B>, alpha: F) -> Result<Self, KDEError> {
+        if !(alpha >= F::zero() && alpha <= F::one()) {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!(
+                    "the sensitivity must lie in [0, 1], found {}",
+                    alpha.to_f64().unwrap()
+                ),
+            ));
+        }
+        let ln_pilot = pilot.ln_eval_batch(pilot.dataset().view());
+        let ln_g = if let Some(ref w) = pilot.weights {
+            ln_pilot.dot(&**w) / w.sum()
+        } else {
+            ln_pilot.mean().unwrap()
+        };
+        let local_factors = ln_pilot.mapv(|ln_f| F::exp(-alpha * (ln_f - ln_g)));
+        let dim = F::from(pilot.n_dims()).unwrap();
+        let mut ln_kernel_weights = local_factors.mapv(|l| -dim * l.ln());
+        if let Some(ref w) = pilot.weights {
+            Zip::from(&mut ln_kernel_weights)
+                .and(&**w)
+                .for_each(|lkw, w| *lkw = *lkw + w.ln());
+        }
+        return Ok(Self {
+            pilot,
+            alpha,
+            local_factors,
+            ln_kernel_weights,
+        });
+    }
+
+    /// Get a reference to the fixed-bandwidth pilot estimate.
+    pub fn pilot(&self) -> &CowGaussianKDE<'a, F, B> {
+        return &self.pilot;
+    }
+
+    /// Get the sensitivity $\alpha$ of the local bandwidth factors.
+    pub fn alpha(&self) -> F {
+        return self.alpha;
+    }
+
+    /// Get a view of the local bandwidth factors $\lambda_i$ of the data points.
+    pub fn local_bandwidth_factors<'kde>(&'kde self) -> ArrayView1<'kde, F> {
+        return self.local_factors.view();
+    }
+
+    /// Evaluate the probability density estimated by the KDE at the point `x`.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn eval(&self, x: ArrayView1<F>) -> F {
+        return self.ln_eval(x).exp();
+    }
+
+    /// Evaluate the probability density estimated by the KDE at multiple points given by the array `x`.
+    ///
+    /// The points are expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    ///
+    /// **Panic**s if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn eval_batch(&self, x: ArrayView2<F>) -> Array1<F> {
+        return self.ln_eval_batch(x).mapv_into(F::exp);
+    }
+
+    /// Evaluate the natural logarithm of the probability density estimated by the KDE at the point `x`.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn ln_eval(&self, x: ArrayView1<F>) -> F {
+        assert_eq!(x.dim(), self.pilot.n_dims());
+        return self.ln_kernel_sum(x) + self.pilot.ln_normalization;
+    }
+
+    /// Evaluate the natural logarithm of the probability density estimated by the KDE at multiple points given by the
+    /// array `x`.
+    ///
+    /// The points are expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    ///
+    /// **Panic**s if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn ln_eval_batch(&self, x: ArrayView2<F>) -> Array1<F> {
+        assert_eq!(x.dim().1, self.pilot.n_dims());
+        return Array1::from_shape_fn(x.dim().0, |j| {
+            self.ln_kernel_sum(x.index_axis(Axis(0), j)) + self.pilot.ln_normalization
+        });
+    }
+
+    /// Logarithm of the unnormalized sum $\sum_i \frac{w_i}{\lambda_i^d} \exp(-\frac{1}{2 \lambda_i^2} z_i^\top z_i)$
+    /// of all kernels evaluated at `x`, computed with a single-pass log-sum-exp reduction.
+    fn ln_kernel_sum(&self, x: ArrayView1<F>) -> F {
+        let half = F::from(0.5).unwrap();
+        return ln_sum_exp(
+            self.pilot
+                .data
+                .rows()
+                .into_iter()
+                .zip(&self.local_factors)
+                .zip(&self.ln_kernel_weights)
+                .map(|((xi, l), lkw)| {
+                    *lkw - half * self.pilot.whitened_sq_distance(xi, x) / (*l * *l)
+                }),
+        );
+    }
+}
+
+#[cfg(feature = "sample")]
+impl<'a, F, B> AdaptiveGaussianKDE<'a, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + 'static,
+    StandardNormal: Distribution<F>,
+{
+    /// Sample a random point from the probability density estimated by the KDE.
+    ///
+    /// This function uses an operating system source to seed the RNG, which requires the `os-rng` feature.
+    #[cfg(feature = "os-rng")]
+    pub fn sample(&self) -> Array1<F> {
+        return self.sample_with_rng(&mut Pcg64Mcg::from_os_rng());
+    }
+
+    /// Sample a random point from the probability density estimated by the KDE using a given RNG.
+    pub fn sample_with_rng(&self, rng: &mut impl Rng) -> Array1<F> {
+        return self
+            .sample_batch_with_rng(1, rng)
+            .index_axis_move(Axis(0), 0);
+    }
+
+    /// Sample `n` random point from the probability density estimated by the KDE.
+    ///
+    /// This function uses an operating system source to seed the RNG, which requires the `os-rng` feature.
+    #[cfg(feature = "os-rng")]
+    pub fn sample_batch(&self, n: usize) -> Array2<F> {
+        return self.sample_batch_with_rng(n, &mut Pcg64Mcg::from_os_rng());
+    }
+
+    /// Sample `n` random point from the probability density estimated by the KDE using a given RNG.
+    ///
+    /// A point is sampled by choosing a kernel according to the weights of the data points and drawing from the
+    /// kernel's normal distribution with covariance $\lambda_i^2 H$.
+    pub fn sample_batch_with_rng(&self, n: usize, rng: &mut impl Rng) -> Array2<F> {
+        let dim = self.pilot.n_dims();
+        let components = self.pilot.component_sampler();
+        let mut res = Array2::zeros((n, dim));
+        let mut z = Array1::zeros(dim);
+        let mut offset = Array1::zeros(dim);
+        for mut row in res.rows_mut() {
+            let k = components.sample(rng);
+            z.mapv_inplace(|_| rng.sample(StandardNormal));
+            offset.fill(F::zero());
+            self.pilot.add_kernel_offset(z.view(), offset.view_mut());
+            self.pilot.assign_data_point(k, row.view_mut());
+            row.scaled_add(self.local_factors[k], &offset);
+        }
+        return res;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AdaptiveGaussianKDE, ErrorKind, GaussianKDE};
+    use approx::assert_relative_eq;
+    use ndarray::{Zip, prelude::*};
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+    use std::f64::consts::PI;
+
+    fn lognormal_pdf(x: f64) -> f64 {
+        return (-0.5 * x.ln().powi(2)).exp() / (x * (2.0 * PI).sqrt());
+    }
+
+    #[test]
+    fn adaptive_alpha_zero_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(3);
+        let data = Array2::<f64>::from_shape_simple_fn((100, 2), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(100, || rng.random::<f64>());
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let adaptive = AdaptiveGaussianKDE::new(data, Some(weights), 0.0).unwrap();
+        assert!(adaptive.local_bandwidth_factors().iter().all(|l| *l == 1.0));
+        let x = array![[0.1, -0.3], [1.5, 2.0], [-4.0, 0.5]];
+        assert_relative_eq!(
+            adaptive.eval_batch(x.view()),
+            kde.eval_batch(x.view()),
+            max_relative = 1e-12
+        );
+    }
+
+    #[test]
+    fn adaptive_lognormal_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(11);
+        let data = Array1::from_shape_simple_fn(500, || f64::exp(rng.sample(StandardNormal)))
+            .insert_axis(Axis(1));
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let adaptive =
+            AdaptiveGaussianKDE::from_pilot(GaussianKDE::new(data.clone(), None).unwrap(), 0.5)
+                .unwrap();
+
+        // Kernels in the tail are widened, the geometric mean of the local factors is one
+        let lambda = adaptive.local_bandwidth_factors();
+        let i_max = data
+            .column(0)
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap()
+            .0;
+        assert!(lambda[i_max] > 2.0);
+        assert_relative_eq!(lambda.mapv(f64::ln).sum(), 0.0, epsilon = 1e-10);
+
+        // Still normalized
+        let grid = Array1::linspace(-20.0, 60.0, 8_001).insert_axis(Axis(1));
+        let integral = adaptive.eval_batch(grid.view()).sum() * 1e-2;
+        assert_relative_eq!(integral, 1.0, epsilon = 1e-6);
+
+        // Improved estimate of the tail density
+        let tail = Array1::linspace(4.0, 12.0, 41).insert_axis(Axis(1));
+        let ln_error = |estimate: Array1<f64>| {
+            Zip::from(&estimate)
+                .and(tail.column(0))
+                .fold(0.0, |acc, f, x| {
+                    acc + (f.ln() - lognormal_pdf(*x).ln()).abs()
+                })
+        };
+        let error_fixed = ln_error(kde.eval_batch(tail.view()));
+        let error_adaptive = ln_error(adaptive.eval_batch(tail.view()));
+        assert!(error_adaptive < 0.6 * error_fixed);
+
+        for alpha in [-0.1, 1.5, f64::NAN] {
+            let res = AdaptiveGaussianKDE::new(data.clone(), None, alpha);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn adaptive_sample_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(5);
+        let data = Array2::<f64>::from_shape_simple_fn((50, 2), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(50, || rng.random::<f64>());
+        let adaptive = AdaptiveGaussianKDE::new(data.clone(), Some(weights.clone()), 0.5).unwrap();
+        let samples = adaptive.sample_batch_with_rng(200_000, &mut rng);
+        let mean = data.t().dot(&weights) / weights.sum();
+        assert_relative_eq!(samples.mean_axis(Axis(0)).unwrap(), mean, epsilon = 1e-2);
+
+        // Second moment of the mixture: sum_i p_i (x_i x_i^T + lambda_i^2 H)
+        let p = &weights / weights.sum();
+        let lambda = adaptive.local_bandwidth_factors();
+        let h = adaptive.pilot().scaled_covariance();
+        let mut second = Array2::<f64>::zeros((2, 2));
+        for (i, xi) in data.rows().into_iter().enumerate() {
+            let outer = xi
+                .to_owned()
+                .insert_axis(Axis(1))
+                .dot(&xi.to_owned().insert_axis(Axis(0)));
+            second = second + (outer + &h * lambda[i].powi(2)) * p[i];
+        }
+        let sample_second = samples.t().dot(&samples) / 200_000.0;
+        assert_relative_eq!(sample_second, second, epsilon = 2e-2);
+    }
+}
@@ -0,0 +1,378 @@
+//! Construction of KDEs from the columns of Arrow record batches and Parquet files.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::{CowGaussianKDE, ErrorKind, KDEError};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float32Type, Float64Type, Int8Type, Int16Type, Int32Type, Int64Type, UInt8Type, UInt16Type,
+    UInt32Type, UInt64Type,
+};
+use arrow_array::{Array, ArrowPrimitiveType, RecordBatch};
+use arrow_schema::DataType;
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive, ToPrimitive};
+
+/// Options for reading the dataset of a KDE from Arrow record batches with
+/// [`GaussianKDE::from_record_batches_with_options`].
+///
+/// By default, only floating point columns (`Float32` and `Float64`) are accepted and integer columns are reported as
+/// `DataError`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArrowOptions {
+    cast_integers: bool,
+}
+
+impl ArrowOptions {
+    /// Create the default options of [`GaussianKDE::from_record_batches`].
+    pub fn new() -> Self {
+        return Self {
+            cast_integers: false,
+        };
+    }
+
+    /// Whether signed and unsigned integer columns are converted to floating point numbers, which is lossy for large
+    /// 64-bit integers.
+    pub fn cast_integers(mut self, cast_integers: bool) -> Self {
+        self.cast_integers = cast_integers;
+        return self;
+    }
+}
+
+/// Copy the values of the primitive Arrow array to `out`, converting them to `F`.
+fn copy_values<F, T>(array: &dyn Array, mut out: ArrayViewMut1<F>)
+where
+    F: Float,
+    T: ArrowPrimitiveType,
+    T::Native: ToPrimitive,
+{
+    let values = array.as_primitive::<T>().values();
+    for (o, v) in out.iter_mut().zip(values.iter()) {
+        *o = F::from(*v).unwrap_or_else(F::nan);
+    }
+}
+
+/// Copy the column `name` of the record batch to `out`, which has the length of the batch.
+fn copy_column<F: Float>(
+    batch: &RecordBatch,
+    name: &str,
+    out: ArrayViewMut1<F>,
+    options: ArrowOptions,
+) -> Result<(), KDEError> {
+    let Some(array) = batch.column_by_name(name) else {
+        let names = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect::<Vec<_>>();
+        return Err(KDEError::new(
+            ErrorKind::IndexError,
+            format!("column `{name}` not found in the record batch with the columns {names:?}"),
+        ));
+    };
+    if array.null_count() > 0 {
+        return Err(KDEError::new(
+            ErrorKind::DataError,
+            format!(
+                "column `{name}` contains {} null values",
+                array.null_count()
+            ),
+        ));
+    }
+    let array = array.as_ref();
+    match array.data_type() {
+        DataType::Float64 => copy_values::<F, Float64Type>(array, out),
+        DataType::Float32 => copy_values::<F, Float32Type>(array, out),
+        DataType::Int8 if options.cast_integers => copy_values::<F, Int8Type>(array, out),
+        DataType::Int16 if options.cast_integers => copy_values::<F, Int16Type>(array, out),
+        DataType::Int32 if options.cast_integers => copy_values::<F, Int32Type>(array, out),
+        DataType::Int64 if options.cast_integers => copy_values::<F, Int64Type>(array, out),
+        DataType::UInt8 if options.cast_integers => copy_values::<F, UInt8Type>(array, out),
+        DataType::UInt16 if options.cast_integers => copy_values::<F, UInt16Type>(array, out),
+        DataType::UInt32 if options.cast_integers => copy_values::<F, UInt32Type>(array, out),
+        DataType::UInt64 if options.cast_integers => copy_values::<F, UInt64Type>(array, out),
+        data_type if data_type.is_integer() => {
+            return Err(KDEError::new(
+                ErrorKind::DataError,
+                format!(
+                    "integer column `{name}` of type `{data_type}` requires `ArrowOptions::cast_integers`"
+                ),
+            ));
+        }
+        data_type => {
+            return Err(KDEError::new(
+                ErrorKind::DataError,
+                format!("unsupported type `{data_type}` of column `{name}`"),
+            ));
+        }
+    }
+    return Ok(());
+}
+
+impl<F> CowGaussianKDE<'static, F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Create a new kernel density estimator from the given columns of the Arrow record batches using the default
+    /// bandwidth choice [`ScottBandwidth`](crate::ScottBandwidth). The named `columns` form the components of the data
+    /// points in the given order and `weight_column` (if given) their weights, where the data points of all batches
+    /// are concatenated. The columns must be `Float32` or `Float64` columns without null values, see
+    /// [`GaussianKDE::from_record_batches_with_options`] for integer columns.
+    ///
+    /// Returns an `IndexError` if a column is missing in one of the batches and a `DataError` if a column has another
+    /// type, contains null values or values which are not finite. Invalid weights are reported as for
+    /// [`GaussianKDE::new`].
+    pub fn from_record_batches(
+        batches: impl IntoIterator<Item = RecordBatch>,
+        columns: &[&str],
+        weight_column: Option<&str>,
+    ) -> Result<Self, KDEError> {
+        return Self::from_record_batches_with_options(
+            batches,
+            columns,
+            weight_column,
+            ArrowOptions::new(),
+        );
+    }
+
+    /// Create a new kernel density estimator from the given columns of the Arrow record batches as for
+    /// [`GaussianKDE::from_record_batches`] with the given options.
+    pub fn from_record_batches_with_options(
+        batches: impl IntoIterator<Item = RecordBatch>,
+        columns: &[&str],
+        weight_column: Option<&str>,
+        options: ArrowOptions,
+    ) -> Result<Self, KDEError> {
+        let batches = batches.into_iter().collect::<Vec<_>>();
+        let n_points = batches.iter().map(|b| b.num_rows()).sum();
+        let mut data = Array2::zeros((n_points, columns.len()));
+        let mut weights = weight_column.map(|_| Array1::zeros(n_points));
+        let mut start = 0;
+        for batch in &batches {
+            let end = start + batch.num_rows();
+            for (j, column) in columns.iter().enumerate() {
+                copy_column(batch, column, data.slice_mut(s![start..end, j]), options)?;
+            }
+            if let (Some(column), Some(weights)) = (weight_column, weights.as_mut()) {
+                copy_column(batch, column, weights.slice_mut(s![start..end]), options)?;
+            }
+            start = end;
+        }
+        return Self::new(data, weights);
+    }
+
+    /// Create a new kernel density estimator from the given columns of the Parquet file at `path` as for
+    /// [`GaussianKDE::from_record_batches`]. Only the selected columns are read from the file.
+    ///
+    /// Returns an `IoError` if the file cannot be read, e.g. for compression codecs other than Snappy, which can be
+    /// enabled with the corresponding features of [`parquet`](https://docs.rs/parquet), and the errors of
+    /// [`GaussianKDE::from_record_batches`] otherwise.
+    #[cfg(feature = "parquet")]
+    pub fn from_parquet(
+        path: impl AsRef<std::path::Path>,
+        columns: &[&str],
+        weight_column: Option<&str>,
+    ) -> Result<Self, KDEError> {
+        return Self::from_parquet_with_options(path, columns, weight_column, ArrowOptions::new());
+    }
+
+    /// Create a new kernel density estimator from the given columns of the Parquet file at `path` as for
+    /// [`GaussianKDE::from_parquet`] with the given options.
+    #[cfg(feature = "parquet")]
+    pub fn from_parquet_with_options(
+        path: impl AsRef<std::path::Path>,
+        columns: &[&str],
+        weight_column: Option<&str>,
+        options: ArrowOptions,
+    ) -> Result<Self, KDEError> {
+        use parquet::arrow::ProjectionMask;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let path = path.as_ref();
+        let io_error = |e: &dyn std::fmt::Display| {
+            KDEError::new(
+                ErrorKind::IoError,
+                format!("failed to read the Parquet file `{}`: {e}", path.display()),
+            )
+        };
+        let file = std::fs::File::open(path).map_err(|e| io_error(&e))?;
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).map_err(|e| io_error(&e))?;
+        let names = columns.iter().copied().chain(weight_column);
+        let projection = ProjectionMask::columns(builder.parquet_schema(), names);
+        let reader = builder
+            .with_projection(projection)
+            .build()
+            .map_err(|e| io_error(&e))?;
+        let batches = reader
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| io_error(&e))?;
+        return Self::from_record_batches_with_options(batches, columns, weight_column, options);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArrowOptions, ErrorKind, GaussianKDE};
+    use arrow_array::{
+        ArrayRef, Float32Array, Float64Array, Int32Array, RecordBatch, StringArray, UInt64Array,
+    };
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+    use std::sync::Arc;
+
+    /// Record batch with the columns `x` (`Float64`), `y` (`Float32`), `n` (`Int32`), `id` (`UInt64`), `label`
+    /// (`Utf8`) and `w` (`Float64`) from the rows `[x, y, n, w]` of `rows`.
+    fn batch(rows: ArrayView2<f64>) -> RecordBatch {
+        let column = |j: usize| rows.column(j).to_vec();
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            ("x", Arc::new(Float64Array::from(column(0)))),
+            (
+                "y",
+                Arc::new(Float32Array::from_iter_values(
+                    column(1).into_iter().map(|y| y as f32),
+                )),
+            ),
+            (
+                "n",
+                Arc::new(Int32Array::from_iter_values(
+                    column(2).into_iter().map(|n| n as i32),
+                )),
+            ),
+            (
+                "id",
+                Arc::new(UInt64Array::from_iter_values(0..rows.nrows() as u64)),
+            ),
+            (
+                "label",
+                Arc::new(StringArray::from_iter_values(
+                    column(0).iter().map(|x| format!("{x}")),
+                )),
+            ),
+            ("w", Arc::new(Float64Array::from(column(3)))),
+        ];
+        return RecordBatch::try_from_iter(columns).unwrap();
+    }
+
+    /// Random rows `[x, y, n, w]` where `y` is exactly representable in single precision and `n` is an integer.
+    fn rows(n_points: usize) -> Array2<f64> {
+        let mut rng = Pcg64Mcg::seed_from_u64(104);
+        return Array2::from_shape_fn((n_points, 4), |(_, j)| match j {
+            0 => rng.sample(StandardNormal),
+            1 => rng.sample::<f64, _>(StandardNormal) as f32 as f64,
+            2 => rng.random_range(-5..5) as f64,
+            _ => rng.random_range(0.5..1.5),
+        });
+    }
+
+    #[test]
+    fn from_record_batches_test() {
+        let rows = rows(300);
+        let batches = [
+            batch(rows.slice(s![..100, ..])),
+            batch(rows.slice(s![100..250, ..])),
+            batch(rows.slice(s![250.., ..])),
+        ];
+        let data = rows.select(Axis(1), &[1, 0]);
+        let weights = rows.column(3).to_owned();
+        let kde = GaussianKDE::<f64>::from_record_batches(batches.clone(), &["y", "x"], Some("w"))
+            .unwrap();
+        assert!(kde == GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap());
+        let kde =
+            GaussianKDE::<f32>::from_record_batches(batches.clone(), &["y", "x"], None).unwrap();
+        assert!(kde == GaussianKDE::new(data.mapv(|x| x as f32), None).unwrap());
+
+        // Integer columns require the conversion to be enabled
+        let res = GaussianKDE::<f64>::from_record_batches(batches.clone(), &["x", "n"], None);
+        assert!(matches!(
+            res,
+            Err(e) if matches!(e.kind, ErrorKind::DataError) && e.message.contains("cast_integers")
+        ));
+        let options = ArrowOptions::new().cast_integers(true);
+        let kde = GaussianKDE::<f64>::from_record_batches_with_options(
+            batches.clone(),
+            &["x", "n", "id"],
+            None,
+            options,
+        )
+        .unwrap();
+        let mut expected = rows.select(Axis(1), &[0, 2, 0]);
+        let ids = [0..100, 0..150, 0..50].into_iter().flatten();
+        expected
+            .column_mut(2)
+            .assign(&ids.map(|i| i as f64).collect::<Array1<_>>());
+        assert!(kde == GaussianKDE::new(expected, None).unwrap());
+
+        // Missing and unsupported columns
+        let res = GaussianKDE::<f64>::from_record_batches(batches.clone(), &["x", "z"], None);
+        assert!(
+            matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError) && e.message.contains("`z`"))
+        );
+        let res = GaussianKDE::<f64>::from_record_batches(batches.clone(), &["x"], Some("weight"));
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+        let res = GaussianKDE::<f64>::from_record_batches_with_options(
+            batches.clone(),
+            &["x", "label"],
+            None,
+            options,
+        );
+        assert!(matches!(
+            res,
+            Err(e) if matches!(e.kind, ErrorKind::DataError) && e.message.contains("`Utf8`")
+        ));
+
+        // Null and non-finite values
+        let x: ArrayRef = Arc::new(Float64Array::from(vec![Some(1.0), None, Some(0.5)]));
+        let nulls = RecordBatch::try_from_iter([("x", x)]).unwrap();
+        let res = GaussianKDE::<f64>::from_record_batches([nulls], &["x"], None);
+        assert!(
+            matches!(res, Err(e) if matches!(e.kind, ErrorKind::DataError) && e.message.contains("null"))
+        );
+        let x: ArrayRef = Arc::new(Float64Array::from(vec![1.0, f64::NAN, 0.5]));
+        let nan = RecordBatch::try_from_iter([("x", x)]).unwrap();
+        let res = GaussianKDE::<f64>::from_record_batches([nan], &["x"], None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DataError)));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn from_parquet_test() {
+        use parquet::arrow::ArrowWriter;
+        use parquet::basic::Compression;
+        use parquet::file::properties::WriterProperties;
+
+        let rows = rows(200);
+        let path =
+            std::env::temp_dir().join(format!("gaussian_kde_{}.parquet", std::process::id()));
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(64)
+            .build();
+        let first = batch(rows.slice(s![..120, ..]));
+        let file = std::fs::File::create(&path).unwrap();
+        let mut writer = ArrowWriter::try_new(file, first.schema(), Some(properties)).unwrap();
+        writer.write(&first).unwrap();
+        writer.write(&batch(rows.slice(s![120.., ..]))).unwrap();
+        writer.close().unwrap();
+
+        let kde = GaussianKDE::<f64>::from_parquet(&path, &["x", "y"], Some("w")).unwrap();
+        let expected = GaussianKDE::new(
+            rows.select(Axis(1), &[0, 1]),
+            Some(rows.column(3).to_owned()),
+        )
+        .unwrap();
+        assert!(kde == expected);
+        let options = ArrowOptions::new().cast_integers(true);
+        let kde =
+            GaussianKDE::<f64>::from_parquet_with_options(&path, &["n"], None, options).unwrap();
+        assert!(kde == GaussianKDE::new(rows.select(Axis(1), &[2]), None).unwrap());
+        let res = GaussianKDE::<f64>::from_parquet(&path, &["x", "z"], None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+        std::fs::remove_file(&path).unwrap();
+
+        let res = GaussianKDE::<f64>::from_parquet(&path, &["x"], None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IoError)));
+    }
+}
@@ -1,12 +1,68 @@
-use ndarray::prelude::*;
+use alloc::{format, vec, vec::Vec};
+use ndarray::{Zip, prelude::*};
 use num_traits::{Float, FloatConst, FromPrimitive};
 
+use crate::cholesky::{cholesky_decomposition, cholesky_inverse};
+use crate::covariance::{covariance, effective_sample_size, quantile};
+use crate::fft::dct;
+use crate::summation::{CompensatedSum, compensated_sum, ln_sum_exp};
+use crate::{ErrorKind, KDEError, check_bandwidth_factor};
+use core::f64::consts::PI;
+
 /// General trait to customize the selection of the scalar bandwidth $h$.
+///
+/// The selector is stored in the fitted [`GaussianKDE`](crate::GaussianKDE), such that derived KDEs (e.g. from
+/// marginalization) use the same selector, including any parameters it carries.
 pub trait Bandwidth<F>
 where
     F: Float + FloatConst + FromPrimitive,
 {
-    fn bandwidth(data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F;
+    fn bandwidth(&self, data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F;
+}
+
+/// Choice of the bandwidth factor of a marginal KDE, see [`GaussianKDE::marginalize_with`](crate::GaussianKDE::marginalize_with).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BandwidthPolicy {
+    /// Keep the bandwidth factor $h$ of the parent KDE, such that the kernel covariance of the marginal is the
+    /// sub-block of the parent's kernel covariance $H$. The marginal is then the exact marginal density of the parent,
+    /// which is also the behavior of `scipy.stats.gaussian_kde.marginal`.
+    #[default]
+    KeepParentFactor,
+    /// Fit the marginal as a new KDE of the remaining components, i.e. choose the bandwidth factor with the bandwidth
+    /// selector and estimate the covariance matrix with the covariance mode and regularization for the reduced
+    /// dimension. The result is the density estimate a KDE fitted to the remaining components alone would give, e.g.
+    /// with the smaller factor $n_\mathrm{eff}^{-1/(d+4)}$ of Scott's rule for the smaller dimension $d$. A
+    /// user-given bandwidth matrix is not affected, its sub-block is always used.
+    Recompute,
+}
+
+/// Use a fixed, user-specified scalar bandwidth factor $h$, independent of the data.
+///
+/// This corresponds to passing a scalar `bw_method` to `scipy.stats.gaussian_kde`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FixedBandwidth<F> {
+    pub factor: F,
+}
+
+impl<F: Float> FixedBandwidth<F> {
+    /// Create a new selector always returning the scalar bandwidth factor `factor`.
+    ///
+    /// Returns a `DomainError` if `factor` is not positive and finite.
+    pub fn new(factor: F) -> Result<Self, KDEError> {
+        check_bandwidth_factor(factor)?;
+        return Ok(Self { factor });
+    }
+}
+
+impl<F> Bandwidth<F> for FixedBandwidth<F>
+where
+    F: Float + FloatConst + FromPrimitive,
+{
+    fn bandwidth(&self, _data: ArrayView2<F>, _weights: Option<ArrayView1<F>>) -> F {
+        return self.factor;
+    }
 }
 
 /// Select the scalar bandwidth factor according to Scott's rule.
@@ -16,17 +72,19 @@ where
 /// where $d$ is the dimension of the dataset and
 /// \\[ n_\mathrm{eff} = \frac{\left(\sum_i w_i\right)^2}{\sum_i w_i^2} \\]
 /// is the effective number of entries in the (weighted) dataset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ScottBandwidth {}
 
 impl<F> Bandwidth<F> for ScottBandwidth
 where
     F: Float + FloatConst + FromPrimitive,
 {
-    fn bandwidth(data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F {
+    fn bandwidth(&self, data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F {
         let n_samples = data.dim().0;
         let d = data.dim().1;
         let n_eff = if let Some(ref w) = weights {
-            F::powi(w.sum(), 2) / w.fold(F::zero(), |acc, w| acc + *w * *w)
+            effective_sample_size(w.view())
         } else {
             F::from(n_samples).unwrap()
         };
@@ -41,17 +99,19 @@ where
 /// where $d$ is the dimension of the dataset and
 /// \\[ n_\mathrm{eff} = \frac{\left(\sum_i w_i\right)^2}{\sum_i w_i^2} \\]
 /// is the effective number of entries in the (weighted) dataset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SilvermanBandwidth {}
 
 impl<F> Bandwidth<F> for SilvermanBandwidth
 where
     F: Float + FloatConst + FromPrimitive,
 {
-    fn bandwidth(data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F {
+    fn bandwidth(&self, data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F {
         let n_samples = data.dim().0;
         let d = data.dim().1;
         let n_eff = if let Some(ref w) = weights {
-            F::powi(w.sum(), 2) / w.fold(F::zero(), |acc, w| acc + *w * *w)
+            effective_sample_size(w.view())
         } else {
             F::from(n_samples).unwrap()
         };
@@ -62,14 +122,517 @@ where
     }
 }
 
+/// Select the scalar bandwidth factor by maximizing the leave-one-out (LOO) log-likelihood of the dataset.
+///
+/// The maximum-likelihood cross-validation (MLCV) bandwidth factor is given by
+/// \\[ h = \underset{h}{\mathrm{arg\\,max}} \sum_i w_i \ln f_{-i}(\bm{x}_i), \quad
+/// f_{-i}(\bm{x}_i) = \frac{1}{\sum_{j \neq i} w_j} \sum_{j \neq i} w_j \\, K_H(\bm{x}_i - \bm{x}_j), \\]
+/// where $f_{-i}$ is the density estimated from the dataset with the $i$-th point removed. The LOO densities are
+/// computed in log-space, points with vanishing LOO density (e.g. if all other points have zero weight) are skipped.
+///
+/// The maximum is first bracketed on a grid of `n_grid` log-spaced factors in
+/// `[min_scale * h_Scott, max_scale * h_Scott]`, where `h_Scott` is the factor chosen by [`ScottBandwidth`], and then
+/// refined by a golden-section search until the bracket is narrower than the `tolerance` (relative to $h$), see
+/// [`MLCVBandwidth::new`]. The default selector searches `[0.1 * h_Scott, 10 * h_Scott]` on a grid of 20 factors
+/// with a tolerance of $10^{-4}$. Note that coinciding data points drive the LOO likelihood to infinity for $h \to 0$, in
+/// which case the lower end of the search range is selected.
+///
+/// The computational cost is $\mathcal{O}(n^2 d)$ per evaluation of the LOO likelihood.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MLCVBandwidth {
+    min_scale: f64,
+    max_scale: f64,
+    n_grid: usize,
+    tolerance: f64,
+}
+
+/// Maximum number of golden-section steps of [`MLCVBandwidth`]. Each step shrinks the bracket by a factor of about
+/// 0.618, such that the limit is only reached for tolerances far below the precision of the factor.
+const MAX_GOLDEN_SECTION_STEPS: usize = 100;
+
+impl MLCVBandwidth {
+    /// Create a new selector, which brackets the maximum on a grid of `n_grid` log-spaced factors in
+    /// `[min_scale * h_Scott, max_scale * h_Scott]` and refines it until the bracket is narrower than the relative
+    /// `tolerance`.
+    ///
+    /// Returns a `DomainError` if `min_scale` is not positive, `max_scale` is not finite or not larger than
+    /// `min_scale`, `n_grid` is less than three or `tolerance` is not positive and finite.
+    pub fn new(
+        min_scale: f64,
+        max_scale: f64,
+        n_grid: usize,
+        tolerance: f64,
+    ) -> Result<Self, KDEError> {
+        if !(min_scale > 0.0 && min_scale < max_scale && max_scale.is_finite()) {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!(
+                    "the search range must satisfy 0 < min < max < inf, found [{min_scale}, {max_scale}]"
+                ),
+            ));
+        }
+        if n_grid < 3 {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!("the grid must have at least 3 points, found {n_grid}"),
+            ));
+        }
+        if !(tolerance > 0.0 && tolerance.is_finite()) {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!("the tolerance must be positive and finite, found {tolerance}"),
+            ));
+        }
+        return Ok(Self {
+            min_scale,
+            max_scale,
+            n_grid,
+            tolerance,
+        });
+    }
+
+    /// Get the lower end of the search range relative to the factor chosen by [`ScottBandwidth`].
+    pub fn min_scale(&self) -> f64 {
+        return self.min_scale;
+    }
+
+    /// Get the upper end of the search range relative to the factor chosen by [`ScottBandwidth`].
+    pub fn max_scale(&self) -> f64 {
+        return self.max_scale;
+    }
+
+    /// Get the number of grid points bracketing the maximum.
+    pub fn n_grid(&self) -> usize {
+        return self.n_grid;
+    }
+
+    /// Get the relative tolerance of the golden-section search.
+    pub fn tolerance(&self) -> f64 {
+        return self.tolerance;
+    }
+}
+
+impl Default for MLCVBandwidth {
+    fn default() -> Self {
+        return Self {
+            min_scale: 0.1,
+            max_scale: 10.,
+            n_grid: 20,
+            tolerance: 1E-4,
+        };
+    }
+}
+
+impl<F> Bandwidth<F> for MLCVBandwidth
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    fn bandwidth(&self, data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F {
+        let h_scott = ScottBandwidth {}.bandwidth(data, weights);
+        // Whiten the data with the (unscaled) covariance, such that the kernel distance for factor h is |z_i - z_j| / h
+        let Ok(cholesky) = cholesky_decomposition(covariance(data, weights).view()) else {
+            // The fit itself fails for a singular covariance, the factor is irrelevant in this case
+            return h_scott;
+        };
+        let z = data.dot(&cholesky_inverse(cholesky.view()).t());
+        let objective = |ln_h: f64| loo_log_likelihood(z.view(), weights, F::from(ln_h).unwrap());
+
+        // Bracket the maximum on a log-spaced grid
+        let ln_min = h_scott.to_f64().unwrap().ln() + self.min_scale.ln();
+        let ln_max = h_scott.to_f64().unwrap().ln() + self.max_scale.ln();
+        let n_grid = self.n_grid;
+        let step = (ln_max - ln_min) / (n_grid - 1) as f64;
+        let (i_best, _) = (0..n_grid)
+            .map(|i| objective(ln_min + i as f64 * step))
+            .enumerate()
+            .fold((0, F::neg_infinity()), |(i_best, best), (i, ll)| {
+                if ll > best { (i, ll) } else { (i_best, best) }
+            });
+        let mut a = ln_min + i_best.saturating_sub(1) as f64 * step;
+        let mut b = ln_min + (i_best + 1).min(n_grid - 1) as f64 * step;
+
+        // Golden-section search within the bracket
+        let inv_phi = 0.5 * (5f64.sqrt() - 1.);
+        let mut c = b - inv_phi * (b - a);
+        let mut d = a + inv_phi * (b - a);
+        let mut f_c = objective(c);
+        let mut f_d = objective(d);
+        for _ in 0..MAX_GOLDEN_SECTION_STEPS {
+            if b - a <= self.tolerance {
+                break;
+            }
+            if f_c > f_d {
+                b = d;
+                d = c;
+                f_d = f_c;
+                c = b - inv_phi * (b - a);
+                f_c = objective(c);
+            } else {
+                a = c;
+                c = d;
+                f_c = f_d;
+                d = a + inv_phi * (b - a);
+                f_d = objective(d);
+            }
+        }
+        return F::from(0.5 * (a + b)).unwrap().exp();
+    }
+}
+
+/// Leave-one-out log-likelihood $\sum_i w_i \ln f_{-i}(\bm{x}_i)$ for the bandwidth factor $h = e^{\ln h}$ of the
+/// whitened dataset `z`, up to terms independent of $h$.
+fn loo_log_likelihood<F>(z: ArrayView2<F>, weights: Option<ArrayView1<F>>, ln_h: F) -> F
+where
+    F: Float + FloatConst + FromPrimitive,
+{
+    let n = z.dim().0;
+    let dim = F::from(z.dim().1).unwrap();
+    let half_inv_h2 = F::from(0.5).unwrap() * F::exp(-F::from(2).unwrap() * ln_h);
+    let sum_weights = weights.map_or(F::from(n).unwrap(), |w| compensated_sum(w.iter().copied()));
+    let weight = |i: usize| weights.map_or(F::one(), |w| w[i]);
+    let mut res = CompensatedSum::new();
+    for i in 0..n {
+        let w_i = weight(i);
+        if w_i == F::zero() {
+            continue;
+        }
+        let ln_sum = ln_sum_exp(
+            (0..n)
+                .filter(|j| *j != i && weight(*j) != F::zero())
+                .map(|j| {
+                    let dist = Zip::from(z.row(i))
+                        .and(z.row(j))
+                        .fold(F::zero(), |acc, a, b| acc + (*a - *b) * (*a - *b));
+                    weight(j).ln() - half_inv_h2 * dist
+                }),
+        );
+        if ln_sum == F::neg_infinity() {
+            continue;
+        }
+        res.add(w_i * (ln_sum - (sum_weights - w_i).ln() - dim * ln_h));
+    }
+    return res.value();
+}
+
+/// Select the scalar bandwidth factor for one-dimensional data according to the Sheather-Jones plug-in rule.
+///
+/// This implements the "solve-the-equation" variant of [[1](#ref-sj)], which chooses the bandwidth $h$ as the
+/// solution of
+/// \\[ h = \left(\frac{R(K)}{n \\, \hat{R}(f''; g(h))}\right)^{\frac{1}{5}}, \\]
+/// where the density functional $R(f'') = \int f''(x)^2 \\, \mathrm{d}x$ is estimated with the pilot bandwidth
+/// $g(h) \propto h^{5/7}$, whose proportionality constant is in turn estimated from further pilot bandwidths based on
+/// the scale $\min(\hat\sigma, \mathrm{IQR} / 1.349)$. As in R's `bw.SJ(x, method = "ste")`, the data points are
+/// binned relative to the origin, truncating towards zero, into bins of 1.01 times the data range divided by
+/// `n_bins`, and the functionals are evaluated from the binned pairwise distances. Since the crate scales the
+/// dataset's covariance matrix, the returned factor is $h / \hat\sigma$.
+///
+/// The selection deviates from R's `bw.SJ` in the following points:
+/// - The plug-in equation is solved to machine precision, while R stops `uniroot` at a tolerance of a tenth of the
+///   lower end of the initial bracket, so the bandwidths typically differ in the third significant digit.
+/// - Weighted data is handled by weighing the pairwise distances and using the effective sample size in place of
+///   $n$.
+/// - Where R stops with an error, e.g. for constant data, a sample too sparse to estimate the functionals or if the
+///   equation has no solution, and for multivariate data, this falls back to [`ScottBandwidth`]. The same holds for
+///   fewer than two bins and data too far from the origin to index the bins.
+///
+/// ---
+///
+/// <a name = "ref-sj"></a> \[1\] [Sheather, Simon J., and Michael C. Jones. "A reliable data-based bandwidth selection
+/// method for kernel density estimation." Journal of the Royal Statistical Society: Series B 53.3 (1991): 683-690.](https://doi.org/10.1111/j.2517-6161.1991.tb01857.x)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SheatherJonesBandwidth {
+    pub n_bins: usize,
+}
+
+impl SheatherJonesBandwidth {
+    /// Create a new selector binning the pairwise distances into `n_bins` bins.
+    ///
+    /// Returns a `DomainError` if `n_bins` is less than two.
+    pub fn new(n_bins: usize) -> Result<Self, KDEError> {
+        if n_bins < 2 {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!("the number of bins must be at least 2, found {n_bins}"),
+            ));
+        }
+        return Ok(Self { n_bins });
+    }
+}
+
+impl Default for SheatherJonesBandwidth {
+    fn default() -> Self {
+        return Self { n_bins: 1000 };
+    }
+}
+
+impl<F> Bandwidth<F> for SheatherJonesBandwidth
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    fn bandwidth(&self, data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F {
+        if data.dim().1 != 1 {
+            return ScottBandwidth {}.bandwidth(data, weights);
+        }
+        let sd = covariance(data, weights)[[0, 0]].sqrt();
+        return match sheather_jones(data.column(0), weights, sd, self.n_bins) {
+            Some(h) => h / sd,
+            None => ScottBandwidth {}.bandwidth(data, weights),
+        };
+    }
+}
+
+/// Solve the Sheather-Jones plug-in equation for the one-dimensional data `x`, see [`SheatherJonesBandwidth`].
+/// Returns `None` if the data range is degenerate, `n_bins` is less than two, the bin indices overflow, the sample is
+/// too sparse to estimate the density functionals or the equation has no solution.
+fn sheather_jones<F>(
+    x: ArrayView1<F>,
+    weights: Option<ArrayView1<F>>,
+    sd: F,
+    n_bins: usize,
+) -> Option<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let n = x.len();
+    if n_bins < 2 {
+        return None;
+    }
+    let c = |x: f64| F::from(x).unwrap();
+    // Normalized weights p_i and their pairwise products binned by distance
+    let sum_weights = weights.map_or(F::from(n).unwrap(), |w| w.sum());
+    let p = |i: usize| weights.map_or(F::one(), |w| w[i]) / sum_weights;
+    let sum_p2 = (0..n).fold(F::zero(), |acc, i| acc + p(i) * p(i));
+    let n_eff = sum_p2.recip();
+    let (min, max) = x.fold((F::infinity(), F::neg_infinity()), |(min, max), x| {
+        (min.min(*x), max.max(*x))
+    });
+    let range = max - min;
+    if !(range > F::zero() && range.is_finite()) {
+        return None;
+    }
+    let bin_width = range * c(1.01) / F::from(n_bins).unwrap();
+    // Bin relative to the origin, truncating towards zero, as R does
+    let mut bins = Vec::with_capacity(n);
+    for x in x.iter() {
+        bins.push((*x / bin_width).to_i64()?);
+    }
+    let mut counts = vec![F::zero(); n_bins];
+    for i in 1..n {
+        for j in 0..i {
+            let k = (bins[i].abs_diff(bins[j]) as usize).min(n_bins - 1);
+            counts[k] = counts[k] + p(i) * p(j);
+        }
+    }
+    // Estimate of the density functional R(f^(r/2)) with pilot bandwidth h for r = 4, 6
+    let functional = |h: F, r: i32| {
+        let mut sum = F::zero();
+        for (i, count) in counts.iter().enumerate() {
+            let delta = F::powi(F::from(i).unwrap() * bin_width / h, 2);
+            if delta >= c(1000.) {
+                break;
+            }
+            let term = if r == 4 {
+                delta * delta - c(6.) * delta + c(3.)
+            } else {
+                delta * delta * delta - c(15.) * delta * delta + c(45.) * delta - c(15.)
+            };
+            sum = sum + F::exp(-c(0.5) * delta) * term * *count;
+        }
+        let diagonal = if r == 4 { c(3.) } else { -c(15.) };
+        sum = c(2.) * sum + diagonal * sum_p2;
+        return sum / ((F::one() - sum_p2) * h.powi(r + 1) * F::sqrt(c(2.) * F::PI()));
+    };
+
+    let iqr = quantile(x, weights, c(0.75)) - quantile(x, weights, c(0.25));
+    let scale = sd.min(iqr / c(1.349));
+    let a = c(1.24) * scale * n_eff.powf(-c(1. / 7.));
+    let b = c(1.23) * scale * n_eff.powf(-c(1. / 9.));
+    let c1 = (c(2.) * F::PI().sqrt() * n_eff).recip();
+    let td = -functional(b, 6);
+    if !td.is_finite() || td <= F::zero() {
+        return None;
+    }
+    let alpha2 = c(1.357) * (functional(a, 4) / td).powf(c(1. / 7.));
+    if !alpha2.is_finite() {
+        return None;
+    }
+    let f = |h: F| (c1 / functional(alpha2 * h.powf(c(5. / 7.)), 4)).powf(c(0.2)) - h;
+
+    // Bracket the root, starting from the same interval as R's `bw.SJ`, and bisect
+    let h_max = c(1.144) * scale * n_eff.powf(-c(0.2));
+    let (mut lower, mut upper) = (c(0.1) * h_max, h_max);
+    let mut i_try = 1;
+    while f(lower) * f(upper) > F::zero() {
+        if i_try > 99 {
+            return None;
+        }
+        if i_try % 2 == 1 {
+            upper = upper * c(1.2);
+        } else {
+            lower = lower / c(1.2);
+        }
+        i_try += 1;
+    }
+    let mut f_lower = f(lower);
+    while upper - lower > c(16.) * F::epsilon() * upper {
+        let mid = c(0.5) * (lower + upper);
+        let f_mid = f(mid);
+        if f_lower * f_mid <= F::zero() {
+            upper = mid;
+        } else {
+            lower = mid;
+            f_lower = f_mid;
+        }
+    }
+    return Some(c(0.5) * (lower + upper));
+}
+
+/// Select the scalar bandwidth factor for one-dimensional data with the improved Sheather-Jones (diffusion) method
+/// of Botev et al. [[1](#ref-isj)].
+///
+/// The (weighted) data is binned onto an equidistant grid of `n_grid` points spanning the data range extended by
+/// 10% on both sides, which is rounded up to the next power of two. After a discrete cosine transform of the binned
+/// data, the squared bandwidth $t = h^2$ is obtained as the root of the fixed-point equation
+/// \\[ t = \xi \gamma^{\[l\]}(t), \\]
+/// where $\gamma^{\[l\]}$ recursively estimates the density functionals $\lVert f^{(j)} \rVert^2$ for
+/// $j = l, \dots, 2$ with $l = 7$ directly from the transformed coefficients. In contrast to
+/// [`SheatherJonesBandwidth`], this does not assume a normal reference density for the pilot estimates and its cost
+/// is dominated by the binning, which makes it applicable to very large samples. Weighted data is handled by binning
+/// the weights and using the effective sample size in place of $n$, while the reference implementation `kde.m` uses
+/// the number of distinct data points. Since the crate scales the dataset's covariance matrix, the returned factor is
+/// $h / \hat\sigma$.
+///
+/// The method is only defined for one-dimensional data, for multivariate data (or if the fixed-point equation has no
+/// solution) it falls back to [`ScottBandwidth`].
+///
+/// ---
+///
+/// <a name = "ref-isj"></a> \[1\] [Botev, Zdravko I., Joseph F. Grotowski, and Dirk P. Kroese. "Kernel density
+/// estimation via diffusion." The Annals of Statistics 38.5 (2010): 2916-2957.](https://doi.org/10.1214/10-AOS799)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ISJBandwidth {
+    pub n_grid: usize,
+}
+
+impl Default for ISJBandwidth {
+    fn default() -> Self {
+        return Self { n_grid: 1 << 14 };
+    }
+}
+
+impl<F> Bandwidth<F> for ISJBandwidth
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    fn bandwidth(&self, data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F {
+        if data.dim().1 == 1 {
+            let x = data.column(0).mapv(|x| x.to_f64().unwrap());
+            let w = weights.map(|w| w.mapv(|w| w.to_f64().unwrap()));
+            let sd = covariance(x.view().insert_axis(Axis(1)), w.as_ref().map(|w| w.view()))
+                [[0, 0]]
+            .sqrt();
+            if let Some(h) =
+                improved_sheather_jones(x.view(), w.as_ref().map(|w| w.view()), self.n_grid)
+            {
+                return F::from(h / sd).unwrap();
+            }
+        }
+        return ScottBandwidth {}.bandwidth(data, weights);
+    }
+}
+
+/// Solve the fixed-point equation of the improved Sheather-Jones method for the one-dimensional data `x`, see
+/// [`ISJBandwidth`]. Returns `None` if the data range is degenerate or the equation has no solution.
+fn improved_sheather_jones(
+    x: ArrayView1<f64>,
+    weights: Option<ArrayView1<f64>>,
+    n_grid: usize,
+) -> Option<f64> {
+    let n_grid = n_grid.max(2).next_power_of_two();
+    let (min, max) = x.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+        (min.min(*x), max.max(*x))
+    });
+    let range = max - min;
+    if range <= 0.0 || !range.is_finite() {
+        return None;
+    }
+    let (lower, range) = (min - 0.1 * range, 1.2 * range);
+    let dx = range / (n_grid - 1) as f64;
+    let mut binned = vec![0.0; n_grid];
+    for (i, x) in x.iter().enumerate() {
+        let bin = (((x - lower) / dx) as usize).min(n_grid - 1);
+        binned[bin] += weights.map_or(1.0, |w| w[i]);
+    }
+    let sum_weights: f64 = binned.iter().sum();
+    binned.iter_mut().for_each(|b| *b /= sum_weights);
+    let n_eff = weights.map_or(x.len() as f64, |w| w.sum().powi(2) / w.dot(&w));
+
+    // Squared DCT coefficients a_k^2 / 4 and the squared frequencies k^2 for k >= 1
+    let a2: Vec<f64> = dct(&binned).into_iter().skip(1).map(|a| a * a).collect();
+    let k2: Vec<f64> = (1..n_grid).map(|k| (k * k) as f64).collect();
+    let functional = |s: i32, t: f64| {
+        let sum: f64 = k2
+            .iter()
+            .zip(&a2)
+            .map(|(k2, a2)| k2.powi(s) * a2 * f64::exp(-k2 * PI * PI * t))
+            .sum();
+        return 2.0 * PI.powi(2 * s) * sum;
+    };
+    let fixed_point = |t: f64| {
+        let l = 7;
+        let mut f = functional(l, t);
+        for s in (2..l).rev() {
+            let k0 = (1..2 * s).step_by(2).product::<i32>() as f64 / (2.0 * PI).sqrt();
+            let c = (1.0 + 0.5.powf(s as f64 + 0.5)) / 3.0;
+            let time = (2.0 * c * k0 / n_eff / f).powf(2.0 / (3.0 + 2.0 * s as f64));
+            f = functional(s, time);
+        }
+        return t - (2.0 * n_eff * PI.sqrt() * f).powf(-0.4);
+    };
+
+    // Search for a sign change on successively larger intervals [0, t_max], as in the reference implementation,
+    // and bisect
+    let n_clamped = n_eff.clamp(50.0, 1050.0);
+    let mut upper = 1e-12 + 0.01 * (n_clamped - 50.0) / 1000.0;
+    let f_lower = fixed_point(0.0);
+    while f_lower * fixed_point(upper) > 0.0 {
+        if upper >= 0.1 {
+            return None;
+        }
+        upper = (2.0 * upper).min(0.1);
+    }
+    let mut lower = 0.0;
+    while upper - lower > 16.0 * f64::EPSILON * upper {
+        let mid = 0.5 * (lower + upper);
+        if f_lower * fixed_point(mid) > 0.0 {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+    let t = 0.5 * (lower + upper);
+    return Some(t.sqrt() * range);
+}
+
 #[cfg(test)]
+#[allow(clippy::excessive_precision)]
 mod tests {
     use crate::{
-        Bandwidth,
-        bandwidth::{ScottBandwidth, SilvermanBandwidth},
+        Bandwidth, ErrorKind, GaussianKDE,
+        bandwidth::{
+            BandwidthPolicy, FixedBandwidth, ISJBandwidth, MLCVBandwidth, ScottBandwidth,
+            SheatherJonesBandwidth, SilvermanBandwidth,
+        },
     };
     use approx::assert_relative_eq;
     use ndarray::prelude::*;
+    use num_traits::{Float, FloatConst, FromPrimitive};
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
 
     #[test]
     fn scott_1d_test() {
@@ -80,12 +643,12 @@ mod tests {
         ];
         let w_test = array![0.04622972052712859, 0.15162404870472723, 0.291517199926956];
         assert_relative_eq!(
-            ScottBandwidth::bandwidth(data.view(), None),
+            ScottBandwidth {}.bandwidth(data.view(), None),
             0.8027415617602307,
             epsilon = 1E-10
         );
         assert_relative_eq!(
-            ScottBandwidth::bandwidth(data.view(), Some(w_test.view())),
+            ScottBandwidth {}.bandwidth(data.view(), Some(w_test.view())),
             0.8560705025393376,
             epsilon = 1E-10
         );
@@ -119,12 +682,12 @@ mod tests {
             6.972756025050139694e-01,
         ];
         assert_relative_eq!(
-            ScottBandwidth::bandwidth(data.view(), None),
+            ScottBandwidth {}.bandwidth(data.view(), None),
             0.719685673001152,
             epsilon = 1E-10
         );
         assert_relative_eq!(
-            ScottBandwidth::bandwidth(data.view(), Some(w_test.view())),
+            ScottBandwidth {}.bandwidth(data.view(), Some(w_test.view())),
             0.7505290905335276,
             epsilon = 1E-10
         );
@@ -139,12 +702,12 @@ mod tests {
         ];
         let w_test = array![0.04622972052712859, 0.15162404870472723, 0.291517199926956];
         assert_relative_eq!(
-            SilvermanBandwidth::bandwidth(data.view(), None),
+            SilvermanBandwidth {}.bandwidth(data.view(), None),
             0.8502830004171938,
             epsilon = 1E-10
         );
         assert_relative_eq!(
-            SilvermanBandwidth::bandwidth(data.view(), Some(w_test.view())),
+            SilvermanBandwidth {}.bandwidth(data.view(), Some(w_test.view())),
             0.9067702859083041,
             epsilon = 1E-10
         );
@@ -178,14 +741,373 @@ mod tests {
             6.972756025050139694e-01,
         ];
         assert_relative_eq!(
-            SilvermanBandwidth::bandwidth(data.view(), None),
+            SilvermanBandwidth {}.bandwidth(data.view(), None),
             0.6971055968511698,
             epsilon = 1E-10
         );
         assert_relative_eq!(
-            SilvermanBandwidth::bandwidth(data.view(), Some(w_test.view())),
+            SilvermanBandwidth {}.bandwidth(data.view(), Some(w_test.view())),
             0.7269813048087493,
             epsilon = 1E-10
         );
     }
+
+    /// Bandwidth selector carrying a parameter, scaling Scott's rule by a constant factor.
+    #[derive(Clone)]
+    struct ScaledScottBandwidth {
+        scale: f64,
+    }
+
+    impl<F> Bandwidth<F> for ScaledScottBandwidth
+    where
+        F: Float + FloatConst + FromPrimitive,
+    {
+        fn bandwidth(&self, data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F {
+            return F::from(self.scale).unwrap() * ScottBandwidth {}.bandwidth(data, weights);
+        }
+    }
+
+    #[test]
+    fn selector_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let x_test = array![0.3, 0.5, 0.7];
+
+        // Zero-sized default selectors
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let kde_scott =
+            GaussianKDE::with_bandwidth_selector(data.clone(), None, ScottBandwidth {}).unwrap();
+        assert_eq!(kde.eval(x_test.view()), kde_scott.eval(x_test.view()));
+        let kde_silverman =
+            GaussianKDE::<f64, SilvermanBandwidth>::with_bandwidth(data.clone(), None).unwrap();
+        let kde_fixed = GaussianKDE::with_bandwidth_factor(
+            data.clone(),
+            None,
+            SilvermanBandwidth {}.bandwidth(data.view(), None),
+        )
+        .unwrap();
+        assert_eq!(
+            kde_silverman.eval(x_test.view()),
+            kde_fixed.eval(x_test.view())
+        );
+
+        // A selector with parameters is forwarded to the marginalized KDE, which keeps the bandwidth factor
+        let kde = GaussianKDE::with_bandwidth_selector(
+            data.clone(),
+            None,
+            ScaledScottBandwidth { scale: 0.5 },
+        )
+        .unwrap();
+        assert_eq!(kde.bandwidth_selector().scale, 0.5);
+        let marginal = kde.marginalize_to(&[0, 2]).unwrap();
+        assert_eq!(marginal.bandwidth_selector().scale, 0.5);
+        let reference = GaussianKDE::with_bandwidth_factor(
+            data.select(Axis(1), &[0, 2]),
+            None,
+            0.5 * ScottBandwidth {}.bandwidth(data.view(), None),
+        )
+        .unwrap();
+        assert_relative_eq!(
+            marginal.eval(array![0.3, 0.7].view()),
+            reference.eval(array![0.3, 0.7].view()),
+            max_relative = 1E-14
+        );
+        // ... or applies the selector to the remaining components
+        let marginal = kde
+            .marginalize_to_with(&[0, 2], BandwidthPolicy::Recompute)
+            .unwrap();
+        let reference = GaussianKDE::with_bandwidth_factor(
+            data.select(Axis(1), &[0, 2]),
+            None,
+            0.5 * ScottBandwidth {}.bandwidth(data.select(Axis(1), &[0, 2]).view(), None),
+        )
+        .unwrap();
+        assert_relative_eq!(
+            marginal.eval(array![0.3, 0.7].view()),
+            reference.eval(array![0.3, 0.7].view()),
+            max_relative = 1E-14
+        );
+
+        // A fixed bandwidth factor is kept during marginalization
+        let kde = GaussianKDE::with_bandwidth_factor(data.clone(), None, 0.4).unwrap();
+        let marginal = kde.marginalize_to(&[0]).unwrap();
+        assert_eq!(
+            marginal.bandwidth_selector(),
+            &FixedBandwidth::new(0.4).unwrap()
+        );
+        assert_relative_eq!(
+            marginal.cholesky()[[0, 0]],
+            kde.cholesky()[[0, 0]],
+            max_relative = 1E-14
+        );
+
+        // Factors which are not positive and finite are rejected, also if chosen by a selector
+        for h in [0.0, -0.4, f64::NAN, f64::INFINITY] {
+            let res = FixedBandwidth::new(h);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+            let res = GaussianKDE::with_bandwidth_factor(data.clone(), None, h);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+            let res = GaussianKDE::builder()
+                .bandwidth_factor(h)
+                .build(data.clone());
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+        let res =
+            GaussianKDE::with_bandwidth_selector(data, None, ScaledScottBandwidth { scale: -1.0 });
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+    }
+
+    #[test]
+    fn mlcv_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(1);
+        for dim in [1, 2] {
+            let n = 200;
+            let data: Array2<f64> =
+                Array2::from_shape_simple_fn((n, dim), || rng.sample(StandardNormal));
+            // AMISE-optimal bandwidth factor for normally distributed data
+            let h_amise = (4. / (dim as f64 + 2.) / n as f64).powf(1. / (dim as f64 + 4.));
+            let h = MLCVBandwidth::default().bandwidth(data.view(), None);
+            assert!(
+                h / h_amise < 1.5 && h_amise / h < 1.5,
+                "h = {h}, h_amise = {h_amise}"
+            );
+
+            let weights = Array1::from_shape_simple_fn(n, || rng.random_range(0.5..1.5));
+            let n_eff = weights.sum().powi(2) / weights.dot(&weights);
+            let h_amise = (4. / (dim as f64 + 2.) / n_eff).powf(1. / (dim as f64 + 4.));
+            let h = MLCVBandwidth::default().bandwidth(data.view(), Some(weights.view()));
+            assert!(
+                h / h_amise < 1.5 && h_amise / h < 1.5,
+                "h = {h}, h_amise = {h_amise}"
+            );
+        }
+    }
+
+    #[test]
+    fn mlcv_tolerance_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(14);
+        let data: Array2<f64> =
+            Array2::from_shape_simple_fn((100, 1), || rng.sample(StandardNormal));
+        let h = MLCVBandwidth::default().bandwidth(data.view(), None);
+        let selector = MLCVBandwidth::new(0.1, 10., 20, 1e-8).unwrap();
+        assert_eq!(selector.tolerance(), 1e-8);
+        assert_relative_eq!(
+            selector.bandwidth(data.view(), None),
+            h,
+            max_relative = 1e-4
+        );
+        // A vanishing tolerance terminates after a limited number of steps
+        let selector = MLCVBandwidth {
+            tolerance: 0.0,
+            ..Default::default()
+        };
+        assert_relative_eq!(
+            selector.bandwidth(data.view(), None),
+            h,
+            max_relative = 1e-4
+        );
+        for tolerance in [0.0, -1e-4, f64::NAN, f64::INFINITY] {
+            let res = MLCVBandwidth::new(0.1, 10., 20, tolerance);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+        for (min_scale, max_scale, n_grid) in [
+            (0.0, 10., 20),
+            (-0.1, 10., 20),
+            (f64::NAN, 10., 20),
+            (10., 0.1, 20),
+            (1., 1., 20),
+            (0.1, f64::INFINITY, 20),
+            (0.1, 10., 2),
+        ] {
+            let res = MLCVBandwidth::new(min_scale, max_scale, n_grid, 1e-4);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+        let selector = MLCVBandwidth::new(0.5, 2., 3, 1e-4).unwrap();
+        assert_eq!(
+            (
+                selector.min_scale(),
+                selector.max_scale(),
+                selector.n_grid()
+            ),
+            (0.5, 2., 3)
+        );
+    }
+
+    #[test]
+    fn mlcv_degenerate_test() {
+        // Coinciding points and zero weights must not produce non-finite factors
+        let data = array![[0.1, 0.2], [0.1, 0.2], [0.5, 0.1], [0.7, 0.9], [0.3, 0.4]];
+        let weights = array![1.0, 0.5, 0.0, 2.0, 1.0];
+        let selector = MLCVBandwidth::default();
+        let h_scott = ScottBandwidth {}.bandwidth(data.view(), Some(weights.view()));
+        let h = selector.bandwidth(data.view(), Some(weights.view()));
+        assert!(h.is_finite());
+        assert!(
+            h >= 0.99 * selector.min_scale() * h_scott
+                && h <= 1.01 * selector.max_scale() * h_scott
+        );
+
+        let kde = GaussianKDE::with_bandwidth_selector(data, None, selector).unwrap();
+        assert!(kde.bandwidth_factor().is_finite());
+        assert!(kde.eval(array![0.4, 0.4].view()).is_finite());
+    }
+
+    #[test]
+    fn sheather_jones_test() {
+        // Reference values of R's `bw.SJ(x, nb = 1000, method = "ste")` and `bw.SJ(x) / sd(x)`, transcribed from the R
+        // sources including `uniroot`. Since R stops the root finding early, they agree to about a percent.
+        #[rustfmt::skip]
+        let lognormal = array![
+            0.37559, 1.352657, 2.216656, 0.663273, 0.345355, 0.948927, 1.465812, 2.407401, 0.378061, 0.355478,
+            1.758994, 1.461215, 3.411079, 2.194528, 0.921802, 0.803009, 7.817951, 0.793706, 0.533686, 0.402253,
+            1.07936, 1.123278, 0.758895, 0.951489, 1.244176, 1.740057, 2.40474, 1.182763, 0.238873, 1.75309,
+            0.321179, 0.871854, 0.307575, 1.001682, 0.536412, 1.18662, 14.016659, 0.961862, 1.961794, 0.34692,
+            0.794182, 1.698912, 0.928545, 1.344665, 1.086176, 0.447129, 1.569875, 0.522244, 4.470061, 0.667535,
+            1.731303, 1.001881, 2.142438, 0.61772, 2.223523, 0.905635, 2.747371, 1.647659, 1.119671, 0.535675,
+            1.848256, 1.454296, 3.719229, 0.739618, 1.558631, 1.501324, 1.25405, 1.181469, 1.156847, 0.619578,
+            0.444158, 0.728204, 1.604245, 3.349962, 2.185128, 2.3224, 1.862346, 1.748543, 1.157985, 1.853759,
+        ];
+        #[rustfmt::skip]
+        let bimodal = array![
+            3.140229, 2.597952, -1.545594, 1.210209, 0.69102, -0.04652, 2.80047, 0.737311, -0.647629, -0.370668,
+            -2.420316, 0.732815, 0.424788, -0.472616, 3.156546, -0.689928, 3.307902, 2.878998, 2.583744, -0.633569,
+            0.717833, 2.914265, 0.601933, -1.205266, -0.069914, -0.370976, 0.828104, -1.104378, 3.026067, 0.175241,
+            0.578336, 0.230897, 0.642274, 3.348846, 2.576875, -1.278328, 0.185435, 3.020774, 3.100831, -0.048052,
+            -0.107123, 2.565078, -1.566323, 2.766614, 2.74531, 2.149532, -0.852008, 2.01308, 0.502746, -1.931245,
+            -0.976893, 0.890774, -2.026632, 1.784404, 2.377232, 1.051179, 0.220525, 2.822256, -0.921039, -0.680605,
+            -1.380535, 3.011926, 0.844212, 0.060078, 1.051668, 1.901842, 0.691786, -0.559764, -1.642942, 3.380926,
+            2.50547, 0.878415, -1.904572, 3.021143, 3.382831, -0.248778, -0.109285, 0.685462, -0.869849, 3.016745,
+            2.811721, 0.115903, 3.758699, 0.404764, 0.050666, -0.189818, 1.858832, 0.291137, 1.347747, 0.409459,
+            -1.01976, 2.249298, 0.35534, 1.07842, -1.558549, 0.275044, -0.874911, 1.274611, 0.038992, 2.500342,
+            3.991264, 3.604963, -1.342329, 3.201704, -1.86726, 2.569135, 2.292976, 0.328154, 3.298229, 2.348743,
+            2.269983, 2.175203, 0.222784, 2.574896, 2.879414, 0.338579, 0.132165, -1.14858, 3.825199, 0.702609,
+        ];
+        let selector = SheatherJonesBandwidth::default();
+        for (x, h_ref, factor_ref) in [
+            (lognormal, 0.28782525951897137, 0.1609297006971645),
+            (bimodal, 0.3392357662902284, 0.20570447643306805),
+        ] {
+            let data = x.insert_axis(Axis(1));
+            let factor = selector.bandwidth(data.view(), None);
+            assert_relative_eq!(factor, factor_ref, max_relative = 2e-2);
+            let kde = GaussianKDE::with_bandwidth_selector(data.clone(), None, selector).unwrap();
+            assert_relative_eq!(
+                kde.scaled_covariance()[[0, 0]].sqrt(),
+                h_ref,
+                max_relative = 2e-2
+            );
+
+            // Equal weights are equivalent to unweighted data
+            let weights = Array1::from_elem(data.dim().0, 2.5);
+            assert_relative_eq!(
+                selector.bandwidth(data.view(), Some(weights.view())),
+                factor,
+                max_relative = 1e-10
+            );
+        }
+    }
+
+    #[test]
+    fn sheather_jones_fallback_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(42);
+        let data = Array2::<f64>::from_shape_simple_fn((50, 3), || rng.sample(StandardNormal));
+        assert_eq!(
+            SheatherJonesBandwidth::default().bandwidth(data.view(), None),
+            ScottBandwidth {}.bandwidth(data.view(), None)
+        );
+
+        // Constant data has no solution
+        let data = Array2::from_elem((20, 1), 3.0);
+        assert_eq!(
+            SheatherJonesBandwidth::default().bandwidth(data.view(), None),
+            ScottBandwidth {}.bandwidth(data.view(), None)
+        );
+        // Fewer than two bins
+        let data = Array2::<f64>::from_shape_simple_fn((50, 1), || rng.sample(StandardNormal));
+        assert_eq!(
+            SheatherJonesBandwidth { n_bins: 1 }.bandwidth(data.view(), None),
+            ScottBandwidth {}.bandwidth(data.view(), None)
+        );
+        for n_bins in [0, 1] {
+            let res = SheatherJonesBandwidth::new(n_bins);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+    }
+
+    #[test]
+    fn sheather_jones_offset_test() {
+        // Shifting the data far from the origin only moves the bin boundaries
+        let mut rng = Pcg64Mcg::seed_from_u64(15);
+        let data = Array2::<f64>::from_shape_simple_fn((200, 1), || rng.sample(StandardNormal));
+        let selector = SheatherJonesBandwidth::default();
+        let h = selector.bandwidth(data.view(), None);
+        let h_offset = selector.bandwidth((&data + 1E10).view(), None);
+        assert_relative_eq!(h, h_offset, max_relative = 1E-2);
+        assert_ne!(h, ScottBandwidth {}.bandwidth(data.view(), None));
+    }
+
+    #[test]
+    fn isj_test() {
+        // Sample from the claw density of Marron and Wand (1992)
+        let mut rng = Pcg64Mcg::seed_from_u64(7);
+        let x = Array1::from_shape_simple_fn(1000, || {
+            let z: f64 = rng.sample(StandardNormal);
+            let u: f64 = rng.random();
+            if u < 0.5 {
+                z
+            } else {
+                ((u - 0.5) / 0.1).floor() / 2.0 - 1.0 + 0.1 * z
+            }
+        });
+        let weights = Array1::from_shape_simple_fn(1000, || rng.random::<f64>() + 0.5);
+        let data = x.insert_axis(Axis(1));
+
+        // Reference values of `kde(x, 1024)` and `kde(x)` from the MATLAB implementation `kde.m` by Z. Botev,
+        // transcribed including its `histc` binning, which only agrees up to the bin boundaries and the root finding
+        let selector = ISJBandwidth { n_grid: 1024 };
+        let factor = selector.bandwidth(data.view(), None);
+        assert_relative_eq!(factor, 0.06763718834507397, max_relative = 1e-3);
+        let kde = GaussianKDE::with_bandwidth_selector(data.clone(), None, selector).unwrap();
+        assert_relative_eq!(
+            kde.scaled_covariance()[[0, 0]].sqrt(),
+            0.061780357933641596,
+            max_relative = 1e-3
+        );
+        let factor_default = ISJBandwidth::default().bandwidth(data.view(), None);
+        assert_relative_eq!(factor_default, 0.06746138319812298, max_relative = 1e-3);
+        assert!(factor_default < 0.5 * ScottBandwidth {}.bandwidth(data.view(), None));
+
+        // `kde.m` does not support weights, this is a regression value
+        assert_relative_eq!(
+            selector.bandwidth(data.view(), Some(weights.view())),
+            0.0680741993308419,
+            max_relative = 1e-10
+        );
+    }
+
+    #[test]
+    fn isj_fallback_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(42);
+        let data = Array2::<f64>::from_shape_simple_fn((50, 3), || rng.sample(StandardNormal));
+        assert_eq!(
+            ISJBandwidth::default().bandwidth(data.view(), None),
+            ScottBandwidth {}.bandwidth(data.view(), None)
+        );
+        let constant = Array2::<f64>::ones((20, 1));
+        assert_eq!(
+            ISJBandwidth::default().bandwidth(constant.view(), None),
+            ScottBandwidth {}.bandwidth(constant.view(), None)
+        );
+    }
 }
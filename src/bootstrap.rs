@@ -0,0 +1,168 @@
+//! Smoothed bootstrap for pointwise uncertainty estimates of the estimated density. Each bootstrap replicate draws as
+//! many points from the fitted density as the original dataset contains and refits a KDE with the same bandwidth
+//! choice, see [`GaussianKDE::resample_unweighted`].
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::covariance::quantile;
+use crate::{Bandwidth, CowGaussianKDE, ErrorKind, KDEError};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+use rand::prelude::*;
+use rand_distr::{StandardNormal, uniform::SampleUniform, weighted::Weight};
+
+/// Number of grid points evaluated at once when computing bootstrap bands, which limits the memory required for the
+/// bootstrapped densities to `n_boot * BAND_CHUNK_SIZE` values independently of the grid size.
+const BAND_CHUNK_SIZE: usize = 1024;
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F> + Clone,
+    F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + 'static,
+    StandardNormal: Distribution<F>,
+{
+    /// Evaluate `n_boot` smoothed bootstrap replicates of the KDE at each point of `grid` using a given RNG. Returns
+    /// an array of shape `(n_boot, n_grid)` whose rows are the densities of the refitted KDEs.
+    ///
+    /// Returns a `ShapeError` if the points in `grid` do not match the dimension of the KDE dataset.
+    pub fn bootstrap_eval(
+        &self,
+        grid: ArrayView2<F>,
+        n_boot: usize,
+        rng: &mut impl Rng,
+    ) -> Result<Array2<F>, KDEError> {
+        self.check_grid_dimension(grid)?;
+        let mut res = Array2::zeros((n_boot, grid.dim().0));
+        for row in res.rows_mut() {
+            self.bootstrap_replicate(rng)?.eval_batch_into(grid, row)?;
+        }
+        return Ok(res);
+    }
+
+    /// Compute a pointwise $(1 - \alpha)$ smoothed bootstrap band of the density at each point of `grid` using a given
+    /// RNG. Returns the lower and upper curves given by the $\alpha / 2$ and $1 - \alpha / 2$ quantiles of the
+    /// densities of `n_boot` refitted KDEs, see [`GaussianKDE::bootstrap_eval`].
+    ///
+    /// Instead of storing all bootstrapped densities, the refitted KDEs are kept and evaluated on chunks of the grid.
+    /// For the same RNG state, the result is identical to the quantiles of the output of
+    /// [`GaussianKDE::bootstrap_eval`].
+    ///
+    /// Returns a `ShapeError` if the points in `grid` do not match the dimension of the KDE dataset and a
+    /// `DomainError` if `alpha` does not lie in the open interval $(0, 1)$.
+    pub fn bootstrap_band(
+        &self,
+        grid: ArrayView2<F>,
+        n_boot: usize,
+        alpha: F,
+        rng: &mut impl Rng,
+    ) -> Result<(Array1<F>, Array1<F>), KDEError> {
+        self.check_grid_dimension(grid)?;
+        if !(alpha > F::zero() && alpha < F::one()) {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!(
+                    "alpha must lie in the open interval (0, 1), found {}",
+                    alpha.to_f64().unwrap()
+                ),
+            ));
+        }
+        let replicates = (0..n_boot)
+            .map(|_| self.bootstrap_replicate(rng))
+            .collect::<Result<Vec<_>, _>>()?;
+        let p_low = alpha / F::from(2).unwrap();
+        let p_high = F::one() - p_low;
+        let mut lower = Array1::zeros(grid.dim().0);
+        let mut upper = Array1::zeros(grid.dim().0);
+        let mut values = Array2::zeros((n_boot, BAND_CHUNK_SIZE.min(grid.dim().0)));
+        for (i, chunk) in grid.axis_chunks_iter(Axis(0), BAND_CHUNK_SIZE).enumerate() {
+            let mut values = values.slice_mut(s![.., ..chunk.dim().0]);
+            for (kde, row) in replicates.iter().zip(values.rows_mut()) {
+                kde.eval_batch_into(chunk, row)?;
+            }
+            let offset = i * BAND_CHUNK_SIZE;
+            for (j, column) in values.columns().into_iter().enumerate() {
+                lower[offset + j] = quantile(column, None, p_low);
+                upper[offset + j] = quantile(column, None, p_high);
+            }
+        }
+        return Ok((lower, upper));
+    }
+
+    /// Draw a smoothed bootstrap replicate of the KDE, i.e. a KDE refitted to as many points drawn from the estimated
+    /// density as the dataset contains.
+    fn bootstrap_replicate(&self, rng: &mut impl Rng) -> Result<Self, KDEError> {
+        return self.resample_unweighted(self.n_points(), rng);
+    }
+
+    /// Return a `ShapeError` if the points in `grid` do not match the dimension of the KDE dataset.
+    fn check_grid_dimension(&self, grid: ArrayView2<F>) -> Result<(), KDEError> {
+        if grid.dim().1 != self.n_dims() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected points of dimension {}, found array with shape `{:?}`",
+                    self.n_dims(),
+                    grid.dim()
+                ),
+            ));
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::covariance::quantile;
+    use crate::{ErrorKind, GaussianKDE};
+    use approx::assert_abs_diff_eq;
+    use ndarray::{Zip, prelude::*};
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn bootstrap_band_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(11);
+        let data = Array2::from_shape_simple_fn((200, 1), || rng.sample::<f64, _>(StandardNormal));
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let grid = Array1::linspace(-2.0, 2.0, 41).insert_axis(Axis(1));
+        let (n_boot, alpha) = (200, 0.1);
+
+        let densities = kde
+            .bootstrap_eval(grid.view(), n_boot, &mut Pcg64Mcg::seed_from_u64(5))
+            .unwrap();
+        assert_eq!(densities.dim(), (n_boot, 41));
+        let (lower, upper) = kde
+            .bootstrap_band(grid.view(), n_boot, alpha, &mut Pcg64Mcg::seed_from_u64(5))
+            .unwrap();
+        for (j, column) in densities.columns().into_iter().enumerate() {
+            assert_eq!(lower[j], quantile(column, None, alpha / 2.0));
+            assert_eq!(upper[j], quantile(column, None, 1.0 - alpha / 2.0));
+        }
+
+        // The band covers the original density as well as independent bootstrap replicates at the nominal rate
+        let original = kde.eval_batch(grid.view());
+        let covered = Zip::from(&original)
+            .and(&lower)
+            .and(&upper)
+            .fold(0, |acc, f, l, u| acc + (l <= f && f <= u) as usize);
+        assert!(covered as f64 / 41.0 >= 1.0 - alpha);
+        let replicates = kde
+            .bootstrap_eval(grid.view(), 500, &mut Pcg64Mcg::seed_from_u64(6))
+            .unwrap();
+        let covered = Zip::from(&replicates)
+            .and_broadcast(&lower)
+            .and_broadcast(&upper)
+            .fold(0, |acc, f, l, u| acc + (l <= f && f <= u) as usize);
+        assert_abs_diff_eq!(
+            covered as f64 / replicates.len() as f64,
+            1.0 - alpha,
+            epsilon = 0.03
+        );
+
+        let res = kde.bootstrap_band(grid.view(), n_boot, 1.0, &mut rng);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        let res = kde.bootstrap_eval(array![[0.0, 1.0]].view(), n_boot, &mut rng);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+}
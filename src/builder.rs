@@ -0,0 +1,414 @@
+//! Builder collecting the construction options of a [`GaussianKDE`], as an alternative to the positional arguments of
+//! its constructors.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::bandwidth::{Bandwidth, FixedBandwidth, ScottBandwidth};
+use crate::covariance::effective_sample_size;
+use crate::{CovarianceMode, CowGaussianKDE, ErrorKind, KDEError, check_finite, shared};
+use alloc::format;
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// Builder for a [`GaussianKDE`], created by [`GaussianKDE::builder`].
+///
+/// Without further configuration, [`GaussianKDEBuilder::build`] is equivalent to [`GaussianKDE::new`] without
+/// weights. Incompatible options, e.g. a fixed bandwidth matrix together with a bandwidth selector or a diagonal
+/// covariance matrix, are reported as `ConfigurationError` when building.
+#[derive(Debug, Clone)]
+pub struct GaussianKDEBuilder<F, B = ScottBandwidth> {
+    weights: Option<Array1<F>>,
+    bandwidth: B,
+    bandwidth_selected: bool,
+    bandwidth_matrix: Option<Array2<F>>,
+    covariance_mode: Option<CovarianceMode>,
+    regularization: F,
+    check_finite: bool,
+    diagonal_fallback: Option<usize>,
+}
+
+impl<F> GaussianKDEBuilder<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Create a builder with the default options of [`GaussianKDE::new`], i.e. [`ScottBandwidth`], the full covariance
+    /// matrix of the dataset and no weights.
+    pub fn new() -> Self {
+        return Self {
+            weights: None,
+            bandwidth: ScottBandwidth {},
+            bandwidth_selected: false,
+            bandwidth_matrix: None,
+            covariance_mode: None,
+            regularization: F::zero(),
+            check_finite: true,
+            diagonal_fallback: None,
+        };
+    }
+}
+
+impl<F> Default for GaussianKDEBuilder<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl<F, B> GaussianKDEBuilder<F, B>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+    B: Bandwidth<F>,
+{
+    /// Weigh the data points with the given weights, whose length must match the number of data points.
+    pub fn weights(mut self, weights: Array1<F>) -> Self {
+        self.weights = Some(weights);
+        return self;
+    }
+
+    /// Choose the bandwidth factor with the given bandwidth selector, e.g. [`ScottBandwidth`],
+    /// [`SilvermanBandwidth`](crate::SilvermanBandwidth) or [`FixedBandwidth`].
+    pub fn bandwidth<B2: Bandwidth<F>>(self, bandwidth: B2) -> GaussianKDEBuilder<F, B2> {
+        return GaussianKDEBuilder {
+            weights: self.weights,
+            bandwidth,
+            bandwidth_selected: true,
+            bandwidth_matrix: self.bandwidth_matrix,
+            covariance_mode: self.covariance_mode,
+            regularization: self.regularization,
+            check_finite: self.check_finite,
+            diagonal_fallback: self.diagonal_fallback,
+        };
+    }
+
+    /// Use the fixed scalar bandwidth factor `h`, i.e. the kernel covariance $H = h^2 V$. This is a shortcut for
+    /// [`GaussianKDEBuilder::bandwidth`] with a [`FixedBandwidth`]. A factor which is not positive and finite is
+    /// reported as `DomainError` when building.
+    pub fn bandwidth_factor(self, h: F) -> GaussianKDEBuilder<F, FixedBandwidth<F>> {
+        return self.bandwidth(FixedBandwidth { factor: h });
+    }
+
+    /// Use the given matrix as kernel covariance $H$, see [`GaussianKDE::with_bandwidth_matrix`]. As there, the
+    /// bandwidth selector of the KDE is a [`FixedBandwidth`] with factor one. This cannot be combined with a bandwidth
+    /// selector, a covariance mode other than [`CovarianceMode::Full`] or a regularization.
+    pub fn bandwidth_matrix(self, h_matrix: Array2<F>) -> GaussianKDEBuilder<F, FixedBandwidth<F>> {
+        return GaussianKDEBuilder {
+            weights: self.weights,
+            bandwidth: FixedBandwidth { factor: F::one() },
+            bandwidth_selected: self.bandwidth_selected,
+            bandwidth_matrix: Some(h_matrix),
+            covariance_mode: self.covariance_mode,
+            regularization: self.regularization,
+            check_finite: self.check_finite,
+            diagonal_fallback: self.diagonal_fallback,
+        };
+    }
+
+    /// Use the given structure of the dataset covariance matrix $V$, see [`GaussianKDE::with_covariance_mode`].
+    pub fn covariance_mode(mut self, covariance_mode: CovarianceMode) -> Self {
+        self.covariance_mode = Some(covariance_mode);
+        return self;
+    }
+
+    /// Use the diagonal covariance matrix of the dataset ([`CovarianceMode::Diagonal`]) if `diagonal` is true and
+    /// the full covariance matrix ([`CovarianceMode::Full`]) otherwise.
+    pub fn diagonal_covariance(self, diagonal: bool) -> Self {
+        return self.covariance_mode(if diagonal {
+            CovarianceMode::Diagonal
+        } else {
+            CovarianceMode::Full
+        });
+    }
+
+    /// Fall back to the diagonal covariance matrix ([`CovarianceMode::Diagonal`]) if the dataset has fewer than
+    /// `points_per_dim` times as many points as dimensions. For weighted data, the effective sample size
+    /// $(\sum_i w_i)^2 / \sum_i w_i^2$ is compared instead. With so few points, the correlations of the full (or
+    /// robust) covariance matrix are dominated by noise, while the per-dimension variances remain well-defined down to
+    /// two points. Shrinkage modes are kept, since they are already well-conditioned. The mode used by the fitted KDE
+    /// is returned by [`GaussianKDE::covariance_mode`].
+    pub fn diagonal_fallback(mut self, points_per_dim: usize) -> Self {
+        self.diagonal_fallback = Some(points_per_dim);
+        return self;
+    }
+
+    /// Regularize the dataset covariance matrix $V$ by adding $\varepsilon \operatorname{tr} V / d$ to its diagonal
+    /// before the decomposition, with the non-negative regularization $\varepsilon$ (default: zero).
+    pub fn regularization(mut self, eps: F) -> Self {
+        self.regularization = eps;
+        return self;
+    }
+
+    /// Check the dataset for non-finite values (default: true), which are reported as `DataError` naming the row and
+    /// column of the first one. The check requires a pass over the dataset and can be skipped for datasets which
+    /// are known to be finite, e.g. since they were validated before. Non-finite values then typically surface as
+    /// `SingularityError` of the covariance matrix or as NaN densities.
+    pub fn check_finite(mut self, check: bool) -> Self {
+        self.check_finite = check;
+        return self;
+    }
+
+    /// Construct the kernel density estimator for the given dataset of shape `(n_points, dim)` with the configured
+    /// options.
+    ///
+    /// Returns a `ConfigurationError` for incompatible options, a `DomainError` for a negative or non-finite
+    /// regularization and a `DataError` for non-finite data if enabled, besides the errors of the corresponding
+    /// constructors.
+    pub fn build<'a>(self, data: Array2<F>) -> Result<CowGaussianKDE<'a, F, B>, KDEError> {
+        if !(self.regularization >= F::zero() && self.regularization.is_finite()) {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!(
+                    "the regularization must be non-negative and finite, found {}",
+                    self.regularization.to_f64().unwrap()
+                ),
+            ));
+        }
+        if self.check_finite {
+            check_finite(data.view())?;
+        }
+        let Some(h_matrix) = self.bandwidth_matrix else {
+            let mut covariance_mode = self.covariance_mode.unwrap_or_default();
+            if let Some(points_per_dim) = self.diagonal_fallback {
+                let n_eff = match self.weights {
+                    Some(ref w) if w.len() == data.dim().0 => effective_sample_size(w.view()),
+                    _ => F::from(data.dim().0).unwrap(),
+                };
+                if !covariance_mode.allows_few_points()
+                    && n_eff < F::from(points_per_dim * data.dim().1).unwrap()
+                {
+                    covariance_mode = CovarianceMode::Diagonal;
+                }
+            }
+            return CowGaussianKDE::fit(
+                shared(data),
+                self.weights.map(shared),
+                self.bandwidth,
+                covariance_mode,
+                self.regularization,
+            );
+        };
+        let conflict = if self.bandwidth_selected {
+            Some("a bandwidth selector")
+        } else if self
+            .covariance_mode
+            .is_some_and(|mode| mode != CovarianceMode::Full)
+        {
+            Some("a covariance mode other than `CovarianceMode::Full`")
+        } else if self.regularization > F::zero() {
+            Some("a regularization")
+        } else if self.diagonal_fallback.is_some() {
+            Some("a diagonal fallback")
+        } else {
+            None
+        };
+        if let Some(conflict) = conflict {
+            return Err(KDEError::new(
+                ErrorKind::ConfigurationError,
+                format!("a fixed bandwidth matrix cannot be combined with {conflict}"),
+            ));
+        }
+        return CowGaussianKDE::from_bandwidth_matrix(
+            shared(data),
+            self.weights.map(shared),
+            self.bandwidth,
+            F::one(),
+            h_matrix.view(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CovarianceMode, ErrorKind, FixedBandwidth, GaussianKDE, GaussianKDEBuilder, ScottBandwidth,
+        SilvermanBandwidth,
+    };
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+
+    #[test]
+    fn builder_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3], [0.4, 1.1], [2.0, -0.5], [0.9, 0.8], [-0.3, -1.4],
+            [1.5, 2.2], [-2.1, 0.6], [0.1, 0.0], [1.1, -0.9], [-0.7, 1.7]
+        ];
+        let weights = array![0.5, 2.0, 1.0, 0.2, 1.5, 0.8, 0.3, 3.0, 1.2, 0.6];
+        let x = array![[0.0, 0.0], [0.5, -0.3], [-1.0, 1.2]];
+
+        let kde = GaussianKDE::builder().build(data.clone()).unwrap();
+        let reference = GaussianKDE::new(data.clone(), None).unwrap();
+        assert_eq!(kde.eval_batch(x.view()), reference.eval_batch(x.view()));
+
+        let kde = GaussianKDE::builder()
+            .weights(weights.clone())
+            .bandwidth(SilvermanBandwidth {})
+            .build(data.clone())
+            .unwrap();
+        let reference: GaussianKDE<_, SilvermanBandwidth> =
+            GaussianKDE::with_bandwidth(data.clone(), Some(weights.clone())).unwrap();
+        assert_eq!(kde.eval_batch(x.view()), reference.eval_batch(x.view()));
+
+        let kde = GaussianKDEBuilder::new()
+            .bandwidth_factor(0.4)
+            .diagonal_covariance(true)
+            .build(data.clone())
+            .unwrap();
+        let reference = GaussianKDE::with_covariance_mode(
+            data.clone(),
+            None,
+            FixedBandwidth::new(0.4).unwrap(),
+            CovarianceMode::Diagonal,
+        )
+        .unwrap();
+        assert_eq!(kde.eval_batch(x.view()), reference.eval_batch(x.view()));
+
+        let kde = GaussianKDE::builder()
+            .weights(weights.clone())
+            .covariance_mode(CovarianceMode::LedoitWolf)
+            .build(data.clone())
+            .unwrap();
+        let reference = GaussianKDE::with_covariance_mode(
+            data.clone(),
+            Some(weights.clone()),
+            ScottBandwidth {},
+            CovarianceMode::LedoitWolf,
+        )
+        .unwrap();
+        assert_eq!(kde.eval_batch(x.view()), reference.eval_batch(x.view()));
+
+        let h_matrix = array![[0.3, 0.1], [0.1, 0.2]];
+        let kde = GaussianKDE::builder()
+            .bandwidth_matrix(h_matrix.clone())
+            .build(data.clone())
+            .unwrap();
+        let reference =
+            GaussianKDE::with_bandwidth_matrix(data.clone(), None, h_matrix.clone()).unwrap();
+        assert_eq!(kde.eval_batch(x.view()), reference.eval_batch(x.view()));
+        assert_eq!(kde.bandwidth_selector(), reference.bandwidth_selector());
+
+        // The regularization adds a multiple of the mean variance to the diagonal
+        let kde = GaussianKDE::builder()
+            .bandwidth_factor(0.5)
+            .regularization(0.1)
+            .build(data.clone())
+            .unwrap();
+        let reference = GaussianKDE::with_bandwidth_factor(data.clone(), None, 0.5).unwrap();
+        let v = reference.covariance();
+        let expected = &v + Array2::from_diag_elem(2, 0.1 * v.diag().mean().unwrap());
+        assert_relative_eq!(kde.covariance(), expected, max_relative = 1e-12);
+        let kde = GaussianKDE::builder()
+            .bandwidth_factor(0.5)
+            .diagonal_covariance(true)
+            .regularization(0.1)
+            .build(data.clone())
+            .unwrap();
+        let expected = Array2::from_diag(&expected.diag());
+        assert_relative_eq!(kde.covariance(), expected, max_relative = 1e-12);
+    }
+
+    #[test]
+    fn diagonal_fallback_test() {
+        #[rustfmt::skip]
+        let data: Array2<f64> = array![
+            [ 0.3, -1.2,  2.1, 0.5],
+            [ 1.1,  0.4,  1.7, 0.9],
+            [-0.6,  0.9,  2.8, 0.1],
+            [ 0.2, -0.3,  2.2, 1.4],
+            [ 0.8,  0.1,  3.1, 0.7]
+        ];
+        let x = array![
+            [0.0, 0.0, 2.0, 0.5],
+            [0.5, -0.5, 2.5, 1.0],
+            [3.0, 1.0, 0.0, -1.0]
+        ];
+
+        let kde = GaussianKDE::builder()
+            .diagonal_fallback(2)
+            .build(data.clone())
+            .unwrap();
+        assert_eq!(kde.covariance_mode(), CovarianceMode::Diagonal);
+        let densities = kde.eval_batch(x.view());
+        assert!(densities.iter().all(|f| f.is_finite() && *f > 0.0));
+
+        // Sum over the data points of products of one-dimensional kernels with the per-dimension standard deviations
+        let h = 5f64.powf(-1.0 / 8.0);
+        let sigma = data.std_axis(Axis(0), 1.0) * h;
+        let expected = x.map_axis(Axis(1), |x| {
+            data.rows()
+                .into_iter()
+                .map(|xi| {
+                    (0..4)
+                        .map(|j| {
+                            let z = (x[j] - xi[j]) / sigma[j];
+                            (-0.5 * z * z).exp() / ((2.0 * std::f64::consts::PI).sqrt() * sigma[j])
+                        })
+                        .product::<f64>()
+                })
+                .sum::<f64>()
+                / 5.0
+        });
+        assert_relative_eq!(densities, expected, max_relative = 1e-12);
+
+        // Down to two points and with fewer points than dimensions
+        let kde = GaussianKDE::builder()
+            .diagonal_fallback(2)
+            .build(data.slice(s![..2, ..]).to_owned())
+            .unwrap();
+        assert!(kde.eval_batch(x.view()).iter().all(|f| f.is_finite()));
+
+        // Enough points, or a weighted dataset with enough effective points
+        let kde = GaussianKDE::builder()
+            .diagonal_fallback(1)
+            .build(data.clone())
+            .unwrap();
+        assert_eq!(kde.covariance_mode(), CovarianceMode::Full);
+        let kde = GaussianKDE::builder()
+            .weights(array![1.0, 1.0, 1.0, 1.0, 20.0])
+            .diagonal_fallback(1)
+            .build(data.clone())
+            .unwrap();
+        assert_eq!(kde.covariance_mode(), CovarianceMode::Diagonal);
+        let kde = GaussianKDE::builder()
+            .covariance_mode(CovarianceMode::LedoitWolf)
+            .diagonal_fallback(2)
+            .build(data.clone())
+            .unwrap();
+        assert_eq!(kde.covariance_mode(), CovarianceMode::LedoitWolf);
+    }
+
+    #[test]
+    fn builder_validation_test() {
+        let data = array![[-1.2, 0.3], [0.4, 1.1], [2.0, -0.5], [0.9, 0.8]];
+        let h_matrix = array![[0.3, 0.1], [0.1, 0.2]];
+        let res = GaussianKDE::builder()
+            .bandwidth_matrix(h_matrix.clone())
+            .diagonal_covariance(true)
+            .build(data.clone());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ConfigurationError)));
+        let res = GaussianKDE::builder()
+            .bandwidth_factor(0.5)
+            .bandwidth_matrix(h_matrix.clone())
+            .build(data.clone());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ConfigurationError)));
+        let res = GaussianKDE::builder()
+            .bandwidth_matrix(h_matrix.clone())
+            .regularization(1e-3)
+            .build(data.clone());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ConfigurationError)));
+        let res = GaussianKDE::builder()
+            .bandwidth_matrix(h_matrix.clone())
+            .diagonal_fallback(2)
+            .build(data.clone());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ConfigurationError)));
+        let res = GaussianKDE::builder()
+            .regularization(-1.0)
+            .build(data.clone());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        let res = GaussianKDE::builder()
+            .weights(array![1.0, 2.0])
+            .build(data.clone());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+}
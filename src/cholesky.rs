@@ -2,16 +2,59 @@
 //! `ndarray-linalg` crate. However, this crate uses LINPACK/BLAS for high-performance implementations of said
 //! algorithms, which carries several dependencies. To avoid these dependencies for the two simple algorithms
 //! required here, they are reimplemented here.
+//!
+//! With the `blas` feature, `f32` and `f64` matrices are instead decomposed and inverted by LAPACK via
+//! `ndarray-linalg`, while other float types keep using the pure-Rust implementations. A LAPACK decomposition is only
+//! accepted if it passes the same pivot criterion, otherwise the pure-Rust decomposition reports the failing pivot,
+//! such that both configurations fail on the same matrices with the same error.
 
+use alloc::format;
+#[cfg(feature = "blas")]
+use core::any::TypeId;
 use ndarray::prelude::*;
 use num_traits::{Float, FloatConst, FromPrimitive};
 
 use crate::KDEError;
 
+/// Cholesky decomposition $M = LL^\top$ of the symmetric matrix `m`, returning the lower-triangular factor $L$.
+///
+/// Returns a `SingularityError` naming the pivot at which the decomposition failed if `m` is not (numerically)
+/// positive-definite, i.e. if a pivot is not larger than a small multiple of the machine epsilon relative to the
+/// corresponding diagonal element of `m`. The pivot $i$ is the first dimension which is (close to) a linear
+/// combination of the previous ones.
 pub(crate) fn cholesky_decomposition<F>(m: ArrayView2<F>) -> Result<Array2<F>, KDEError>
 where
     F: Float + FloatConst + FromPrimitive + 'static,
 {
+    #[cfg(feature = "blas")]
+    if let Some(res) = via_lapack(
+        m,
+        lapack::decomposition::<f64>,
+        lapack::decomposition::<f32>,
+    ) {
+        return Ok(res);
+    }
+    return decomposition(m);
+}
+
+/// Inverse of the lower-triangular matrix `m`.
+pub(crate) fn cholesky_inverse<F>(m: ArrayView2<F>) -> Array2<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    #[cfg(feature = "blas")]
+    if let Some(res) = via_lapack(m, lapack::inverse::<f64>, lapack::inverse::<f32>) {
+        return res;
+    }
+    return inverse(m);
+}
+
+/// Pure-Rust implementation of [`cholesky_decomposition`].
+fn decomposition<F>(m: ArrayView2<F>) -> Result<Array2<F>, KDEError>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let rel_tolerance = pivot_tolerance::<F>(m.nrows());
     let mut res = Array2::zeros(m.raw_dim());
     let mut tmp;
     for i in 0..m.dim().0 {
@@ -20,10 +63,13 @@ where
                 (m[[i, j]] - res.slice(s![i, ..=i]).dot(&res.slice(s![j, ..=i]))) / res[[j, j]];
         }
         tmp = m[[i, i]] - res.slice(s![i, ..i]).dot(&res.slice(s![i, ..i]));
-        if tmp <= F::zero() {
+        if tmp <= rel_tolerance * m[[i, i]] || tmp.is_nan() {
             return Err(KDEError::new(
                 crate::ErrorKind::SingularityError,
-                "the covariance matrix appears to not be positive-definite",
+                format!(
+                    "the covariance matrix appears to not be positive-definite, the Cholesky decomposition failed at \
+                     pivot {i}, i.e. dimension {i} is (close to) a linear combination of the previous dimensions"
+                ),
             ));
         }
         res[[i, i]] = F::sqrt(tmp);
@@ -31,7 +77,8 @@ where
     return Ok(res);
 }
 
-pub(crate) fn cholesky_inverse<F>(m: ArrayView2<F>) -> Array2<F>
+/// Pure-Rust implementation of [`cholesky_inverse`] by forward substitution.
+fn inverse<F>(m: ArrayView2<F>) -> Array2<F>
 where
     F: Float + FloatConst + FromPrimitive + 'static,
 {
@@ -45,13 +92,69 @@ where
     return res;
 }
 
+/// Smallest pivot relative to the diagonal element of a `dim`-dimensional matrix accepted by the decomposition.
+fn pivot_tolerance<F: Float>(dim: usize) -> F {
+    return F::from(100 * dim).unwrap() * F::epsilon();
+}
+
+/// Applies the LAPACK routine `f64_routine` or `f32_routine` to a copy of `m` if `F` is `f64` or `f32`, respectively.
+/// Returns `None` for other types or if the routine fails.
+#[cfg(feature = "blas")]
+fn via_lapack<F: Float + 'static>(
+    m: ArrayView2<F>,
+    f64_routine: fn(Array2<f64>) -> Option<Array2<f64>>,
+    f32_routine: fn(Array2<f32>) -> Option<Array2<f32>>,
+) -> Option<Array2<F>> {
+    if TypeId::of::<F>() == TypeId::of::<f64>() {
+        let res = f64_routine(m.mapv(|x| x.to_f64().unwrap()))?;
+        return Some(res.mapv(|x| F::from(x).unwrap()));
+    }
+    if TypeId::of::<F>() == TypeId::of::<f32>() {
+        let res = f32_routine(m.mapv(|x| x.to_f32().unwrap()))?;
+        return Some(res.mapv(|x| F::from(x).unwrap()));
+    }
+    return None;
+}
+
+#[cfg(feature = "blas")]
+mod lapack {
+    use ndarray::prelude::*;
+    use ndarray_linalg::{Cholesky, Diag, Lapack, SolveTriangular, UPLO};
+    use num_traits::Float;
+
+    /// Lower-triangular Cholesky factor computed by `?potrf`, if it satisfies the pivot criterion of
+    /// [`super::decomposition`].
+    pub(super) fn decomposition<A: Lapack + Float>(m: Array2<A>) -> Option<Array2<A>> {
+        let l = m.cholesky(UPLO::Lower).ok()?;
+        let rel_tolerance = super::pivot_tolerance::<A>(m.nrows());
+        let accepted = (0..m.nrows()).all(|i| {
+            let pivot = l[[i, i]] * l[[i, i]];
+            // LAPACK only rejects non-positive pivots
+            pivot > rel_tolerance * m[[i, i]]
+        });
+        return accepted.then_some(l);
+    }
+
+    /// Inverse of the lower-triangular matrix `m` by solving $MX = I$ with `?trtrs`.
+    pub(super) fn inverse<A: Lapack + Float>(m: Array2<A>) -> Option<Array2<A>> {
+        let identity = Array2::eye(m.nrows());
+        return m
+            .solve_triangular(UPLO::Lower, Diag::NonUnit, &identity)
+            .ok();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::cholesky::cholesky_inverse;
 
-    use super::cholesky_decomposition;
+    use super::{cholesky_decomposition, decomposition, inverse};
+    use crate::{ErrorKind, GaussianKDE};
     use approx::assert_relative_eq;
     use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
 
     #[test]
     fn cholesky_test() {
@@ -96,4 +199,74 @@ mod tests {
         let res = cholesky_inverse(m.view());
         assert_relative_eq!(inv_numpy.as_slice().unwrap(), res.as_slice().unwrap());
     }
+
+    /// Compares the configured backend to the pure-Rust implementations, which is trivial unless the `blas` feature
+    /// is enabled.
+    #[test]
+    fn backend_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(84);
+        for dim in [1, 2, 5, 12] {
+            let a = Array2::from_shape_simple_fn((dim + 3, dim), || {
+                rng.sample::<f64, _>(StandardNormal)
+            });
+            let m = a.t().dot(&a);
+            let l = cholesky_decomposition(m.view()).unwrap();
+            assert_relative_eq!(
+                l,
+                decomposition(m.view()).unwrap(),
+                max_relative = 1e-10,
+                epsilon = 1e-12
+            );
+            assert_relative_eq!(
+                cholesky_inverse(l.view()),
+                inverse(l.view()),
+                max_relative = 1e-10,
+                epsilon = 1e-12
+            );
+
+            let m = m.mapv(|x| x as f32);
+            let l = cholesky_decomposition(m.view()).unwrap();
+            assert_relative_eq!(
+                l,
+                decomposition(m.view()).unwrap(),
+                max_relative = 1e-3,
+                epsilon = 1e-4
+            );
+            assert_relative_eq!(
+                cholesky_inverse(l.view()),
+                inverse(l.view()),
+                max_relative = 1e-3,
+                epsilon = 1e-4
+            );
+        }
+
+        // Singular matrices fail with the same pivot
+        let m = array![[1.0, 2.0, 0.0], [2.0, 4.0, 0.0], [0.0, 0.0, 1.0]];
+        for res in [cholesky_decomposition(m.view()), decomposition(m.view())] {
+            assert!(
+                matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError) && e.message.contains("pivot 1"))
+            );
+        }
+
+        // Batch evaluation through the matrix product against the direct sum of the kernels
+        let data = Array2::from_shape_simple_fn((200, 3), || rng.sample::<f64, _>(StandardNormal));
+        let x =
+            Array2::from_shape_simple_fn((50, 3), || 2.0 * rng.sample::<f64, _>(StandardNormal));
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let covariance = kde.covariance();
+        let l = decomposition(covariance.view()).unwrap();
+        let inv_l = inverse(l.view());
+        let norm = (2.0 * std::f64::consts::PI).powf(1.5) * l.diag().product() * 200.0;
+        let direct = x.map_axis(Axis(1), |x| {
+            data.rows()
+                .into_iter()
+                .map(|y| {
+                    let z = inv_l.dot(&(&x - &y));
+                    (-0.5 * z.dot(&z)).exp()
+                })
+                .sum::<f64>()
+                / norm
+        });
+        assert_relative_eq!(kde.eval_batch(x.view()), direct, max_relative = 1e-12);
+    }
 }
@@ -0,0 +1,246 @@
+//! Attribution of the density estimated by a KDE at a point to the kernels of the individual data points, e.g. to
+//! find the training points responsible for a surprising density.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::kdtree::{Nearest, push_nearest};
+use crate::summation::ln_sum_exp;
+use crate::{Bandwidth, CowGaussianKDE};
+use alloc::vec::Vec;
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Compute the fractions $w_i K_H(x - x_i) / \sum_j w_j K_H(x - x_j)$ of the density at the point `x` contributed
+    /// by the kernel of each data point, in the order of the dataset. The contributions sum to one.
+    ///
+    /// The fractions are computed from the logarithms of the kernels with a log-sum-exp reduction, such that they
+    /// remain accurate far in the tails, where all kernels underflow and the density itself vanishes. Points with zero
+    /// weight do not contribute. See [`GaussianKDE::contributions_above`] to keep only the relevant contributions.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn contributions(&self, x: ArrayView1<F>) -> Array1<F> {
+        let mut ln_terms = self.ln_kernel_terms(x);
+        let ln_sum = ln_sum_exp(ln_terms.iter().copied());
+        ln_terms.mapv_inplace(|t| F::exp(t - ln_sum));
+        return ln_terms;
+    }
+
+    /// Compute the fractions of the density at the point `x` contributed by the kernels of the data points as in
+    /// [`GaussianKDE::contributions`], and return the indices and contributions of the data points whose contribution
+    /// is at least `threshold`, in the order of the dataset.
+    ///
+    /// In contrast to [`GaussianKDE::contributions`], the size of the result is bounded by `1 / threshold` for a
+    /// positive threshold, independent of the number of data points.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn contributions_above(&self, x: ArrayView1<F>, threshold: F) -> Vec<(usize, F)> {
+        let ln_terms = self.ln_kernel_terms(x);
+        let ln_sum = ln_sum_exp(ln_terms.iter().copied());
+        return ln_terms
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (i, F::exp(*t - ln_sum)))
+            .filter(|(_, c)| *c >= threshold)
+            .collect();
+    }
+
+    /// Find the `k` data points closest to the point `x` in the whitened metric of the kernels, i.e. with the smallest
+    /// Mahalanobis distances $\lVert L^{-1}(x - x_i) \rVert$ for the kernel covariance $H = LL^\top$, and return their
+    /// indices and distances in the order of increasing distance. Ties are resolved in the order of the dataset. If
+    /// `k` exceeds the number of data points, all points are returned.
+    ///
+    /// For unweighted data, these are the kernels contributing the most to the density at `x`, see
+    /// [`GaussianKDE::contributions`], while weighted kernels further away may contribute more. The closest points are
+    /// selected with a heap of size `k` instead of sorting all distances. If the kd-tree of the whitened dataset was
+    /// already built by [`GaussianKDE::eval_approx`], it is used to skip the data points far away from `x`.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn top_contributors(&self, x: ArrayView1<F>, k: usize) -> Vec<(usize, F)> {
+        assert_eq!(x.dim(), self.n_dims());
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut nearest = Nearest::with_capacity(k.min(self.n_points()) + 1);
+        match self.tree.get() {
+            Some(tree) => {
+                let z = self.inv_cholesky.dot(&(&x - &tree.center));
+                tree.nearest(z.view(), k, &mut nearest);
+            }
+            None => {
+                for (i, q) in self.sq_distances_to_data(x).into_iter().enumerate() {
+                    push_nearest(&mut nearest, k, i, q);
+                }
+            }
+        }
+        return nearest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|c| (c.index, c.sq_distance.sqrt()))
+            .collect();
+    }
+
+    /// Logarithms $\ln w_i - \frac{1}{2} \lVert L^{-1}(x_i - x) \rVert^2$ of the unnormalized (weighted) kernels of all
+    /// data points at `x`.
+    fn ln_kernel_terms(&self, x: ArrayView1<F>) -> Array1<F> {
+        assert_eq!(x.dim(), self.n_dims());
+        let half = F::from(0.5).unwrap();
+        let mut res = self.sq_distances_to_data(x).mapv(|q| -half * q);
+        if let Some(w) = self.weights() {
+            res.zip_mut_with(&w, |t, w| *t = *t + w.ln());
+        }
+        return res;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GaussianKDE;
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn contributions_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(92);
+        let mut data = Array2::<f64>::from_shape_simple_fn((500, 3), || rng.sample(StandardNormal));
+        // An isolated point far from all others
+        data.row_mut(17).assign(&array![8.0, -8.0, 8.0]);
+        let mut weights = Array1::from_shape_simple_fn(500, || rng.random_range(0.0..1.0));
+        weights[3] = 0.0;
+        for weights in [None, Some(weights)] {
+            let mut kde = GaussianKDE::new(data.clone(), weights.clone()).unwrap();
+            for cache in [true, false] {
+                kde.set_whitened_data_cache(cache);
+                let x = array![0.3, -0.2, 0.5];
+                let res = kde.contributions(x.view());
+                assert_relative_eq!(res.sum(), 1.0, max_relative = 1e-12);
+
+                // Brute-force reference from the (weighted) kernels
+                let inv_cov = kde.inv_covariance();
+                let kernels = Array1::from_shape_fn(500, |i| {
+                    let d = &data.row(i) - &x;
+                    let w = weights.as_ref().map_or(1.0, |w| w[i]);
+                    w * f64::exp(-0.5 * d.dot(&inv_cov.dot(&d)))
+                });
+                assert_relative_eq!(res, &kernels / kernels.sum(), max_relative = 1e-10);
+                assert!(res[17] < 1e-10);
+                if weights.is_some() {
+                    assert_eq!(res[3], 0.0);
+                }
+
+                let above = kde.contributions_above(x.view(), 0.01);
+                let expected = res
+                    .indexed_iter()
+                    .filter(|(_, c)| **c >= 0.01)
+                    .map(|(i, c)| (i, *c))
+                    .collect::<Vec<_>>();
+                assert!(!above.is_empty() && above.len() <= 100);
+                assert_eq!(above, expected);
+
+                // The isolated point explains the density at itself
+                let res = kde.contributions(data.row(17));
+                assert_relative_eq!(res[17], 1.0, max_relative = 1e-6);
+                assert_eq!(
+                    kde.contributions_above(data.row(17), 0.5),
+                    vec![(17, res[17])]
+                );
+            }
+        }
+
+        // Far in the tails, where the density underflows, the closest points still explain the density
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let x = array![1e3, -1e3, 1e3];
+        assert_eq!(kde.eval(x.view()), 0.0);
+        let res = kde.contributions(x.view());
+        assert_relative_eq!(res.sum(), 1.0, max_relative = 1e-12);
+        assert_relative_eq!(res[17], 1.0, max_relative = 1e-12);
+    }
+
+    #[test]
+    fn top_contributors_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(93);
+        let mut data =
+            Array2::<f64>::from_shape_simple_fn((2000, 2), || rng.sample(StandardNormal));
+        // Points at the same distance from the origin
+        for (i, p) in [(5, [1.0, 0.0]), (900, [-1.0, 0.0]), (42, [1.0, 0.0])] {
+            data.row_mut(i).assign(&arr1(&p));
+        }
+        let mut kde = GaussianKDE::with_bandwidth_matrix(
+            data.clone(),
+            None,
+            array![[0.04, 0.0], [0.0, 0.04]],
+        )
+        .unwrap();
+
+        // Brute-force reference by sorting all distances
+        let brute_force = |x: ArrayView1<f64>, k: usize| {
+            let mut res = data
+                .rows()
+                .into_iter()
+                .map(|xi| (&xi - &x).mapv(|d| d * d).sum().sqrt() / 0.2)
+                .enumerate()
+                .collect::<Vec<_>>();
+            res.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            res.truncate(k);
+            return res;
+        };
+        let queries = Array2::<f64>::from_shape_simple_fn((20, 2), || {
+            2.0 * rng.sample::<f64, _>(StandardNormal)
+        });
+        let check = |kde: &GaussianKDE<f64, _>| {
+            for x in queries.rows() {
+                for k in [1, 10, 100] {
+                    let res = kde.top_contributors(x, k);
+                    let expected = brute_force(x, k);
+                    assert_eq!(res.len(), k);
+                    for ((i, d), (i_expected, d_expected)) in res.iter().zip(&expected) {
+                        assert_eq!(i, i_expected);
+                        assert_relative_eq!(*d, *d_expected, max_relative = 1e-12);
+                    }
+                }
+            }
+            // Ties are resolved in the order of the dataset
+            let origin = array![0.0, 0.0];
+            let res = kde.top_contributors(origin.view(), 3);
+            let indices = res.iter().map(|(i, _)| *i).collect::<Vec<_>>();
+            assert_eq!(
+                indices,
+                brute_force(origin.view(), 3)
+                    .iter()
+                    .map(|(i, _)| *i)
+                    .collect::<Vec<_>>()
+            );
+            let x = array![1.0, 0.0];
+            let res = kde.top_contributors(x.view(), 2);
+            assert_eq!(res, vec![(5, 0.0), (42, 0.0)]);
+            assert_eq!(kde.top_contributors(x.view(), 0), vec![]);
+            // All points are returned if `k` exceeds their number
+            let res = kde.top_contributors(x.view(), 5000);
+            assert_eq!(res.len(), 2000);
+            assert!(res.windows(2).all(|r| r[0].1 <= r[1].1));
+        };
+        for cache in [true, false] {
+            kde.set_whitened_data_cache(cache);
+            check(&kde);
+        }
+        // With the kd-tree of the approximate evaluation
+        kde.eval_approx(array![0.0, 0.0].view(), 1e-8).unwrap();
+        check(&kde);
+
+        // The closest kernels dominate the contributions of unweighted data
+        let x = queries.row(0);
+        let contributions = kde.contributions(x);
+        let (i, _) = kde.top_contributors(x, 1)[0];
+        assert_eq!(
+            contributions.iter().copied().fold(0.0, f64::max),
+            contributions[i]
+        );
+    }
+}
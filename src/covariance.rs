@@ -0,0 +1,405 @@
+use alloc::vec::Vec;
+use ndarray::{Zip, prelude::*};
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+use crate::summation::compensated_sum;
+
+/// Structure of the dataset covariance matrix $V$ entering the kernel covariance $H = h^2 V$.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CovarianceMode {
+    /// Full (weighted) covariance matrix of the dataset, i.e. the kernels are aligned with the correlations of the
+    /// data.
+    #[default]
+    Full,
+    /// Diagonal matrix of the per-dimension (weighted) variances $V = \mathrm{diag}(\sigma_1^2, \dots, \sigma_d^2)$,
+    /// i.e. the kernels are products of one-dimensional kernels. Correlations are then only reproduced by the
+    /// arrangement of the data points, while construction and evaluation scale linearly with the dimension. Unlike
+    /// the full covariance matrix, this is well-defined down to two data points, see
+    /// [`GaussianKDEBuilder::diagonal_fallback`](crate::GaussianKDEBuilder::diagonal_fallback).
+    Diagonal,
+    /// Robust estimate of the covariance matrix, which is insensitive to a small fraction of outliers. The standard
+    /// deviations are estimated as $\sigma_i = \mathrm{IQR}_i / 1.349$ from the (weighted) interquartile ranges and
+    /// the correlations as $r_{ij} = 2 \sin(\pi \rho_{ij} / 6)$ from Spearman's rank correlation coefficients
+    /// $\rho_{ij}$. Both are consistent estimates for normally distributed data.
+    Robust,
+    /// Shrinkage estimate $(1 - \lambda) V + \lambda \frac{\operatorname{tr} V}{d} I$ of the covariance matrix with
+    /// the given intensity $\lambda \in [0, 1]$, which remains well-conditioned even if the number of data points is
+    /// of the order of (or below) the dimension. Fitting with an intensity outside of $[0, 1]$ fails with a
+    /// `DomainError`.
+    Shrinkage(f64),
+    /// Shrinkage estimate as for [`CovarianceMode::Shrinkage`], with the intensity $\lambda$ estimated from the data
+    /// according to Ledoit and Wolf [[1](#ref-lw)], where the sample size is replaced by the effective sample size
+    /// for weighted data.
+    ///
+    /// ---
+    ///
+    /// <a name = "ref-lw"></a> \[1\] [Ledoit, Olivier, and Michael Wolf. "A well-conditioned estimator for
+    /// large-dimensional covariance matrices." Journal of Multivariate Analysis 88.2 (2004): 365-411.](https://doi.org/10.1016/S0047-259X(03)00096-4)
+    LedoitWolf,
+}
+
+impl CovarianceMode {
+    /// Whether the covariance matrix remains non-singular for fewer data points than dimensions.
+    pub(crate) fn allows_few_points(self) -> bool {
+        return matches!(
+            self,
+            CovarianceMode::Diagonal | CovarianceMode::Shrinkage(_) | CovarianceMode::LedoitWolf
+        );
+    }
+}
+
+/// Unbiased (weighted) covariance matrix of the dataset `data` of shape `(n_points, dim)`.
+///
+/// For weighted data, the normalization uses the "reliability weights" convention of `numpy.cov`, i.e. the sum of
+/// squared deviations is divided by $\sum_i w_i - \sum_i w_i^2 / \sum_i w_i$.
+pub(crate) fn covariance<F>(data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> Array2<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let dim = data.dim().1;
+    return if let Some(w) = weights {
+        let sum_weights = compensated_sum(w.iter().copied());
+        let means = Array1::from_shape_fn(dim, |i| {
+            compensated_sum(data.column(i).iter().zip(w).map(|(x, w)| *w * *x)) / sum_weights
+        });
+        let denom = sum_weights * (F::one() - effective_sample_size(w).recip());
+        Array2::from_shape_fn((dim, dim), |(i, j)| {
+            compensated_sum(
+                data.column(i)
+                    .iter()
+                    .zip(data.column(j))
+                    .zip(w)
+                    .map(|((x, y), w)| *w * (*x - means[i]) * (*y - means[j])),
+            ) / denom
+        })
+    } else {
+        let n = F::from(data.dim().0).unwrap();
+        let means =
+            Array1::from_shape_fn(dim, |i| compensated_sum(data.column(i).iter().copied()) / n);
+        Array2::from_shape_fn((dim, dim), |(i, j)| {
+            compensated_sum(
+                data.column(i)
+                    .iter()
+                    .zip(data.column(j))
+                    .map(|(x, y)| (*x - means[i]) * (*y - means[j])),
+            ) / (n - F::one())
+        })
+    };
+}
+
+/// Effective number of entries $n_\mathrm{eff} = (\sum_i w_i)^2 / \sum_i w_i^2$ of a weighted dataset, computed from
+/// the normalized weights $w_i / \sum_j w_j$ such that it does not overflow for huge weights.
+pub(crate) fn effective_sample_size<F: Float>(weights: ArrayView1<F>) -> F {
+    let sum = compensated_sum(weights.iter().copied());
+    return compensated_sum(weights.iter().map(|w| (*w / sum) * (*w / sum))).recip();
+}
+
+/// Unbiased (weighted) variances of the components of the dataset `data` of shape `(n_points, dim)`, i.e. the
+/// diagonal of [`covariance`] without computing the off-diagonal elements.
+pub(crate) fn variance<F>(data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> Array1<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    return Array1::from_shape_fn(data.dim().1, |i| {
+        covariance(data.slice(s![.., i..=i]), weights)[[0, 0]]
+    });
+}
+
+/// Running (weighted) mean and sum of squared deviations of a dataset, which are updated point by point with the
+/// weighted variant of Welford's algorithm, such that the covariance matrix of a growing dataset is available without
+/// another pass over all points.
+#[derive(Debug, Clone)]
+pub(crate) struct RunningMoments<F> {
+    sum_weights: F,
+    sum_sq_weights: F,
+    mean: Array1<F>,
+    scatter: Array2<F>,
+}
+
+impl<F> RunningMoments<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Accumulate the moments of the dataset `data` of shape `(n_points, dim)`, where missing weights default to one.
+    pub(crate) fn new(data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> Self {
+        let dim = data.dim().1;
+        let mut moments = Self {
+            sum_weights: F::zero(),
+            sum_sq_weights: F::zero(),
+            mean: Array1::zeros(dim),
+            scatter: Array2::zeros((dim, dim)),
+        };
+        for (i, x) in data.rows().into_iter().enumerate() {
+            moments.push(x, weights.map_or(F::one(), |w| w[i]));
+        }
+        return moments;
+    }
+
+    /// Add the point `x` with weight `w`.
+    pub(crate) fn push(&mut self, x: ArrayView1<F>, w: F) {
+        self.sum_weights = self.sum_weights + w;
+        self.sum_sq_weights = self.sum_sq_weights + w * w;
+        let delta = &x - &self.mean;
+        self.mean.scaled_add(w / self.sum_weights, &delta);
+        let delta_new = &x - &self.mean;
+        Zip::from(self.scatter.rows_mut())
+            .and(&delta)
+            .for_each(|mut row, d| row.scaled_add(w * *d, &delta_new));
+    }
+
+    /// Remove the point `x` with weight `w`, which must have been added before, by reverting [`RunningMoments::push`].
+    pub(crate) fn remove(&mut self, x: ArrayView1<F>, w: F) {
+        self.sum_weights = self.sum_weights - w;
+        self.sum_sq_weights = self.sum_sq_weights - w * w;
+        let delta = &x - &self.mean;
+        if self.sum_weights > F::zero() {
+            self.mean.scaled_add(-w / self.sum_weights, &delta);
+        }
+        let delta_old = &x - &self.mean;
+        Zip::from(self.scatter.rows_mut())
+            .and(&delta_old)
+            .for_each(|mut row, d| row.scaled_add(-w * *d, &delta));
+    }
+
+    /// Unbiased covariance matrix with the normalization of [`covariance`], symmetrized against rounding errors of
+    /// the updates.
+    pub(crate) fn covariance(&self) -> Array2<F> {
+        let denom =
+            F::from(2).unwrap() * (self.sum_weights - self.sum_sq_weights / self.sum_weights);
+        return Array2::from_shape_fn(self.scatter.dim(), |(i, j)| {
+            (self.scatter[[i, j]] + self.scatter[[j, i]]) / denom
+        });
+    }
+}
+
+/// Shrink the covariance matrix `cov` towards the scaled identity with the intensity `lambda`, see
+/// [`CovarianceMode::Shrinkage`].
+pub(crate) fn shrunk_covariance<F>(cov: Array2<F>, lambda: F) -> Array2<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let mu = cov.diag().sum() / F::from(cov.dim().0).unwrap();
+    let mut res = cov.mapv_into(|c| (F::one() - lambda) * c);
+    res.diag_mut().mapv_inplace(|c| c + lambda * mu);
+    return res;
+}
+
+/// Ledoit-Wolf estimate of the optimal shrinkage intensity for the dataset `data` of shape `(n_points, dim)`.
+///
+/// With the normalized weights $p_k$ and the centered data points $x_k$, the (biased) covariance matrix is given by
+/// $S = \sum_k p_k x_k x_k^\top$ and the intensity by $\lambda = \min(\beta, \delta) / \delta$ with
+/// \[ \beta = \frac{1}{d \\, n_\mathrm{eff}} \left(\sum_k p_k \lVert x_k \rVert^4 - \lVert S \rVert_F^2\right),
+/// \quad \delta = \frac{1}{d} \left\lVert S - \frac{\operatorname{tr} S}{d} I \right\rVert_F^2. \]
+/// For unweighted data, this coincides with `sklearn.covariance.ledoit_wolf_shrinkage`.
+pub(crate) fn ledoit_wolf_shrinkage<F>(data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let (n, dim) = data.dim();
+    let p = weights.map_or_else(
+        || Array1::from_elem(n, F::one() / F::from(n).unwrap()),
+        |w| {
+            let sum = w.sum();
+            w.mapv(|w| w / sum)
+        },
+    );
+    let n_eff = p.dot(&p).recip();
+    let mean = data.t().dot(&p);
+    let centered = &data - &mean;
+    let weighted = &centered * &p.view().insert_axis(Axis(1));
+    let s = centered.t().dot(&weighted);
+    let d = F::from(dim).unwrap();
+    let mu = s.diag().sum() / d;
+    let frobenius = s.iter().fold(F::zero(), |acc, x| acc + *x * *x);
+    let fourth = Zip::from(centered.rows())
+        .and(&p)
+        .fold(F::zero(), |acc, x, p| acc + *p * x.dot(&x).powi(2));
+    let beta = (fourth - frobenius) / (d * n_eff);
+    let delta = (frobenius - F::from(2).unwrap() * mu * s.diag().sum() + d * mu * mu) / d;
+    let beta = beta.min(delta);
+    return if beta <= F::zero() {
+        F::zero()
+    } else {
+        beta / delta
+    };
+}
+
+/// Robust (weighted) covariance matrix of the dataset `data` of shape `(n_points, dim)`, see
+/// [`CovarianceMode::Robust`].
+pub(crate) fn robust_covariance<F>(data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> Array2<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let dim = data.dim().1;
+    let (q1, q3) = (F::from(0.25).unwrap(), F::from(0.75).unwrap());
+    let sd = Array1::from_shape_fn(dim, |i| {
+        let x = data.column(i);
+        (quantile(x, weights, q3) - quantile(x, weights, q1)) / F::from(1.349).unwrap()
+    });
+    let mut ranks = Array2::zeros(data.raw_dim());
+    for (x, mut r) in data.columns().into_iter().zip(ranks.columns_mut()) {
+        r.assign(&ranks_of(x, weights));
+    }
+    let rank_cov = covariance(ranks.view(), weights);
+    let six = F::from(6).unwrap();
+    return Array2::from_shape_fn((dim, dim), |(i, j)| {
+        if i == j {
+            sd[i] * sd[i]
+        } else {
+            let rho = rank_cov[[i, j]] / (rank_cov[[i, i]] * rank_cov[[j, j]]).sqrt();
+            F::from(2).unwrap() * F::sin(F::PI() * rho / six) * sd[i] * sd[j]
+        }
+    });
+}
+
+/// (Weighted) mid-ranks of `values`, i.e. the cumulative weight of all smaller values plus half of the weight of all
+/// values tied with the given one.
+fn ranks_of<F>(values: ArrayView1<F>, weights: Option<ArrayView1<F>>) -> Array1<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| values[*a].partial_cmp(&values[*b]).unwrap());
+    let weight = |i: usize| weights.map_or(F::one(), |w| w[i]);
+    let half = F::from(0.5).unwrap();
+    let mut res = Array1::zeros(values.len());
+    let mut cumulative = F::zero();
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start;
+        let mut tied = F::zero();
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            tied = tied + weight(order[end]);
+            end += 1;
+        }
+        for i in &order[start..end] {
+            res[*i] = cumulative + half * tied;
+        }
+        cumulative = cumulative + tied;
+        start = end;
+    }
+    return res;
+}
+
+/// (Weighted) quantile of `values` for the probability `p`.
+///
+/// The sorted values are placed at the cumulative positions $u_k = (C_k - w_k) / (W - w_n)$, where $C_k$ is the
+/// cumulative weight up to and including the $k$-th value and $W$ the total weight, and the quantile is obtained by
+/// linear interpolation. For equal weights, this coincides with the default quantile definition of R and `numpy`
+/// ("type 7").
+pub(crate) fn quantile<F>(values: ArrayView1<F>, weights: Option<ArrayView1<F>>, p: F) -> F
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| values[*a].partial_cmp(&values[*b]).unwrap());
+    let weight = |i: usize| weights.map_or(F::one(), |w| w[i]);
+    let total =
+        order.iter().fold(F::zero(), |acc, i| acc + weight(*i)) - weight(order[order.len() - 1]);
+    let mut cumulative = F::zero();
+    let mut prev = (F::zero(), values[order[0]]);
+    for i in order {
+        let u = cumulative / total;
+        if u >= p {
+            return if u == prev.0 {
+                values[i]
+            } else {
+                prev.1 + (p - prev.0) / (u - prev.0) * (values[i] - prev.1)
+            };
+        }
+        prev = (u, values[i]);
+        cumulative = cumulative + weight(i);
+    }
+    return prev.1;
+}
+
+#[cfg(test)]
+#[allow(clippy::excessive_precision)]
+mod tests {
+    use super::{ledoit_wolf_shrinkage, ranks_of, robust_covariance, shrunk_covariance};
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn ranks_test() {
+        let x = array![0.3, -1.0, 0.3, 2.0, 0.1];
+        assert_eq!(ranks_of(x.view(), None), array![3.0, 0.5, 3.0, 4.5, 1.5]);
+        let w = array![1.0, 2.0, 0.5, 1.0, 1.0];
+        assert_eq!(
+            ranks_of(x.view(), Some(w.view())),
+            array![3.75, 1.0, 3.75, 5.0, 2.5]
+        );
+    }
+
+    #[test]
+    fn robust_covariance_test() {
+        // Consistent for normally distributed data
+        let mut rng = Pcg64Mcg::seed_from_u64(17);
+        let cov = array![[4.0, 1.2, -0.2], [1.2, 1.0, 0.3], [-0.2, 0.3, 0.25]];
+        let l = crate::cholesky::cholesky_decomposition(cov.view()).unwrap();
+        let z = Array2::<f64>::from_shape_simple_fn((20_000, 3), || rng.sample(StandardNormal));
+        let mut data = z.dot(&l.t());
+        assert_relative_eq!(
+            robust_covariance(data.view(), None),
+            cov,
+            max_relative = 5e-2
+        );
+
+        // Insensitive to outliers
+        data.slice_mut(s![..100, ..])
+            .mapv_inplace(|x| 1e3 * x.signum());
+        assert_relative_eq!(
+            robust_covariance(data.view(), None),
+            cov,
+            max_relative = 1e-1
+        );
+    }
+
+    #[test]
+    fn ledoit_wolf_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let weights = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        // Reference values from `sklearn.covariance.ledoit_wolf_shrinkage(data)` and its weighted generalization
+        assert_relative_eq!(
+            ledoit_wolf_shrinkage(data.view(), None),
+            0.836994352142487,
+            max_relative = 1e-12
+        );
+        assert_relative_eq!(
+            ledoit_wolf_shrinkage(data.view(), Some(weights.view())),
+            0.2637799905054941,
+            max_relative = 1e-12
+        );
+
+        let cov = array![[2.0, 0.5], [0.5, 1.0]];
+        assert_eq!(shrunk_covariance(cov.clone(), 0.0), cov);
+        assert_eq!(shrunk_covariance(cov, 1.0), array![[1.5, 0.0], [0.0, 1.5]]);
+    }
+}
@@ -0,0 +1,286 @@
+//! Construction of KDEs from the columns of CSV files.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::{CowGaussianKDE, ErrorKind, KDEError};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// Options for reading the dataset of a KDE from CSV with [`GaussianKDE::from_csv_with_options`].
+///
+/// By default, the first row is a header with the column names, cells are separated by commas and cells which are not
+/// numbers are reported as `DataError`.
+#[derive(Debug, Clone, Copy)]
+pub struct CsvOptions {
+    has_headers: bool,
+    skip_invalid: bool,
+    delimiter: u8,
+}
+
+impl CsvOptions {
+    /// Create the default options of [`GaussianKDE::from_csv`].
+    pub fn new() -> Self {
+        return Self {
+            has_headers: true,
+            skip_invalid: false,
+            delimiter: b',',
+        };
+    }
+
+    /// Whether the first row is a header with the column names. Without a header, columns are selected by their
+    /// zero-based indices, e.g. `"0"` for the first column.
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        return self;
+    }
+
+    /// Whether rows with a cell in one of the selected columns which is not a number, including empty cells, are
+    /// skipped instead of reported as `DataError`. Non-finite numbers, e.g. `NaN`, are reported in both cases.
+    pub fn skip_invalid(mut self, skip_invalid: bool) -> Self {
+        self.skip_invalid = skip_invalid;
+        return self;
+    }
+
+    /// Separator of the cells, e.g. `b';'` or `b'\t'`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        return self;
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl<F> CowGaussianKDE<'static, F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Create a new kernel density estimator from the given columns of the CSV data read from `reader`, whose first
+    /// row is a header with the column names, using the default bandwidth choice
+    /// [`ScottBandwidth`](crate::ScottBandwidth). The cells of the named `columns` form the components of the data points in the given order and the cells of
+    /// `weight_column` (if given) their weights. See [`GaussianKDE::from_csv_with_options`] for CSV data without a
+    /// header or with invalid cells.
+    ///
+    /// Returns an `IndexError` if a column is missing, a `DataError` naming the line of the first cell which is not a
+    /// finite number and an `IoError` if the CSV data cannot be read, e.g. for rows of different lengths. Invalid
+    /// weights are reported as for [`GaussianKDE::new`].
+    pub fn from_csv(
+        reader: impl std::io::Read,
+        columns: &[&str],
+        weight_column: Option<&str>,
+    ) -> Result<Self, KDEError> {
+        return Self::from_csv_with_options(reader, columns, weight_column, CsvOptions::new());
+    }
+
+    /// Create a new kernel density estimator from the given columns of the CSV data read from `reader` as for
+    /// [`GaussianKDE::from_csv`] with the given options. Without a header, the `columns` and the `weight_column` are
+    /// zero-based column indices, e.g. `&["0", "2"]`.
+    ///
+    /// Returns an `IndexError` if a column is missing or not a valid index for CSV data without a header, a `DataError`
+    /// naming the line of the first cell which is not a finite number (or of the first non-finite number if invalid
+    /// cells are skipped) and an `IoError` if the CSV data cannot be read.
+    pub fn from_csv_with_options(
+        reader: impl std::io::Read,
+        columns: &[&str],
+        weight_column: Option<&str>,
+        options: CsvOptions,
+    ) -> Result<Self, KDEError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(options.has_headers)
+            .delimiter(options.delimiter)
+            .from_reader(reader);
+        let io_error = |e: csv::Error| {
+            KDEError::new(ErrorKind::IoError, format!("failed to read CSV data: {e}"))
+        };
+        let headers = if options.has_headers {
+            Some(reader.headers().map_err(io_error)?.clone())
+        } else {
+            None
+        };
+        let index = |column: &str| -> Result<usize, KDEError> {
+            return match headers {
+                Some(ref headers) => headers.iter().position(|h| h == column).ok_or_else(|| {
+                    KDEError::new(
+                        ErrorKind::IndexError,
+                        format!("column `{column}` not found in the CSV header `{}`", headers.as_slice()),
+                    )
+                }),
+                None => column.parse().map_err(|_| {
+                    KDEError::new(
+                        ErrorKind::IndexError,
+                        format!("expected a column index for CSV data without a header, found `{column}`"),
+                    )
+                }),
+            };
+        };
+        let indices = columns
+            .iter()
+            .map(|c| index(c))
+            .collect::<Result<Vec<_>, _>>()?;
+        let weight_index = weight_column.map(index).transpose()?;
+        let names = columns
+            .iter()
+            .copied()
+            .chain(weight_column)
+            .collect::<Vec<_>>();
+
+        let mut values = Vec::new();
+        let mut weights = Vec::new();
+        let mut row = Vec::with_capacity(names.len());
+        let mut n_points = 0;
+        for record in reader.records() {
+            let record = record.map_err(io_error)?;
+            let line = record.position().map_or(0, |p| p.line());
+            row.clear();
+            let mut invalid = false;
+            for (i, name) in indices.iter().chain(&weight_index).zip(&names) {
+                let Some(cell) = record.get(*i) else {
+                    return Err(KDEError::new(
+                        ErrorKind::IndexError,
+                        format!(
+                            "column `{name}` not found on line {line} with {} cells",
+                            record.len()
+                        ),
+                    ));
+                };
+                match F::from_str_radix(cell.trim(), 10) {
+                    Ok(x) if x.is_finite() => row.push(x),
+                    Ok(_) => {
+                        return Err(KDEError::new(
+                            ErrorKind::DataError,
+                            format!("non-finite value `{cell}` in column `{name}` on line {line}"),
+                        ));
+                    }
+                    Err(_) if options.skip_invalid => {
+                        invalid = true;
+                        break;
+                    }
+                    Err(_) => {
+                        return Err(KDEError::new(
+                            ErrorKind::DataError,
+                            format!(
+                                "failed to parse `{cell}` in column `{name}` on line {line} as a number"
+                            ),
+                        ));
+                    }
+                }
+            }
+            if invalid {
+                continue;
+            }
+            values.extend_from_slice(&row[..indices.len()]);
+            weights.extend_from_slice(&row[indices.len()..]);
+            n_points += 1;
+        }
+        let data = Array2::from_shape_vec((n_points, indices.len()), values).unwrap();
+        let weights = weight_index.map(|_| Array1::from_vec(weights));
+        return Self::new(data, weights);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CsvOptions, ErrorKind, GaussianKDE};
+    use ndarray::prelude::*;
+
+    const CSV: &str = "\
+name,x,w,y
+a,0.5,1.0,2.0
+b,1.5,0.5,1.0
+c,-0.5,2.0,3.5
+d,2.0,1.5,-1.0
+e,0.0,1.0,0.25
+";
+
+    #[test]
+    fn from_csv_test() {
+        let data = array![
+            [2.0, 0.5],
+            [1.0, 1.5],
+            [3.5, -0.5],
+            [-1.0, 2.0],
+            [0.25, 0.0]
+        ];
+        let weights = array![1.0, 0.5, 2.0, 1.5, 1.0];
+        let kde = GaussianKDE::<f64>::from_csv(CSV.as_bytes(), &["y", "x"], Some("w")).unwrap();
+        assert!(kde == GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap());
+        let kde = GaussianKDE::<f64>::from_csv(CSV.as_bytes(), &["y", "x"], None).unwrap();
+        assert!(kde == GaussianKDE::new(data.clone(), None).unwrap());
+
+        // Without a header and with another delimiter
+        let headerless = CSV
+            .lines()
+            .skip(1)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .replace(',', ";");
+        let options = CsvOptions::new().has_headers(false).delimiter(b';');
+        let kde = GaussianKDE::<f32>::from_csv_with_options(
+            headerless.as_bytes(),
+            &["3", "1"],
+            Some("2"),
+            options,
+        )
+        .unwrap();
+        assert!(
+            kde == GaussianKDE::new(data.mapv(|x| x as f32), Some(weights.mapv(|w| w as f32)))
+                .unwrap()
+        );
+        let res =
+            GaussianKDE::<f64>::from_csv_with_options(headerless.as_bytes(), &["y"], None, options);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+        let res =
+            GaussianKDE::<f64>::from_csv_with_options(headerless.as_bytes(), &["4"], None, options);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+
+        // Missing columns
+        let res = GaussianKDE::<f64>::from_csv(CSV.as_bytes(), &["x", "z"], None);
+        assert!(
+            matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError) && e.message.contains("`z`"))
+        );
+        let res = GaussianKDE::<f64>::from_csv(CSV.as_bytes(), &["x"], Some("weight"));
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+
+        // Malformed cells are reported with their line, or skipped
+        let malformed = CSV
+            .replace("c,-0.5,2.0,3.5", "c,-0.5,2.0,abc")
+            .replace("e,0.0,1.0", "e,0.0,");
+        let res = GaussianKDE::<f64>::from_csv(malformed.as_bytes(), &["y", "x"], Some("w"));
+        assert!(matches!(
+            res,
+            Err(e) if matches!(e.kind, ErrorKind::DataError) && e.message.contains("`abc` in column `y` on line 4")
+        ));
+        let res = GaussianKDE::<f64>::from_csv(malformed.as_bytes(), &["x"], Some("w"));
+        assert!(
+            matches!(res, Err(e) if matches!(e.kind, ErrorKind::DataError) && e.message.contains("line 6"))
+        );
+        let options = CsvOptions::new().skip_invalid(true);
+        let kde = GaussianKDE::<f64>::from_csv_with_options(
+            malformed.as_bytes(),
+            &["y", "x"],
+            Some("w"),
+            options,
+        )
+        .unwrap();
+        let expected = GaussianKDE::new(
+            data.select(Axis(0), &[0, 1, 3]),
+            Some(weights.select(Axis(0), &[0, 1, 3])),
+        )
+        .unwrap();
+        assert!(kde == expected);
+        let nan = CSV.replace("0.5,1.0,2.0", "0.5,1.0,NaN");
+        let res =
+            GaussianKDE::<f64>::from_csv_with_options(nan.as_bytes(), &["y", "x"], None, options);
+        assert!(
+            matches!(res, Err(e) if matches!(e.kind, ErrorKind::DataError) && e.message.contains("line 2"))
+        );
+
+        // Rows of different lengths
+        let ragged = format!("{CSV}f,1.0\n");
+        let res = GaussianKDE::<f64>::from_csv(ragged.as_bytes(), &["x"], None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IoError)));
+    }
+}
@@ -0,0 +1,151 @@
+//! Closed-form derivatives of the log-density. With the responsibilities $r_i(x) \propto w_i K_H(x - x_i)$,
+//! normalized to $\sum_i r_i = 1$, and $u_i = H^{-1}(x_i - x)$, the gradient and Hessian of $\ln f_\mathrm{KDE}$ read
+//! \\[ \nabla \ln f_\mathrm{KDE}(x) = \sum_i r_i u_i \quad \text{and} \quad \nabla^2 \ln f_\mathrm{KDE}(x) = \sum_i r_i u_i u_i^\top - H^{-1} - \nabla \ln f_\mathrm{KDE}(x) \nabla \ln f_\mathrm{KDE}(x)^\top. \\]
+
+use crate::summation::LnSumExp;
+use crate::{Bandwidth, CowGaussianKDE};
+use ndarray::{Zip, prelude::*};
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Evaluate the gradient $\nabla \ln f_\mathrm{KDE}(x)$ of the logarithm of the probability density at the
+    /// point `x`.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn ln_eval_gradient(&self, x: ArrayView1<F>) -> Array1<F> {
+        assert_eq!(x.dim(), self.n_dims());
+        return self.ln_derivatives(x, false).0;
+    }
+
+    /// Evaluate the Hessian $\nabla^2 \ln f_\mathrm{KDE}(x)$ of the logarithm of the probability density at the
+    /// point `x`, e.g. for a Laplace approximation around a mode of the density. The gradient required for the
+    /// Hessian is accumulated in the same pass over the data.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn ln_eval_hessian(&self, x: ArrayView1<F>) -> Array2<F> {
+        assert_eq!(x.dim(), self.n_dims());
+        let (gradient, second_moment) = self.ln_derivatives(x, true);
+        let gradient_2d = gradient.view().insert_axis(Axis(1));
+        return second_moment.unwrap() - self.inv_covariance() - gradient_2d.dot(&gradient_2d.t());
+    }
+
+    /// Responsibility-weighted averages $\sum_i r_i u_i$ and (optionally) $\sum_i r_i u_i u_i^\top$ over all kernels,
+    /// accumulated in a single pass. As in the log-space evaluation of the density, the running sums are rescaled
+    /// whenever the maximal kernel exponent increases to avoid underflow.
+    fn ln_derivatives(
+        &self,
+        x: ArrayView1<F>,
+        second_moment: bool,
+    ) -> (Array1<F>, Option<Array2<F>>) {
+        let dim = x.dim();
+        let half = F::from(0.5).unwrap();
+        let mut sum = LnSumExp::new();
+        let mut first = Array1::zeros(dim);
+        let mut second = second_moment.then(|| Array2::zeros((dim, dim)));
+        let mut accumulate = |xi: ArrayView1<F>, ln_w: F| {
+            let z = self.inv_cholesky.dot(&(&xi - &x));
+            let arg = ln_w - half * z.dot(&z);
+            if arg == F::neg_infinity() {
+                return;
+            }
+            let u = self.inv_cholesky.t().dot(&z);
+            let (rescale, scale) = sum.add(arg);
+            if rescale != F::one() {
+                first.mapv_inplace(|s| s * rescale);
+                if let Some(ref mut second) = second {
+                    second.mapv_inplace(|s| s * rescale);
+                }
+            }
+            first.scaled_add(scale, &u);
+            if let Some(ref mut second) = second {
+                Zip::from(second.rows_mut())
+                    .and(&u)
+                    .for_each(|mut row, u_j| {
+                        row.scaled_add(scale * *u_j, &u);
+                    });
+            }
+        };
+        let data = self.dataset();
+        if let Some(ref w) = self.weights {
+            Zip::from(data.rows())
+                .and(&**w)
+                .for_each(|xi, w| accumulate(xi, w.ln()));
+        } else {
+            for xi in data.rows() {
+                accumulate(xi, F::zero());
+            }
+        }
+        let sum = sum.rescaled_sum();
+        return (
+            first.mapv_into(|s| s / sum),
+            second.map(|second| second.mapv_into(|s| s / sum)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GaussianKDE;
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+
+    #[test]
+    fn ln_eval_hessian_test() {
+        let data = array![
+            [0.47782895, 0.69158108],
+            [0.80929817, 0.69522064],
+            [0.40165057, 0.67355606],
+            [0.61834332, 0.97825068],
+            [0.84709143, 0.80621183],
+            [0.43361213, 0.80696007]
+        ];
+        let w = array![
+            0.45459650, 0.00665601, 0.40898702, 0.81705163, 0.79906497, 0.27877430
+        ];
+        let kde = GaussianKDE::new(data, Some(w)).unwrap();
+        let step = 1e-5;
+        for x in [array![0.5, 0.7], array![0.9, 0.6], array![2.0, -1.0]] {
+            let gradient = kde.ln_eval_gradient(x.view());
+            let hessian = kde.ln_eval_hessian(x.view());
+            assert_relative_eq!(hessian, hessian.t(), max_relative = 1e-12);
+            for i in 0..2 {
+                let mut shift = Array1::zeros(2);
+                shift[i] = step;
+                let (plus, minus) = (&x + &shift, &x - &shift);
+                // Central finite differences of the log-density and its gradient
+                let fd_gradient =
+                    (kde.ln_eval(plus.view()) - kde.ln_eval(minus.view())) / (2.0 * step);
+                assert_relative_eq!(gradient[i], fd_gradient, max_relative = 1e-6);
+                let fd_hessian = (kde.ln_eval_gradient(plus.view())
+                    - kde.ln_eval_gradient(minus.view()))
+                    / (2.0 * step);
+                for j in 0..2 {
+                    assert_relative_eq!(hessian[[i, j]], fd_hessian[j], max_relative = 1e-6);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ln_eval_hessian_single_kernel_test() {
+        let h = array![[0.5, 0.2], [0.2, 0.3]];
+        let kde = GaussianKDE::with_bandwidth_matrix(array![[1.0, -2.0]], None, h).unwrap();
+        let mode = array![1.0, -2.0];
+        assert_relative_eq!(kde.ln_eval_gradient(mode.view()), Array1::zeros(2));
+        assert_relative_eq!(
+            kde.ln_eval_hessian(mode.view()),
+            -kde.inv_covariance(),
+            max_relative = 1e-12
+        );
+        // The Hessian of a single Gaussian is constant
+        assert_relative_eq!(
+            kde.ln_eval_hessian(array![3.0, 5.0].view()),
+            -kde.inv_covariance(),
+            max_relative = 1e-12
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Human-readable summaries of KDEs for debugging and logging, which do not print the (possibly huge) dataset.
+
+use crate::{Bandwidth, CowGaussianKDE};
+use core::fmt::{Debug, Display, Formatter, Result};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// Maximum number of entries of an array printed by the `Debug` implementation of [`CowGaussianKDE`].
+const MAX_ENTRIES: usize = 8;
+
+/// Debug formatting of a one-dimensional array, which prints at most [`MAX_ENTRIES`] entries followed by the number of
+/// omitted entries.
+struct Truncated<F>(Array1<F>, usize);
+
+impl<F: Debug> Debug for Truncated<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut list = f.debug_list();
+        list.entries(self.0.iter());
+        if self.1 > self.0.len() {
+            list.entry(&format_args!("... {} more", self.1 - self.0.len()));
+        }
+        return list.finish();
+    }
+}
+
+/// Summarize the KDE by its number of data points, dimension, whether the data points are weighted, the bandwidth
+/// selector and factor, and the diagonal of the kernel covariance, which is truncated in high dimensions. The dataset
+/// itself is not printed.
+impl<F, B> Debug for CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + Debug + 'static,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        // Diagonal of the kernel covariance $H = LL^\top$ from the rows of its Cholesky factor
+        let diagonal = self
+            .cholesky
+            .rows()
+            .into_iter()
+            .take(MAX_ENTRIES)
+            .map(|row| row.dot(&row))
+            .collect::<Array1<F>>();
+        return f
+            .debug_struct("GaussianKDE")
+            .field("n_points", &self.n_points())
+            .field("dim", &self.n_dims())
+            .field("weighted", &self.weights.is_some())
+            .field(
+                "bandwidth",
+                &format_args!("{}", core::any::type_name::<B>()),
+            )
+            .field("bandwidth_factor", &self.bandwidth_factor)
+            .field("covariance_diagonal", &Truncated(diagonal, self.n_dims()))
+            .finish();
+    }
+}
+
+/// One-line summary of the KDE, e.g. `Gaussian KDE of 1000 weighted points in 3 dimensions with bandwidth factor
+/// 0.316`. A precision given in the format string applies to the bandwidth factor.
+impl<F, B> Display for CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + Display + 'static,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let weighted = if self.weights.is_some() {
+            " weighted"
+        } else {
+            ""
+        };
+        let dimensions = if self.n_dims() == 1 {
+            "dimension"
+        } else {
+            "dimensions"
+        };
+        write!(
+            f,
+            "Gaussian KDE of {}{} points in {} {} with bandwidth factor ",
+            self.n_points(),
+            weighted,
+            self.n_dims(),
+            dimensions
+        )?;
+        return match f.precision() {
+            Some(precision) => write!(f, "{:.*}", precision, self.bandwidth_factor),
+            None => write!(f, "{}", self.bandwidth_factor),
+        };
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::excessive_precision)]
+mod tests {
+    use crate::GaussianKDE;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn debug_display_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let h = kde.bandwidth_factor();
+        let debug = format!("{kde:?}");
+        assert!(debug.starts_with("GaussianKDE {"));
+        assert!(debug.contains("n_points: 10"));
+        assert!(debug.contains("dim: 3"));
+        assert!(debug.contains("weighted: false"));
+        assert!(debug.contains("ScottBandwidth"));
+        assert!(debug.contains(&format!("bandwidth_factor: {h:?}")));
+        let diagonal = kde.covariance().diag().to_vec();
+        assert!(debug.contains(&format!("covariance_diagonal: {diagonal:?}")));
+        assert!(format!("{kde:#?}").lines().count() > 3);
+
+        assert_eq!(
+            format!("{kde:.3}"),
+            format!("Gaussian KDE of 10 points in 3 dimensions with bandwidth factor {h:.3}")
+        );
+        let marginal = GaussianKDE::new(data, Some(Array1::ones(10)))
+            .unwrap()
+            .marginalize(&[0, 1])
+            .unwrap();
+        assert!(
+            format!("{marginal}").starts_with("Gaussian KDE of 10 weighted points in 1 dimension ")
+        );
+        assert!(format!("{marginal:?}").contains("weighted: true"));
+
+        // The output does not grow with the size of the dataset and truncates high dimensions
+        let mut rng = Pcg64Mcg::seed_from_u64(99);
+        let data =
+            Array2::<f64>::from_shape_simple_fn((1_000_000, 1), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data, None).unwrap();
+        assert!(format!("{kde:?}").len() < 300);
+        assert!(format!("{kde}").len() < 100);
+        let data = Array2::<f64>::from_shape_simple_fn((200, 50), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let debug = format!("{kde:?}");
+        assert!(debug.len() < 500);
+        assert!(debug.contains("... 42 more"));
+    }
+}
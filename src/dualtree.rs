@@ -0,0 +1,263 @@
+//! Dual-tree evaluation of KDEs at large batches of points, which organizes both the dataset and the points of
+//! evaluation in kd-trees and approximates the contributions of pairs of distant nodes at once.
+//!
+//! For each pair of a query node $Q$ and a reference (data) node $R$ with total weight $W_R$, the contribution of $R$
+//! to the kernel sum at any point of $Q$ lies between $W_R K_\mathrm{min}$ and $W_R K_\mathrm{max}$, where the kernels
+//! are evaluated at the largest and smallest distance between the bounding boxes. The pair is approximated by the
+//! midpoint of both bounds if its error $W_R (K_\mathrm{max} - K_\mathrm{min}) / 2$ is at most
+//! $\epsilon \, l_Q W_R / W$, where $l_Q$ is a lower bound of the kernel sum at all points of $Q$ accumulated so far
+//! and $W$ the total weight. Summing over the reference nodes, the error at each point is at most $\epsilon$ times its
+//! kernel sum. See Gray and Moore, *Nonparametric Density Estimation: Toward Computational Tractability*, SDM 2003.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::kdtree::KdTree;
+use crate::summation::compensated_sum;
+use crate::{Bandwidth, CowGaussianKDE};
+use alloc::{vec, vec::Vec};
+use ndarray::{Zip, prelude::*};
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// State of the dual-tree traversal. The kernel sums of the query points are accumulated per point for exactly
+/// evaluated pairs and per query node for approximated ones, which apply to all points of the node and are only
+/// distributed to the points at the end. The lower bounds of the approximated contributions are recorded alongside.
+struct DualTree<'t, F> {
+    queries: &'t KdTree<F>,
+    data: &'t KdTree<F>,
+    // Total weight of each data node
+    weights: Vec<F>,
+    rtol: F,
+    sums: Array1<F>,
+    node_sums: Vec<F>,
+    node_lower: Vec<F>,
+    // Lower bound of the kernel sum at all points of each query node, excluding the contributions recorded at its
+    // ancestors
+    node_bounds: Vec<F>,
+}
+
+impl<F: Float + 'static> DualTree<'_, F> {
+    /// Accumulate the contributions of the data node `r` to the points of the query node `q`, where `inherited` is
+    /// the lower bound recorded at the ancestors of `q`.
+    fn visit(&mut self, q: usize, r: usize, inherited: F) {
+        let half = F::from(0.5).unwrap();
+        let (min, max) = self.sq_distance_bounds(q, r);
+        let (k_max, k_min) = (F::exp(-half * min), F::exp(-half * max));
+        let weight = self.weights[r];
+        let bound = inherited + self.node_bounds[q];
+        if (k_max - k_min) * self.data.total_weight <= F::from(2).unwrap() * self.rtol * bound {
+            self.node_sums[q] = self.node_sums[q] + half * weight * (k_max + k_min);
+            self.node_lower[q] = self.node_lower[q] + weight * k_min;
+            self.node_bounds[q] = self.node_bounds[q] + weight * k_min;
+            return;
+        }
+        match (self.queries.nodes[q].children, self.data.nodes[r].children) {
+            (None, None) => self.visit_leaves(q, r),
+            (None, Some(children)) => self.visit_closer_first(q, children, inherited),
+            (Some((left, right)), data_children) => {
+                let inherited = inherited + self.node_lower[q];
+                for child in [left, right] {
+                    match data_children {
+                        Some(children) => self.visit_closer_first(child, children, inherited),
+                        None => self.visit(child, r, inherited),
+                    }
+                }
+                self.node_bounds[q] =
+                    self.node_lower[q] + self.node_bounds[left].min(self.node_bounds[right]);
+            }
+        }
+    }
+
+    /// Visit the pairs of the query node `q` and both data nodes `children`, starting with the closer one.
+    fn visit_closer_first(&mut self, q: usize, children: (usize, usize), inherited: F) {
+        let (left, right) = children;
+        let (d_left, _) = self.sq_distance_bounds(q, left);
+        let (d_right, _) = self.sq_distance_bounds(q, right);
+        let (first, second) = if d_left <= d_right {
+            (left, right)
+        } else {
+            (right, left)
+        };
+        self.visit(q, first, inherited);
+        self.visit(q, second, inherited);
+    }
+
+    /// Evaluate the contributions of the points of the data leaf `r` to the points of the query leaf `q` exactly.
+    fn visit_leaves(&mut self, q: usize, r: usize) {
+        let half = F::from(0.5).unwrap();
+        let (queries, data) = (&self.queries.nodes[q], &self.data.nodes[r]);
+        let dim = self.data.points.ncols();
+        // Both trees store their points in standard layout
+        let points = self.data.points.as_slice().unwrap();
+        let mut bound = F::infinity();
+        for i in queries.start..queries.end {
+            let z = self.queries.points.row(i);
+            let z = z.as_slice().unwrap();
+            let mut sum = F::zero();
+            for j in data.start..data.end {
+                let mut q = F::zero();
+                for (a, b) in points[j * dim..(j + 1) * dim].iter().zip(z) {
+                    q = q + (*a - *b) * (*a - *b);
+                }
+                let w = self.data.weights.as_ref().map_or(F::one(), |w| w[j]);
+                sum = sum + w * F::exp(-half * q);
+            }
+            self.sums[i] = self.sums[i] + sum;
+            bound = bound.min(self.sums[i]);
+        }
+        self.node_bounds[q] = self.node_lower[q] + bound;
+    }
+
+    /// Smallest and largest squared distance between the bounding boxes of the query node `q` and the data node `r`.
+    fn sq_distance_bounds(&self, q: usize, r: usize) -> (F, F) {
+        let (q, r) = (&self.queries.nodes[q], &self.data.nodes[r]);
+        return Zip::from(&q.lower)
+            .and(&q.upper)
+            .and(&r.lower)
+            .and(&r.upper)
+            .fold(
+                (F::zero(), F::zero()),
+                |(min, max), q_lower, q_upper, r_lower, r_upper| {
+                    let d_min = (*r_lower - *q_upper)
+                        .max(*q_lower - *r_upper)
+                        .max(F::zero());
+                    let d_max = (*q_upper - *r_lower).max(*r_upper - *q_lower);
+                    (min + d_min * d_min, max + d_max * d_max)
+                },
+            );
+    }
+
+    /// Add the sums recorded at the query node `q` and its ancestors (`inherited`) to its points.
+    fn distribute(&mut self, q: usize, inherited: F) {
+        let inherited = inherited + self.node_sums[q];
+        let node = &self.queries.nodes[q];
+        match node.children {
+            Some((left, right)) => {
+                self.distribute(left, inherited);
+                self.distribute(right, inherited);
+            }
+            None => {
+                for i in node.start..node.end {
+                    self.sums[i] = self.sums[i] + inherited;
+                }
+            }
+        }
+    }
+}
+
+/// Total weight of each node of `tree`, computed bottom-up since the children follow their parent.
+fn node_weights<F: Float + 'static>(tree: &KdTree<F>) -> Vec<F> {
+    let mut weights = vec![F::zero(); tree.nodes.len()];
+    for (index, node) in tree.nodes.iter().enumerate().rev() {
+        weights[index] = match node.children {
+            Some((left, right)) => weights[left] + weights[right],
+            None => match tree.weights {
+                Some(ref w) => compensated_sum(w.slice(s![node.start..node.end]).iter().copied()),
+                None => F::from(node.end - node.start).unwrap(),
+            },
+        };
+    }
+    return weights;
+}
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Evaluate the probability density estimated by the KDE at multiple points given by the array `x` up to the
+    /// relative tolerance `rtol` with the dual-tree algorithm.
+    ///
+    /// Both the (whitened) dataset and the points `x` are organized in kd-trees, and the contributions of pairs of
+    /// nodes far apart are approximated at once. For large batches of points in few dimensions, the cost grows
+    /// considerably slower than the product of the numbers of data points and points of evaluation, e.g. evaluating
+    /// a two-dimensional KDE of $4 \cdot 10^4$ points on as many grid points is about ten times faster than
+    /// [`GaussianKDE::eval_batch`] for `rtol = 1e-6`, and the advantage grows with the size. The tree of the dataset
+    /// is the one of [`GaussianKDE::eval_approx`], which is cached, while the tree of the points is built on each
+    /// call. The results are within `rtol` of the exact densities, and for `rtol = 0`, they are exact.
+    ///
+    /// The points are expected to be given as array of shape `(n_points, dim)`.
+    ///
+    /// **Panic**s if the dimension of `x` does not match the dimension of the KDE dataset or if `rtol` is not in
+    /// $[0, 1)$.
+    pub fn eval_batch_dualtree(&self, x: ArrayView2<F>, rtol: F) -> Array1<F> {
+        assert_eq!(x.dim().1, self.n_dims());
+        assert!(
+            rtol >= F::zero() && rtol < F::one(),
+            "the relative tolerance must be in [0, 1)"
+        );
+        if rtol == F::zero() || x.nrows() == 0 {
+            return self.eval_batch(x);
+        }
+        let data = self.kd_tree();
+        let z = (&x - &data.center).dot(&self.inv_cholesky.t());
+        let queries = KdTree::new(data.center.clone(), z.view(), None);
+        let n = x.nrows();
+        let mut traversal = DualTree {
+            queries: &queries,
+            data,
+            weights: node_weights(data),
+            rtol,
+            sums: Array1::zeros(n),
+            node_sums: vec![F::zero(); queries.nodes.len()],
+            node_lower: vec![F::zero(); queries.nodes.len()],
+            node_bounds: vec![F::zero(); queries.nodes.len()],
+        };
+        traversal.visit(0, 0, F::zero());
+        traversal.distribute(0, F::zero());
+
+        let mut res = Array1::zeros(n);
+        for (i, sum) in queries.order.iter().zip(traversal.sums.iter()) {
+            res[*i] = if self.normalization.is_normal() {
+                *sum * self.normalization
+            } else {
+                F::exp(sum.ln() + self.ln_normalization)
+            };
+        }
+        return res;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GaussianKDE;
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn eval_batch_dualtree_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(79);
+        let mut data =
+            Array2::<f64>::from_shape_simple_fn((5000, 2), || rng.sample(StandardNormal));
+        let x0 = data.column(0).to_owned();
+        data.column_mut(1).scaled_add(-0.6, &x0);
+        let weights = Array1::from_shape_simple_fn(5000, || rng.random_range(0.0..1.0));
+        // Grid covering the bulk and the tails of the density
+        let grid = Array1::linspace(-6.0, 6.0, 25);
+        let x = Array2::from_shape_fn(
+            (625, 2),
+            |(i, k)| if k == 0 { grid[i / 25] } else { grid[i % 25] },
+        );
+
+        for weights in [None, Some(weights)] {
+            let kde = GaussianKDE::new(data.clone(), weights).unwrap();
+            let exact = kde.eval_batch(x.view());
+            for rtol in [1e-3, 1e-9] {
+                let approx = kde.eval_batch_dualtree(x.view(), rtol);
+                for (approx, exact) in approx.iter().zip(exact.iter()) {
+                    assert!((approx - exact).abs() <= rtol * exact * (1.0 + 1e-9));
+                }
+            }
+            assert_relative_eq!(kde.eval_batch_dualtree(x.view(), 0.0), exact);
+            // Single points and (unsorted) query points outside of the dataset
+            let x = array![[4.5, -2.0], [0.1, 0.2]];
+            assert_relative_eq!(
+                kde.eval_batch_dualtree(x.view(), 1e-9),
+                kde.eval_batch(x.view()),
+                max_relative = 1e-9
+            );
+        }
+    }
+}
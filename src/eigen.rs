@@ -0,0 +1,107 @@
+//! Eigendecomposition of the (small) symmetric covariance matrices of a dataset, reimplemented with the cyclic Jacobi
+//! method for the same reason as the Cholesky decomposition, i.e. to avoid a dependency on LAPACK.
+
+use alloc::vec::Vec;
+use ndarray::prelude::*;
+use num_traits::{Float, FromPrimitive};
+
+/// Maximal number of sweeps over all off-diagonal elements, which is never reached in practice since the Jacobi
+/// method converges quadratically.
+const MAX_SWEEPS: usize = 100;
+
+/// Eigendecomposition $M = Q \Lambda Q^\top$ of the symmetric matrix `m`, returning the eigenvalues in descending
+/// order and the orthonormal matrix $Q$ with the corresponding eigenvectors as columns.
+pub(crate) fn symmetric_eigen<F>(m: ArrayView2<F>) -> (Array1<F>, Array2<F>)
+where
+    F: Float + FromPrimitive + 'static,
+{
+    let n = m.dim().0;
+    let mut a = m.to_owned();
+    let mut q = Array2::<F>::eye(n);
+    let two = F::from(2).unwrap();
+    for _ in 0..MAX_SWEEPS {
+        let mut off_diagonal = F::zero();
+        let mut total = F::zero();
+        for ((i, j), x) in a.indexed_iter() {
+            total = total + *x * *x;
+            if i != j {
+                off_diagonal = off_diagonal + *x * *x;
+            }
+        }
+        if off_diagonal <= F::epsilon() * F::epsilon() * total {
+            break;
+        }
+        for p in 0..n {
+            for r in (p + 1)..n {
+                if a[[p, r]] == F::zero() {
+                    continue;
+                }
+                // Rotation in the (p, r)-plane annihilating the element a_pr
+                let theta = (a[[r, r]] - a[[p, p]]) / (two * a[[p, r]]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + F::one()).sqrt());
+                let c = (t * t + F::one()).sqrt().recip();
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akr) = (a[[k, p]], a[[k, r]]);
+                    a[[k, p]] = c * akp - s * akr;
+                    a[[k, r]] = s * akp + c * akr;
+                }
+                for k in 0..n {
+                    let (apk, ark) = (a[[p, k]], a[[r, k]]);
+                    a[[p, k]] = c * apk - s * ark;
+                    a[[r, k]] = s * apk + c * ark;
+                }
+                for k in 0..n {
+                    let (qkp, qkr) = (q[[k, p]], q[[k, r]]);
+                    q[[k, p]] = c * qkp - s * qkr;
+                    q[[k, r]] = s * qkp + c * qkr;
+                }
+            }
+        }
+    }
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_by(|i, j| a[[*j, *j]].partial_cmp(&a[[*i, *i]]).unwrap());
+    let eigenvalues = order.iter().map(|i| a[[*i, *i]]).collect::<Array1<F>>();
+    return (eigenvalues, q.select(Axis(1), &order));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::symmetric_eigen;
+    use approx::assert_abs_diff_eq;
+    use ndarray::prelude::*;
+
+    #[test]
+    fn symmetric_eigen_test() {
+        #[rustfmt::skip]
+        let m = array![
+            [2.0542973311512913, 1.9711183024062744, 1.2585589835749347, 1.5690403985438703],
+            [1.9711183024062744, 2.1801084607977526, 1.2007754146681564, 1.543014147477119 ],
+            [1.2585589835749347, 1.2007754146681564, 1.1775143453668928, 1.0301046678804817],
+            [1.5690403985438703, 1.543014147477119 , 1.0301046678804817, 1.7990191449143589]
+        ];
+        let (eigenvalues, q) = symmetric_eigen(m.view());
+        assert!(eigenvalues.windows(2).into_iter().all(|w| w[0] >= w[1]));
+        assert_abs_diff_eq!(q.t().dot(&q), Array2::eye(4), epsilon = 1e-14);
+        assert_abs_diff_eq!(
+            q.dot(&Array2::from_diag(&eigenvalues)).dot(&q.t()),
+            m,
+            epsilon = 1e-13
+        );
+        assert_abs_diff_eq!(eigenvalues.sum(), m.diag().sum(), epsilon = 1e-13);
+
+        // Rank-deficient matrix with a two-dimensional null space
+        let u = array![[1.0, 2.0], [0.5, -1.0], [0.0, 1.0], [-1.0, 0.3]];
+        let (eigenvalues, q) = symmetric_eigen(u.dot(&u.t()).view());
+        assert_abs_diff_eq!(
+            eigenvalues.slice(s![2..]),
+            array![0.0, 0.0],
+            epsilon = 1e-14
+        );
+        assert_abs_diff_eq!(
+            u.t().dot(&q.slice(s![.., 2..])),
+            Array2::zeros((2, 2)),
+            epsilon = 1e-14
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Information-theoretic quantities of KDEs, estimated from evaluations of the density at the data points or at
+//! samples drawn from the KDE.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::{Bandwidth, CowGaussianKDE, ErrorKind, KDEError};
+use alloc::format;
+use ndarray::{Zip, prelude::*};
+use num_traits::{Float, FloatConst, FromPrimitive};
+#[cfg(feature = "sample")]
+use rand::prelude::*;
+#[cfg(feature = "sample")]
+use rand_distr::{StandardNormal, uniform::SampleUniform, weighted::Weight};
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Estimate the differential entropy $-\int f(x) \ln f(x) \\, \mathrm{d}x$ of the density underlying the dataset
+    /// by resubstitution, i.e. as the (weighted) average
+    /// \\[ \hat S = -\frac{1}{\sum_i w_i} \sum_{i=1}^n w_i \ln f_\mathrm{KDE}(x_i) \\]
+    /// over the data points.
+    ///
+    /// Since each data point contributes to the density at its own position, $f_\mathrm{KDE}(x_i)$ is biased upwards
+    /// and the estimate is biased towards lower entropies, in particular for small bandwidths. For a density
+    /// estimate with reasonable bandwidth and a moderate number of data points, the bias is small compared to the
+    /// statistical uncertainty. The cost is $\mathcal{O}(n^2)$ kernel evaluations.
+    pub fn entropy(&self) -> F {
+        return -self.data_mean(self.self_log_densities(false));
+    }
+
+    /// Estimate the Kullback-Leibler divergence
+    /// \\[ D_\mathrm{KL}(f \\,\\|\\, g) = \int f(x) \ln \frac{f(x)}{g(x)} \\, \mathrm{d}x \\]
+    /// of the density $g$ estimated by `other` from the density $f$ underlying the dataset of `self` by
+    /// resubstitution, i.e. as the (weighted) average of $\ln f_\mathrm{KDE}(x_i) - \ln g_\mathrm{KDE}(x_i)$ over
+    /// the data points of `self`.
+    ///
+    /// As for [`GaussianKDE::entropy`], $f_\mathrm{KDE}(x_i)$ is biased upwards by the contribution of $x_i$ itself,
+    /// so the estimate is biased towards larger divergences.
+    ///
+    /// Returns a `ShapeError` if the dimensions of the KDEs do not match.
+    pub fn kl_divergence_resubstitution<B2>(
+        &self,
+        other: &CowGaussianKDE<'_, F, B2>,
+    ) -> Result<F, KDEError>
+    where
+        B2: Bandwidth<F>,
+    {
+        self.check_same_dimension(other)?;
+        let data = self.dataset();
+        let ln_ratio = self.ln_eval_batch(data.view()) - other.ln_eval_batch(data.view());
+        return Ok(self.data_mean(ln_ratio));
+    }
+
+    /// (Weighted) mean of `values` given at the data points.
+    fn data_mean(&self, values: Array1<F>) -> F {
+        return if let Some(ref w) = self.weights {
+            Zip::from(&values)
+                .and(&**w)
+                .fold(F::zero(), |acc, v, w| acc + *w * *v)
+                / w.sum()
+        } else {
+            values.mean().unwrap()
+        };
+    }
+
+    /// Return a `ShapeError` if the KDE `other` has a different dimension than `self`.
+    fn check_same_dimension<B2>(&self, other: &CowGaussianKDE<'_, F, B2>) -> Result<(), KDEError>
+    where
+        B2: Bandwidth<F>,
+    {
+        if other.n_dims() != self.n_dims() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "cannot compare KDEs of different dimensions {} and {}",
+                    self.n_dims(),
+                    other.n_dims()
+                ),
+            ));
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(feature = "sample")]
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + 'static,
+    StandardNormal: Distribution<F>,
+{
+    /// Estimate the differential entropy $-\int f_\mathrm{KDE}(x) \ln f_\mathrm{KDE}(x) \\, \mathrm{d}x$ of the KDE
+    /// by Monte Carlo integration with `n_samples` points drawn from the KDE using `rng`. Returns the estimate and its
+    /// standard error.
+    ///
+    /// In contrast to [`GaussianKDE::entropy`], this is an unbiased estimate of the entropy of the KDE itself.
+    /// However, the smoothing with the kernels increases the entropy with respect to the density underlying the
+    /// dataset, such that the estimate is biased towards higher entropies, in particular for large bandwidths.
+    ///
+    /// Returns a `DomainError` if `n_samples` is less than two, since the standard error requires at least two
+    /// samples.
+    pub fn entropy_mc(&self, n_samples: usize, rng: &mut impl Rng) -> Result<(F, F), KDEError> {
+        check_n_samples(n_samples)?;
+        let samples = self.sample_batch_with_rng(n_samples, rng);
+        return Ok(mean_and_standard_error(
+            self.ln_eval_batch(samples.view()).mapv_into(|x| -x),
+        ));
+    }
+
+    /// Estimate the Kullback-Leibler divergence $D_\mathrm{KL}(f_\mathrm{KDE} \\,\\|\\, g_\mathrm{KDE})$ of the KDE
+    /// `other` from this KDE as the average of $\ln f_\mathrm{KDE}(x) - \ln g_\mathrm{KDE}(x)$ over `n_samples`
+    /// points drawn from this KDE using `rng`.
+    ///
+    /// This is an unbiased estimate of the divergence between the KDEs themselves. As the smoothing with the kernels
+    /// makes both densities more similar, it is biased towards smaller divergences with respect to the underlying
+    /// densities, in particular for large bandwidths.
+    ///
+    /// Returns a `ShapeError` if the dimensions of the KDEs do not match and a `DomainError` if `n_samples` is less
+    /// than two.
+    pub fn kl_divergence<B2>(
+        &self,
+        other: &CowGaussianKDE<'_, F, B2>,
+        n_samples: usize,
+        rng: &mut impl Rng,
+    ) -> Result<F, KDEError>
+    where
+        B2: Bandwidth<F>,
+    {
+        return Ok(self.kl_divergence_with_error(other, n_samples, rng)?.0);
+    }
+
+    /// Like [`GaussianKDE::kl_divergence`], but additionally returns the standard error of the Monte Carlo estimate.
+    pub fn kl_divergence_with_error<B2>(
+        &self,
+        other: &CowGaussianKDE<'_, F, B2>,
+        n_samples: usize,
+        rng: &mut impl Rng,
+    ) -> Result<(F, F), KDEError>
+    where
+        B2: Bandwidth<F>,
+    {
+        self.check_same_dimension(other)?;
+        check_n_samples(n_samples)?;
+        let samples = self.sample_batch_with_rng(n_samples, rng);
+        let ln_ratio = self.ln_eval_batch(samples.view()) - other.ln_eval_batch(samples.view());
+        return Ok(mean_and_standard_error(ln_ratio));
+    }
+}
+
+/// Return a `DomainError` if fewer than two samples are requested for a Monte Carlo estimate with standard error.
+#[cfg(feature = "sample")]
+fn check_n_samples(n_samples: usize) -> Result<(), KDEError> {
+    if n_samples < 2 {
+        return Err(KDEError::new(
+            ErrorKind::DomainError,
+            format!(
+                "at least two samples are required for the Monte Carlo estimate, found {n_samples}"
+            ),
+        ));
+    }
+    return Ok(());
+}
+
+/// Sample mean of `values` and its standard error.
+#[cfg(feature = "sample")]
+pub(crate) fn mean_and_standard_error<F>(values: Array1<F>) -> (F, F)
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let n = F::from(values.len()).unwrap();
+    let mean = values.sum() / n;
+    let variance =
+        values.fold(F::zero(), |acc, x| acc + (*x - mean) * (*x - mean)) / (n - F::one());
+    return (mean, (variance / n).sqrt());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ErrorKind, GaussianKDE};
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+    use std::f64::consts::{E, PI};
+
+    #[test]
+    fn entropy_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(2);
+        // 1D normal distribution with standard deviation 2
+        let data =
+            Array2::from_shape_simple_fn((1000, 1), || 2.0 * rng.sample::<f64, _>(StandardNormal));
+        let exact = 0.5 * (2.0 * PI * E * 4.0).ln();
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        assert_relative_eq!(kde.entropy(), exact, max_relative = 3e-2);
+        // Equal weights do not change the estimate
+        let kde_w = GaussianKDE::new(data, Some(Array1::from_elem(1000, 0.3))).unwrap();
+        assert_relative_eq!(kde_w.entropy(), kde.entropy(), max_relative = 1e-12);
+
+        // Correlated 2D normal distribution
+        let cov = array![[1.0, 0.8], [0.8, 2.0]];
+        let l = crate::cholesky::cholesky_decomposition(cov.view()).unwrap();
+        let z = Array2::<f64>::from_shape_simple_fn((1000, 2), || rng.sample(StandardNormal));
+        let exact = 0.5 * ((2.0 * PI * E).powi(2) * (2.0 - 0.64)).ln();
+        let kde = GaussianKDE::new(z.dot(&l.t()), None).unwrap();
+        assert_relative_eq!(kde.entropy(), exact, max_relative = 3e-2);
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn entropy_mc_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(4);
+        let cov = array![[1.0, 0.8], [0.8, 2.0]];
+        let l = crate::cholesky::cholesky_decomposition(cov.view()).unwrap();
+        let z = Array2::<f64>::from_shape_simple_fn((1000, 2), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(z.dot(&l.t()), None).unwrap();
+        let (entropy, err) = kde.entropy_mc(4000, &mut rng).unwrap();
+        let exact = 0.5 * ((2.0 * PI * E).powi(2) * (2.0 - 0.64)).ln();
+        assert!(err > 0.0 && err < 2e-2);
+        assert_relative_eq!(entropy, exact, max_relative = 3e-2);
+        // The smoothing increases the entropy
+        assert!(entropy > kde.entropy());
+
+        for n_samples in [0, 1] {
+            let res = kde.entropy_mc(n_samples, &mut rng);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+    }
+
+    #[test]
+    fn kl_divergence_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(5);
+        // Unit normal distributions with means differing by 1 in 1D, the exact divergence is 1/2
+        let f_data = Array2::<f64>::from_shape_simple_fn((2000, 1), || rng.sample(StandardNormal));
+        let g_data = Array2::<f64>::from_shape_simple_fn((2000, 1), || {
+            1.0 + rng.sample::<f64, _>(StandardNormal)
+        });
+        let f = GaussianKDE::new(f_data, None).unwrap();
+        let g = GaussianKDE::new(g_data, None).unwrap();
+        assert_relative_eq!(
+            f.kl_divergence_resubstitution(&g).unwrap(),
+            0.5,
+            max_relative = 0.1
+        );
+        assert_relative_eq!(f.kl_divergence_resubstitution(&f).unwrap(), 0.0);
+
+        let g_2d = GaussianKDE::new(
+            Array2::<f64>::from_shape_simple_fn((10, 2), || rng.sample(StandardNormal)),
+            None,
+        )
+        .unwrap();
+        let res = f.kl_divergence_resubstitution(&g_2d);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn kl_divergence_mc_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(6);
+        // 2D normal distributions with covariance `cov` and means differing by `delta`, the exact divergence is
+        // delta^T cov^-1 delta / 2
+        let cov = array![[1.0, 0.8], [0.8, 2.0]];
+        let delta = array![0.5, 0.5];
+        let exact = 0.5 * (2.0 * 0.25 - 0.4 + 0.25) / 1.36;
+        let l = crate::cholesky::cholesky_decomposition(cov.view()).unwrap();
+        let z = Array2::<f64>::from_shape_simple_fn((1000, 2), || rng.sample(StandardNormal));
+        let f = GaussianKDE::new(z.dot(&l.t()), None).unwrap();
+        let z = Array2::<f64>::from_shape_simple_fn((1000, 2), || rng.sample(StandardNormal));
+        let g = GaussianKDE::new(z.dot(&l.t()) + &delta, None).unwrap();
+        let (kl, err) = f.kl_divergence_with_error(&g, 2000, &mut rng).unwrap();
+        assert!(err > 0.0);
+        assert_relative_eq!(kl, exact, epsilon = 3.0 * err + 0.1 * exact);
+
+        let g_1d = GaussianKDE::new(array![[0.0], [1.0], [3.0]], None).unwrap();
+        let res = f.kl_divergence(&g_1d, 10, &mut rng);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        for n_samples in [0, 1] {
+            let res = f.kl_divergence(&g, n_samples, &mut rng);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Comparison of fitted KDEs, exactly via [`PartialEq`] and with tolerances via the traits of
+//! [`approx`](https://docs.rs/approx) with the `approx` feature.
+
+use crate::{Bandwidth, CowGaussianKDE};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// Two KDEs are equal if they have the same dataset (of their components, for marginals), the same weights, the same
+/// Cholesky factor of the kernel covariance and the same normalization. A KDE without weights is never equal to a
+/// KDE with weights, even if all weights are one. The bandwidth selectors and the settings which do not change the
+/// estimated density, e.g. [`GaussianKDE::set_whitened_data_cache`](crate::GaussianKDE::set_whitened_data_cache),
+/// are not compared.
+impl<F, B> PartialEq for CowGaussianKDE<'_, F, B>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+    B: Bandwidth<F>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        return self.approx_eq(other, |a, b| a == b, |a, b| a == b);
+    }
+}
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+    B: Bandwidth<F>,
+{
+    /// Compare the datasets, weights, Cholesky factors and normalizations of two KDEs with the given comparisons of
+    /// arrays and scalars, where unweighted KDEs only match unweighted KDEs.
+    fn approx_eq(
+        &self,
+        other: &Self,
+        arrays: impl Fn(ArrayViewD<F>, ArrayViewD<F>) -> bool,
+        scalars: impl Fn(&F, &F) -> bool,
+    ) -> bool {
+        let weights = match (self.weights(), other.weights()) {
+            (None, None) => true,
+            (Some(a), Some(b)) => arrays(a.into_dyn(), b.into_dyn()),
+            _ => false,
+        };
+        return weights
+            && arrays(
+                self.dataset().view().into_dyn(),
+                other.dataset().view().into_dyn(),
+            )
+            && arrays(
+                self.cholesky.view().into_dyn(),
+                other.cholesky.view().into_dyn(),
+            )
+            && scalars(&self.normalization, &other.normalization);
+    }
+}
+
+/// Compare the datasets, weights, Cholesky factors and normalizations of two KDEs element-wise with the absolute
+/// tolerance `epsilon`, as for [`PartialEq`]. Arrays of different shapes, e.g. datasets with different numbers of
+/// points, and unweighted and weighted KDEs are never equal.
+#[cfg(feature = "approx")]
+impl<F, B> approx::AbsDiffEq for CowGaussianKDE<'_, F, B>
+where
+    F: Float + FloatConst + FromPrimitive + approx::AbsDiffEq<Epsilon = F> + 'static,
+    B: Bandwidth<F>,
+{
+    type Epsilon = F;
+
+    fn default_epsilon() -> F {
+        return F::default_epsilon();
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: F) -> bool {
+        return self.approx_eq(
+            other,
+            |a, b| a.abs_diff_eq(&b, epsilon),
+            |a, b| a.abs_diff_eq(b, epsilon),
+        );
+    }
+}
+
+/// Compare the datasets, weights, Cholesky factors and normalizations of two KDEs element-wise with the absolute
+/// tolerance `epsilon` and the relative tolerance `max_relative`, i.e. elements are equal if they are equal within
+/// either tolerance, following the conventions of [`approx::RelativeEq`]. Arrays of different shapes and unweighted
+/// and weighted KDEs are never equal.
+#[cfg(feature = "approx")]
+impl<F, B> approx::RelativeEq for CowGaussianKDE<'_, F, B>
+where
+    F: Float + FloatConst + FromPrimitive + approx::RelativeEq<Epsilon = F> + 'static,
+    B: Bandwidth<F>,
+{
+    fn default_max_relative() -> F {
+        return F::default_max_relative();
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: F, max_relative: F) -> bool {
+        return self.approx_eq(
+            other,
+            |a, b| a.relative_eq(&b, epsilon, max_relative),
+            |a, b| a.relative_eq(b, epsilon, max_relative),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GaussianKDE;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn eq_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(101);
+        let data = Array2::<f64>::from_shape_simple_fn((200, 3), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        assert!(kde == GaussianKDE::new(data.clone(), None).unwrap());
+        assert!(kde == kde.clone());
+        let view = GaussianKDE::from_view(data.view(), None).unwrap();
+        assert!(view == GaussianKDE::from_view(data.view(), None).unwrap());
+
+        // Unweighted KDEs differ from KDEs with unit weights
+        let ones = GaussianKDE::new(data.clone(), Some(Array1::ones(200))).unwrap();
+        assert!(kde != ones);
+        assert_eq!(ones.eval_batch(data.view()), kde.eval_batch(data.view()));
+
+        // Marginals compare their components only
+        let marginal = kde.marginalize(&[1]).unwrap();
+        assert!(marginal == kde.marginalize(&[1]).unwrap());
+        assert!(marginal != kde.marginalize(&[0]).unwrap());
+        assert!(kde != marginal);
+
+        let mut perturbed = data.clone();
+        perturbed[[17, 1]] += 1e-3;
+        assert!(kde != GaussianKDE::new(perturbed, None).unwrap());
+    }
+
+    #[cfg(feature = "approx")]
+    #[test]
+    fn approx_eq_test() {
+        use approx::{
+            AbsDiffEq, RelativeEq, assert_abs_diff_eq, assert_relative_eq, assert_relative_ne,
+        };
+
+        let mut rng = Pcg64Mcg::seed_from_u64(101);
+        let data = Array2::<f64>::from_shape_simple_fn((200, 3), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(200, || rng.random_range(0.5..1.5));
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        assert_relative_eq!(kde, kde.clone(), max_relative = 0.0, epsilon = 0.0);
+
+        // A refit of a tiny perturbation is equal within the tolerances, but not exactly
+        let mut perturbed = data.clone();
+        perturbed[[17, 1]] *= 1.0 + 1e-12;
+        let close = GaussianKDE::new(perturbed, Some(weights.clone())).unwrap();
+        assert!(kde != close);
+        assert_relative_eq!(kde, close, max_relative = 1e-9);
+        assert_abs_diff_eq!(kde, close, epsilon = 1e-9);
+        assert!(!kde.abs_diff_eq(&close, 0.0));
+
+        // A larger perturbation is not
+        let mut perturbed = data.clone();
+        perturbed[[17, 1]] += 1e-3;
+        let far = GaussianKDE::new(perturbed, Some(weights.clone())).unwrap();
+        assert_relative_ne!(kde, far, max_relative = 1e-6);
+        assert!(kde.abs_diff_eq(&far, 1e-2));
+        assert!(!kde.abs_diff_eq(&far, 1e-4));
+        // Elements are equal within either tolerance
+        assert!(kde.relative_eq(&far, 1e-2, 0.0));
+        assert!(kde.relative_eq(&far, 0.0, 1e-2));
+
+        // Different weights, number of points or weighting
+        let reweighted = GaussianKDE::new(data.clone(), Some(weights.mapv(|w| w * 2.0))).unwrap();
+        assert_relative_ne!(kde, reweighted, max_relative = 1e-6);
+        let shorter = GaussianKDE::new(
+            data.slice(s![..199, ..]).to_owned(),
+            Some(weights.slice(s![..199]).to_owned()),
+        )
+        .unwrap();
+        assert!(!kde.relative_eq(&shorter, 1.0, 1.0));
+        let unweighted = GaussianKDE::new(data.clone(), None).unwrap();
+        let ones = GaussianKDE::new(data.clone(), Some(Array1::ones(200))).unwrap();
+        assert!(!unweighted.relative_eq(&ones, 1.0, 1.0));
+
+        // Serialization round trip
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&kde).unwrap();
+            let deserialized: GaussianKDE<f64> = serde_json::from_str(&json).unwrap();
+            assert_relative_eq!(kde, deserialized, max_relative = 1e-15);
+            let marginal = kde.marginalize(&[0]).unwrap();
+            let json = serde_json::to_string(&marginal).unwrap();
+            let deserialized: GaussianKDE<f64> = serde_json::from_str(&json).unwrap();
+            assert_relative_eq!(marginal, deserialized, max_relative = 1e-15);
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use std::fmt::Display;
+use alloc::string::String;
+use core::fmt::Display;
 
 /// General error type for any kind of error appearing during KDE calculation.
 ///
@@ -20,7 +21,7 @@ impl KDEError {
 }
 
 impl Display for KDEError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}: {}", self.kind, self.message)
     }
 }
@@ -34,14 +35,32 @@ pub enum ErrorKind {
     IndexError,
     /// Singular (or non-positive-definite) covariance matrix
     SingularityError,
+    /// Argument outside of its valid domain
+    DomainError,
+    /// Sampling did not succeed within the given number of attempts
+    SamplingError,
+    /// Incompatible combination of construction options
+    ConfigurationError,
+    /// Negative or non-finite weights, or a non-positive sum of weights
+    WeightError,
+    /// Non-finite (NaN or infinite), missing or non-numeric values in the dataset
+    DataError,
+    /// Reading or writing a file failed, or the file content is not a valid KDE
+    IoError,
 }
 
 impl Display for ErrorKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             ErrorKind::ShapeError => write!(f, "ShapeError"),
             ErrorKind::IndexError => write!(f, "IndexError"),
             ErrorKind::SingularityError => write!(f, "SingularityError"),
+            ErrorKind::DomainError => write!(f, "DomainError"),
+            ErrorKind::SamplingError => write!(f, "SamplingError"),
+            ErrorKind::ConfigurationError => write!(f, "ConfigurationError"),
+            ErrorKind::WeightError => write!(f, "WeightError"),
+            ErrorKind::DataError => write!(f, "DataError"),
+            ErrorKind::IoError => write!(f, "IoError"),
         }
     }
 }
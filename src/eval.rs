@@ -1,83 +1,893 @@
+#[cfg(doc)]
 use crate::GaussianKDE;
+use crate::summation::{CompensatedSum, compensated_sum, ln_sum_exp};
+use crate::{Bandwidth, CovarianceMode, CowGaussianKDE, ErrorKind, KDEError};
+use alloc::format;
+#[cfg(feature = "simd")]
+use core::any::TypeId;
 use ndarray::{Zip, prelude::*};
 use num_traits::{Float, FloatConst, FromPrimitive};
 
-impl<F> GaussianKDE<F>
+/// Maximal number of elements of the temporary matrix of squared distances between all data points and a block of
+/// query points in the batch evaluation, i.e. 2 MB in double precision.
+const BLOCK_ELEMENTS: usize = 1 << 18;
+
+/// Dataset of a KDE whitened with the inverse Cholesky factor, $z_i = L^{-1}(x_i - c)$, and the squared norms of the
+/// whitened points. The data points are centered at their mean $c$ first, which keeps the norms small and thereby
+/// limits the cancellation in the distances computed from them.
+pub(crate) struct WhitenedData<F> {
+    pub(crate) center: Array1<F>,
+    pub(crate) points: Array2<F>,
+    sq_norms: Array1<F>,
+    // Whitened points and weights converted once for the SIMD evaluation in double precision, see `simd`
+    #[cfg(feature = "simd")]
+    simd: Option<SimdData>,
+}
+
+/// Whitened data points with the components along the rows and weights in double precision for the SIMD evaluation.
+#[cfg(feature = "simd")]
+struct SimdData {
+    columns: Array2<f64>,
+    weights: Option<Vec<f64>>,
+}
+
+impl<F: Float + 'static> WhitenedData<F> {
+    /// Squared distances $\lVert z_i - z \rVert^2$ between all whitened data points $z_i$ and the whitened point `z`,
+    /// in the order of the dataset.
+    fn sq_distances_to<'w>(&'w self, z: &'w Array1<F>) -> impl Iterator<Item = F> + 'w {
+        return self.points.rows().into_iter().map(move |z_i| {
+            Zip::from(&z_i)
+                .and(z)
+                .fold(F::zero(), |acc, a, b| acc + (*a - *b) * (*a - *b))
+        });
+    }
+}
+
+impl<F, B> CowGaussianKDE<'_, F, B>
 where
+    B: Bandwidth<F>,
     F: Float + FloatConst + FromPrimitive + 'static,
 {
-    /// Evaluate the probability density estimated by the KDE at the point `x`.
+    /// Evaluate the probability density estimated by the KDE at the point `x`. This is the panicking shortcut for
+    /// [`GaussianKDE::try_eval`].
+    ///
+    /// The evaluation allocates at most the whitened point $L^{-1}(x - c)$ of length `dim`: the whitened distances to
+    /// the data points are computed from the cached whitened dataset or, if the cache is disabled, directly from the
+    /// triangular inverse Cholesky factor without any allocation (see `benches/eval.rs`). Both agree with whitening
+    /// each distance $x_i - x$ separately up to rounding, since the cached dataset is centered first.
     ///
     /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
     pub fn eval(&self, x: ArrayView1<F>) -> F {
-        assert_eq!(x.dim(), self.data.dim().1);
-        return if let Some(ref w) = self.weights {
-            Zip::from(self.data.rows())
-                .and(w)
-                .fold(F::zero(), |acc, xi, w| {
-                    let z: Array1<F> = self.inv_cholesky.dot(&(&xi - &x));
-                    acc + *w * F::exp(-F::from(0.5).unwrap() * z.dot(&z))
-                })
-                * self.normalization
-        } else {
-            self.data.rows().into_iter().fold(F::zero(), |acc, xi| {
-                let z: Array1<F> = self.inv_cholesky.dot(&(&xi - &x));
-                acc + F::exp(-F::from(0.5).unwrap() * z.dot(&z))
-            }) * self.normalization
-        };
+        assert_eq!(x.dim(), self.n_dims());
+        return self.density(x);
     }
 
-    /// Evaluate the probability density estimated by the KDE at multiple points given by the array `x`.
+    /// Evaluate the probability density estimated by the KDE at multiple points given by the array `x`. This is the
+    /// panicking shortcut for [`GaussianKDE::try_eval_batch`].
     ///
     /// The points are expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
     /// lie along `Axis(1)`.
     ///
     /// **Panic**s if the dimension of `x` does not match the dimension of the KDE dataset.
     pub fn eval_batch(&self, x: ArrayView2<F>) -> Array1<F> {
-        assert_eq!(x.dim().1, self.data.dim().1);
-        let mut arg = F::zero();
-        let mut tmp = F::zero();
-        return if let Some(ref w) = self.weights {
-            Array1::from_shape_fn(x.dim().0, |j| {
-                Zip::from(self.data.rows())
-                    .and(w)
-                    .fold(F::zero(), |acc, xi, w| {
-                        arg = F::zero();
-                        tmp = F::zero();
-                        for i in 0..self.inv_cholesky.dim().0 {
-                            for k in 0..=i {
-                                tmp = self.inv_cholesky[[i, k]] * (xi[[k]] - x[[j, k]]);
-                                arg = arg + tmp * tmp;
-                            }
-                        }
-                        acc + *w * F::exp(-F::from(0.5).unwrap() * arg)
-                    })
-                    * self.normalization
-            })
+        assert_eq!(x.dim().1, self.n_dims());
+        let mut res = Array1::zeros(x.dim().0);
+        self.eval_batch_into(x, res.view_mut()).unwrap();
+        return res;
+    }
+
+    /// Evaluate the probability density estimated by the KDE at the point `x`.
+    ///
+    /// Returns a `ShapeError` if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn try_eval(&self, x: ArrayView1<F>) -> Result<F, KDEError> {
+        if x.dim() != self.n_dims() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected point of dimension {}, found dimension {}",
+                    self.n_dims(),
+                    x.dim()
+                ),
+            ));
+        }
+        return Ok(self.density(x));
+    }
+
+    /// Evaluate the probability density estimated by the KDE at multiple points given by the array `x`.
+    ///
+    /// The points are expected to be given as array of shape `(n_points, dim)`. Returns a `ShapeError` if the
+    /// dimension of `x` does not match the dimension of the KDE dataset. An empty array of shape `(0, dim)` yields an
+    /// empty result.
+    pub fn try_eval_batch(&self, x: ArrayView2<F>) -> Result<Array1<F>, KDEError> {
+        let mut res = Array1::zeros(x.dim().0);
+        self.eval_batch_into(x, res.view_mut())?;
+        return Ok(res);
+    }
+
+    /// Evaluate the probability density estimated by the KDE at multiple points given by the array `x` and write
+    /// the results into `out`, avoiding the allocation of [`GaussianKDE::eval_batch`] for repeated evaluations.
+    ///
+    /// The batch evaluation whitens the dataset once and computes the squared distances between all data points and a
+    /// block of query points with a single matrix product, which is considerably faster than evaluating each point on
+    /// its own. The results agree with [`GaussianKDE::eval`] up to rounding. The blocks are chosen such that the
+    /// temporary matrix of distances has at most $2^{18}$ elements. With the `simd` feature, KDEs in double precision
+    /// instead compute the distances and kernels of four data points at once with SIMD instructions, unless the
+    /// normalization has to be applied in log-space, with a relative deviation of about $10^{-14}$ from the scalar
+    /// evaluation.
+    ///
+    /// The points are expected to be given as array of shape `(n_points, dim)`. Returns a `ShapeError` if the
+    /// dimension of `x` does not match the dimension of the KDE dataset or if `out` does not have length `n_points`.
+    pub fn eval_batch_into(
+        &self,
+        x: ArrayView2<F>,
+        mut out: ArrayViewMut1<F>,
+    ) -> Result<(), KDEError> {
+        self.check_batch_shapes(x, out.dim())?;
+        self.with_whitened_data(|whitened| {
+            for (x, out) in x
+                .axis_chunks_iter(Axis(0), self.block_size())
+                .zip(out.axis_chunks_iter_mut(Axis(0), self.block_size()))
+            {
+                self.eval_block(whitened, x, out, false);
+            }
+        });
+        return Ok(());
+    }
+
+    /// Evaluate the probability density estimated by the KDE at multiple points given by the array `x` in chunks of
+    /// `chunk` points, calling `f` with the index of the first point of each chunk and the densities at its points.
+    ///
+    /// In contrast to [`GaussianKDE::eval_batch`], the densities are written into a buffer of length `chunk` which is
+    /// reused for all chunks, such that the memory required for the results does not grow with the number of points.
+    /// The densities agree exactly with the ones of [`GaussianKDE::eval_batch`]. See [`GaussianKDE::eval_iter`] for
+    /// points which are not stored in an array.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset or if `chunk` is zero.
+    pub fn eval_batch_chunked(
+        &self,
+        x: ArrayView2<F>,
+        chunk: usize,
+        mut f: impl FnMut(usize, ArrayView1<F>),
+    ) {
+        assert_eq!(x.dim().1, self.n_dims());
+        assert!(chunk > 0, "the chunk size must be positive");
+        let mut buffer = Array1::zeros(chunk.min(x.nrows()));
+        self.with_whitened_data(|whitened| {
+            for (i, x) in x.axis_chunks_iter(Axis(0), chunk).enumerate() {
+                let mut out = buffer.slice_mut(s![..x.nrows()]);
+                for (x, out) in x
+                    .axis_chunks_iter(Axis(0), self.block_size())
+                    .zip(out.axis_chunks_iter_mut(Axis(0), self.block_size()))
+                {
+                    self.eval_block(whitened, x, out, false);
+                }
+                f(i * chunk, buffer.slice(s![..x.nrows()]));
+            }
+        });
+    }
+
+    /// Evaluate the probability density estimated by the KDE at the points yielded by `points`, e.g. read from a file
+    /// or generated on the fly, without collecting them into an array first.
+    ///
+    /// The points are taken from `points` and evaluated in blocks as in [`GaussianKDE::eval_batch`] whenever the
+    /// densities of the previous block are used up, such that at most one block of points and densities is held in
+    /// memory at any time. The first block is sized by the lower bound of the size hint of `points` and doubled
+    /// whenever a block is filled, up to the block size of [`GaussianKDE::eval_batch`], such that short iterators do
+    /// not allocate a full block. The densities agree exactly with the ones of [`GaussianKDE::eval_batch`].
+    ///
+    /// *Panics* if the dimension of any point does not match the dimension of the KDE dataset.
+    pub fn eval_iter<'k>(
+        &'k self,
+        points: impl IntoIterator<Item = Array1<F>> + 'k,
+    ) -> impl Iterator<Item = F> + 'k {
+        let mut points = points.into_iter();
+        // Without the cache, the dataset is whitened once for all blocks
+        let whitened = (!self.cache_whitened).then(|| self.whitened_data());
+        let rows = self.block_size().min(points.size_hint().0.max(1));
+        let mut x = Array2::zeros((rows, self.n_dims()));
+        let mut out = Array1::zeros(rows);
+        let (mut len, mut next) = (0, 0);
+        return core::iter::from_fn(move || {
+            if next == len {
+                // Grow the buffers if the previous block was filled completely
+                if len == out.len() && len < self.block_size() {
+                    let rows = (2 * len).min(self.block_size());
+                    x = Array2::zeros((rows, self.n_dims()));
+                    out = Array1::zeros(rows);
+                }
+                len = 0;
+                for (mut row, point) in x.rows_mut().into_iter().zip(points.by_ref()) {
+                    assert_eq!(point.dim(), self.n_dims());
+                    row.assign(&point);
+                    len += 1;
+                }
+                if len == 0 {
+                    return None;
+                }
+                let whitened = match whitened {
+                    Some(ref whitened) => whitened,
+                    None => self.cached_whitened_data().unwrap(),
+                };
+                self.eval_block(
+                    whitened,
+                    x.slice(s![..len, ..]),
+                    out.slice_mut(s![..len]),
+                    false,
+                );
+                next = 0;
+            }
+            next += 1;
+            return Some(out[next - 1]);
+        });
+    }
+
+    /// Evaluate the natural logarithm of the probability density estimated by the KDE at the point `x`.
+    ///
+    /// In contrast to `eval(x).ln()`, the kernel sum is reduced in log-space, such that the result remains finite
+    /// far in the tails of the density where [`GaussianKDE::eval`] underflows to zero.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn ln_eval(&self, x: ArrayView1<F>) -> F {
+        assert_eq!(x.dim(), self.n_dims());
+        return self.ln_kernel_sum_excluding(x, None) + self.ln_normalization;
+    }
+
+    /// Evaluate the natural logarithm of the probability density estimated by the KDE at multiple points given by the
+    /// array `x`. See [`GaussianKDE::ln_eval`] for details.
+    ///
+    /// The points are expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    ///
+    /// **Panic**s if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn ln_eval_batch(&self, x: ArrayView2<F>) -> Array1<F> {
+        assert_eq!(x.dim().1, self.n_dims());
+        let mut res = Array1::zeros(x.dim().0);
+        self.ln_eval_batch_into(x, res.view_mut()).unwrap();
+        return res;
+    }
+
+    /// Evaluate the natural logarithm of the probability density estimated by the KDE at multiple points given by
+    /// the array `x` and write the results into `out`. See [`GaussianKDE::ln_eval`] and
+    /// [`GaussianKDE::eval_batch_into`] for details.
+    ///
+    /// The points are expected to be given as array of shape `(n_points, dim)`. Returns a `ShapeError` if the
+    /// dimension of `x` does not match the dimension of the KDE dataset or if `out` does not have length `n_points`.
+    pub fn ln_eval_batch_into(
+        &self,
+        x: ArrayView2<F>,
+        mut out: ArrayViewMut1<F>,
+    ) -> Result<(), KDEError> {
+        self.check_batch_shapes(x, out.dim())?;
+        self.with_whitened_data(|whitened| {
+            for (x, out) in x
+                .axis_chunks_iter(Axis(0), self.block_size())
+                .zip(out.axis_chunks_iter_mut(Axis(0), self.block_size()))
+            {
+                self.eval_block(whitened, x, out, true);
+            }
+        });
+        return Ok(());
+    }
+
+    /// Enable (default) or disable the cache of the whitened dataset $z_i = L^{-1}(x_i - c)$.
+    ///
+    /// With the cache, the dataset is whitened once on the first evaluation, such that [`GaussianKDE::eval`] only
+    /// needs to whiten the point of evaluation and then computes one squared distance of length $d$ per data point.
+    /// Without it, each data point is whitened again for every evaluation, which is slower by a factor of about
+    /// $d / 2$ for the full covariance matrix, and batch evaluations whiten the dataset once per call. The cache requires memory for an array of the
+    /// size of the dataset and is reset if the kernel changes, e.g. when adding points. Marginals inherit the choice.
+    pub fn set_whitened_data_cache(&mut self, enabled: bool) {
+        self.cache_whitened = enabled;
+        self.whitened = Default::default();
+    }
+
+    /// Return a `ShapeError` if the points `x` do not match the dimension of the KDE dataset or if the output buffer
+    /// length `n_out` does not match the number of points.
+    pub(crate) fn check_batch_shapes(
+        &self,
+        x: ArrayView2<F>,
+        n_out: usize,
+    ) -> Result<(), KDEError> {
+        if x.dim().1 != self.n_dims() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected points of dimension {}, found array with shape `{:?}`",
+                    self.n_dims(),
+                    x.dim()
+                ),
+            ));
+        }
+        if n_out != x.dim().0 {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected output buffer of length {} for array with shape `{:?}`, found {n_out}",
+                    x.dim().0,
+                    x.dim()
+                ),
+            ));
+        }
+        return Ok(());
+    }
+
+    /// Evaluate the natural logarithm of the probability density at each point of the dataset `x`, e.g. to score
+    /// held-out data. This is the equivalent of `sklearn.neighbors.KernelDensity.score_samples` and uses the same
+    /// log-space reduction as [`GaussianKDE::ln_eval_batch`], such that the result remains finite far from the data.
+    ///
+    /// The points are expected to be given as array of shape `(n_points, dim)`. Returns a `ShapeError` if the
+    /// dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn score_samples(&self, x: ArrayView2<F>) -> Result<Array1<F>, KDEError> {
+        let mut res = Array1::zeros(x.dim().0);
+        self.ln_eval_batch_into(x, res.view_mut())?;
+        return Ok(res);
+    }
+
+    /// Compute the total log-likelihood $\sum_j v_j \ln f_\mathrm{KDE}(\bm{y}_j)$ of the dataset `x` with
+    /// (optional) `weights` $v_j$, see [`GaussianKDE::score_samples`]. If no weights are given, all points are
+    /// weighed equally with $v_j = 1$.
+    ///
+    /// Returns a `ShapeError` if the dimension of `x` does not match the dimension of the KDE dataset or if the
+    /// number of weights does not match the number of points.
+    pub fn score(&self, x: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> Result<F, KDEError> {
+        let ln_f = self.score_samples(x)?;
+        return Ok(match weights {
+            Some(w) if w.dim() != x.dim().0 => {
+                return Err(KDEError::new(
+                    ErrorKind::ShapeError,
+                    format!(
+                        "expected {} weights for array with shape `{:?}`, found {}",
+                        x.dim().0,
+                        x.dim(),
+                        w.dim()
+                    ),
+                ));
+            }
+            Some(w) => ln_f.dot(&w),
+            None => ln_f.sum(),
+        });
+    }
+
+    /// Compute the leave-one-out (LOO) log-likelihood
+    /// \\[ \sum_i w_i \ln f_{-i}(\bm{x}_i), \quad f_{-i}(\bm{x}_i) = \frac{1}{\sum_{j \neq i} w_j} \sum_{j \neq i} w_j \, K_H(\bm{x}_i - \bm{x}_j) \\]
+    /// of the dataset for the current kernel covariance $H$, where $f_{-i}$ is the density estimated with the $i$-th
+    /// point removed. This is the objective maximized by [`MLCVBandwidth`](crate::MLCVBandwidth) and allows to compare
+    /// the fits of different bandwidths on the same data.
+    ///
+    /// The LOO densities are computed in log-space, such that isolated points do not underflow to $-\infty$. Points
+    /// with zero weight do not contribute. If the LOO density of a point with non-zero weight vanishes identically
+    /// (e.g. for a single data point), the result is $-\infty$. The computational cost is $\mathcal{O}(n^2 d)$.
+    pub fn loo_log_likelihood(&self) -> F {
+        let sum_weights = self
+            .weights
+            .as_ref()
+            .map_or(F::from(self.n_points()).unwrap(), |w| {
+                compensated_sum(w.iter().copied())
+            });
+        let mut res = CompensatedSum::new();
+        for (i, xi) in self.dataset().rows().into_iter().enumerate() {
+            let w_i = self.weights.as_ref().map_or(F::one(), |w| w[i]);
+            if w_i == F::zero() {
+                continue;
+            }
+            let ln_density = self.loo_ln_density(xi, i, sum_weights);
+            if ln_density == F::neg_infinity() {
+                return F::neg_infinity();
+            }
+            res.add(w_i * ln_density);
+        }
+        return res.value();
+    }
+
+    /// Evaluate the probability density estimated by the KDE at each data point, in the order of the dataset. If
+    /// `leave_one_out` is true, the kernel of each point is excluded from its density, i.e. the density $f_{-i}(x_i)$
+    /// of the KDE with the $i$-th point removed is returned, see [`GaussianKDE::loo_log_likelihood`].
+    ///
+    /// Including the own kernels, the result is the same as [`GaussianKDE::eval_batch`] of the dataset. The
+    /// leave-one-out densities are computed in log-space as in [`GaussianKDE::self_log_densities`] and vanish if no
+    /// other point has non-zero weight. Both variants use the cache of the whitened dataset and cost
+    /// $\mathcal{O}(n^2 d)$.
+    pub fn self_densities(&self, leave_one_out: bool) -> Array1<F> {
+        if !leave_one_out {
+            return self.eval_batch(self.dataset().view());
+        }
+        return self.self_log_densities(true).mapv(F::exp);
+    }
+
+    /// Evaluate the natural logarithm of the probability density estimated by the KDE at each data point, optionally
+    /// excluding the kernel of each point, see [`GaussianKDE::self_densities`]. Including the own kernels, the result
+    /// is the same as [`GaussianKDE::ln_eval_batch`] of the dataset.
+    pub fn self_log_densities(&self, leave_one_out: bool) -> Array1<F> {
+        if !leave_one_out {
+            return self.ln_eval_batch(self.dataset().view());
+        }
+        let sum_weights = self
+            .weights
+            .as_ref()
+            .map_or(F::from(self.n_points()).unwrap(), |w| {
+                compensated_sum(w.iter().copied())
+            });
+        return self
+            .dataset()
+            .rows()
+            .into_iter()
+            .enumerate()
+            .map(|(i, xi)| self.loo_ln_density(xi, i, sum_weights))
+            .collect();
+    }
+
+    /// Logarithm of the density at the data point `xi` with index `i` estimated without its own kernel, for the total
+    /// weight `sum_weights` of all points. Returns $-\infty$ if the other kernels vanish at `xi`.
+    fn loo_ln_density(&self, xi: ArrayView1<F>, i: usize, sum_weights: F) -> F {
+        let ln_sum = self.ln_kernel_sum_excluding(xi, Some(i));
+        if ln_sum == F::neg_infinity() {
+            return F::neg_infinity();
+        }
+        // Replace the total weight in the normalization by the one of the remaining points
+        let w_i = self.weights.as_ref().map_or(F::one(), |w| w[i]);
+        return ln_sum + self.ln_normalization + sum_weights.ln() - (sum_weights - w_i).ln();
+    }
+
+    /// Normalized density at `x`. If the normalization constant is not representable (e.g. for high-dimensional data),
+    /// the density is computed in log-space instead.
+    pub(crate) fn density(&self, x: ArrayView1<F>) -> F {
+        if let Some(whitened) = self.cached_whitened_data() {
+            let z = self.whiten_point(whitened, x);
+            return self.density_from(whitened.sq_distances_to(&z));
+        }
+        return self.density_from(
+            self.data
+                .rows()
+                .into_iter()
+                .map(|xi| self.whitened_sq_distance(xi, x)),
+        );
+    }
+
+    /// Normalized density for the squared whitened distances `sq_distances` between all data points and the point of
+    /// evaluation, see [`GaussianKDE::density`].
+    fn density_from(&self, sq_distances: impl Iterator<Item = F>) -> F {
+        return if self.normalization.is_normal() {
+            self.kernel_sum(sq_distances) * self.normalization
         } else {
-            Array1::from_shape_fn(x.dim().0, |j| {
-                self.data.rows().into_iter().fold(F::zero(), |acc, xi| {
-                    arg = F::zero();
-                    tmp = F::zero();
-                    for i in 0..self.inv_cholesky.dim().0 {
-                        for k in 0..=i {
-                            tmp = self.inv_cholesky[[i, k]] * (xi[[k]] - x[[j, k]]);
-                            arg = arg + tmp * tmp;
-                        }
-                    }
-                    acc + F::exp(-F::from(0.5).unwrap() * tmp * tmp)
-                }) * self.normalization
+            F::exp(self.ln_kernel_sum(sq_distances) + self.ln_normalization)
+        };
+    }
+
+    /// Logarithm of the kernel sum at `x`, optionally excluding the kernel of the data point with index `exclude`.
+    fn ln_kernel_sum_excluding(&self, x: ArrayView1<F>, exclude: Option<usize>) -> F {
+        let exclude = |(i, q)| if exclude == Some(i) { F::infinity() } else { q };
+        if let Some(whitened) = self.cached_whitened_data() {
+            let z = self.whiten_point(whitened, x);
+            return self.ln_kernel_sum(whitened.sq_distances_to(&z).enumerate().map(exclude));
+        }
+        return self.ln_kernel_sum(
+            self.data
+                .rows()
+                .into_iter()
+                .map(|xi| self.whitened_sq_distance(xi, x))
+                .enumerate()
+                .map(exclude),
+        );
+    }
+
+    /// Squared whitened distances $\lVert L^{-1}(x_i - x) \rVert^2$ between all data points and `x`, in the order of
+    /// the dataset, computed from the cached whitened dataset if available.
+    pub(crate) fn sq_distances_to_data(&self, x: ArrayView1<F>) -> Array1<F> {
+        if let Some(whitened) = self.cached_whitened_data() {
+            let z = self.whiten_point(whitened, x);
+            return whitened.sq_distances_to(&z).collect();
+        }
+        return self
+            .data
+            .rows()
+            .into_iter()
+            .map(|xi| self.whitened_sq_distance(xi, x))
+            .collect();
+    }
+
+    /// Logarithm of [`GaussianKDE::kernel_sum`], computed with a single-pass log-sum-exp reduction over the kernels.
+    /// Kernels at an infinite distance do not contribute.
+    fn ln_kernel_sum(&self, sq_distances: impl Iterator<Item = F>) -> F {
+        let half = F::from(0.5).unwrap();
+        return match self.weights {
+            Some(ref w) => ln_sum_exp(sq_distances.zip(w.iter()).map(|(q, w)| w.ln() - half * q)),
+            None => ln_sum_exp(sq_distances.map(|q| -half * q)),
+        };
+    }
+
+    /// Unnormalized (weighted) sum of all kernels $\sum_i w_i \exp(-\frac{1}{2} z_i^\top z_i)$ for the squared norms
+    /// `sq_distances` of the whitened distances $z_i = L^{-1}(x_i - x)$, in the order of the dataset.
+    fn kernel_sum(&self, sq_distances: impl Iterator<Item = F>) -> F {
+        let half = F::from(0.5).unwrap();
+        let mut sum = CompensatedSum::new();
+        if let Some(ref w) = self.weights {
+            for (q, w) in sq_distances.zip(w.iter()) {
+                sum.add(*w * F::exp(-half * q));
+            }
+        } else {
+            for q in sq_distances {
+                sum.add(F::exp(-half * q));
+            }
+        }
+        return sum.value();
+    }
+
+    /// Number of query points evaluated at once in the batch evaluation, such that the temporary matrix of squared
+    /// distances to all data points has at most `BLOCK_ELEMENTS` elements.
+    pub(crate) fn block_size(&self) -> usize {
+        return (BLOCK_ELEMENTS / self.n_points().max(1)).max(1);
+    }
+
+    /// Get the cached whitened dataset, which is constructed on first use, or `None` if the cache is disabled.
+    fn cached_whitened_data(&self) -> Option<&WhitenedData<F>> {
+        if !self.cache_whitened {
+            return None;
+        }
+        return Some(self.whitened.get_or_init(|| self.whitened_data()));
+    }
+
+    /// Call `f` with the whitened dataset, which is only constructed temporarily if the cache is disabled.
+    pub(crate) fn with_whitened_data<R>(&self, f: impl FnOnce(&WhitenedData<F>) -> R) -> R {
+        return match self.cached_whitened_data() {
+            Some(whitened) => f(whitened),
+            None => f(&self.whitened_data()),
+        };
+    }
+
+    /// Whiten the point `x` in the same way as the data points of `whitened`, i.e. $z = L^{-1}(x - c)$, exploiting the
+    /// lower-triangular structure of $L^{-1}$ to allocate only the result.
+    fn whiten_point(&self, whitened: &WhitenedData<F>, x: ArrayView1<F>) -> Array1<F> {
+        let c = &whitened.center;
+        return Array1::from_shape_fn(self.inv_cholesky.dim().0, |i| {
+            (0..=i).fold(F::zero(), |acc, k| {
+                acc + self.inv_cholesky[[i, k]] * (x[k] - c[k])
             })
+        });
+    }
+
+    /// Whitened dataset for the evaluation, see [`WhitenedData`].
+    fn whitened_data(&self) -> WhitenedData<F> {
+        let data = self.dataset();
+        let center = data
+            .mean_axis(Axis(0))
+            .unwrap_or_else(|| Array1::zeros(self.n_dims()));
+        let points = (&data - &center).dot(&self.inv_cholesky.t());
+        let sq_norms = points.map_axis(Axis(1), |z| z.dot(&z));
+        #[cfg(feature = "simd")]
+        let simd = (TypeId::of::<F>() == TypeId::of::<f64>()).then(|| SimdData {
+            columns: points
+                .t()
+                .as_standard_layout()
+                .mapv(|z| z.to_f64().unwrap()),
+            weights: self
+                .weights()
+                .map(|w| w.iter().map(|w| w.to_f64().unwrap()).collect()),
+        });
+        return WhitenedData {
+            center,
+            points,
+            sq_norms,
+            #[cfg(feature = "simd")]
+            simd,
         };
     }
+
+    /// Evaluate the density (or its logarithm if `ln` is true) at the block of points `x`. With the `simd` feature,
+    /// densities in double precision are evaluated with [`GaussianKDE::eval_block_simd`], and otherwise with
+    /// [`GaussianKDE::eval_block_gemm`].
+    pub(crate) fn eval_block(
+        &self,
+        whitened: &WhitenedData<F>,
+        x: ArrayView2<F>,
+        out: ArrayViewMut1<F>,
+        ln: bool,
+    ) {
+        #[cfg(feature = "simd")]
+        if !ln && self.normalization.is_normal() {
+            if let Some(ref simd) = whitened.simd {
+                let z = (&x - &whitened.center).dot(&self.inv_cholesky.t());
+                self.eval_block_simd(simd, z.view(), out);
+                return;
+            }
+        }
+        self.eval_block_gemm(whitened, x, out, ln);
+    }
+
+    /// Evaluate the density (or its logarithm if `ln` is true) at the block of points `x`, computing the squared
+    /// whitened distances $\lVert z_i \rVert^2 + \lVert z \rVert^2 - 2 z_i^\top z$ to all data points with a matrix
+    /// product.
+    ///
+    /// The rounding error of this expression is proportional to the norms instead of the distance itself, which
+    /// matters for points close to each other but far from the center, e.g. in the tails of the density or in
+    /// separated modes. Distances which are small compared to the norms are therefore recomputed directly from the
+    /// whitened points. With the `blas` feature, the matrix product of `f32` and `f64` points is computed by `?gemm` of
+    /// the BLAS implementation linked by the application.
+    fn eval_block_gemm(
+        &self,
+        whitened: &WhitenedData<F>,
+        x: ArrayView2<F>,
+        mut out: ArrayViewMut1<F>,
+        ln: bool,
+    ) {
+        let two = F::from(2).unwrap();
+        // Maximal ratio of the norms to the distance, which limits the loss of precision to a few bits
+        let cancellation = F::from(16).unwrap();
+        let z = (&x - &whitened.center).dot(&self.inv_cholesky.t());
+        let mut sq_distances = z.dot(&whitened.points.t());
+        Zip::from(sq_distances.rows_mut())
+            .and(z.rows())
+            .and(&mut out)
+            .for_each(|mut q, z, r| {
+                let sq_norm = z.dot(&z);
+                Zip::from(&mut q)
+                    .and(&whitened.sq_norms)
+                    .and(whitened.points.rows())
+                    .for_each(|q, sq_norm_i, z_i| {
+                        let sum = *sq_norm_i + sq_norm;
+                        *q = sum - two * *q;
+                        if *q * cancellation < sum {
+                            *q = Zip::from(&z_i)
+                                .and(&z)
+                                .fold(F::zero(), |acc, a, b| acc + (*a - *b) * (*a - *b));
+                        }
+                    });
+                *r = if ln {
+                    self.ln_kernel_sum(q.iter().copied()) + self.ln_normalization
+                } else {
+                    self.density_from(q.iter().copied())
+                };
+            });
+    }
+
+    /// Evaluate the density at the block of whitened points `z` with the SIMD kernel sums of [`crate::simd`] for the
+    /// whitened data points and weights converted to double precision in `simd`.
+    #[cfg(feature = "simd")]
+    fn eval_block_simd(&self, simd: &SimdData, z: ArrayView2<F>, mut out: ArrayViewMut1<F>) {
+        let mut z_f64 = vec![0.0; z.ncols()];
+        for (z, r) in z.rows().into_iter().zip(out.iter_mut()) {
+            for (z_f64, z) in z_f64.iter_mut().zip(z) {
+                *z_f64 = z.to_f64().unwrap();
+            }
+            let sum = crate::simd::kernel_sum(simd.columns.view(), simd.weights.as_deref(), &z_f64);
+            *r = F::from(sum).unwrap() * self.normalization;
+        }
+    }
+
+    /// Squared norm of the whitened distance $z = L^{-1}(x_i - x)$ between the row `xi` of the stored dataset, of
+    /// which only the columns forming the components of the KDE are used, and the point `x`.
+    pub(crate) fn whitened_sq_distance(&self, xi: ArrayView1<F>, x: ArrayView1<F>) -> F {
+        return match self.dims {
+            Some(ref dims) => self.whitened_sq_distance_by(|k| xi[dims[k]], x),
+            None => self.whitened_sq_distance_by(|k| xi[k], x),
+        };
+    }
+
+    /// Squared norm of the whitened distance $z = L^{-1}(x_i - x)$ with the components of $x_i$ given by `xi`,
+    /// exploiting the lower-triangular (or diagonal) structure of $L^{-1}$ to avoid any allocation.
+    fn whitened_sq_distance_by(&self, xi: impl Fn(usize) -> F, x: ArrayView1<F>) -> F {
+        let mut res = F::zero();
+        if self.covariance_mode == CovarianceMode::Diagonal {
+            for i in 0..self.inv_cholesky.dim().0 {
+                let z = self.inv_cholesky[[i, i]] * (xi(i) - x[i]);
+                res = res + z * z;
+            }
+            return res;
+        }
+        for i in 0..self.inv_cholesky.dim().0 {
+            let mut z = F::zero();
+            for k in 0..=i {
+                z = z + self.inv_cholesky[[i, k]] * (xi(k) - x[k]);
+            }
+            res = res + z * z;
+        }
+        return res;
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F> + Sync,
+    F: Float + FloatConst + FromPrimitive + Send + Sync + 'static,
+{
+    /// Evaluate the probability density estimated by the KDE at multiple points given by the array `x`, distributing
+    /// the points over the threads of the global `rayon` thread pool.
+    ///
+    /// The result is identical to the one of [`GaussianKDE::eval_batch`], independent of the number of threads.
+    ///
+    /// **Panic**s if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn eval_batch_par(&self, x: ArrayView2<F>) -> Array1<F> {
+        use rayon::prelude::*;
+
+        assert_eq!(x.dim().1, self.n_dims());
+        let mut res = Array1::zeros(x.dim().0);
+        self.with_whitened_data(|whitened| {
+            x.axis_chunks_iter(Axis(0), self.block_size())
+                .into_par_iter()
+                .zip(res.axis_chunks_iter_mut(Axis(0), self.block_size()))
+                .for_each(|(x, out)| self.eval_block(whitened, x, out, false));
+        });
+        return res;
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::excessive_precision)]
 mod tests {
     use approx::assert_relative_eq;
     use ndarray::prelude::*;
 
-    use crate::GaussianKDE;
+    use crate::{Bandwidth, ErrorKind, GaussianKDE, MLCVBandwidth};
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn eval_batch_matrix_product_test() {
+        // Two separated modes far from the origin, such that the distances suffer from cancellation
+        let mut rng = Pcg64Mcg::seed_from_u64(76);
+        let mut data =
+            Array2::<f64>::from_shape_simple_fn((1200, 3), || rng.sample(StandardNormal));
+        data.slice_mut(s![..600, ..])
+            .mapv_inplace(|x| 0.2 * x + 50.0);
+        data.slice_mut(s![600.., ..])
+            .mapv_inplace(|x| 0.3 * x - 20.0);
+        let weights = Array1::from_shape_simple_fn(1200, || rng.random_range(0.0..2.0));
+        let mut x = Array2::<f64>::from_shape_simple_fn((300, 3), || rng.sample(StandardNormal));
+        x.slice_mut(s![..100, ..]).mapv_inplace(|x| 0.3 * x + 50.0);
+        x.slice_mut(s![100..200, ..])
+            .mapv_inplace(|x| 0.3 * x - 20.0);
+        x.slice_mut(s![200.., ..]).mapv_inplace(|x| 40.0 * x);
+
+        let check = |kde: &GaussianKDE<f64>, x: ArrayView2<f64>| {
+            let scalar = x.map_axis(Axis(1), |xi| kde.eval(xi));
+            assert_relative_eq!(kde.eval_batch(x), scalar, max_relative = 1e-12);
+            // The matrix product is used for all blocks if SIMD is disabled or not applicable
+            let mut gemm = Array1::zeros(x.nrows());
+            kde.with_whitened_data(|whitened| {
+                kde.eval_block_gemm(whitened, x, gemm.view_mut(), false)
+            });
+            assert_relative_eq!(gemm, scalar, max_relative = 1e-12);
+            let scalar = x.map_axis(Axis(1), |xi| kde.ln_eval(xi));
+            assert_relative_eq!(kde.ln_eval_batch(x), scalar, max_relative = 1e-12);
+        };
+        for weights in [None, Some(weights)] {
+            let kde = GaussianKDE::new(data.clone(), weights.clone()).unwrap();
+            // The evaluation is split into several blocks of query points
+            assert!(kde.block_size() < 300);
+            check(&kde, x.view());
+            let kde = GaussianKDE::with_diagonal_covariance(data.clone(), weights.clone()).unwrap();
+            check(&kde, x.view());
+            check(&kde.marginalize_to(&[2, 0]).unwrap(), x.slice(s![.., ..2]));
+        }
+
+        // The kernel sum is reduced in log-space if the normalization is not representable
+        let data = Array2::from_shape_simple_fn((300, 100), || {
+            2.5E-4 * rng.sample::<f64, _>(StandardNormal)
+        });
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        assert!(!kde.normalization.is_normal());
+        check(&kde, data.slice(s![..5, ..]));
+    }
+
+    #[test]
+    fn whitened_data_cache_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(77);
+        let data = Array2::<f64>::from_shape_simple_fn((500, 5), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(500, || rng.random_range(0.0..2.0));
+        let x = Array2::<f64>::from_shape_simple_fn((50, 5), || {
+            3.0 * rng.sample::<f64, _>(StandardNormal)
+        });
+
+        let check = |cached: &GaussianKDE<f64>, uncached: &GaussianKDE<f64>, x: ArrayView2<f64>| {
+            for xi in x.rows() {
+                assert_relative_eq!(cached.eval(xi), uncached.eval(xi), max_relative = 1e-12);
+                assert_relative_eq!(
+                    cached.ln_eval(xi),
+                    uncached.ln_eval(xi),
+                    max_relative = 1e-12
+                );
+            }
+            assert_relative_eq!(
+                cached.loo_log_likelihood(),
+                uncached.loo_log_likelihood(),
+                max_relative = 1e-12
+            );
+            assert_relative_eq!(
+                cached.eval_batch(x),
+                uncached.eval_batch(x),
+                max_relative = 1e-12
+            );
+        };
+        let mut cached = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let mut uncached = GaussianKDE::new(data, Some(weights)).unwrap();
+        uncached.set_whitened_data_cache(false);
+        check(&cached, &uncached, x.view());
+        // Marginals inherit the choice
+        check(
+            &cached.marginalize_to(&[4, 1, 2]).unwrap(),
+            &uncached.marginalize_to(&[4, 1, 2]).unwrap(),
+            x.slice(s![.., ..3]),
+        );
+
+        // The cache is reset if the kernel changes
+        cached.set_bandwidth_factor(0.3).unwrap();
+        uncached.set_bandwidth_factor(0.3).unwrap();
+        check(&cached, &uncached, x.view());
+        cached.push(x.row(0), Some(1.5)).unwrap();
+        uncached.push(x.row(0), Some(1.5)).unwrap();
+        check(&cached, &uncached, x.view());
+    }
+
+    #[test]
+    fn eval_allocating_reference_test() {
+        // Both single-point evaluation paths agree with whitening each distance with an allocation up to rounding
+        let mut rng = Pcg64Mcg::seed_from_u64(85);
+        let reference = |kde: &GaussianKDE<f64>, x: ArrayView1<f64>| {
+            let data = kde.data();
+            let kernels = data.rows().into_iter().map(|xi| {
+                let z = kde.inv_cholesky().dot(&(&xi - &x));
+                (-0.5 * z.dot(&z)).exp()
+            });
+            let sum: f64 = match kde.weights() {
+                Some(w) => kernels.zip(w.iter()).map(|(k, w)| w * k).sum(),
+                None => kernels.sum(),
+            };
+            sum * kde.normalization
+        };
+        for dim in [2, 5] {
+            let data =
+                Array2::<f64>::from_shape_simple_fn((1000, dim), || rng.sample(StandardNormal));
+            let weights = Array1::from_shape_simple_fn(1000, || rng.random_range(0.0..2.0));
+            let x = Array2::<f64>::from_shape_simple_fn((20, dim), || {
+                2.0 * rng.sample::<f64, _>(StandardNormal)
+            });
+            for weights in [None, Some(weights)] {
+                for mut kde in [
+                    GaussianKDE::new(data.clone(), weights.clone()).unwrap(),
+                    GaussianKDE::with_diagonal_covariance(data.clone(), weights.clone()).unwrap(),
+                ] {
+                    for xi in x.rows() {
+                        let expected = reference(&kde, xi);
+                        kde.set_whitened_data_cache(true);
+                        assert_relative_eq!(kde.eval(xi), expected, max_relative = 1e-12);
+                        kde.set_whitened_data_cache(false);
+                        assert_relative_eq!(kde.eval(xi), expected, max_relative = 1e-12);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn f32_precision_test() {
+        // Single-precision data, which is represented exactly in double precision
+        let mut rng = Pcg64Mcg::seed_from_u64(75);
+        let data = Array2::<f32>::from_shape_simple_fn((10_000, 2), || {
+            let z: f32 = rng.sample(StandardNormal);
+            100.0 + z
+        });
+        let weights = Array1::<f32>::from_shape_simple_fn(10_000, || rng.random_range(0.5..2.0));
+        let x = array![[100.0f32, 100.0], [100.5, 99.2], [98.0, 101.5]];
+
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let reference =
+            GaussianKDE::new(data.mapv(f64::from), Some(weights.mapv(f64::from))).unwrap();
+        let expected = reference
+            .eval_batch(x.mapv(f64::from).view())
+            .mapv(|f| f as f32);
+        assert_relative_eq!(
+            kde.covariance(),
+            reference.covariance().mapv(|c| c as f32),
+            epsilon = 1e-6 * reference.covariance()[[0, 0]] as f32,
+            max_relative = 1e-6
+        );
+        assert_relative_eq!(kde.eval_batch(x.view()), expected, max_relative = 1e-6);
+        let expected = reference
+            .ln_eval_batch(x.mapv(f64::from).view())
+            .mapv(|f| f as f32);
+        assert_relative_eq!(kde.ln_eval_batch(x.view()), expected, max_relative = 1e-6);
+    }
 
     #[test]
     fn eval_1d_test() {
@@ -146,4 +956,539 @@ mod tests {
             epsilon = 1E-10
         );
     }
+
+    #[test]
+    fn eval_batch_1d_test() {
+        let data = array![
+            [0.5634880436705391],
+            [0.445981611845074],
+            [0.7438671296401687]
+        ];
+        let w_test = array![0.04622972052712859, 0.15162404870472723, 0.291517199926956];
+        let x_test = array![[0.3], [0.5], [0.62], [1.1]];
+        for weights in [None, Some(w_test)] {
+            let kde = GaussianKDE::new(data.clone(), weights).unwrap();
+            let res = kde.eval_batch(x_test.view());
+            for (x, r) in x_test.rows().into_iter().zip(res.iter()) {
+                assert_relative_eq!(kde.eval(x), *r, epsilon = 1E-14);
+            }
+        }
+    }
+
+    #[test]
+    fn eval_batch_3d_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let w_test = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        #[rustfmt::skip]
+        let x_test = array![
+            [4.184559795606306309e-01, 1.755027879973122262e-01, 9.086181878876633533e-01],
+            [0.5, 0.6, 0.7],
+            [0.9, 0.1, 0.3]
+        ];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let res = kde.eval_batch(x_test.view());
+        assert_relative_eq!(res[0], 0.012985562962085305, epsilon = 1E-10);
+        for (x, r) in x_test.rows().into_iter().zip(res.iter()) {
+            assert_relative_eq!(kde.eval(x), *r, epsilon = 1E-14);
+        }
+        let kde = GaussianKDE::new(data, Some(w_test)).unwrap();
+        let res = kde.eval_batch(x_test.view());
+        assert_relative_eq!(res[0], 0.00019416613783346587, epsilon = 1E-10);
+        for (x, r) in x_test.rows().into_iter().zip(res.iter()) {
+            assert_relative_eq!(kde.eval(x), *r, epsilon = 1E-14);
+        }
+    }
+
+    #[test]
+    fn eval_batch_5d_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [0.6229016948897019, 0.7417869892607294, 0.7951935655656966, 0.9424502837770503, 0.7398985747399307],
+            [0.9223249966654170, 0.0290052282836147, 0.4656226543781053, 0.9433567169983137, 0.6489745531369242],
+            [0.9009004917506227, 0.1132059646531444, 0.4690690477821637, 0.2465728326198303, 0.5437608592359304],
+            [0.5739411879281008, 0.0131141895889022, 0.2167298004638482, 0.2794823660111103, 0.9163453718085519],
+            [0.7657254516291417, 0.1596042123580382, 0.7971469914312045, 0.1387674183989032, 0.6174525204661166],
+            [0.1266992325502697, 0.0017748622025346, 0.8714047447242821, 0.2094563824951179, 0.2154811692247323],
+            [0.9824211088259253, 0.8724077654368019, 0.2893051677469265, 0.9614779889500835, 0.5392234688708106],
+            [0.6778304772505923, 0.2047795145337928, 0.9409760010879991, 0.6906419411069082, 0.9665643123171954],
+            [0.8937416775764785, 0.2987888978538678, 0.3611899347223841, 0.1659560571297456, 0.1457019095406825],
+            [0.0651397133756763, 0.3013591007694625, 0.6031099974076544, 0.0033831193743568, 0.6779342495476912],
+            [0.3378968616278651, 0.3099579316031288, 0.8185180746470708, 0.4807451866300390, 0.3157931058464440],
+            [0.4812183862368639, 0.7046691341409093, 0.0570009295357899, 0.9750995631442353, 0.0228655632527207]
+        ];
+        #[rustfmt::skip]
+        let w_test = array![
+            0.7497950222912733, 0.8448808893881297, 0.0180675353785301, 0.7877383039804342, 0.3661844758418605,
+            0.5785188290568746, 0.0090783868195284, 0.0467271186989415, 0.1809194879510478, 0.9551798995911663,
+            0.1965216705130082, 0.7557364124513177
+        ];
+        #[rustfmt::skip]
+        let x_test = array![
+            [0.9296553195975211, 0.9420438294276994, 0.3443818130530120, 0.3547932050579122, 0.5247018206931200],
+            [0.7756030146989953, 0.1080528690648329, 0.7483980564846631, 0.7972266775913328, 0.8596943191875801],
+            [0.0366315799428275, 0.9458001850421838, 0.0911798641717686, 0.3407405355042222, 0.6108275380926242],
+            [0.9180871855333121, 0.3399595266258446, 0.9241976194277907, 0.5451440370982389, 0.3124503693429069]
+        ];
+        for weights in [None, Some(w_test)] {
+            let kde = GaussianKDE::new(data.clone(), weights).unwrap();
+            let res = kde.eval_batch(x_test.view());
+            for (x, r) in x_test.rows().into_iter().zip(res.iter()) {
+                assert_relative_eq!(kde.eval(x), *r, epsilon = 1E-14);
+            }
+        }
+    }
+
+    #[test]
+    fn ln_eval_1d_test() {
+        let data = array![
+            [0.5634880436705391],
+            [0.445981611845074],
+            [0.7438671296401687]
+        ];
+        let w_test = array![0.04622972052712859, 0.15162404870472723, 0.291517199926956];
+        let x_test: Array2<f64> = array![[0.3], [0.5], [0.62], [1.1]];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        for x in x_test.rows() {
+            assert_relative_eq!(kde.ln_eval(x), kde.eval(x).ln(), epsilon = 1E-12);
+        }
+        assert_eq!(kde.eval(array![9.0].view()), 0.0);
+        assert_relative_eq!(
+            kde.ln_eval(array![9.0].view()),
+            -2349.1634945202695,
+            max_relative = 1E-12
+        );
+        let kde = GaussianKDE::new(data, Some(w_test)).unwrap();
+        let res = kde.ln_eval_batch(x_test.view());
+        for (x, r) in x_test.rows().into_iter().zip(res.iter()) {
+            assert_relative_eq!(*r, kde.eval(x).ln(), epsilon = 1E-12);
+        }
+        assert_eq!(kde.eval(array![9.0].view()), 0.0);
+        assert_relative_eq!(
+            kde.ln_eval(array![9.0].view()),
+            -1349.3788077285562,
+            max_relative = 1E-12
+        );
+    }
+
+    #[test]
+    fn ln_eval_3d_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let w_test = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        #[rustfmt::skip]
+        let x_test = array![
+            [4.184559795606306309e-01, 1.755027879973122262e-01, 9.086181878876633533e-01],
+            [5.0, -4.0, 6.0]
+        ];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let res = kde.ln_eval_batch(x_test.view());
+        assert_relative_eq!(res[0], 0.012985562962085305_f64.ln(), epsilon = 1E-10);
+        assert_eq!(kde.eval(x_test.row(1)), 0.0);
+        assert_relative_eq!(res[1], -5971.914550828752, max_relative = 1E-12);
+        let kde = GaussianKDE::new(data, Some(w_test)).unwrap();
+        let res = kde.ln_eval_batch(x_test.view());
+        assert_relative_eq!(res[0], 0.00019416613783346587_f64.ln(), epsilon = 1E-10);
+        assert_eq!(kde.eval(x_test.row(1)), 0.0);
+        assert_relative_eq!(res[1], -12128.587050728787, max_relative = 1E-12);
+    }
+
+    #[test]
+    fn eval_high_dim_test() {
+        let dim = 50;
+        let mut rng = Pcg64Mcg::seed_from_u64(42);
+        let data: Array2<f64> =
+            Array2::from_shape_simple_fn((500, dim), || rng.sample(StandardNormal));
+        let mean = data.mean_axis(Axis(0)).unwrap();
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let ln_pdf = kde.ln_eval(mean.view());
+        assert!(ln_pdf.is_finite());
+        assert_relative_eq!(kde.eval(mean.view()), ln_pdf.exp(), max_relative = 1E-10);
+
+        // Shrinking the data by `scale` changes the density by `scale^(-dim)`. For this scale, the determinant of the
+        // covariance underflows while the density at the mean itself is still representable.
+        let scale: f64 = 3E-7;
+        let kde = GaussianKDE::new(data * scale, None).unwrap();
+        let pdf = kde.eval((&mean * scale).view());
+        assert!(pdf.is_finite());
+        assert_relative_eq!(
+            pdf,
+            f64::exp(ln_pdf - dim as f64 * scale.ln()),
+            max_relative = 1E-8
+        );
+        assert_relative_eq!(
+            kde.ln_eval((&mean * scale).view()),
+            ln_pdf - dim as f64 * scale.ln(),
+            max_relative = 1E-12
+        );
+    }
+
+    #[test]
+    fn eval_very_high_dim_test() {
+        let dim = 100;
+        let mut rng = Pcg64Mcg::seed_from_u64(7);
+        // For this scale, the normalization constant overflows while the density at the mean does not.
+        let data = Array2::from_shape_simple_fn((300, dim), || {
+            2.5E-4 * rng.sample::<f64, _>(StandardNormal)
+        });
+        let mean = data.mean_axis(Axis(0)).unwrap();
+        let kde = GaussianKDE::new(data, None).unwrap();
+        assert!(!kde.normalization.is_normal());
+        let pdf = kde.eval(mean.view());
+        assert!(pdf.is_finite() && pdf > 0.0);
+        assert_relative_eq!(pdf.ln(), kde.ln_eval(mean.view()), max_relative = 1E-10);
+    }
+
+    #[test]
+    fn eval_batch_chunked_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(86);
+        let data = Array2::<f64>::from_shape_simple_fn((2000, 3), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(2000, || rng.random_range(0.0..2.0));
+        let x = Array2::<f64>::from_shape_simple_fn((1000, 3), || {
+            2.0 * rng.sample::<f64, _>(StandardNormal)
+        });
+
+        for weights in [None, Some(weights)] {
+            let mut kde = GaussianKDE::new(data.clone(), weights).unwrap();
+            // The chunks do not coincide with the blocks of the batch evaluation
+            assert_eq!(kde.block_size(), 131);
+            for cache in [true, false] {
+                kde.set_whitened_data_cache(cache);
+                let expected = kde.eval_batch(x.view());
+                for chunk in [1, 7, 131, 400, 5000] {
+                    let mut res = Array1::from_elem(1000, f64::NAN);
+                    let mut n_chunks = 0;
+                    kde.eval_batch_chunked(x.view(), chunk, |i, densities| {
+                        assert!(densities.len() <= chunk);
+                        res.slice_mut(s![i..i + densities.len()]).assign(&densities);
+                        n_chunks += 1;
+                    });
+                    assert_eq!(res, expected);
+                    assert_eq!(n_chunks, 1000usize.div_ceil(chunk));
+                }
+                let res = kde
+                    .eval_iter(x.rows().into_iter().map(|x| x.to_owned()))
+                    .collect::<Array1<_>>();
+                assert_eq!(res, expected);
+                // Without a size hint, the blocks grow from a single point
+                let res = kde
+                    .eval_iter(x.rows().into_iter().map(|x| x.to_owned()).filter(|_| true))
+                    .collect::<Array1<_>>();
+                assert_eq!(res, expected);
+            }
+        }
+
+        let kde = GaussianKDE::new(data, None).unwrap();
+        kde.eval_batch_chunked(x.slice(s![..0, ..]), 10, |_, _| panic!());
+        assert_eq!(kde.eval_iter(std::iter::empty()).count(), 0);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn eval_batch_par_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(3);
+        let data: Array2<f64> =
+            Array2::from_shape_simple_fn((500, 3), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(500, || rng.random::<f64>());
+        let x_test: Array2<f64> =
+            Array2::from_shape_simple_fn((1000, 3), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+        let serial = kde.eval_batch(x_test.view());
+        assert_eq!(serial, kde.eval_batch_par(x_test.view()));
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(3)
+            .build()
+            .unwrap();
+        assert_eq!(serial, pool.install(|| kde.eval_batch_par(x_test.view())));
+    }
+
+    #[test]
+    fn loo_log_likelihood_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(4);
+        let data: Array2<f64> =
+            Array2::from_shape_simple_fn((50, 2), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(50, || rng.random::<f64>());
+        for weights in [None, Some(weights)] {
+            let kde = GaussianKDE::new(data.clone(), weights.clone()).unwrap();
+            // Brute-force reference with refits on the dataset with the i-th point removed
+            let mut reference = 0.0;
+            for i in 0..50 {
+                let keep = (0..50).filter(|j| *j != i).collect::<Vec<_>>();
+                let loo = GaussianKDE::with_bandwidth_matrix(
+                    data.select(Axis(0), &keep),
+                    weights.as_ref().map(|w| w.select(Axis(0), &keep)),
+                    kde.covariance(),
+                )
+                .unwrap();
+                let w_i = weights.as_ref().map_or(1.0, |w| w[i]);
+                reference += w_i * loo.ln_eval(data.row(i));
+            }
+            let res = kde.loo_log_likelihood();
+            assert_relative_eq!(res, reference, max_relative = 1e-12);
+            assert_eq!(res, kde.loo_log_likelihood());
+        }
+
+        // A single point has no LOO density
+        let kde = GaussianKDE::with_bandwidth_matrix(array![[0.0]], None, array![[1.0]]).unwrap();
+        assert_eq!(kde.loo_log_likelihood(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn self_densities_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(91);
+        let data: Array2<f64> =
+            Array2::from_shape_simple_fn((100, 3), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(100, || rng.random::<f64>());
+        for weights in [None, Some(weights)] {
+            let mut kde = GaussianKDE::new(data.clone(), weights.clone()).unwrap();
+            for cache in [true, false] {
+                kde.set_whitened_data_cache(cache);
+                assert_eq!(kde.self_densities(false), kde.eval_batch(kde.data()));
+                assert_eq!(kde.self_log_densities(false), kde.ln_eval_batch(kde.data()));
+
+                // Brute-force reference with refits on the dataset with the i-th point removed
+                let reference = Array1::from_shape_fn(100, |i| {
+                    let keep = (0..100).filter(|j| *j != i).collect::<Vec<_>>();
+                    let loo = GaussianKDE::with_bandwidth_matrix(
+                        data.select(Axis(0), &keep),
+                        weights.as_ref().map(|w| w.select(Axis(0), &keep)),
+                        kde.covariance(),
+                    )
+                    .unwrap();
+                    loo.eval(data.row(i))
+                });
+                let loo = kde.self_densities(true);
+                assert_relative_eq!(loo, reference, max_relative = 1e-12);
+                assert_relative_eq!(
+                    kde.self_log_densities(true),
+                    reference.ln(),
+                    max_relative = 1e-12
+                );
+                assert!(
+                    ndarray::Zip::from(&loo)
+                        .and(&kde.self_densities(false))
+                        .all(|a, b| a < b)
+                );
+            }
+        }
+
+        // The LOO density of a single point vanishes
+        let kde = GaussianKDE::with_bandwidth_matrix(array![[0.0]], None, array![[1.0]]).unwrap();
+        assert_eq!(kde.self_densities(true), array![0.0]);
+        assert_eq!(kde.self_log_densities(true), array![f64::NEG_INFINITY]);
+    }
+
+    #[test]
+    fn loo_log_likelihood_peak_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(5);
+        let n = 200;
+        let data: Array2<f64> = Array2::from_shape_simple_fn((n, 1), || rng.sample(StandardNormal));
+        let mut kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let factors = Array1::geomspace(0.05, 2.0, 60).unwrap();
+        let (h_best, _) = factors.iter().fold((0.0, f64::NEG_INFINITY), |best, h| {
+            kde.set_bandwidth_factor(*h).unwrap();
+            let ll = kde.loo_log_likelihood();
+            if ll > best.1 { (*h, ll) } else { best }
+        });
+        // AMISE-optimal bandwidth factor for normally distributed data
+        let h_amise = (4.0 / 3.0 / n as f64).powf(0.2);
+        assert!(h_best / h_amise < 1.5 && h_amise / h_best < 1.5);
+        let h_mlcv = MLCVBandwidth::default().bandwidth(data.view(), None);
+        assert_relative_eq!(h_best, h_mlcv, max_relative = 0.07);
+    }
+
+    #[test]
+    fn score_samples_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let w_test = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        #[rustfmt::skip]
+        let x_test = array![
+            [4.184559795606306309e-01, 1.755027879973122262e-01, 9.086181878876633533e-01],
+            [0.5, 0.5, 0.5],
+            [0.9, 0.1, 0.2],
+            [10.0, -10.0, 10.0]
+        ];
+        let score_weights = array![0.5, 1.0, 2.0, 0.25];
+        // Reference values for scikit-learn's `KernelDensity(bandwidth=0.3, rtol=0)` with an isotropic kernel
+        // H = 0.09 I, evaluated with an independent log-sum-exp implementation of its formula
+        let res = [
+            -1.0027234226334867,
+            -0.4700141487460321,
+            -3.199439093187651,
+            -1567.6023096981785,
+        ];
+        let res_w = [
+            -0.843623828056469,
+            -0.45730298055829954,
+            -3.968402944263611,
+            -1571.9405553611641,
+        ];
+        for (weights, res, res_score, res_score_w) in [
+            (None, res, -1572.2744863627456, -399.27083147098267),
+            (Some(w_test), res_w, -1577.2098851140424, -401.8010596234048),
+        ] {
+            let kde = GaussianKDE::with_bandwidth_matrix(
+                data.clone(),
+                weights,
+                Array2::from_diag_elem(3, 0.09),
+            )
+            .unwrap();
+            let scores = kde.score_samples(x_test.view()).unwrap();
+            assert!(scores.iter().all(|s: &f64| s.is_finite()));
+            assert_relative_eq!(scores, Array1::from(res.to_vec()), max_relative = 1e-12);
+            assert_relative_eq!(
+                kde.score(x_test.view(), None).unwrap(),
+                res_score,
+                max_relative = 1e-12
+            );
+            assert_relative_eq!(
+                kde.score(x_test.view(), Some(score_weights.view()))
+                    .unwrap(),
+                res_score_w,
+                max_relative = 1e-12
+            );
+        }
+
+        // The far-away query underflows in `eval` but not in `score_samples`
+        let kde = GaussianKDE::new(data, None).unwrap();
+        assert_eq!(kde.eval(x_test.row(3)), 0.0);
+        assert_relative_eq!(
+            kde.score_samples(x_test.view()).unwrap()[3],
+            -26922.419160716177,
+            max_relative = 1e-12
+        );
+
+        let res = kde.score_samples(array![[0.1, 0.2]].view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.score(x_test.view(), Some(array![1.0, 2.0].view()));
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn eval_batch_into_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(6);
+        let data: Array2<f64> =
+            Array2::from_shape_simple_fn((100, 3), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(100, || rng.random::<f64>());
+        let x_test: Array2<f64> =
+            Array2::from_shape_simple_fn((20, 3), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+        let mut out = Array1::from_elem(20, f64::NAN);
+        kde.eval_batch_into(x_test.view(), out.view_mut()).unwrap();
+        assert_eq!(out, kde.eval_batch(x_test.view()));
+        // Reuse the buffer for the log-density
+        kde.ln_eval_batch_into(x_test.view(), out.view_mut())
+            .unwrap();
+        assert_eq!(out, kde.ln_eval_batch(x_test.view()));
+        // Strided output views
+        let mut out = Array2::zeros((20, 2));
+        kde.eval_batch_into(x_test.view(), out.column_mut(1))
+            .unwrap();
+        assert_eq!(out.column(1), kde.eval_batch(x_test.view()));
+
+        let mut out = Array1::zeros(19);
+        let res = kde.eval_batch_into(x_test.view(), out.view_mut());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.ln_eval_batch_into(x_test.view(), out.view_mut());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        assert!(out.iter().all(|x| *x == 0.0));
+        let mut out = Array1::zeros(20);
+        let res = kde.eval_batch_into(x_test.slice(s![.., ..2]), out.view_mut());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn try_eval_test() {
+        let data = array![[0.1, 0.4], [0.3, -0.2], [-0.5, 0.7], [0.9, 0.0]];
+        let kde = GaussianKDE::new(data, Some(array![1.0, 0.5, 2.0, 0.3])).unwrap();
+        let x = array![[0.0, 0.0], [0.2, -0.1], [1.5, 2.0]];
+
+        for xi in x.rows() {
+            assert_eq!(kde.try_eval(xi).unwrap(), kde.eval(xi));
+        }
+        assert_eq!(
+            kde.try_eval_batch(x.view()).unwrap(),
+            kde.eval_batch(x.view())
+        );
+        assert_eq!(
+            kde.try_eval_batch(Array2::zeros((0, 2)).view())
+                .unwrap()
+                .dim(),
+            0
+        );
+
+        let res = kde.try_eval(array![0.0, 0.0, 0.0].view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.try_eval_batch(array![[0.0], [1.0]].view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.try_eval_batch(Array2::zeros((0, 3)).view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
 }
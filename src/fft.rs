@@ -0,0 +1,200 @@
+//! A minimal radix-2 fast Fourier transform and the discrete cosine transform built on top of it. Both are only
+//! needed for binned data, whose grid sizes are chosen by the crate itself, so only power-of-two lengths are
+//! supported. This avoids pulling in a dedicated FFT crate for a few dozen lines of code.
+
+use alloc::{vec, vec::Vec};
+use ndarray::{Axis, Zip, prelude::*};
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// In-place forward discrete Fourier transform $X_k = \sum_j x_j e^{-2 \pi i j k / n}$ of the complex sequence
+/// given by its real and imaginary parts. The length $n$ must be a power of two.
+pub(crate) fn fft<F>(re: &mut [F], im: &mut [F])
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    // Butterflies, with the twiddle factors of all stages taken from the ones of the last stage
+    let angle = -F::TAU() / F::from_usize(n).unwrap();
+    let twiddles: Vec<(F, F)> = (0..n / 2)
+        .map(|k| (angle * F::from_usize(k).unwrap()).sin_cos())
+        .collect();
+    let mut len = 2;
+    while len <= n {
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_im, w_re) = twiddles[k * (n / len)];
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] = re[a] + t_re;
+                im[a] = im[a] + t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// In-place inverse discrete Fourier transform $x_j = \frac{1}{n} \sum_k X_k e^{2 \pi i j k / n}$, i.e. the inverse
+/// of [`fft`]. The length $n$ must be a power of two.
+pub(crate) fn ifft<F>(re: &mut [F], im: &mut [F])
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    // The inverse transform is the conjugate of the forward transform of the conjugate
+    im.iter_mut().for_each(|x| *x = -*x);
+    fft(re, im);
+    let scale = F::from_usize(re.len()).unwrap().recip();
+    re.iter_mut().for_each(|x| *x = *x * scale);
+    im.iter_mut().for_each(|x| *x = -*x * scale);
+}
+
+/// In-place multi-dimensional (inverse) discrete Fourier transform of the complex array given by its real and
+/// imaginary parts, i.e. the one-dimensional transform along each axis. All lengths must be powers of two.
+pub(crate) fn fft_nd<F>(re: &mut ArrayD<F>, im: &mut ArrayD<F>, inverse: bool)
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    for axis in 0..re.ndim() {
+        let n = re.len_of(Axis(axis));
+        let (mut lane_re, mut lane_im) = (vec![F::zero(); n], vec![F::zero(); n]);
+        Zip::from(re.lanes_mut(Axis(axis)))
+            .and(im.lanes_mut(Axis(axis)))
+            .for_each(|mut re, mut im| {
+                lane_re.iter_mut().zip(re.iter()).for_each(|(a, b)| *a = *b);
+                lane_im.iter_mut().zip(im.iter()).for_each(|(a, b)| *a = *b);
+                if inverse {
+                    ifft(&mut lane_re, &mut lane_im);
+                } else {
+                    fft(&mut lane_re, &mut lane_im);
+                }
+                re.iter_mut().zip(lane_re.iter()).for_each(|(a, b)| *a = *b);
+                im.iter_mut().zip(lane_im.iter()).for_each(|(a, b)| *a = *b);
+            });
+    }
+}
+
+/// Unnormalized type-II discrete cosine transform $X_k = \sum_j x_j \cos\left(\frac{\pi k (2j + 1)}{2n}\right)$ of a
+/// real sequence whose length $n$ is a power of two, computed via an FFT of length $n$.
+pub(crate) fn dct<F>(x: &[F]) -> Vec<F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let n = x.len();
+    // Even-indexed elements in ascending order followed by odd-indexed elements in descending order
+    let mut re: Vec<F> = x
+        .iter()
+        .step_by(2)
+        .chain(x.iter().skip(1).step_by(2).rev())
+        .copied()
+        .collect();
+    let mut im = vec![F::zero(); n];
+    fft(&mut re, &mut im);
+    let angle = -F::PI() / F::from_usize(2 * n).unwrap();
+    return (0..n)
+        .map(|k| {
+            let (w_im, w_re) = (angle * F::from_usize(k).unwrap()).sin_cos();
+            re[k] * w_re - im[k] * w_im
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dct, fft, fft_nd, ifft};
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn fft_test() {
+        let x: Vec<f64> = (0..16)
+            .map(|i| (0.3 * i as f64).sin() + 0.1 * i as f64)
+            .collect();
+        let (mut re, mut im) = (x.clone(), vec![0.0; 16]);
+        fft(&mut re, &mut im);
+        for k in 0..16 {
+            let (mut naive_re, mut naive_im) = (0.0, 0.0);
+            for (j, x_j) in x.iter().enumerate() {
+                let angle = -2.0 * PI * (j * k) as f64 / 16.0;
+                naive_re += x_j * angle.cos();
+                naive_im += x_j * angle.sin();
+            }
+            assert_relative_eq!(re[k], naive_re, epsilon = 1e-12);
+            assert_relative_eq!(im[k], naive_im, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn fft_nd_test() {
+        let x = Array2::from_shape_fn((4, 8), |(i, j)| {
+            (0.3 * i as f64).sin() + 0.1 * (i * j) as f64
+        });
+        let (mut re, mut im) = (x.clone().into_dyn(), ArrayD::zeros(IxDyn(&[4, 8])));
+        fft_nd(&mut re, &mut im, false);
+        for ((k, l), x_kl) in re
+            .clone()
+            .into_dimensionality::<Ix2>()
+            .unwrap()
+            .indexed_iter()
+        {
+            let (mut naive_re, mut naive_im) = (0.0, 0.0);
+            for ((i, j), x_ij) in x.indexed_iter() {
+                let angle = -2.0 * PI * ((i * k) as f64 / 4.0 + (j * l) as f64 / 8.0);
+                naive_re += x_ij * angle.cos();
+                naive_im += x_ij * angle.sin();
+            }
+            assert_relative_eq!(*x_kl, naive_re, epsilon = 1e-12);
+            assert_relative_eq!(im[[k, l]], naive_im, epsilon = 1e-12);
+        }
+        // The inverse transform restores the input
+        fft_nd(&mut re, &mut im, true);
+        assert_relative_eq!(re, x.into_dyn(), epsilon = 1e-12);
+        assert_relative_eq!(im, ArrayD::zeros(IxDyn(&[4, 8])), epsilon = 1e-12);
+        let (mut re, mut im) = (vec![1.0, 2.0, -1.0, 0.5], vec![0.0, 0.3, 0.0, -0.2]);
+        fft(&mut re, &mut im);
+        ifft(&mut re, &mut im);
+        assert_relative_eq!(
+            re.as_slice(),
+            [1.0, 2.0, -1.0, 0.5].as_slice(),
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(
+            im.as_slice(),
+            [0.0, 0.3, 0.0, -0.2].as_slice(),
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn dct_test() {
+        let x: Vec<f64> = (0..32)
+            .map(|i| (0.7 * i as f64).cos() * (i % 5) as f64)
+            .collect();
+        let res = dct(&x);
+        for (k, res_k) in res.iter().enumerate() {
+            let naive: f64 = x
+                .iter()
+                .enumerate()
+                .map(|(j, x_j)| x_j * (PI * (k * (2 * j + 1)) as f64 / 64.0).cos())
+                .sum();
+            assert_relative_eq!(*res_k, naive, epsilon = 1e-12);
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! Evaluation of KDEs on regular grids, e.g. for plotting curves and heatmaps.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::binning::{bin, check_grid, quintic_stencil};
+use crate::fft::fft_nd;
+use crate::{Bandwidth, CowGaussianKDE, ErrorKind, KDEError};
+use alloc::{format, vec, vec::Vec};
+use ndarray::{Slice, Zip, prelude::*};
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// Number of cells of the binning grid per (conditional) standard deviation of the kernel along each axis, which
+/// limits the relative error of the binning to a few $10^{-7}$ within about three standard deviations of the data.
+const CELLS_PER_STD: f64 = 12.0;
+
+/// Support of the kernel in (marginal) standard deviations along each axis, beyond which the kernel is below
+/// $e^{-32} \approx 10^{-14}$ of its maximum.
+const KERNEL_SUPPORT: f64 = 8.0;
+
+/// Maximal number of elements of the zero-padded grid, i.e. 128 MB per array in double precision.
+const MAX_FFT_SIZE: usize = 1 << 24;
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Evaluate the probability density estimated by the KDE at the Cartesian product of the coordinates `axes[k]`
+    /// along each axis $k$, and return it as array of shape `(axes[0].len(), ..., axes[dim - 1].len())`, i.e. the
+    /// element `[j_0, ..., j_{dim - 1}]` is the density at the point `[axes[0][j_0], ..., axes[dim - 1][j_{dim - 1}]]`.
+    ///
+    /// The grid points are generated block by block in the same order and with the same blocks as in
+    /// [`GaussianKDE::eval_batch`], such that the results agree exactly with the ones of the meshgrid evaluated by
+    /// [`GaussianKDE::eval_batch`], without storing all grid points at once. The axes need not be equidistant. For
+    /// large regular grids of KDEs of dimension one or two, see [`GaussianKDE::eval_grid_fft`].
+    ///
+    /// Returns a `ShapeError` if the number of axes does not match the dimension of the KDE.
+    pub fn eval_grid(&self, axes: &[ArrayView1<F>]) -> Result<ArrayD<F>, KDEError> {
+        let dim = self.n_dims();
+        if axes.len() != dim {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!("expected {dim} grid axes, found {}", axes.len()),
+            ));
+        }
+        let shape = axes.iter().map(|axis| axis.len()).collect::<Vec<_>>();
+        let mut res = ArrayD::zeros(IxDyn(&shape));
+        let n_points = res.len();
+        let block_size = self.block_size().min(n_points.max(1));
+        let mut x = Array2::zeros((block_size, dim));
+        self.with_whitened_data(|whitened| {
+            let mut out = res.view_mut().into_shape_with_order(n_points).unwrap();
+            for (i, out) in out.axis_chunks_iter_mut(Axis(0), block_size).enumerate() {
+                let len = out.len();
+                for (j, mut point) in x.rows_mut().into_iter().take(len).enumerate() {
+                    // Unravel the index of the grid point in row-major order
+                    let mut index = i * block_size + j;
+                    for k in (0..dim).rev() {
+                        point[k] = axes[k][index % shape[k]];
+                        index /= shape[k];
+                    }
+                }
+                self.eval_block(whitened, x.slice(s![..len, ..]), out, false);
+            }
+        });
+        return Ok(res);
+    }
+
+    /// Evaluate the probability density estimated by the KDE on the regular grid with `shape[k]` points spanning
+    /// `ranges[k]` (including both ends) along each axis $k$, by binning the data and convolving it with the kernel via
+    /// FFT. See [`GaussianKDE::eval_grid_fft_with_axes`] for details.
+    pub fn eval_grid_fft(&self, ranges: &[(F, F)], shape: &[usize]) -> Result<ArrayD<F>, KDEError> {
+        return self
+            .eval_grid_fft_with_axes(ranges, shape)
+            .map(|(res, _)| res);
+    }
+
+    /// Evaluate the probability density estimated by the KDE on the regular grid with `shape[k]` points spanning
+    /// `ranges[k]` (including both ends) along each axis $k$, and return it as array of the given shape together with
+    /// the coordinates of the grid points along each axis.
+    ///
+    /// Instead of evaluating all kernels at all grid points, which costs $\mathcal{O}(n G)$ for $G$ grid points, the
+    /// (weighted) data is binned onto a grid and convolved with the kernel via FFT, which costs
+    /// $\mathcal{O}(n + G \log G)$. The binning grid refines the requested one to about a dozen cells per standard
+    /// deviation of the kernel along each axis, and extends it by the support of the kernel (8 standard deviations)
+    /// with zero-padding to avoid wrap-around, such that data points outside of the range contribute as well. To keep
+    /// the binning error below about $10^{-6}$ relative to the density, each point is distributed over the six
+    /// nearest nodes along each axis with the weights of quintic Lagrange interpolation, since linear binning would
+    /// require a much finer grid for the same accuracy. The rounding errors of the FFT are of the order of the machine
+    /// precision relative to the maximum of the density, which limits the relative accuracy far in the tails, where
+    /// negative results are clamped to zero. For small grids or datasets, [`GaussianKDE::eval_batch`] is faster.
+    ///
+    /// Only KDEs of dimension one and two are supported, i.e. higher-dimensional KDEs have to be marginalized to the
+    /// components of interest first, which uses the corresponding sub-block of the kernel covariance matrix.
+    ///
+    /// Returns a `ShapeError` if the dimension of the KDE is not one or two, if the lengths of `ranges` and `shape` do
+    /// not match it or if any axis has no grid points, and a `DomainError` if a range is not finite, its lower
+    /// bound exceeds its upper bound (or equals it for more than one point), or if the binning grid would exceed
+    /// $2^{24}$ points, e.g. for a range much wider than the kernel.
+    pub fn eval_grid_fft_with_axes(
+        &self,
+        ranges: &[(F, F)],
+        shape: &[usize],
+    ) -> Result<(ArrayD<F>, Vec<Array1<F>>), KDEError> {
+        let dim = self.n_dims();
+        if dim > 2 {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "FFT grid evaluation supports KDEs of dimension 1 and 2, found dimension {dim}, which has to be marginalized first"
+                ),
+            ));
+        }
+        check_grid(ranges, shape, dim)?;
+
+        // Binning grid along each axis, with the requested grid points at `first + j * refinement`
+        let mut origin = Vec::with_capacity(dim);
+        let mut spacing = Vec::with_capacity(dim);
+        let mut refinement = Vec::with_capacity(dim);
+        let mut first = Vec::with_capacity(dim);
+        let mut support = Vec::with_capacity(dim);
+        let mut n_bins = Vec::with_capacity(dim);
+        let mut n_fft = Vec::with_capacity(dim);
+        for k in 0..dim {
+            let ((lower, upper), n) = (ranges[k], shape[k]);
+            // Standard deviation of the kernel along the axis for fixed other components, and of its marginal
+            let std = self
+                .inv_cholesky
+                .column(k)
+                .dot(&self.inv_cholesky.column(k))
+                .sqrt()
+                .recip();
+            let marginal_std = self.cholesky.row(k).dot(&self.cholesky.row(k)).sqrt();
+            let max_spacing = std / F::from(CELLS_PER_STD).unwrap();
+            let (delta, r) = if n > 1 {
+                let delta = (upper - lower) / F::from(n - 1).unwrap();
+                let r = (delta / max_spacing).ceil();
+                (delta / r, r)
+            } else {
+                (max_spacing, F::one())
+            };
+            let s = (F::from(KERNEL_SUPPORT).unwrap() * marginal_std / delta).ceil();
+            let size = F::from(n - 1).unwrap() * r + F::from(2).unwrap() * s;
+            if size >= F::from(MAX_FFT_SIZE).unwrap() {
+                return Err(grid_size_error());
+            }
+            let (r, s) = (r.to_usize().unwrap(), s.to_usize().unwrap());
+            // The quintic stencil extends up to three nodes beyond the support of the kernel
+            let offset = s + 3;
+            origin.push(lower - F::from(offset).unwrap() * delta);
+            spacing.push(delta);
+            refinement.push(r);
+            first.push(offset);
+            support.push(s);
+            n_bins.push((n - 1) * r + 1 + 2 * offset);
+            // Linear convolution without wrap-around of the kernel on the binning grid
+            n_fft.push(((n - 1) * r + 1 + 2 * offset + s).next_power_of_two());
+        }
+        if n_fft
+            .iter()
+            .try_fold(1usize, |acc, n| acc.checked_mul(*n))
+            .is_none_or(|n| n > MAX_FFT_SIZE)
+        {
+            return Err(grid_size_error());
+        }
+
+        let binned = bin(self.dataset().view(), self.weights(), &n_bins, |k, x| {
+            return quintic_stencil(x, origin[k], spacing[k]);
+        });
+        let mut re = ArrayD::zeros(IxDyn(&n_fft));
+        re.slice_each_axis_mut(|axis| Slice::from(0..n_bins[axis.axis.index()]))
+            .assign(&binned);
+        let mut im = ArrayD::zeros(IxDyn(&n_fft));
+
+        // Unnormalized kernel at the offsets within its support, where negative offsets wrap around
+        let half = F::from(0.5).unwrap();
+        let mut kernel_re = ArrayD::zeros(IxDyn(&n_fft));
+        let mut delta = vec![F::zero(); dim];
+        for (index, kernel) in kernel_re.indexed_iter_mut() {
+            let mut within = true;
+            for k in 0..dim {
+                let offset = if index[k] <= support[k] {
+                    F::from(index[k]).unwrap()
+                } else if index[k] >= n_fft[k] - support[k] {
+                    -F::from(n_fft[k] - index[k]).unwrap()
+                } else {
+                    within = false;
+                    break;
+                };
+                delta[k] = offset * spacing[k];
+            }
+            if within {
+                let mut q = F::zero();
+                for i in 0..dim {
+                    let z = (0..=i).fold(F::zero(), |acc, k| {
+                        acc + self.inv_cholesky[[i, k]] * delta[k]
+                    });
+                    q = q + z * z;
+                }
+                *kernel = F::exp(-half * q);
+            }
+        }
+        let mut kernel_im = ArrayD::zeros(IxDyn(&n_fft));
+
+        fft_nd(&mut re, &mut im, false);
+        fft_nd(&mut kernel_re, &mut kernel_im, false);
+        Zip::from(&mut re)
+            .and(&mut im)
+            .and(&kernel_re)
+            .and(&kernel_im)
+            .for_each(|re, im, k_re, k_im| {
+                (*re, *im) = (*re * *k_re - *im * *k_im, *re * *k_im + *im * *k_re);
+            });
+        fft_nd(&mut re, &mut im, true);
+
+        let mut index = vec![0; dim];
+        let res = ArrayD::from_shape_fn(IxDyn(shape), |j| {
+            for k in 0..dim {
+                index[k] = first[k] + j[k] * refinement[k];
+            }
+            let sum = re[index.as_slice()].max(F::zero());
+            if self.normalization.is_normal() {
+                sum * self.normalization
+            } else {
+                F::exp(sum.ln() + self.ln_normalization)
+            }
+        });
+        let axes = ranges
+            .iter()
+            .zip(shape)
+            .map(|((lower, upper), n)| linspace(*lower, *upper, *n))
+            .collect();
+        return Ok((res, axes));
+    }
+}
+
+/// Error for binning grids exceeding `MAX_FFT_SIZE` points.
+fn grid_size_error() -> KDEError {
+    return KDEError::new(
+        ErrorKind::DomainError,
+        format!(
+            "the binning grid would exceed {MAX_FFT_SIZE} points, since the grid range is too wide compared to the kernel"
+        ),
+    );
+}
+
+/// `n` evenly spaced points from `lower` to `upper`, identical to `Array1::linspace`, which requires `std`.
+fn linspace<F: Float>(lower: F, upper: F, n: usize) -> Array1<F> {
+    let step = if n > 1 {
+        (upper - lower) / F::from(n - 1).unwrap()
+    } else {
+        F::zero()
+    };
+    return Array1::from_shape_fn(n, |i| lower + step * F::from(i).unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ErrorKind, GaussianKDE};
+    use ndarray::{Zip, prelude::*};
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    /// Largest relative deviation of `approx` from `exact`.
+    fn max_relative_error(approx: ArrayViewD<f64>, exact: ArrayViewD<f64>) -> f64 {
+        return Zip::from(&approx)
+            .and(&exact)
+            .fold(0.0f64, |acc, a, e| acc.max((a - e).abs() / e));
+    }
+
+    #[test]
+    fn eval_grid_fft_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(80);
+        let mut data =
+            Array2::<f64>::from_shape_simple_fn((5000, 2), || rng.sample(StandardNormal));
+        let x0 = data.column(0).to_owned();
+        data.column_mut(1).scaled_add(0.7, &x0);
+        let weights = Array1::from_shape_simple_fn(5000, || rng.random_range(0.0..1.0));
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+
+        // The grid covers part of the data only, such that points outside of it contribute
+        let ranges = [(-1.5, 2.0), (-2.5, 1.0)];
+        let (res, axes) = kde.eval_grid_fft_with_axes(&ranges, &[36, 29]).unwrap();
+        assert_eq!(res.shape(), &[36, 29]);
+        let x = Array2::from_shape_fn((36 * 29, 2), |(i, k)| {
+            if k == 0 {
+                axes[0][i / 29]
+            } else {
+                axes[1][i % 29]
+            }
+        });
+        let exact = kde
+            .eval_batch(x.view())
+            .into_shape_with_order(IxDyn(&[36, 29]))
+            .unwrap();
+        assert!(max_relative_error(res.view(), exact.view()) < 1e-6);
+
+        // One-dimensional marginal with a single grid point and with a grid coarser than the bandwidth
+        let marginal = kde.marginalize_to(&[1]).unwrap();
+        let res = marginal.eval_grid_fft(&[(0.3, 0.3)], &[1]).unwrap();
+        assert!((res[[0]] / marginal.eval(array![0.3].view()) - 1.0).abs() < 1e-6);
+        let (res, axes) = marginal
+            .eval_grid_fft_with_axes(&[(-3.0, 3.0)], &[7])
+            .unwrap();
+        let exact = marginal.eval_batch(axes[0].view().insert_axis(Axis(1)));
+        assert!(max_relative_error(res.view(), exact.into_dyn().view()) < 1e-6);
+    }
+
+    #[test]
+    fn eval_grid_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(87);
+        let data = Array2::<f64>::from_shape_simple_fn((3000, 2), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(3000, || rng.random_range(0.0..1.0));
+        let mut kde = GaussianKDE::new(data.clone(), Some(weights)).unwrap();
+
+        // Meshgrid as in `examples/tth.rs`
+        const N_GRID: usize = 50;
+        let x = Array1::linspace(-3.0, 3.0, N_GRID);
+        let grid = Array3::from_shape_fn(
+            (N_GRID, N_GRID, 2),
+            |(i, j, k)| if k == 0 { x[i] } else { x[j] },
+        )
+        .into_shape_with_order((N_GRID * N_GRID, 2))
+        .unwrap();
+        let expected = kde
+            .eval_batch(grid.view())
+            .into_shape_with_order((N_GRID, N_GRID))
+            .unwrap()
+            .into_dyn();
+        for cache in [true, false] {
+            kde.set_whitened_data_cache(cache);
+            assert_eq!(kde.eval_grid(&[x.view(), x.view()]).unwrap(), expected);
+        }
+
+        // Axes of different lengths, which are not equidistant
+        let data = Array2::<f64>::from_shape_simple_fn((200, 3), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let axes = [
+            array![-1.0, 0.5],
+            array![0.0, 0.1, 2.0],
+            array![-0.3, 0.4, 0.8, 1.9],
+        ];
+        let res = kde
+            .eval_grid(&axes.each_ref().map(|axis| axis.view()))
+            .unwrap();
+        assert_eq!(res.shape(), &[2, 3, 4]);
+        for ((i, j, k), density) in res.into_dimensionality::<Ix3>().unwrap().indexed_iter() {
+            let point = array![axes[0][i], axes[1][j], axes[2][k]];
+            assert_eq!(
+                *density,
+                kde.eval_batch(point.insert_axis(Axis(0)).view())[0]
+            );
+        }
+
+        // Axes without coordinates give an empty grid
+        let res = kde
+            .eval_grid(&[axes[0].view(), array![].view(), axes[2].view()])
+            .unwrap();
+        assert_eq!(res.shape(), &[2, 0, 4]);
+
+        let res = kde.eval_grid(&[axes[0].view(), axes[1].view()]);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn eval_grid_fft_error_test() {
+        let data = array![
+            [0.1, 0.2, 0.3],
+            [0.5, -0.3, 0.1],
+            [0.8, 0.4, -0.2],
+            [-0.2, 0.6, 0.4],
+            [0.3, 0.1, 0.9]
+        ];
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let res = kde.eval_grid_fft(&[(0.0, 1.0); 3], &[10; 3]);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let kde = kde.marginalize_to(&[0, 1]).unwrap();
+        let res = kde.eval_grid_fft(&[(0.0, 1.0)], &[10, 10]);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.eval_grid_fft(&[(0.0, 1.0); 2], &[10, 0]);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        for range in [
+            (1.0, 0.0),
+            (0.0, 0.0),
+            (0.0, f64::INFINITY),
+            (f64::NAN, 1.0),
+        ] {
+            let res = kde.eval_grid_fft(&[(0.0, 1.0), range], &[10, 10]);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+        // Ranges much wider than the kernel
+        let res = kde.eval_grid_fft(&[(-1e6, 1e6), (0.0, 1.0)], &[10, 10]);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+    }
+}
@@ -0,0 +1,393 @@
+//! Highest density regions (HDRs) of KDEs, i.e. the smallest regions containing a given probability mass, which are
+//! bounded by contours of the density, e.g. for credible regions in contour plots.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::{Bandwidth, CowGaussianKDE};
+use alloc::vec::Vec;
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+#[cfg(feature = "sample")]
+use rand::Rng;
+#[cfg(feature = "sample")]
+use rand_distr::{Distribution, StandardNormal, uniform::SampleUniform, weighted::Weight};
+
+/// Empirical distribution of the density $f(X)$ of a KDE at points $X$ distributed according to it, from which the
+/// thresholds $f_p$ of the highest density regions $\\{x : f(x) \geq f_p\\}$ with probability mass $p$ are obtained,
+/// as returned by [`GaussianKDE::hdr_levels_from_data`] or, with the `sample` feature, by `hdr_levels`.
+///
+/// Estimating the distribution requires evaluating the KDE at many points, while the thresholds for any mass are then
+/// looked up in $\mathcal{O}(\log n)$, such that the levels are estimated once and reused, e.g. for testing many
+/// points with [`GaussianKDE::in_hdr`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HdrLevels<F> {
+    // Densities in decreasing order
+    densities: Vec<F>,
+    // Fraction of the total weight of the densities up to and including each one, where the last one is exactly one
+    cumulative: Vec<F>,
+}
+
+impl<F> HdrLevels<F>
+where
+    F: Float + 'static,
+{
+    /// Construct the distribution from the (weighted) `densities` at the points.
+    fn new(densities: ArrayView1<F>, weights: Option<ArrayView1<F>>) -> Self {
+        let mut order = (0..densities.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| densities[*b].partial_cmp(&densities[*a]).unwrap());
+        let weight = |i: usize| weights.map_or(F::one(), |w| w[i]);
+        let mut cumulative = order
+            .iter()
+            .scan(F::zero(), |acc, i| {
+                *acc = *acc + weight(*i);
+                Some(*acc)
+            })
+            .collect::<Vec<_>>();
+        let total = cumulative[cumulative.len() - 1];
+        cumulative.iter_mut().for_each(|c| *c = *c / total);
+        // Guard against rounding of the total weight for the mass one
+        *cumulative.last_mut().unwrap() = F::one();
+        return Self {
+            densities: order.into_iter().map(|i| densities[i]).collect(),
+            cumulative,
+        };
+    }
+
+    /// The largest threshold $f_p$ such that the densities at or above it hold at least the fraction `mass` of the
+    /// total weight, i.e. the $(1 - p)$-quantile of the densities as inverse of their empirical distribution function.
+    ///
+    /// *Panics* if `mass` is not in $[0, 1]$.
+    pub fn level(&self, mass: F) -> F {
+        assert!(
+            mass >= F::zero() && mass <= F::one(),
+            "the probability mass must be in [0, 1], found {}",
+            mass.to_f64().unwrap()
+        );
+        return self.densities[self.cumulative.partition_point(|c| *c < mass)];
+    }
+
+    /// The thresholds for the probability masses given by `masses`, see [`HdrLevels::level`].
+    ///
+    /// *Panics* if any mass is not in $[0, 1]$.
+    pub fn levels(&self, masses: &[F]) -> Vec<F> {
+        return masses.iter().map(|p| self.level(*p)).collect();
+    }
+}
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Estimate the distribution of the density at points distributed according to the KDE from the densities at the
+    /// (weighted) data points instead of points drawn from the KDE, e.g. if the `sample` feature is disabled.
+    ///
+    /// The data points are distributed according to the underlying density rather than the KDE, which does not
+    /// include the smearing by the kernels. The levels therefore enclose slightly less mass of the KDE than
+    /// requested, with a difference which vanishes with the bandwidth, but they do not depend on random draws.
+    pub fn hdr_levels_from_data(&self) -> HdrLevels<F> {
+        let densities = self.self_densities(false);
+        return HdrLevels::new(densities.view(), self.weights());
+    }
+
+    /// Estimate the density thresholds $f_p$ such that the regions $\\{x : f(x) \geq f_p\\}$ contain the probability
+    /// masses $p$ given by `masses` from the densities at the (weighted) data points, see
+    /// [`GaussianKDE::hdr_levels_from_data`].
+    ///
+    /// *Panics* if any mass is not in $[0, 1]$.
+    pub fn contour_levels_from_data(&self, masses: &[F]) -> Vec<F> {
+        return self.hdr_levels_from_data().levels(masses);
+    }
+
+    /// Estimate the density rank of the point `x`, i.e. the probability mass $P(f(Y) \leq f(x))$ of the points $Y$
+    /// distributed according to the KDE with a density at most as high as at `x`, from the (weighted) densities at
+    /// the data points instead of points drawn from the KDE, see [`GaussianKDE::hdr_levels_from_data`].
+    ///
+    /// The rank is one minus the mass of the highest density region whose boundary passes through `x`, i.e. it is one
+    /// at the mode and decreases towards zero in the tails, such that it can be used as p-value of `x` under the KDE.
+    /// The densities are compared in log-space, see [`GaussianKDE::ln_eval_batch`], such that the ranks of points far
+    /// in the tails remain meaningful. Since the densities at all data points are evaluated, the cost is quadratic in
+    /// the number of data points.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn density_rank_from_data(&self, x: ArrayView1<F>) -> F {
+        assert_eq!(x.dim(), self.n_dims());
+        let ln_densities = self.self_log_densities(false);
+        return self.density_rank_among(x, ln_densities.view(), self.weights());
+    }
+
+    /// Fraction of the total weight of the points with the logarithmic densities `ln_densities` whose density is at
+    /// most the density at `x`.
+    fn density_rank_among(
+        &self,
+        x: ArrayView1<F>,
+        ln_densities: ArrayView1<F>,
+        weights: Option<ArrayView1<F>>,
+    ) -> F {
+        // The density at `x` is evaluated in the same way as at the other points, such that ties compare equal
+        let ln_density = self.ln_eval_batch(x.insert_axis(Axis(0)))[0];
+        let weight = |i: usize| weights.map_or(F::one(), |w| w[i]);
+        let mut below = F::zero();
+        let mut total = F::zero();
+        for (i, ln_f) in ln_densities.indexed_iter() {
+            if *ln_f <= ln_density {
+                below = below + weight(i);
+            }
+            total = total + weight(i);
+        }
+        return below / total;
+    }
+
+    /// Check whether the point `x` lies inside the highest density region with probability mass `mass`, i.e. whether
+    /// the density at `x` is at least the threshold of `levels`, which were estimated for this KDE with `hdr_levels`
+    /// or [`GaussianKDE::hdr_levels_from_data`]. Points outside of the region of a large mass, e.g. 99%, can be
+    /// flagged as outliers.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset or if `mass` is not in
+    /// $[0, 1]$.
+    pub fn in_hdr(&self, x: ArrayView1<F>, mass: F, levels: &HdrLevels<F>) -> bool {
+        let level = levels.level(mass);
+        return self.eval(x) >= level;
+    }
+
+    /// Check for multiple points given by the array `x` of shape `(n_points, dim)` whether they lie inside the highest
+    /// density region with probability mass `mass`, see [`GaussianKDE::in_hdr`]. The densities are evaluated with
+    /// [`GaussianKDE::eval_batch`].
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset or if `mass` is not in
+    /// $[0, 1]$.
+    pub fn in_hdr_batch(&self, x: ArrayView2<F>, mass: F, levels: &HdrLevels<F>) -> Array1<bool> {
+        let level = levels.level(mass);
+        return self.eval_batch(x).mapv(|f| f >= level);
+    }
+}
+
+#[cfg(feature = "sample")]
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + 'static,
+    StandardNormal: Distribution<F>,
+{
+    /// Estimate the distribution of the density at points distributed according to the KDE by drawing `n_samples`
+    /// points from the KDE using the given RNG, from which the levels of the highest density regions are obtained.
+    ///
+    /// Since $P(f(X) \geq f_p) = p$ for $X$ distributed according to the density $f$, the threshold $f_p$ is the
+    /// $(1 - p)$-quantile of the densities at the drawn points. The statistical uncertainty decreases with
+    /// $1 / \sqrt{n_\mathrm{samples}}$, while the cost of evaluating the densities grows with $n_\mathrm{samples}$
+    /// times the number of data points. See [`GaussianKDE::hdr_levels_from_data`] for a deterministic variant.
+    ///
+    /// *Panics* if `n_samples` is zero.
+    pub fn hdr_levels(&self, n_samples: usize, rng: &mut impl Rng) -> HdrLevels<F> {
+        assert!(n_samples > 0, "the number of samples must be positive");
+        let densities = self.eval_batch(self.sample_batch_with_rng(n_samples, rng).view());
+        return HdrLevels::new(densities.view(), None);
+    }
+
+    /// Estimate the density rank of the point `x`, i.e. the probability mass $P(f(Y) \leq f(x))$ of the points $Y$
+    /// distributed according to the KDE with a density at most as high as at `x`, by drawing `n_samples` points from
+    /// the KDE using the given RNG. See [`GaussianKDE::density_rank_from_data`] for a deterministic variant.
+    ///
+    /// The densities at the drawn points are compared in log-space, see [`GaussianKDE::ln_eval_batch`], such that the
+    /// ranks of points far in the tails remain meaningful. The statistical uncertainty of the rank $r$ is
+    /// $\sqrt{r (1 - r) / n_\mathrm{samples}}$.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset or if `n_samples` is zero.
+    pub fn density_rank(&self, x: ArrayView1<F>, n_samples: usize, rng: &mut impl Rng) -> F {
+        assert_eq!(x.dim(), self.n_dims());
+        assert!(n_samples > 0, "the number of samples must be positive");
+        let ln_densities = self.ln_eval_batch(self.sample_batch_with_rng(n_samples, rng).view());
+        return self.density_rank_among(x, ln_densities.view(), None);
+    }
+
+    /// Estimate the density thresholds $f_p$ such that the regions $\\{x : f(x) \geq f_p\\}$ contain the probability
+    /// masses $p$ given by `masses`, i.e. the levels of the contours enclosing the highest density regions, by drawing
+    /// `n_samples` points from the KDE using the given RNG, see [`GaussianKDE::hdr_levels`]. The levels are returned
+    /// in the order of `masses`, i.e. they decrease with increasing mass.
+    ///
+    /// *Panics* if any mass is not in $[0, 1]$ or if `n_samples` is zero.
+    pub fn contour_levels(&self, masses: &[F], n_samples: usize, rng: &mut impl Rng) -> Vec<F> {
+        return self.hdr_levels(n_samples, rng).levels(masses);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GaussianKDE;
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn contour_levels_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(88);
+        let mut data =
+            Array2::<f64>::from_shape_simple_fn((6000, 2), || rng.sample(StandardNormal));
+        let x0 = data.column(0).to_owned();
+        data.column_mut(1).scaled_add(0.6, &x0);
+        data.column_mut(0).mapv_inplace(|x| 2.0 * x);
+        let kde = GaussianKDE::new(data, None).unwrap();
+
+        // For a normal density with covariance $\Sigma$, the region with mass $p$ is bounded by the contour at the
+        // squared Mahalanobis distance $-2 \ln(1 - p)$, where the density is $(1 - p) / (2 \pi \sqrt{\det \Sigma})$.
+        // The KDE of normal data is approximately normal with the covariance $V + H = (1 + h^{-2}) H$ of the data plus
+        // the kernels, up to statistical fluctuations of a few percent for this dataset.
+        let cov = kde.covariance() * (1.0 + kde.bandwidth_factor().powi(-2));
+        let det = cov[[0, 0]] * cov[[1, 1]] - cov[[0, 1]] * cov[[1, 0]];
+        let masses = [0.68, 0.9, 0.3];
+        let expected = masses.map(|p| (1.0 - p) / (2.0 * std::f64::consts::PI * det.sqrt()));
+        assert_relative_eq!(
+            expected[0],
+            (-1.14f64).exp() / (2.0 * std::f64::consts::PI * det.sqrt()),
+            max_relative = 1e-3
+        );
+
+        #[cfg(feature = "sample")]
+        {
+            let levels = kde.contour_levels(&masses, 6000, &mut rng);
+            for (level, expected) in levels.iter().zip(expected) {
+                assert_relative_eq!(*level, expected, max_relative = 0.06);
+            }
+            assert!(levels[1] < levels[0] && levels[0] < levels[2]);
+        }
+
+        // The data points do not include the kernel smearing, i.e. their covariance is $V = H / h^2$, such that the
+        // fraction $p$ of them lies within the contour of the KDE at the mass $1 - (1 - p)^{1 / (1 + h^2)}$
+        let h2 = kde.bandwidth_factor().powi(2);
+        let levels = kde.contour_levels_from_data(&masses);
+        for ((level, expected), p) in levels.iter().zip(expected).zip(masses) {
+            let smeared = expected / (1.0 - p) * (1.0 - p).powf(1.0 / (1.0 + h2));
+            assert_relative_eq!(*level, smeared, max_relative = 0.06);
+        }
+        assert!(levels[1] < levels[0] && levels[0] < levels[2]);
+    }
+
+    #[test]
+    fn contour_levels_weighted_test() {
+        // Equal weights give the same levels as unweighted data
+        let data = array![[0.0], [0.3], [1.1], [-0.5], [2.0], [0.7]];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let weighted = GaussianKDE::new(data.clone(), Some(Array1::from_elem(6, 3.0))).unwrap();
+        let masses = [0.0, 0.2, 0.5, 0.9, 1.0];
+        let levels = kde.contour_levels_from_data(&masses);
+        assert_relative_eq!(
+            Array1::from(weighted.contour_levels_from_data(&masses)),
+            Array1::from(levels.clone()),
+            max_relative = 1e-12
+        );
+        // The extreme masses give the maximal and minimal density at the data points
+        let densities = kde.eval_batch(data.view());
+        assert_eq!(levels[0], densities.fold(0.0f64, |acc, f| acc.max(*f)));
+        assert_eq!(
+            levels[4],
+            densities.fold(f64::INFINITY, |acc, f| acc.min(*f))
+        );
+
+        // Points with larger weights shift the levels towards their densities
+        let weighted = GaussianKDE::new(data, Some(array![1.0, 1.0, 1.0, 1.0, 20.0, 1.0])).unwrap();
+        let level = weighted.contour_levels_from_data(&[0.5])[0];
+        let densities = weighted.eval_batch(array![[2.0]].view());
+        assert!(level < levels[2]);
+        assert_relative_eq!(level, densities[0], max_relative = 1e-12);
+    }
+
+    #[test]
+    fn in_hdr_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(89);
+        let data = Array2::<f64>::from_shape_simple_fn((2000, 2), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let levels = kde.hdr_levels_from_data();
+        assert_eq!(
+            kde.contour_levels_from_data(&[0.68, 0.95]),
+            levels.levels(&[0.68, 0.95])
+        );
+
+        // The region with mass 68% is the disk of radius $\sqrt{2 \ln(1 / 0.32)} \approx 1.5$
+        for phi in [0.0, 1.0, 2.5, 4.0] {
+            let direction = array![f64::cos(phi), f64::sin(phi)];
+            assert!(kde.in_hdr((&direction * 1.0).view(), 0.68, &levels));
+            assert!(!kde.in_hdr((&direction * 3.0).view(), 0.68, &levels));
+            assert!(kde.in_hdr((&direction * 2.0).view(), 0.95, &levels));
+        }
+
+        // The fractions of a held-out sample from the underlying density inside the regions match their masses, since
+        // the levels from the data points do not include the kernel smearing
+        let held_out =
+            Array2::<f64>::from_shape_simple_fn((2000, 2), || rng.sample(StandardNormal));
+        for p in [0.5, 0.68, 0.95] {
+            let inside = kde.in_hdr_batch(held_out.view(), p, &levels);
+            let fraction = inside.iter().filter(|inside| **inside).count() as f64 / 2000.0;
+            assert!((fraction - p).abs() < 0.03);
+            for (x, inside) in held_out.rows().into_iter().zip(inside).take(100) {
+                assert_eq!(kde.in_hdr(x, p, &levels), inside);
+            }
+        }
+
+        // The region with mass $p$ of the KDE with covariance $(1 + h^2) I$ has the squared radius
+        // $-2 (1 + h^2) \ln(1 - p)$, which contains the mass $1 - (1 - p)^{1 + h^2}$ of the underlying density
+        #[cfg(feature = "sample")]
+        {
+            let levels = kde.hdr_levels(2000, &mut rng);
+            let inside = kde.in_hdr_batch(held_out.view(), 0.68, &levels);
+            let fraction = inside.iter().filter(|inside| **inside).count() as f64 / 2000.0;
+            let expected = 1.0 - 0.32f64.powf(1.0 + kde.bandwidth_factor().powi(2));
+            assert!((fraction - expected).abs() < 0.03);
+        }
+    }
+
+    #[test]
+    fn density_rank_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(90);
+        let data = Array2::<f64>::from_shape_simple_fn((2000, 2), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data, None).unwrap();
+
+        // The contour at radius $r$ of the KDE with covariance $(1 + h^2) I$ leaves the mass
+        // $\exp(-r^2 / (2 (1 + h^2)))$ of the KDE and $\exp(-r^2 / 2)$ of the underlying density outside, up to the
+        // statistical fluctuations of the density along the ray
+        let direction = array![0.6, -0.8];
+        let radii = [0.0, 0.5, 1.0, 1.5, 2.0, 3.0, 4.0];
+        let ranks = radii.map(|r| kde.density_rank_from_data((&direction * r).view()));
+        for (rank, r) in ranks.iter().zip(radii) {
+            assert!((rank - f64::exp(-r * r / 2.0)).abs() < 0.05);
+        }
+        assert!(ranks[0] > 0.95);
+        assert_eq!(ranks[6], 0.0);
+        assert!(ranks.windows(2).all(|r| r[0] >= r[1]));
+
+        #[cfg(feature = "sample")]
+        {
+            let h2 = kde.bandwidth_factor().powi(2);
+            let ranks = radii.map(|r| kde.density_rank((&direction * r).view(), 1000, &mut rng));
+            for (rank, r) in ranks.iter().zip(radii) {
+                assert!((rank - f64::exp(-r * r / (2.0 * (1.0 + h2)))).abs() < 0.05);
+            }
+            assert!(ranks[0] > 0.95);
+            assert!(ranks[6] < 0.01);
+            assert!(ranks.windows(2).all(|r| r[0] >= r[1]));
+        }
+
+        // The densities of a wide KDE in single precision underflow, while their logarithms are still ordered
+        let data = Array2::<f32>::from_shape_simple_fn((1000, 3), || {
+            1e16 * rng.sample::<f32, _>(StandardNormal)
+        });
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        assert_eq!(kde.eval(data.row(0)), 0.0);
+        assert!(kde.density_rank_from_data(Array1::zeros(3).view()) > 0.95);
+        let rank = kde.density_rank_from_data(data.row(0));
+        assert!(rank > 0.0 && rank < 1.0);
+        assert_eq!(
+            kde.density_rank_from_data(Array1::from_elem(3, 5e16).view()),
+            0.0
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn contour_levels_mass_test() {
+        let kde = GaussianKDE::new(array![[0.0], [1.0], [3.0]], None).unwrap();
+        kde.contour_levels_from_data(&[0.5, 1.5]);
+    }
+}
@@ -0,0 +1,554 @@
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::cholesky::{cholesky_decomposition, cholesky_inverse};
+use crate::special::std_normal_interval;
+use crate::{Bandwidth, CowGaussianKDE, ErrorKind, KDEError};
+use alloc::format;
+use ndarray::{Zip, prelude::*};
+use num_traits::{Float, FloatConst, FromPrimitive};
+#[cfg(feature = "sample")]
+use rand::prelude::*;
+#[cfg(feature = "sample")]
+use rand_distr::{StandardNormal, uniform::SampleUniform, weighted::Weight};
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Integrate the probability density of a one-dimensional KDE over the interval $[a, b]$ given by `low` and
+    /// `high`, i.e.
+    /// \\[ \int_a^b f_\mathrm{KDE}(x) \\, \mathrm{d}x = \frac{1}{\sum_i w_i} \sum_{i=1}^n w_i \left[\Phi\left(\frac{b - x_i}{\sigma}\right) - \Phi\left(\frac{a - x_i}{\sigma}\right)\right], \\]
+    /// where $\Phi$ is the cumulative distribution function of the standard normal distribution and $\sigma^2 = H$
+    /// is the kernel variance. Infinite bounds are allowed. This is equivalent to
+    /// `scipy.stats.gaussian_kde.integrate_box_1d(low, high)`.
+    ///
+    /// Returns a `ShapeError` if the KDE is multivariate.
+    pub fn integrate_box_1d(&self, low: F, high: F) -> Result<F, KDEError> {
+        self.check_univariate("integrate_box_1d")?;
+        let sigma = self.cholesky[[0, 0]];
+        return Ok(self.univariate_kernel_average(|xi| {
+            std_normal_interval((low - xi) / sigma, (high - xi) / sigma)
+        }));
+    }
+
+    /// Integrate the product of the KDE's probability density and the multivariate normal density with the given
+    /// `mean` $\mu$ and covariance `cov` $\Sigma$, i.e.
+    /// \\[ \int f_\mathrm{KDE}(x) \\, \mathcal{N}(x; \mu, \Sigma) \\, \mathrm{d}x = \frac{1}{\sum_i w_i} \sum_{i=1}^n w_i \\, \mathcal{N}(x_i; \mu, H + \Sigma). \\]
+    /// This is equivalent to `scipy.stats.gaussian_kde.integrate_gaussian(mean, cov)`.
+    ///
+    /// Returns a `ShapeError` if the shapes of `mean` or `cov` do not match the dimension of the KDE and a
+    /// `SingularityError` if $H + \Sigma$ is not positive-definite.
+    pub fn integrate_gaussian(
+        &self,
+        mean: ArrayView1<F>,
+        cov: ArrayView2<F>,
+    ) -> Result<F, KDEError> {
+        let dim = self.n_dims();
+        if mean.dim() != dim || cov.dim() != (dim, dim) {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected mean of shape `({dim},)` and covariance of shape `{:?}`, found `({},)` and `{:?}`",
+                    (dim, dim),
+                    mean.dim(),
+                    cov.dim()
+                ),
+            ));
+        }
+        let sum_cov = &self.covariance() + &cov;
+        let cholesky = cholesky_decomposition(sum_cov.view())?;
+        let inv_cholesky = cholesky_inverse(cholesky.view());
+        let data = self.dataset();
+        let sum = overlap_sum(data.view(), self.weights(), mean, inv_cholesky.view());
+        let ln_normalization =
+            -(sum_of_weights(data.view(), self.weights()).ln() + gaussian_ln_det(cholesky.view()));
+        return Ok(sum * ln_normalization.exp());
+    }
+
+    /// Integrate the product of the probability densities of this and the `other` KDE, i.e.
+    /// \\[ \int f_\mathrm{KDE}(x) \\, g_\mathrm{KDE}(x) \\, \mathrm{d}x = \frac{1}{\sum_i w_i \sum_j v_j} \sum_{i,j} w_i v_j \\, \mathcal{N}(x_i; y_j, H_f + H_g). \\]
+    /// The double sum is evaluated with the smaller dataset in the outer loop. This is equivalent to
+    /// `scipy.stats.gaussian_kde.integrate_kde(other)`.
+    ///
+    /// Returns a `ShapeError` if the dimensions of the KDEs do not match.
+    pub fn integrate_kde<B2>(&self, other: &CowGaussianKDE<'_, F, B2>) -> Result<F, KDEError>
+    where
+        B2: Bandwidth<F>,
+    {
+        let dim = self.n_dims();
+        if other.n_dims() != dim {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "cannot integrate KDEs of different dimensions {dim} and {}",
+                    other.n_dims()
+                ),
+            ));
+        }
+        let sum_cov = &self.covariance() + &other.covariance();
+        let cholesky = cholesky_decomposition(sum_cov.view())?;
+        let inv_cholesky = cholesky_inverse(cholesky.view());
+        let (self_data, other_data) = (self.dataset(), other.dataset());
+        let ((small, small_weights), (large, large_weights)) = if other.n_points() < self.n_points()
+        {
+            (
+                (other_data.view(), other.weights()),
+                (self_data.view(), self.weights()),
+            )
+        } else {
+            (
+                (self_data.view(), self.weights()),
+                (other_data.view(), other.weights()),
+            )
+        };
+        let mut sum = F::zero();
+        for (j, yj) in small.rows().into_iter().enumerate() {
+            let v = small_weights.map_or(F::one(), |w| w[j]);
+            sum = sum + v * overlap_sum(large, large_weights, yj, inv_cholesky.view());
+        }
+        let ln_normalization = -(sum_of_weights(small, small_weights).ln()
+            + sum_of_weights(large, large_weights).ln()
+            + gaussian_ln_det(cholesky.view()));
+        return Ok(sum * ln_normalization.exp());
+    }
+}
+
+/// Unnormalized weighted sum $\sum_i w_i \exp(-\frac{1}{2} z_i^\top z_i)$ of the overlap integrals with the whitened
+/// distances $z_i = L^{-1}(x_i - \mu)$ of the points `data` to `mean`.
+fn overlap_sum<F>(
+    data: ArrayView2<F>,
+    weights: Option<ArrayView1<F>>,
+    mean: ArrayView1<F>,
+    inv_cholesky: ArrayView2<F>,
+) -> F
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    let half = F::from(0.5).unwrap();
+    let kernel = |xi: ArrayView1<F>| {
+        let mut sq_distance = F::zero();
+        for i in 0..inv_cholesky.dim().0 {
+            let mut z = F::zero();
+            for k in 0..=i {
+                z = z + inv_cholesky[[i, k]] * (xi[k] - mean[k]);
+            }
+            sq_distance = sq_distance + z * z;
+        }
+        F::exp(-half * sq_distance)
+    };
+    return if let Some(w) = weights {
+        Zip::from(data.rows())
+            .and(w)
+            .fold(F::zero(), |acc, xi, w| acc + *w * kernel(xi))
+    } else {
+        data.rows()
+            .into_iter()
+            .fold(F::zero(), |acc, xi| acc + kernel(xi))
+    };
+}
+
+/// Sum of the weights of the points `data`, i.e. their number for unweighted data.
+fn sum_of_weights<F>(data: ArrayView2<F>, weights: Option<ArrayView1<F>>) -> F
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    return weights.map_or_else(|| F::from(data.dim().0).unwrap(), |w| w.sum());
+}
+
+/// Logarithm $\ln \sqrt{(2\pi)^d \det \Sigma}$ of the normalization of a normal distribution with the covariance
+/// $\Sigma = LL^\top$.
+fn gaussian_ln_det<F>(cholesky: ArrayView2<F>) -> F
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    return cholesky.diag().mapv(F::ln).sum()
+        + F::from(0.5 * cholesky.dim().0 as f64).unwrap() * F::ln(F::from(2).unwrap() * F::PI());
+}
+
+#[cfg(feature = "sample")]
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + 'static,
+    StandardNormal: Distribution<F>,
+{
+    /// Estimate the probability mass of the KDE inside the box $[a_1, b_1] \times \dots \times [a_d, b_d]$ given by the
+    /// corners `low` and `high`. Infinite bounds are allowed.
+    ///
+    /// In general, the probability is estimated as the fraction of `n_samples` points drawn from the KDE using `rng`
+    /// which fall inside the box. If the kernel covariance is diagonal (e.g. for [`crate::CovarianceMode::Diagonal`]),
+    /// the integral factorizes into one-dimensional integrals and is computed exactly, without drawing any samples. A
+    /// box with `low[k] > high[k]` along any dimension is empty.
+    ///
+    /// *Panics* if the dimension of `low` or `high` does not match the dimension of the KDE dataset, or if
+    /// `n_samples` is zero while the kernel covariance is not diagonal.
+    pub fn integrate_box(
+        &self,
+        low: ArrayView1<F>,
+        high: ArrayView1<F>,
+        n_samples: usize,
+        rng: &mut impl Rng,
+    ) -> F {
+        return self.integrate_box_with_error(low, high, n_samples, rng).0;
+    }
+
+    /// Same as [`GaussianKDE::integrate_box`], additionally returning the standard error
+    /// $\sqrt{\hat p (1 - \hat p) / N}$ of the Monte Carlo estimate $\hat p$, which is zero if the integral is computed
+    /// exactly.
+    ///
+    /// *Panics* if the dimension of `low` or `high` does not match the dimension of the KDE dataset, or if
+    /// `n_samples` is zero while the kernel covariance is not diagonal.
+    pub fn integrate_box_with_error(
+        &self,
+        low: ArrayView1<F>,
+        high: ArrayView1<F>,
+        n_samples: usize,
+        rng: &mut impl Rng,
+    ) -> (F, F) {
+        const CHUNK_SIZE: usize = 4096;
+        let dim = self.n_dims();
+        assert_eq!(low.dim(), dim);
+        assert_eq!(high.dim(), dim);
+        let is_diagonal = self
+            .cholesky
+            .indexed_iter()
+            .all(|((i, j), l)| i == j || *l == F::zero());
+        if is_diagonal {
+            let sigma = self.cholesky.diag();
+            let probability = |xi: ArrayView1<F>| {
+                // Inverted bounds contain no mass, as for the Monte Carlo estimate
+                (0..dim).fold(F::one(), |acc, k| {
+                    acc * std_normal_interval(
+                        (low[k] - xi[k]) / sigma[k],
+                        (high[k] - xi[k]) / sigma[k],
+                    )
+                    .max(F::zero())
+                })
+            };
+            let data = self.dataset();
+            let res = if let Some(ref w) = self.weights {
+                Zip::from(data.rows())
+                    .and(&**w)
+                    .fold(F::zero(), |acc, xi, w| acc + *w * probability(xi))
+                    / w.sum()
+            } else {
+                data.rows()
+                    .into_iter()
+                    .fold(F::zero(), |acc, xi| acc + probability(xi))
+                    / F::from(self.n_points()).unwrap()
+            };
+            return (res, F::zero());
+        }
+        assert!(
+            n_samples > 0,
+            "at least one sample is required for the Monte Carlo estimate"
+        );
+        let mut hits = 0usize;
+        let mut remaining = n_samples;
+        while remaining > 0 {
+            let n = remaining.min(CHUNK_SIZE);
+            let samples = self.sample_batch_with_rng(n, rng);
+            hits += samples
+                .rows()
+                .into_iter()
+                .filter(|x| (0..dim).all(|k| x[k] >= low[k] && x[k] <= high[k]))
+                .count();
+            remaining -= n;
+        }
+        let n = F::from(n_samples).unwrap();
+        let p = F::from(hits).unwrap() / n;
+        return (p, (p * (F::one() - p) / n).sqrt());
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::excessive_precision)]
+mod tests {
+    use crate::{ErrorKind, GaussianKDE};
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+
+    #[test]
+    fn integrate_box_1d_test() {
+        let data = array![
+            [0.5634880436705391],
+            [0.445981611845074],
+            [0.7438671296401687]
+        ];
+        let w_test = array![0.04622972052712859, 0.15162404870472723, 0.291517199926956];
+        let bounds = [
+            (0.3, 0.6),
+            (f64::NEG_INFINITY, 0.5),
+            (0.7, f64::INFINITY),
+            (1.5, 2.0),
+        ];
+        // Reference values from `scipy.stats.gaussian_kde(data, weights=w).integrate_box_1d(low, high)`
+        let res = [
+            0.5025139288373281,
+            0.33120275236204605,
+            0.26271610627870384,
+            5.7262120970828313e-11,
+        ];
+        let res_w = [
+            0.361299925834793,
+            0.2658975463673464,
+            0.3980935631873124,
+            5.811594714277458e-07,
+        ];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let kde_w = GaussianKDE::new(data, Some(w_test)).unwrap();
+        for (j, (low, high)) in bounds.into_iter().enumerate() {
+            assert_relative_eq!(
+                kde.integrate_box_1d(low, high).unwrap(),
+                res[j],
+                max_relative = 1e-10
+            );
+            assert_relative_eq!(
+                kde_w.integrate_box_1d(low, high).unwrap(),
+                res_w[j],
+                max_relative = 1e-10
+            );
+        }
+        assert_relative_eq!(
+            kde_w
+                .integrate_box_1d(f64::NEG_INFINITY, f64::INFINITY)
+                .unwrap(),
+            1.0,
+            max_relative = 1e-15
+        );
+
+        let kde = GaussianKDE::new(array![[0.1, 0.2], [0.3, 0.1], [0.5, 0.7]], None).unwrap();
+        let res = kde.integrate_box_1d(0.0, 1.0);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn integrate_box_gaussian_test() {
+        use rand::SeedableRng;
+        use rand_pcg::Pcg64Mcg;
+        use std::f64::consts::PI;
+
+        let mut rng = Pcg64Mcg::seed_from_u64(8);
+        // A single kernel, i.e. a pure Gaussian, with correlation rho: P(X < 0, Y < 0) = 1/4 + asin(rho) / (2 pi)
+        let rho: f64 = 0.6;
+        let cov = array![[4.0, rho * 2.0 * 0.5], [rho * 2.0 * 0.5, 0.25]];
+        let kde = GaussianKDE::with_bandwidth_matrix(array![[0.0, 0.0]], None, cov).unwrap();
+        let (low, high) = (
+            array![f64::NEG_INFINITY, f64::NEG_INFINITY],
+            array![0.0, 0.0],
+        );
+        let (p, err) = kde.integrate_box_with_error(low.view(), high.view(), 200_000, &mut rng);
+        let exact = 0.25 + rho.asin() / (2.0 * PI);
+        assert!(err > 0.0 && err < 2e-3);
+        assert!((p - exact).abs() < 4.0 * err);
+
+        // Diagonal kernel covariance: exact product of one-dimensional integrals
+        let cov = array![[4.0, 0.0], [0.0, 0.25]];
+        let kde = GaussianKDE::with_bandwidth_matrix(array![[1.0, -1.0]], None, cov).unwrap();
+        let (low, high) = (array![-1.0, f64::NEG_INFINITY], array![3.0, -0.5]);
+        let (p, err) = kde.integrate_box_with_error(low.view(), high.view(), 0, &mut rng);
+        // P(|Z| < 1) * P(Z < 1)
+        assert_relative_eq!(
+            p,
+            0.6826894921370859 * 0.8413447460685429,
+            max_relative = 1e-14
+        );
+        assert_eq!(err, 0.0);
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn integrate_box_2d_test() {
+        use rand::SeedableRng;
+        use rand_pcg::Pcg64Mcg;
+
+        let data = array![
+            [0.15, 0.4],
+            [0.2, 0.3],
+            [0.21, 0.29],
+            [0.31, 0.74],
+            [0.72, 0.9],
+            [0.74, 0.84],
+            [0.6, 0.3]
+        ];
+        let weights = array![0.5, 1.0, 0.2, 2.0, 0.7, 0.3, 1.2];
+        let (low, high) = (array![0.1, 0.25], array![0.5, 0.8]);
+        for kde in [
+            GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap(),
+            GaussianKDE::with_diagonal_covariance(data.clone(), Some(weights.clone())).unwrap(),
+        ] {
+            // Midpoint rule on a dense grid
+            let n = 400;
+            let (dx, dy) = ((high[0] - low[0]) / n as f64, (high[1] - low[1]) / n as f64);
+            let grid = Array2::from_shape_fn((n * n, 2), |(k, j)| {
+                if j == 0 {
+                    low[0] + ((k / n) as f64 + 0.5) * dx
+                } else {
+                    low[1] + ((k % n) as f64 + 0.5) * dy
+                }
+            });
+            let numerical = kde.eval_batch(grid.view()).sum() * dx * dy;
+
+            let mut rng = Pcg64Mcg::seed_from_u64(9);
+            let (p, err) = kde.integrate_box_with_error(low.view(), high.view(), 100_000, &mut rng);
+            assert!((p - numerical).abs() < 4.0 * err + 1e-5);
+
+            // Inverted and empty boxes contain no mass for the exact and the Monte Carlo estimate
+            for (low, high) in [
+                (array![0.5, 0.8], array![0.1, 0.25]),
+                (array![0.5, 0.25], array![0.1, 0.8]),
+                (array![0.3, 0.25], array![0.3, 0.8]),
+            ] {
+                let (p, err) =
+                    kde.integrate_box_with_error(low.view(), high.view(), 1000, &mut rng);
+                assert_eq!(p, 0.0);
+                assert_eq!(err, 0.0);
+            }
+        }
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    #[should_panic(expected = "at least one sample")]
+    fn integrate_box_no_samples_test() {
+        use rand::SeedableRng;
+        use rand_pcg::Pcg64Mcg;
+
+        let data = array![[0.15, 0.4], [0.2, 0.3], [0.31, 0.74], [0.72, 0.9]];
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let (low, high) = (array![0.1, 0.25], array![0.5, 0.8]);
+        kde.integrate_box(low.view(), high.view(), 0, &mut Pcg64Mcg::seed_from_u64(10));
+    }
+
+    #[test]
+    fn integrate_gaussian_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let w_test = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let kde_w = GaussianKDE::new(data, Some(w_test)).unwrap();
+        // Reference values from `scipy.stats.gaussian_kde(data, weights=w).integrate_gaussian(mean, cov)`
+        for (mean, cov, res, res_w) in [
+            (
+                array![0.5, 0.6, 0.7],
+                array![[0.01, 0.0, 0.0], [0.0, 0.02, 0.0], [0.0, 0.0, 0.03]],
+                1.8101921992957417,
+                2.2098690291286727,
+            ),
+            (
+                array![0.2, 0.9, 0.4],
+                array![[0.1, 0.02, -0.01], [0.02, 0.05, 0.01], [-0.01, 0.01, 0.08]],
+                0.1333348320162008,
+                0.12278711419564832,
+            ),
+        ] {
+            assert_relative_eq!(
+                kde.integrate_gaussian(mean.view(), cov.view()).unwrap(),
+                res,
+                max_relative = 1e-10
+            );
+            assert_relative_eq!(
+                kde_w.integrate_gaussian(mean.view(), cov.view()).unwrap(),
+                res_w,
+                max_relative = 1e-10
+            );
+        }
+
+        let res = kde.integrate_gaussian(array![0.0, 0.0].view(), Array2::eye(3).view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.integrate_gaussian(array![0.0, 0.0, 0.0].view(), Array2::eye(2).view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.integrate_gaussian(array![0.0, 0.0, 0.0].view(), (-Array2::eye(3)).view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+    }
+
+    #[test]
+    fn integrate_kde_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let w_test = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        // Second dataset with fewer points, obtained by transforming the first seven points
+        let other = Array2::from_shape_fn((7, 3), |(i, j)| match j {
+            0 => data[[i, 0]] * 0.8 + 0.1,
+            1 => data[[i, 2]],
+            _ => data[[i, 1]] * 1.2 - 0.1,
+        });
+        let w_other = array![1.0, 2.0, 3.0, 1.0, 2.0, 3.0, 1.0];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let kde_w = GaussianKDE::new(data, Some(w_test)).unwrap();
+        let other_w = GaussianKDE::new(other.clone(), Some(w_other)).unwrap();
+        let other_h = GaussianKDE::with_bandwidth_factor(other, None, 0.5).unwrap();
+
+        // Reference values from `scipy.stats.gaussian_kde.integrate_kde`
+        assert_relative_eq!(
+            kde.integrate_kde(&kde).unwrap(),
+            1.434654026834319,
+            max_relative = 1e-10
+        );
+        assert_relative_eq!(
+            kde_w.integrate_kde(&other_w).unwrap(),
+            2.0194319898874324,
+            max_relative = 1e-10
+        );
+        assert_relative_eq!(
+            other_w.integrate_kde(&kde_w).unwrap(),
+            2.0194319898874324,
+            max_relative = 1e-10
+        );
+        assert_relative_eq!(
+            kde.integrate_kde(&other_h).unwrap(),
+            1.6919829826752124,
+            max_relative = 1e-10
+        );
+
+        let kde_2d = GaussianKDE::new(array![[0.1, 0.2], [0.3, 0.1], [0.5, 0.7]], None).unwrap();
+        let res = kde.integrate_kde(&kde_2d);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+}
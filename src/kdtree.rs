@@ -0,0 +1,452 @@
+//! Approximate evaluation of KDEs with many points in few dimensions, using a kd-tree of the whitened dataset to skip
+//! the kernels whose contribution is negligible for the requested relative tolerance.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::summation::{CompensatedSum, compensated_sum};
+use crate::{Bandwidth, CowGaussianKDE, ErrorKind, KDEError};
+use alloc::{collections::BinaryHeap, format, vec, vec::Vec};
+use core::cmp::Ordering;
+use ndarray::{Zip, prelude::*};
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// Maximal number of points in a leaf of the tree, which are evaluated directly.
+const LEAF_SIZE: usize = 32;
+
+/// Node of a [`KdTree`], holding the points `start..end` of the reordered dataset within the bounding box given by
+/// `lower` and `upper`.
+pub(crate) struct Node<F> {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) lower: Array1<F>,
+    pub(crate) upper: Array1<F>,
+    pub(crate) children: Option<(usize, usize)>,
+}
+
+/// kd-tree of the whitened data points $z_i = L^{-1}(x_i - c)$, in which all kernels are standard normal. The points
+/// and weights are reordered such that each node holds a contiguous range of them, and nodes are split at the median
+/// of their widest extent until at most `LEAF_SIZE` points remain. The children of a node follow it in `nodes`.
+pub(crate) struct KdTree<F> {
+    pub(crate) center: Array1<F>,
+    pub(crate) points: Array2<F>,
+    pub(crate) weights: Option<Array1<F>>,
+    pub(crate) total_weight: F,
+    pub(crate) nodes: Vec<Node<F>>,
+    // Original index of each reordered point, which is needed to restore the order of the points of evaluation and
+    // to identify the nearest data points
+    pub(crate) order: Vec<usize>,
+}
+
+impl<F: Float + 'static> KdTree<F> {
+    /// Build the tree of the whitened `points` of shape `(n_points, dim)`, centered at `center`, with (optional)
+    /// `weights`.
+    pub(crate) fn new(
+        center: Array1<F>,
+        points: ArrayView2<F>,
+        weights: Option<ArrayView1<F>>,
+    ) -> Self {
+        let mut order: Vec<usize> = (0..points.nrows()).collect();
+        let mut nodes = Vec::new();
+        let standard = points.as_standard_layout();
+        build_node(
+            standard.as_slice().unwrap(),
+            points.ncols(),
+            &mut order,
+            0,
+            &mut nodes,
+        );
+        return Self {
+            center,
+            points: points.select(Axis(0), &order),
+            weights: weights.map(|w| w.select(Axis(0), &order)),
+            total_weight: weights.map_or(F::from(points.nrows()).unwrap(), |w| {
+                compensated_sum(w.iter().copied())
+            }),
+            nodes,
+            order,
+        };
+    }
+
+    /// Unnormalized (weighted) kernel sum at the whitened point `z`, omitting kernels whose total contribution is at
+    /// most `rtol` times the result. The number of evaluated kernels is added to `visits`.
+    ///
+    /// The nodes are visited depth-first with the closer child first, and a node is skipped if the kernel at the
+    /// closest point of its bounding box is at most `rtol` times the partial sum divided by the total weight, i.e. if
+    /// its kernels contribute at most `rtol` times the partial sum times the node's share of the total weight. Since
+    /// the partial sum only grows, the skipped kernels sum up to at most `rtol` times the result.
+    pub(crate) fn kernel_sum(&self, z: ArrayView1<F>, rtol: F, visits: &mut usize) -> F {
+        let mut sum = CompensatedSum::new();
+        let threshold = rtol / self.total_weight;
+        self.visit(
+            0,
+            self.sq_distance_to_node(0, z),
+            z,
+            threshold,
+            &mut sum,
+            visits,
+        );
+        return sum.value();
+    }
+
+    fn visit(
+        &self,
+        index: usize,
+        sq_distance: F,
+        z: ArrayView1<F>,
+        threshold: F,
+        sum: &mut CompensatedSum<F>,
+        visits: &mut usize,
+    ) {
+        let half = F::from(0.5).unwrap();
+        let node = &self.nodes[index];
+        if F::exp(-half * sq_distance) <= threshold * sum.value() {
+            return;
+        }
+        let Some((left, right)) = node.children else {
+            for i in node.start..node.end {
+                let q = Zip::from(self.points.row(i))
+                    .and(z)
+                    .fold(F::zero(), |acc, a, b| acc + (*a - *b) * (*a - *b));
+                let w = self.weights.as_ref().map_or(F::one(), |w| w[i]);
+                sum.add(w * F::exp(-half * q));
+            }
+            *visits += node.end - node.start;
+            return;
+        };
+        let (q_left, q_right) = (
+            self.sq_distance_to_node(left, z),
+            self.sq_distance_to_node(right, z),
+        );
+        if q_left <= q_right {
+            self.visit(left, q_left, z, threshold, sum, visits);
+            self.visit(right, q_right, z, threshold, sum, visits);
+        } else {
+            self.visit(right, q_right, z, threshold, sum, visits);
+            self.visit(left, q_left, z, threshold, sum, visits);
+        }
+    }
+
+    /// Add the points closer to the whitened point `z` than the `k`-th closest point of `nearest` to it, keeping only
+    /// the `k` closest ones, where the nodes are visited depth-first with the closer child first and skipped if their
+    /// bounding box is further away than the `k`-th closest point found so far.
+    pub(crate) fn nearest(&self, z: ArrayView1<F>, k: usize, nearest: &mut Nearest<F>) {
+        self.visit_nearest(0, self.sq_distance_to_node(0, z), z, k, nearest);
+    }
+
+    fn visit_nearest(
+        &self,
+        index: usize,
+        sq_distance: F,
+        z: ArrayView1<F>,
+        k: usize,
+        nearest: &mut Nearest<F>,
+    ) {
+        let node = &self.nodes[index];
+        if nearest.len() == k && nearest.peek().is_some_and(|c| sq_distance > c.sq_distance) {
+            return;
+        }
+        let Some((left, right)) = node.children else {
+            for i in node.start..node.end {
+                let q = Zip::from(self.points.row(i))
+                    .and(z)
+                    .fold(F::zero(), |acc, a, b| acc + (*a - *b) * (*a - *b));
+                push_nearest(nearest, k, self.order[i], q);
+            }
+            return;
+        };
+        let (q_left, q_right) = (
+            self.sq_distance_to_node(left, z),
+            self.sq_distance_to_node(right, z),
+        );
+        if q_left <= q_right {
+            self.visit_nearest(left, q_left, z, k, nearest);
+            self.visit_nearest(right, q_right, z, k, nearest);
+        } else {
+            self.visit_nearest(right, q_right, z, k, nearest);
+            self.visit_nearest(left, q_left, z, k, nearest);
+        }
+    }
+
+    /// Squared distance between `z` and the closest point of the bounding box of the node `index`.
+    fn sq_distance_to_node(&self, index: usize, z: ArrayView1<F>) -> F {
+        let node = &self.nodes[index];
+        return Zip::from(z).and(&node.lower).and(&node.upper).fold(
+            F::zero(),
+            |acc, z, lower, upper| {
+                let d = (*lower - *z).max(*z - *upper).max(F::zero());
+                acc + d * d
+            },
+        );
+    }
+}
+
+/// Data point with index `index` at the squared whitened distance `sq_distance` from a query point, ordered by the
+/// distance and then by the index, such that ties are resolved in the order of the dataset.
+pub(crate) struct Candidate<F> {
+    pub(crate) sq_distance: F,
+    pub(crate) index: usize,
+}
+
+impl<F: Float> Ord for Candidate<F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return self
+            .sq_distance
+            .partial_cmp(&other.sq_distance)
+            .unwrap_or(Ordering::Equal)
+            .then(self.index.cmp(&other.index));
+    }
+}
+
+impl<F: Float> PartialOrd for Candidate<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl<F: Float> PartialEq for Candidate<F> {
+    fn eq(&self, other: &Self) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl<F: Float> Eq for Candidate<F> {}
+
+/// Max-heap of the closest data points found so far, with the furthest one on top.
+pub(crate) type Nearest<F> = BinaryHeap<Candidate<F>>;
+
+/// Add the data point with index `index` at the squared distance `sq_distance` to `nearest` if it is closer than the
+/// furthest of the `k` closest points so far.
+pub(crate) fn push_nearest<F: Float>(
+    nearest: &mut Nearest<F>,
+    k: usize,
+    index: usize,
+    sq_distance: F,
+) {
+    let candidate = Candidate { sq_distance, index };
+    if nearest.len() < k {
+        nearest.push(candidate);
+    } else if nearest.peek().is_some_and(|furthest| candidate < *furthest) {
+        nearest.pop();
+        nearest.push(candidate);
+    }
+}
+
+/// Append the node of the points `order` (indices into the rows of `points`, given in row-major order with `dim`
+/// columns), whose first point is at position `offset` of the reordered dataset, and its descendants to `nodes`,
+/// reordering `order` in place. Returns the index of the node.
+fn build_node<F: Float>(
+    points: &[F],
+    dim: usize,
+    order: &mut [usize],
+    offset: usize,
+    nodes: &mut Vec<Node<F>>,
+) -> usize {
+    let mut lower = vec![F::infinity(); dim];
+    let mut upper = vec![F::neg_infinity(); dim];
+    for &i in order.iter() {
+        for (k, x) in points[i * dim..(i + 1) * dim].iter().enumerate() {
+            lower[k] = lower[k].min(*x);
+            upper[k] = upper[k].max(*x);
+        }
+    }
+    let mut axis = 0;
+    for k in 1..dim {
+        if upper[k] - lower[k] > upper[axis] - lower[axis] {
+            axis = k;
+        }
+    }
+    let extent = upper[axis] - lower[axis];
+    let index = nodes.len();
+    nodes.push(Node {
+        start: offset,
+        end: offset + order.len(),
+        lower: Array1::from(lower),
+        upper: Array1::from(upper),
+        children: None,
+    });
+    // Split along the widest extent at the median, unless all points coincide
+    if order.len() > LEAF_SIZE && extent > F::zero() {
+        let mid = order.len() / 2;
+        order.select_nth_unstable_by(mid, |&a, &b| {
+            points[a * dim + axis]
+                .partial_cmp(&points[b * dim + axis])
+                .unwrap_or(Ordering::Equal)
+        });
+        let (left, right) = order.split_at_mut(mid);
+        let left = build_node(points, dim, left, offset, nodes);
+        let right = build_node(points, dim, right, offset + mid, nodes);
+        nodes[index].children = Some((left, right));
+    }
+    return index;
+}
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Evaluate the probability density estimated by the KDE at the point `x` up to the relative tolerance `rtol`,
+    /// skipping the kernels far away from `x`.
+    ///
+    /// The kernels are organized in a kd-tree of the whitened dataset, which is built on first use and cached until
+    /// the kernel changes (e.g. when adding points). Regions of the tree whose kernels are guaranteed to contribute
+    /// less than `rtol` relative to the density are not visited, e.g. kernels further than about 8 whitened standard
+    /// deviations away for `rtol = 1e-14`. This is considerably faster than [`GaussianKDE::eval`] for large datasets
+    /// in few (about up to three) dimensions, while the tree barely prunes anything in high dimensions. The result
+    /// is at most the exact density, and for `rtol = 0` it is the exact density.
+    ///
+    /// Returns a `ShapeError` if the dimension of `x` does not match the dimension of the KDE dataset and a
+    /// `DomainError` if `rtol` is not in $[0, 1)$.
+    pub fn eval_approx(&self, x: ArrayView1<F>, rtol: F) -> Result<F, KDEError> {
+        if x.dim() != self.n_dims() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected point of dimension {}, found dimension {}",
+                    self.n_dims(),
+                    x.dim()
+                ),
+            ));
+        }
+        check_rtol(rtol)?;
+        return Ok(self.approx_density(x, rtol, &mut 0));
+    }
+
+    /// Evaluate the probability density estimated by the KDE at multiple points given by the array `x` up to the
+    /// relative tolerance `rtol`, see [`GaussianKDE::eval_approx`].
+    ///
+    /// The points are expected to be given as array of shape `(n_points, dim)`. Returns a `ShapeError` if the
+    /// dimension of `x` does not match the dimension of the KDE dataset and a `DomainError` if `rtol` is not in
+    /// $[0, 1)$.
+    pub fn eval_batch_approx(&self, x: ArrayView2<F>, rtol: F) -> Result<Array1<F>, KDEError> {
+        self.check_batch_shapes(x, x.dim().0)?;
+        check_rtol(rtol)?;
+        if rtol == F::zero() {
+            return Ok(self.eval_batch(x));
+        }
+        return Ok(x.map_axis(Axis(1), |x| self.approx_density(x, rtol, &mut 0)));
+    }
+
+    /// Density at `x` up to the relative tolerance `rtol`, adding the number of evaluated kernels to `visits`.
+    fn approx_density(&self, x: ArrayView1<F>, rtol: F, visits: &mut usize) -> F {
+        if rtol == F::zero() {
+            *visits += self.n_points();
+            return self.density(x);
+        }
+        let tree = self.kd_tree();
+        let z = self.inv_cholesky.dot(&(&x - &tree.center));
+        let sum = tree.kernel_sum(z.view(), rtol, visits);
+        return if self.normalization.is_normal() {
+            sum * self.normalization
+        } else {
+            F::exp(sum.ln() + self.ln_normalization)
+        };
+    }
+
+    /// Get the kd-tree of the whitened dataset, which is constructed on first use.
+    pub(crate) fn kd_tree(&self) -> &KdTree<F> {
+        return self.tree.get_or_init(|| {
+            self.with_whitened_data(|whitened| {
+                KdTree::new(
+                    whitened.center.clone(),
+                    whitened.points.view(),
+                    self.weights(),
+                )
+            })
+        });
+    }
+}
+
+/// Return a `DomainError` if the relative tolerance `rtol` is not in $[0, 1)$.
+fn check_rtol<F: Float>(rtol: F) -> Result<(), KDEError> {
+    if !(rtol >= F::zero() && rtol < F::one()) {
+        return Err(KDEError::new(
+            ErrorKind::DomainError,
+            format!(
+                "the relative tolerance must be in [0, 1), found {}",
+                rtol.to_f64().unwrap()
+            ),
+        ));
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ErrorKind, GaussianKDE};
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn eval_approx_test() {
+        let n = 20_000;
+        let mut rng = Pcg64Mcg::seed_from_u64(78);
+        let mut data = Array2::<f64>::from_shape_simple_fn((n, 2), || rng.sample(StandardNormal));
+        // Correlated components, such that the tree is built on the whitened data
+        let x0 = data.column(0).to_owned();
+        data.column_mut(1).scaled_add(0.8, &x0);
+        let weights = Array1::from_shape_simple_fn(n, || rng.random_range(0.0..1.0));
+        let x = Array2::<f64>::from_shape_simple_fn((10, 2), || {
+            2.0 * rng.sample::<f64, _>(StandardNormal)
+        });
+
+        for weights in [None, Some(weights)] {
+            // A narrow kernel as for a large dataset, for which most of the kernels are pruned
+            let kde = GaussianKDE::with_bandwidth_factor(data.clone(), weights, 0.05).unwrap();
+            let exact = kde.eval_batch(x.view());
+            let mut visits = 0;
+            for (xi, exact) in x.rows().into_iter().zip(exact.iter()) {
+                let approx = kde.approx_density(xi, 1e-9, &mut visits);
+                assert!(approx <= *exact * (1.0 + 1e-12));
+                assert_relative_eq!(approx, *exact, max_relative = 1e-9);
+            }
+            // Only a few percent of the kernels are evaluated
+            assert!(visits < n * x.nrows() / 20);
+            assert_relative_eq!(
+                kde.eval_batch_approx(x.view(), 1e-9).unwrap(),
+                exact,
+                max_relative = 1e-9
+            );
+            assert_eq!(kde.eval_approx(x.row(0), 0.0).unwrap(), kde.eval(x.row(0)));
+        }
+    }
+
+    #[test]
+    fn eval_approx_update_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(78);
+        let data = Array2::<f64>::from_shape_simple_fn((2000, 3), || rng.sample(StandardNormal));
+        let x = Array2::<f64>::from_shape_simple_fn((10, 3), || rng.sample(StandardNormal));
+        let mut kde = GaussianKDE::new(data, None).unwrap();
+        let check = |kde: &GaussianKDE<f64>, x: ArrayView2<f64>| {
+            assert_relative_eq!(
+                kde.eval_batch_approx(x, 1e-9).unwrap(),
+                kde.eval_batch(x),
+                max_relative = 1e-9
+            );
+        };
+        check(&kde, x.view());
+        // The tree is rebuilt if the kernel changes
+        kde.set_bandwidth_factor(0.5).unwrap();
+        check(&kde, x.view());
+        kde.push(array![0.1, 0.2, 0.3].view(), None).unwrap();
+        check(&kde, x.view());
+        check(&kde.marginalize_to(&[2, 0]).unwrap(), x.slice(s![.., ..2]));
+    }
+
+    #[test]
+    fn eval_approx_error_test() {
+        let data = array![[0.1, 0.2], [0.5, -0.3], [0.8, 0.4], [-0.2, 0.6]];
+        let kde = GaussianKDE::new(data, None).unwrap();
+        for rtol in [-1e-9, 1.0, f64::NAN] {
+            let res = kde.eval_approx(array![0.0, 0.0].view(), rtol);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+        let res = kde.eval_approx(array![0.0].view(), 1e-9);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.eval_batch_approx(array![[0.0, 0.0, 0.0]].view(), 1e-9);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+}
@@ -1,3 +1,4 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![cfg_attr(docsrs, doc(auto_cfg))]
 #![allow(clippy::needless_return)]
@@ -24,40 +25,225 @@
 //!
 //! <a name = "ref2"></a> \[2\] [Scott, David W. Multivariate Density Estimation: Theory, Practice, and Visualization. Second edition. Wiley, 2014.](https://doi.org/10.1002/9781118575574)
 
-use std::marker::PhantomData;
+extern crate alloc;
 
-use ndarray::{Zip, prelude::*};
+use alloc::sync::Arc;
+use alloc::{format, vec::Vec};
+use ndarray::prelude::*;
 use num_traits::{Float, FloatConst, FromPrimitive};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
 
-pub use bandwidth::{Bandwidth, ScottBandwidth, SilvermanBandwidth};
+pub use adaptive::AdaptiveGaussianKDE;
+#[cfg(feature = "arrow")]
+pub use arrow_data::ArrowOptions;
+pub use bandwidth::{
+    Bandwidth, BandwidthPolicy, FixedBandwidth, ISJBandwidth, MLCVBandwidth, ScottBandwidth,
+    SheatherJonesBandwidth, SilvermanBandwidth,
+};
+pub use binning::{OutOfRange, linear_binning};
+pub use builder::GaussianKDEBuilder;
+pub use covariance::CovarianceMode;
+#[cfg(feature = "csv")]
+pub use csv_data::CsvOptions;
 pub use error::{ErrorKind, KDEError};
+pub use hdr::HdrLevels;
+#[cfg(feature = "python")]
+pub use python::PyGaussianKDE;
+#[cfg(feature = "sample")]
+pub use sample::Samples;
+pub use subspace::SubspaceProjection;
 
 use crate::cholesky::{cholesky_decomposition, cholesky_inverse};
+use crate::covariance::{
+    RunningMoments, covariance, ledoit_wolf_shrinkage, robust_covariance, shrunk_covariance,
+    variance,
+};
+#[cfg(not(feature = "std"))]
+use crate::once::OnceLock;
+use crate::summation::compensated_sum;
 
+mod adaptive;
+#[cfg(feature = "arrow")]
+mod arrow_data;
 mod bandwidth;
+mod binning;
+#[cfg(feature = "sample")]
+mod bootstrap;
+mod builder;
 mod cholesky;
+mod contributions;
+mod covariance;
+#[cfg(feature = "csv")]
+mod csv_data;
+mod derivatives;
+mod display;
+#[cfg(feature = "dualtree")]
+mod dualtree;
+mod eigen;
+mod entropy;
+mod equality;
 mod error;
 mod eval;
+mod fft;
+mod grid;
+mod hdr;
+mod ifgt;
+mod integrate;
+mod kdtree;
+mod merge;
+mod moments;
+#[cfg(feature = "nalgebra")]
+mod nalgebra_interop;
+#[cfg(feature = "npz")]
+mod npz;
+#[cfg(not(feature = "std"))]
+mod once;
+#[cfg(feature = "python")]
+mod python;
 #[cfg(feature = "sample")]
 mod sample;
+#[cfg(feature = "simd")]
+mod simd;
+mod special;
+#[cfg(feature = "statrs")]
+mod statrs_distribution;
+mod subspace;
+mod summation;
+mod univariate;
+mod update;
+mod whitening;
+
+/// Multivariate kernel density estimation with Gaussian kernels and optionally weighed data points, owning its
+/// dataset. This is the estimator returned by all constructors taking the dataset by value, see [`CowGaussianKDE`]
+/// for the methods and for estimators borrowing their dataset.
+pub type GaussianKDE<F, B = bandwidth::ScottBandwidth> = CowGaussianKDE<'static, F, B>;
 
 /// Multivariate kernel density estimation with Gaussian kernels and optionally weighed data points.
-pub struct GaussianKDE<F, B = bandwidth::ScottBandwidth>
+///
+/// The dataset and weights are either owned, as for [`GaussianKDE`], which is this type with the lifetime `'static`,
+/// or borrowed for the lifetime `'a` from the caller, see [`CowGaussianKDE::from_view`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize, B: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>, B: serde::Deserialize<'de>"
+    ))
+)]
+pub struct CowGaussianKDE<'a, F, B = bandwidth::ScottBandwidth>
 where
     F: Float + FloatConst + FromPrimitive,
     B: Bandwidth<F>,
 {
-    data: Array2<F>,
-    weights: Option<Array1<F>>,
+    // The dataset and weights are either owned or borrowed from the caller (see `GaussianKDE::from_view`), and shared
+    // with derived KDEs which do not modify them, e.g. marginals.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_shared"))]
+    data: SharedArray<'a, F, Ix2>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "deserialize_shared_option")
+    )]
+    weights: Option<SharedArray<'a, F, Ix1>>,
+    // Columns of `data` forming the components of the KDE, in order, for marginals sharing the dataset of their
+    // parent. `None` if all columns are used in their original order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    dims: Option<Vec<usize>>,
+    // Lazily copied components of the dataset for marginals sharing the dataset of their parent, see
+    // `GaussianKDE::data`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    selected_data: OnceLock<Array2<F>>,
+    // Components of the original KDE the components of a marginal (or conditional) KDE correspond to, see
+    // `GaussianKDE::original_dims`. Unlike `dims`, this is kept if the dataset is copied, e.g. when adding points.
+    #[cfg_attr(feature = "serde", serde(default))]
+    original_dims: Option<Vec<usize>>,
+    bandwidth_factor: F,
     cholesky: Array2<F>,
     inv_cholesky: Array2<F>,
     normalization: F,
-    // The bandwidth is only used as static function during init, but we keep it attached to the struct in order to
-    // properly forward it in case of e.g. marginalization.
-    _bandwidth_marker: PhantomData<B>,
+    // Logarithm of `normalization`, which remains finite even if `normalization` itself under- or overflows for
+    // high-dimensional data.
+    ln_normalization: F,
+    #[cfg_attr(feature = "serde", serde(default))]
+    covariance_mode: CovarianceMode,
+    // Whether the kernel covariance was given by the user (see `with_bandwidth_matrix`), in which case derived KDEs
+    // use the corresponding sub-block instead of the dataset's covariance matrix.
+    #[cfg_attr(feature = "serde", serde(default))]
+    custom_bandwidth_matrix: bool,
+    // Relative regularization of the dataset covariance matrix, see `GaussianKDEBuilder::regularization`.
+    #[cfg_attr(feature = "serde", serde(default = "num_traits::Zero::zero"))]
+    regularization: F,
+    // The bandwidth selector is only used during init and when adding points, but we keep it attached to the struct
+    // in order to properly forward it in case of e.g. marginalization.
+    bandwidth: B,
+    // Running moments of the dataset, computed on the first update with additional points (see `GaussianKDE::push`)
+    // and maintained afterwards.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    moments: Option<RunningMoments<F>>,
+    // Whether the whitened dataset is cached for the evaluation, see `GaussianKDE::set_whitened_data_cache`.
+    #[cfg_attr(feature = "serde", serde(default = "enabled"))]
+    cache_whitened: bool,
+    // Lazily whitened dataset, shared by all evaluations of the KDE and reset whenever the kernel changes.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    whitened: OnceLock<eval::WhitenedData<F>>,
+    // Lazily built kd-tree of the whitened dataset for the approximate evaluation, reset together with `whitened`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tree: OnceLock<kdtree::KdTree<F>>,
+    // Lazily constructed selection of the kernel to draw from, shared by all draws from the KDE.
+    #[cfg(feature = "sample")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    components: OnceLock<sample::ComponentSampler>,
+}
+
+/// Clones share the dataset and weights with the original KDE, which are copied only once either of them is modified,
+/// e.g. by [`GaussianKDE::push`]. The lazily computed caches (e.g. the whitened dataset) are not copied but rebuilt
+/// by the clone on first use.
+impl<F, B> Clone for CowGaussianKDE<'_, F, B>
+where
+    F: Float + FloatConst + FromPrimitive,
+    B: Bandwidth<F> + Clone,
+{
+    fn clone(&self) -> Self {
+        return Self {
+            data: Arc::clone(&self.data),
+            weights: self.weights.clone(),
+            dims: self.dims.clone(),
+            selected_data: Default::default(),
+            original_dims: self.original_dims.clone(),
+            bandwidth_factor: self.bandwidth_factor,
+            cholesky: self.cholesky.clone(),
+            inv_cholesky: self.inv_cholesky.clone(),
+            normalization: self.normalization,
+            ln_normalization: self.ln_normalization,
+            covariance_mode: self.covariance_mode,
+            custom_bandwidth_matrix: self.custom_bandwidth_matrix,
+            regularization: self.regularization,
+            bandwidth: self.bandwidth.clone(),
+            moments: self.moments.clone(),
+            cache_whitened: self.cache_whitened,
+            whitened: Default::default(),
+            tree: Default::default(),
+            #[cfg(feature = "sample")]
+            components: Default::default(),
+        };
+    }
 }
 
-impl<F> GaussianKDE<F>
+// KDEs (including their lazily computed caches) can be shared between and sent to threads if the element type and the
+// bandwidth selector can, which new fields must not break.
+#[allow(dead_code)]
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+    fn assert_kde<'a, F, B>()
+    where
+        F: Float + FloatConst + FromPrimitive + Send + Sync + 'a,
+        B: Bandwidth<F> + Send + Sync,
+    {
+        assert_send_sync::<CowGaussianKDE<'a, F, B>>();
+    }
+};
+
+impl<'a, F> CowGaussianKDE<'a, F>
 where
     F: Float + FloatConst + FromPrimitive + 'static,
 {
@@ -66,31 +252,168 @@ where
     ///
     /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
     /// lie along `Axis(1)`.
+    ///
+    /// Returns a `WeightError` if any weight is negative or not finite or the weights do not have a positive sum.
+    /// Individual weights may be zero. Non-finite values in the dataset are reported as `DataError`, see
+    /// [`GaussianKDEBuilder::check_finite`] to skip this check.
     pub fn new(
         data: Array2<F>,
         weights: Option<Array1<F>>,
-    ) -> Result<GaussianKDE<F, bandwidth::ScottBandwidth>, KDEError> {
+    ) -> Result<CowGaussianKDE<'a, F, bandwidth::ScottBandwidth>, KDEError> {
         return Self::with_bandwidth(data, weights);
     }
 
-    /// Get a view of the KDE's data.
-    pub fn data<'kde>(&'kde self) -> ArrayView2<'kde, F> {
-        return self.data.view();
+    /// Create a new kernel density estimator from the given dataset and (optionally) weights using the default
+    /// bandwidth choice [`ScottBandwidth`], borrowing the arrays instead of taking ownership. Evaluation and sampling
+    /// only read the data, such that it can be shared with other readers without copying. Adding points, e.g. with
+    /// [`GaussianKDE::push`], copies the data first.
+    ///
+    /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    pub fn from_view<'v>(
+        data: ArrayView2<'v, F>,
+        weights: Option<ArrayView1<'v, F>>,
+    ) -> Result<CowGaussianKDE<'v, F, bandwidth::ScottBandwidth>, KDEError> {
+        check_finite(data)?;
+        return CowGaussianKDE::fit(
+            shared(data),
+            weights.map(shared),
+            ScottBandwidth {},
+            CovarianceMode::Full,
+            F::zero(),
+        );
     }
 
-    /// Get a view of the KDE's weights.
-    pub fn weights<'kde>(&'kde self) -> Option<ArrayView1<'kde, F>> {
-        return self.weights.as_ref().map(|w| w.view());
+    /// Create a [`GaussianKDEBuilder`] to configure the construction of a kernel density estimator, starting from
+    /// the default options of [`GaussianKDE::new`].
+    pub fn builder() -> GaussianKDEBuilder<F> {
+        return GaussianKDEBuilder::new();
     }
 
-    /// Get a view of the lower-triangular matrix $L$ obtained from the Cholesky decomposition $V = LL^\top$ of the dataset's
-    /// covariance matrix $V$.
-    pub fn cholesky<'kde>(&'kde self) -> ArrayView2<'kde, F> {
-        return self.cholesky.view();
+    /// Create a new kernel density estimator from the given dataset and (optionally) weights using the fixed scalar
+    /// bandwidth factor `h`, i.e. the kernel covariance is given by $H = h^2 V$. This is equivalent to
+    /// `scipy.stats.gaussian_kde(data, bw_method=h)`. If no weights are given, all points are weighed equally.
+    ///
+    /// This is a shortcut for [`GaussianKDE::with_bandwidth_selector`] with a [`FixedBandwidth`]. Returns a
+    /// `DomainError` if `h` is not positive and finite.
+    ///
+    /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    pub fn with_bandwidth_factor(
+        data: Array2<F>,
+        weights: Option<Array1<F>>,
+        h: F,
+    ) -> Result<CowGaussianKDE<'a, F, FixedBandwidth<F>>, KDEError> {
+        return CowGaussianKDE::with_bandwidth_selector(data, weights, FixedBandwidth::new(h)?);
+    }
+
+    /// Create a new kernel density estimator from the given dataset and (optionally) weights using the given
+    /// bandwidth matrix `h_matrix` as kernel covariance $H$, e.g. a plug-in estimate computed externally. If no
+    /// weights are given, all points are weighed equally.
+    ///
+    /// `h_matrix` must be a symmetric, positive-definite matrix of shape `(dim, dim)`, otherwise a `ShapeError` or
+    /// `SingularityError` is returned. The bandwidth factor of the resulting KDE is $h = 1$, such that
+    /// [`GaussianKDE::set_bandwidth_factor`] rescales the given matrix. Marginalized KDEs use the corresponding
+    /// sub-block of $H$.
+    ///
+    /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    pub fn with_bandwidth_matrix(
+        data: Array2<F>,
+        weights: Option<Array1<F>>,
+        h_matrix: Array2<F>,
+    ) -> Result<CowGaussianKDE<'a, F, FixedBandwidth<F>>, KDEError> {
+        check_finite(data.view())?;
+        return CowGaussianKDE::from_bandwidth_matrix(
+            shared(data),
+            weights.map(shared),
+            FixedBandwidth { factor: F::one() },
+            F::one(),
+            h_matrix.view(),
+        );
+    }
+
+    /// Create a new kernel density estimator from the given dataset and (optionally) weights using the default
+    /// bandwidth choice [`ScottBandwidth`] and the diagonal covariance matrix of the dataset, i.e. the kernel
+    /// covariance is given by $H = h^2 \mathrm{diag}(\sigma_1^2, \dots, \sigma_d^2)$. If no weights are given, all
+    /// points are weighed equally.
+    ///
+    /// This is a shortcut for [`GaussianKDE::with_covariance_mode`] with [`CovarianceMode::Diagonal`].
+    ///
+    /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    pub fn with_diagonal_covariance(
+        data: Array2<F>,
+        weights: Option<Array1<F>>,
+    ) -> Result<CowGaussianKDE<'a, F, bandwidth::ScottBandwidth>, KDEError> {
+        return Self::with_covariance_mode(
+            data,
+            weights,
+            ScottBandwidth {},
+            CovarianceMode::Diagonal,
+        );
+    }
+
+    /// Create a new kernel density estimator from a dataset given as flat vector of `dim`-dimensional points in
+    /// row-major order, e.g. received over an FFI boundary, and (optionally) weights using the default bandwidth
+    /// choice [`ScottBandwidth`]. The vectors are used as storage of the internal arrays without copying.
+    ///
+    /// Returns a `ShapeError` if `dim` is zero or does not divide the length of `data`, besides the errors of
+    /// [`GaussianKDE::new`].
+    pub fn from_flat(
+        data: Vec<F>,
+        dim: usize,
+        weights: Option<Vec<F>>,
+    ) -> Result<CowGaussianKDE<'a, F, bandwidth::ScottBandwidth>, KDEError> {
+        if dim == 0 || data.len() % dim != 0 {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected a data vector whose length is a positive multiple of the dimension {dim}, found length {}",
+                    data.len()
+                ),
+            ));
+        }
+        let data = Array2::from_shape_vec((data.len() / dim, dim), data).unwrap();
+        return Self::new(data, weights.map(Array1::from_vec));
+    }
+
+    /// Create a new kernel density estimator from an iterator over the points of the dataset and (optionally) weights
+    /// using the default bandwidth choice [`ScottBandwidth`]. The dimension is inferred from the first point and the
+    /// points are collected into the internal array in a single pass.
+    ///
+    /// Returns a `ShapeError` if the iterator is empty or the points have different (or zero) lengths, besides the
+    /// errors of [`GaussianKDE::new`].
+    pub fn from_points<I, P>(
+        points: I,
+        weights: Option<Array1<F>>,
+    ) -> Result<CowGaussianKDE<'a, F, bandwidth::ScottBandwidth>, KDEError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[F]>,
+    {
+        return Self::new(collect_points(points)?, weights);
+    }
+
+    /// Create a new kernel density estimator from an iterator over pairs of points and their weights using the default
+    /// bandwidth choice [`ScottBandwidth`]. See [`GaussianKDE::from_points`] for details.
+    pub fn from_points_weighted<I, P>(
+        points: I,
+    ) -> Result<CowGaussianKDE<'a, F, bandwidth::ScottBandwidth>, KDEError>
+    where
+        I: IntoIterator<Item = (P, F)>,
+        P: AsRef<[F]>,
+    {
+        let mut weights = Vec::new();
+        let data = collect_points(points.into_iter().map(|(point, weight)| {
+            weights.push(weight);
+            point
+        }))?;
+        return Self::new(data, Some(Array1::from_vec(weights)));
     }
 }
 
-impl<F, B> GaussianKDE<F, B>
+impl<'a, F, B> CowGaussianKDE<'a, F, B>
 where
     F: Float + FloatConst + FromPrimitive + 'static,
     B: Bandwidth<F>,
@@ -98,29 +421,124 @@ where
     /// Create a new kernel density estimator from the given dataset and (optionally) weights using the specified
     /// bandwidth factor choice. If no weights are given, all points are weighed equally.
     ///
+    /// The bandwidth selector is constructed via [`Default`], use [`GaussianKDE::with_bandwidth_selector`] for
+    /// selectors carrying parameters.
+    ///
     /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
     /// lie along `Axis(1)`.
+    ///
+    /// As for all constructors, non-finite data and invalid weights are reported as `DataError` and `WeightError`,
+    /// see [`GaussianKDE::new`].
     pub fn with_bandwidth(
         data: Array2<F>,
         weights: Option<Array1<F>>,
-    ) -> Result<GaussianKDE<F, B>, KDEError> {
+    ) -> Result<CowGaussianKDE<'a, F, B>, KDEError>
+    where
+        B: Default,
+    {
+        return Self::with_bandwidth_selector(data, weights, B::default());
+    }
+
+    /// Create a new kernel density estimator from the given dataset and (optionally) weights using the given
+    /// bandwidth selector. If no weights are given, all points are weighed equally.
+    ///
+    /// The selector is stored in the KDE and reused for derived KDEs, e.g. during marginalization. Returns a
+    /// `DomainError` if the selected bandwidth factor is not positive and finite.
+    ///
+    /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    pub fn with_bandwidth_selector(
+        data: Array2<F>,
+        weights: Option<Array1<F>>,
+        bandwidth: B,
+    ) -> Result<CowGaussianKDE<'a, F, B>, KDEError> {
+        return Self::with_covariance_mode(data, weights, bandwidth, CovarianceMode::Full);
+    }
+
+    /// Create a new kernel density estimator from the given dataset and (optionally) weights using the given
+    /// bandwidth selector and the given structure of the dataset covariance matrix $V$. If no weights are given, all
+    /// points are weighed equally.
+    ///
+    /// For [`CovarianceMode::Diagonal`], the Cholesky decomposition reduces to the per-dimension standard deviations
+    /// and evaluation as well as sampling use cheaper per-dimension code paths.
+    /// The shrinkage modes [`CovarianceMode::Shrinkage`] and [`CovarianceMode::LedoitWolf`] also accept datasets with
+    /// fewer points than dimensions. A shrinkage intensity outside of $[0, 1]$ results in a `DomainError`.
+    ///
+    /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    pub fn with_covariance_mode(
+        data: Array2<F>,
+        weights: Option<Array1<F>>,
+        bandwidth: B,
+        covariance_mode: CovarianceMode,
+    ) -> Result<CowGaussianKDE<'a, F, B>, KDEError> {
+        check_finite(data.view())?;
+        return Self::fit(
+            shared(data),
+            weights.map(shared),
+            bandwidth,
+            covariance_mode,
+            F::zero(),
+        );
+    }
+
+    /// Create a new kernel density estimator from the given dataset and (optionally) weights using the given
+    /// bandwidth selector, where the dataset covariance matrix $V$ is regularized by adding
+    /// $\varepsilon \operatorname{tr} V / d$ to its diagonal before the decomposition. If no weights are given, all
+    /// points are weighed equally.
+    ///
+    /// The ridge keeps the kernel covariance well-conditioned for (almost) perfectly correlated dimensions, at the
+    /// cost of slightly broader kernels. The regularization $\varepsilon$ is recorded in the KDE and reused for
+    /// derived KDEs, and the regularized kernel covariance is returned by [`GaussianKDE::covariance`]. This is a
+    /// shortcut for [`GaussianKDEBuilder::regularization`].
+    ///
+    /// Returns a `DomainError` if `regularization` is negative or not finite.
+    ///
+    /// The dataset is expected to be given as array of shape `(n_points, dim)`, i.e. a single point is expected to
+    /// lie along `Axis(1)`.
+    pub fn with_regularization(
+        data: Array2<F>,
+        weights: Option<Array1<F>>,
+        bandwidth: B,
+        regularization: F,
+    ) -> Result<CowGaussianKDE<'a, F, B>, KDEError> {
+        let mut builder = GaussianKDEBuilder::new()
+            .bandwidth(bandwidth)
+            .regularization(regularization);
+        if let Some(weights) = weights {
+            builder = builder.weights(weights);
+        }
+        return builder.build(data);
+    }
+
+    /// Create a kernel density estimator with the given covariance mode, where $\varepsilon \operatorname{tr} V / d$
+    /// is added to the diagonal of the dataset covariance matrix $V$ for the given regularization $\varepsilon$.
+    fn fit(
+        data: SharedArray<'a, F, Ix2>,
+        weights: Option<SharedArray<'a, F, Ix1>>,
+        bandwidth: B,
+        covariance_mode: CovarianceMode,
+        regularization: F,
+    ) -> Result<Self, KDEError> {
         let n_samples = data.dim().0;
-        let dim = data.dim().1;
         // Preliminary shape checks
-        if let Some(ref w) = weights
-            && data.dim().0 != w.dim()
-        {
-            return Err(KDEError::new(
-                ErrorKind::ShapeError,
-                format!(
-                    "expected {} weights for data array with shape `{:?}`, found {}",
-                    n_samples,
-                    data.dim(),
-                    w.dim()
-                ),
-            ));
+        if let Some(ref w) = weights {
+            if data.dim().0 != w.dim() {
+                return Err(KDEError::new(
+                    ErrorKind::ShapeError,
+                    format!(
+                        "expected {} weights for data array with shape `{:?}`, found {}",
+                        n_samples,
+                        data.dim(),
+                        w.dim()
+                    ),
+                ));
+            }
         }
-        if data.dim().0 < data.dim().1 {
+        if let Some(ref w) = weights {
+            check_weights(w.view())?;
+        }
+        if data.dim().0 < data.dim().1 && !covariance_mode.allows_few_points() {
             return Err(KDEError::new(
                 ErrorKind::SingularityError,
                 format!(
@@ -131,99 +549,2111 @@ where
             ));
         }
         // Prepare values which are repeatedly used during evaluation / sampling
-        let sum_weights = if let Some(ref w) = weights {
-            w.sum()
-        } else {
-            F::from(n_samples).unwrap()
+        let bw = bandwidth.bandwidth(data.view(), weights.as_ref().map(|w| w.view()));
+        check_bandwidth_factor(bw)?;
+        let (cholesky, inv_cholesky) = kernel_cholesky(
+            data.view(),
+            weights.as_ref().map(|w| w.view()),
+            bw,
+            covariance_mode,
+            regularization,
+            None,
+        )?;
+        let mut kde = Self {
+            data,
+            weights,
+            dims: None,
+            selected_data: Default::default(),
+            original_dims: None,
+            bandwidth_factor: bw,
+            cholesky,
+            inv_cholesky,
+            normalization: F::zero(),
+            ln_normalization: F::zero(),
+            covariance_mode,
+            custom_bandwidth_matrix: false,
+            regularization,
+            bandwidth,
+            moments: None,
+            cache_whitened: true,
+            whitened: Default::default(),
+            tree: Default::default(),
+            #[cfg(feature = "sample")]
+            components: Default::default(),
         };
-        let bw = B::bandwidth(data.view(), weights.as_ref().map(|w| w.view()));
-        let cov;
+        kde.update_normalization();
+        return Ok(kde);
+    }
+
+    /// Create a kernel density estimator with the kernel covariance $H = h^2 V$ for the given matrix $V$ instead of
+    /// the dataset's covariance matrix.
+    fn from_bandwidth_matrix(
+        data: SharedArray<'a, F, Ix2>,
+        weights: Option<SharedArray<'a, F, Ix1>>,
+        bandwidth: B,
+        h: F,
+        v: ArrayView2<F>,
+    ) -> Result<Self, KDEError> {
+        let dim = data.dim().1;
         if let Some(ref w) = weights {
-            // Weighted data -> weighted mean / covariance
-            let means = Array1::from_shape_fn(dim, |i| {
-                Zip::from(data.index_axis(Axis(1), i))
-                    .and(w)
-                    .fold(F::zero(), |acc, x, w| acc + *w * *x)
-                    / sum_weights
-            });
-            cov = Array2::from_shape_fn((dim, dim), |(i, j)| {
-                Zip::from(data.index_axis(Axis(1), i))
-                    .and(data.index_axis(Axis(1), j))
-                    .and(w)
-                    .fold(F::zero(), |acc, x, y, w| {
-                        acc + *w * (*x - means[i]) * (*y - means[j])
-                    })
-                    / (sum_weights
-                        - w.iter().map(|w| *w * *w).fold(F::zero(), |acc, x| acc + x) / sum_weights)
-                    * bw
-                    * bw
-            });
-        } else {
-            let means = Array1::from_shape_fn(dim, |i| data.index_axis(Axis(1), i).mean().unwrap());
-            cov = Array2::from_shape_fn((dim, dim), |(i, j)| {
-                Zip::from(data.index_axis(Axis(1), i))
-                    .and(data.index_axis(Axis(1), j))
-                    .fold(F::zero(), |acc, x, y| {
-                        acc + (*x - means[i]) * (*y - means[j])
-                    })
-                    / (sum_weights - F::one())
-                    * bw
-                    * bw
-            });
+            if data.dim().0 != w.dim() {
+                return Err(KDEError::new(
+                    ErrorKind::ShapeError,
+                    format!(
+                        "expected {} weights for data array with shape `{:?}`, found {}",
+                        data.dim().0,
+                        data.dim(),
+                        w.dim()
+                    ),
+                ));
+            }
         }
-
-        let cholesky = cholesky_decomposition(cov.view())?;
+        if let Some(ref w) = weights {
+            check_weights(w.view())?;
+        }
+        if v.dim() != (dim, dim) {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected bandwidth matrix of shape `{:?}` for data array with shape `{:?}`, found `{:?}`",
+                    (dim, dim),
+                    data.dim(),
+                    v.dim()
+                ),
+            ));
+        }
+        // Allow for asymmetries due to rounding in externally computed matrices
+        let tolerance =
+            F::from(100).unwrap() * F::epsilon() * v.fold(F::zero(), |acc, x| acc.max(x.abs()));
+        if v.indexed_iter()
+            .any(|((i, j), x)| (*x - v[[j, i]]).abs() > tolerance)
+        {
+            return Err(KDEError::new(
+                ErrorKind::SingularityError,
+                "the bandwidth matrix is not symmetric",
+            ));
+        }
+        let cholesky = cholesky_decomposition(v.mapv(|x| x * h * h).view())?;
         let inv_cholesky = cholesky_inverse(cholesky.view());
-        let det = cholesky.diag().product();
-        let normalization = F::recip(
-            sum_weights * det * F::powi(F::sqrt(F::from(2).unwrap() * F::PI()), dim as i32),
-        );
-        return Ok(Self {
+        let mut kde = Self {
             data,
             weights,
+            dims: None,
+            selected_data: Default::default(),
+            original_dims: None,
+            bandwidth_factor: h,
             cholesky,
             inv_cholesky,
-            normalization,
-            _bandwidth_marker: PhantomData,
-        });
+            normalization: F::zero(),
+            ln_normalization: F::zero(),
+            covariance_mode: CovarianceMode::Full,
+            custom_bandwidth_matrix: true,
+            regularization: F::zero(),
+            bandwidth,
+            moments: None,
+            cache_whitened: true,
+            whitened: Default::default(),
+            tree: Default::default(),
+            #[cfg(feature = "sample")]
+            components: Default::default(),
+        };
+        kde.update_normalization();
+        return Ok(kde);
+    }
+
+    /// Compute the normalization $1 / (\sqrt{(2\pi)^d \det H} \sum_i w_i)$ from the Cholesky factor of $H$.
+    fn update_normalization(&mut self) {
+        let sum_weights = if let Some(ref w) = self.weights {
+            compensated_sum(w.iter().copied())
+        } else {
+            F::from(self.n_points()).unwrap()
+        };
+        let ln_det = self.cholesky.diag().mapv(F::ln).sum();
+        self.ln_normalization = -(sum_weights.ln()
+            + ln_det
+            + F::from(0.5 * self.n_dims() as f64).unwrap() * F::ln(F::from(2).unwrap() * F::PI()));
+        self.normalization = self.ln_normalization.exp();
+    }
+
+    /// Get the KDE's data as array of shape `(n_points, dim)`. This is a view of the stored dataset, except for
+    /// marginals sharing the dataset of their parent, for which the remaining components are copied once on the
+    /// first call.
+    pub fn data(&self) -> ArrayView2<'_, F> {
+        return match self.dims {
+            Some(ref dims) => self
+                .selected_data
+                .get_or_init(|| self.data.select(Axis(1), dims))
+                .view(),
+            None => self.data.view(),
+        };
+    }
+
+    /// Dataset of the KDE's components as in [`GaussianKDE::data`], but without keeping the copy for marginals
+    /// sharing the dataset of their parent unless it was already made.
+    pub(crate) fn dataset(&self) -> CowArray<'_, F, Ix2> {
+        return match (&self.dims, self.selected_data.get()) {
+            (None, _) => self.data.view().into(),
+            (Some(_), Some(selected)) => selected.view().into(),
+            (Some(dims), None) => self.data.select(Axis(1), dims).into(),
+        };
+    }
+
+    /// Number of points in the KDE's dataset.
+    pub(crate) fn n_points(&self) -> usize {
+        return self.data.dim().0;
+    }
+
+    /// Dimension of the KDE, which is smaller than the number of columns of the stored dataset for marginals sharing
+    /// the dataset of their parent.
+    pub(crate) fn n_dims(&self) -> usize {
+        return self.dims.as_ref().map_or(self.data.dim().1, Vec::len);
+    }
+
+    /// Column of the stored dataset holding the component `j` of the data points.
+    pub(crate) fn data_column(&self, j: usize) -> ArrayView1<'_, F> {
+        return self
+            .data
+            .column(self.dims.as_ref().map_or(j, |dims| dims[j]));
+    }
+
+    /// Write the data point with index `i` into `out`, whose length must match the dimension of the KDE.
+    #[cfg(feature = "sample")]
+    pub(crate) fn assign_data_point(&self, i: usize, mut out: ArrayViewMut1<F>) {
+        let row = self.data.row(i);
+        match self.dims {
+            Some(ref dims) => out.zip_mut_with(&aview1(dims), |o, j| *o = row[*j]),
+            None => out.assign(&row),
+        }
+    }
+
+    /// Get the indices of the components of the original KDE the components of this KDE correspond to, e.g. `[3, 1]`
+    /// for `kde.marginalize_to(&[3, 1])`, whose first component is the component 3 of `kde`. For nested
+    /// marginalizations (or conditionals), the indices refer to the outermost KDE, and for a KDE which was not derived
+    /// from another one, they are `0..dim`.
+    pub fn original_dims(&self) -> Vec<usize> {
+        return self
+            .original_dims
+            .clone()
+            .unwrap_or_else(|| (0..self.n_dims()).collect());
+    }
+
+    /// Get a view of the KDE's weights.
+    pub fn weights<'kde>(&'kde self) -> Option<ArrayView1<'kde, F>> {
+        return self.weights.as_ref().map(|w| w.view());
+    }
+
+    /// Get a view of the lower-triangular matrix $L$ obtained from the Cholesky decomposition $V = LL^\top$ of the dataset's
+    /// covariance matrix $V$.
+    pub fn cholesky<'kde>(&'kde self) -> ArrayView2<'kde, F> {
+        return self.cholesky.view();
+    }
+
+    /// Get the scalar bandwidth factor $h$ chosen by the bandwidth selector during construction.
+    pub fn bandwidth_factor(&self) -> F {
+        return self.bandwidth_factor;
+    }
+
+    /// Get a view of the inverse $L^{-1}$ of the Cholesky factor of the kernel covariance $H = LL^\top$, i.e. the
+    /// whitening matrix mapping a distance $x - x_i$ to the standard normal coordinates of the kernel.
+    pub fn inv_cholesky<'kde>(&'kde self) -> ArrayView2<'kde, F> {
+        return self.inv_cholesky.view();
+    }
+
+    /// Get the covariance matrix $H = h^2 V$ of the kernels, reconstructed as $H = LL^\top$ from its Cholesky factor.
+    /// For a regularized KDE, $V$ includes the ridge added to its diagonal, see [`GaussianKDE::regularization`].
+    pub fn covariance(&self) -> Array2<F> {
+        return self.cholesky.dot(&self.cholesky.t());
+    }
+
+    /// Get the inverse $H^{-1} = L^{-\top} L^{-1}$ of the kernel covariance, computed from the stored inverse Cholesky
+    /// factor.
+    pub fn inv_covariance(&self) -> Array2<F> {
+        return self.inv_cholesky.t().dot(&self.inv_cholesky);
+    }
+
+    /// Get the scaled covariance matrix $H = h^2 V$ used as kernel covariance. Same as [`GaussianKDE::covariance`].
+    pub fn scaled_covariance(&self) -> Array2<F> {
+        return self.covariance();
+    }
+
+    /// Change the scalar bandwidth factor of the fitted KDE to `h` without refitting the dataset.
+    ///
+    /// Since the kernel covariance is given by $H = h^2 V$, this only requires rescaling the stored Cholesky factor,
+    /// its inverse and the normalization, which is $\mathcal{O}(d^2)$. Marginals inherit the new factor, while the
+    /// bandwidth selector is kept as is, i.e. refits (e.g. after adding points) choose the factor with the selector.
+    ///
+    /// Returns a `DomainError` and leaves the KDE unchanged if `h` is not positive and finite.
+    pub fn set_bandwidth_factor(&mut self, h: F) -> Result<(), KDEError> {
+        check_bandwidth_factor(h)?;
+        let ratio = h / self.bandwidth_factor;
+        self.cholesky.mapv_inplace(|x| x * ratio);
+        self.inv_cholesky.mapv_inplace(|x| x / ratio);
+        self.ln_normalization =
+            self.ln_normalization - F::from(self.n_dims()).unwrap() * ratio.ln();
+        self.normalization = self.ln_normalization.exp();
+        self.bandwidth_factor = h;
+        self.whitened = Default::default();
+        self.tree = Default::default();
+        return Ok(());
+    }
+
+    /// Consuming version of [`GaussianKDE::set_bandwidth_factor`].
+    pub fn with_rescaled_bandwidth(mut self, h: F) -> Result<Self, KDEError> {
+        self.set_bandwidth_factor(h)?;
+        return Ok(self);
+    }
+
+    /// Get the structure of the dataset covariance matrix used by the KDE, which is [`CovarianceMode::Diagonal`] if
+    /// the [`GaussianKDEBuilder::diagonal_fallback`] applied.
+    pub fn covariance_mode(&self) -> CovarianceMode {
+        return self.covariance_mode;
+    }
+
+    /// Get the regularization $\varepsilon$ of the dataset covariance matrix, i.e. $\varepsilon \operatorname{tr} V / d$
+    /// was added to the diagonal of $V$ (zero if the KDE is not regularized), see
+    /// [`GaussianKDE::with_regularization`].
+    pub fn regularization(&self) -> F {
+        return self.regularization;
+    }
+
+    /// Get a reference to the bandwidth selector used by the KDE.
+    pub fn bandwidth_selector(&self) -> &B {
+        return &self.bandwidth;
     }
 
     /// Marginalize the density by integrating out the components given in `dims`. For Gaussian kernels, this is
     /// equivalent to simply remove the marginalized components from the dataset.
-    pub fn marginalize(&self, dims: &[usize]) -> Result<Self, KDEError> {
-        for i in dims {
-            if *i > self.data.dim().0 {
+    ///
+    /// The kernel covariance of the marginal KDE is the corresponding sub-block of $H$, i.e. the bandwidth factor of
+    /// the parent is kept and the dataset is not traversed again, such that the cost is independent of the number of
+    /// data points. See [`GaussianKDE::marginalize_with`] to choose the bandwidth factor for the reduced dimension
+    /// instead.
+    ///
+    /// Returns an `IndexError` if any index in `dims` is out of bounds or repeated and a `ShapeError` if no components
+    /// would remain. Marginalizing no components is cheap, since the result shares the dataset and decomposition.
+    /// Errors of the construction of the marginal, e.g. a `SingularityError` for a constant remaining component with
+    /// [`BandwidthPolicy::Recompute`], are propagated with a message naming the kept components.
+    pub fn marginalize(&self, dims: &[usize]) -> Result<Self, KDEError>
+    where
+        B: Clone,
+    {
+        return self.marginalize_with(dims, BandwidthPolicy::KeepParentFactor);
+    }
+
+    /// Marginalize the density by integrating out the components given in `dims` with the given choice of the
+    /// bandwidth factor.
+    ///
+    /// With [`BandwidthPolicy::KeepParentFactor`], the result is the exact marginal of the density, computed from the
+    /// sub-block of the kernel covariance. With [`BandwidthPolicy::Recompute`], the marginal is fitted to the
+    /// remaining components as a new KDE, which requires a pass over the dataset and differs from the exact marginal
+    /// if the bandwidth selector depends on the dimension (e.g. [`ScottBandwidth`]) or if the covariance estimate
+    /// depends on all components (e.g. the shrinkage covariance modes or a regularization).
+    pub fn marginalize_with(
+        &self,
+        dims: &[usize],
+        policy: BandwidthPolicy,
+    ) -> Result<Self, KDEError>
+    where
+        B: Clone,
+    {
+        self.check_dims(dims)?;
+        let indices = (0..self.n_dims())
+            .filter(|i| !dims.contains(i))
+            .collect::<Vec<_>>();
+        if indices.is_empty() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                "cannot marginalize all dimensions of the data",
+            ));
+        }
+        return self.select_dims(&indices, policy);
+    }
+
+    /// Marginalize the density by integrating out all components but the ones given in `dims`. For Gaussian kernels,
+    /// this is equivalent to simply remove the marginalized components from the dataset. As for
+    /// [`GaussianKDE::marginalize`], the bandwidth factor of the parent is kept.
+    ///
+    /// The components of the marginal follow the order of `dims`, which is not sorted, e.g. the first component of
+    /// `kde.marginalize_to(&[2, 0])` is the component 2 of `kde`, see [`GaussianKDE::original_dims`]. Points at which
+    /// the marginal is evaluated have to be given in the same order. Returns an `IndexError` if any index in `dims` is
+    /// out of bounds or repeated and a `ShapeError` if `dims` is empty.
+    pub fn marginalize_to(&self, dims: &[usize]) -> Result<Self, KDEError>
+    where
+        B: Clone,
+    {
+        return self.marginalize_to_with(dims, BandwidthPolicy::KeepParentFactor);
+    }
+
+    /// Marginalize the density by integrating out all components but the ones given in `dims` with the given choice
+    /// of the bandwidth factor, see [`GaussianKDE::marginalize_with`].
+    pub fn marginalize_to_with(
+        &self,
+        dims: &[usize],
+        policy: BandwidthPolicy,
+    ) -> Result<Self, KDEError>
+    where
+        B: Clone,
+    {
+        self.check_dims(dims)?;
+        if dims.is_empty() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                "cannot marginalize to zero dimensions",
+            ));
+        }
+        return self.select_dims(dims, policy);
+    }
+
+    /// Condition the density on the components given in `dims` taking the given `values`, i.e. construct the
+    /// (normalized) conditional density $f(\bm{x}_a \mid \bm{x}_b = \bm{v})$ of the remaining components $a$.
+    ///
+    /// For Gaussian kernels, this is again a Gaussian KDE: each kernel is replaced by its conditional distribution,
+    /// with center and covariance
+    /// \\[ \bm{x}_{i,a} + H_{ab} H_{bb}^{-1} (\bm{v} - \bm{x}_{i,b}) \quad \text{and} \quad H_{aa} - H_{ab} H_{bb}^{-1} H_{ba}, \\]
+    /// and its weight is multiplied by the kernel's marginal density $K_{H_{bb}}(\bm{v} - \bm{x}_{i,b})$ at the
+    /// conditioned values. The conditional kernel covariance is kept as fixed bandwidth matrix, see
+    /// [`GaussianKDE::with_bandwidth_matrix`].
+    ///
+    /// Returns an `IndexError` if any index in `dims` is out of bounds or repeated and a `ShapeError` if the lengths of
+    /// `dims` and `values` differ or no components would remain.
+    pub fn condition(&self, dims: &[usize], values: ArrayView1<F>) -> Result<Self, KDEError>
+    where
+        B: Clone,
+    {
+        self.check_dims(dims)?;
+        if values.len() != dims.len() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected {} values for conditioning on dimensions {dims:?}, found {}",
+                    dims.len(),
+                    values.len()
+                ),
+            ));
+        }
+        let free = (0..self.n_dims())
+            .filter(|i| !dims.contains(i))
+            .collect::<Vec<_>>();
+        if free.is_empty() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                "cannot condition on all dimensions of the data",
+            ));
+        }
+
+        let h = self.covariance();
+        let h_bb = h.select(Axis(0), dims).select(Axis(1), dims);
+        let h_ab = h.select(Axis(0), &free).select(Axis(1), dims);
+        let inv_cholesky_bb = cholesky_inverse(cholesky_decomposition(h_bb.view())?.view());
+        // Gain $H_{ab} H_{bb}^{-1}$ of the conditional mean
+        let gain = h_ab.dot(&inv_cholesky_bb.t()).dot(&inv_cholesky_bb);
+        let mut h_cond = h.select(Axis(0), &free).select(Axis(1), &free) - gain.dot(&h_ab.t());
+        // Remove asymmetries due to rounding
+        h_cond = (&h_cond + &h_cond.t()).mapv(|x| x * F::from(0.5).unwrap());
+
+        let points = self.dataset();
+        let residuals = &values - &points.select(Axis(1), dims);
+        let data = points.select(Axis(1), &free) + residuals.dot(&gain.t());
+        // Kernel weights in log space, shifted by their maximum to avoid underflow far from the data
+        let half = F::from(0.5).unwrap();
+        let mut ln_weights = residuals
+            .dot(&inv_cholesky_bb.t())
+            .map_axis(Axis(1), |z| -half * z.dot(&z));
+        if let Some(ref w) = self.weights {
+            ln_weights.zip_mut_with(w, |ln_w, w| *ln_w = *ln_w + w.ln());
+        }
+        let max = ln_weights.fold(F::neg_infinity(), |acc, x| acc.max(*x));
+        let weights = ln_weights.mapv_into(|x| (x - max).exp());
+
+        let h2 = self.bandwidth_factor * self.bandwidth_factor;
+        let mut res = Self::from_bandwidth_matrix(
+            shared(data),
+            Some(shared(weights)),
+            self.bandwidth.clone(),
+            self.bandwidth_factor,
+            h_cond.mapv(|x| x / h2).view(),
+        )?;
+        res.original_dims = Some(self.map_to_original(&free));
+        return Ok(res);
+    }
+
+    /// Fit a new KDE to `data` and `weights` with the same bandwidth choice, covariance mode and regularization as
+    /// the KDE, or the same kernel covariance if it was given by the user.
+    fn refit<'b>(
+        &self,
+        data: Array2<F>,
+        weights: Option<Array1<F>>,
+    ) -> Result<CowGaussianKDE<'b, F, B>, KDEError>
+    where
+        B: Clone,
+    {
+        if self.custom_bandwidth_matrix {
+            let h2 = self.bandwidth_factor * self.bandwidth_factor;
+            let v = self.scaled_covariance().mapv(|x| x / h2);
+            return CowGaussianKDE::from_bandwidth_matrix(
+                shared(data),
+                weights.map(shared),
+                self.bandwidth.clone(),
+                self.bandwidth_factor,
+                v.view(),
+            );
+        }
+        return CowGaussianKDE::fit(
+            shared(data),
+            weights.map(shared),
+            self.bandwidth.clone(),
+            self.covariance_mode,
+            self.regularization,
+        );
+    }
+
+    /// Map the indices `dims` of components of the KDE to the components of the original KDE.
+    fn map_to_original(&self, dims: &[usize]) -> Vec<usize> {
+        return dims
+            .iter()
+            .map(|j| {
+                self.original_dims
+                    .as_ref()
+                    .map_or(*j, |original| original[*j])
+            })
+            .collect();
+    }
+
+    /// Return an `IndexError` if any index in `dims` is out of bounds or repeated.
+    fn check_dims(&self, dims: &[usize]) -> Result<(), KDEError> {
+        for (k, i) in dims.iter().enumerate() {
+            if *i >= self.n_dims() {
                 return Err(KDEError::new(
                     ErrorKind::IndexError,
                     format!(
                         "index `{i}` out of bounds for data of dimension `{}`",
-                        self.data.dim().0
+                        self.n_dims()
                     ),
                 ));
             }
+            if dims[..k].contains(i) {
+                return Err(KDEError::new(
+                    ErrorKind::IndexError,
+                    format!("index `{i}` is repeated in the dimensions {dims:?}"),
+                ));
+            }
         }
-        let indices = (0..self.data.dim().0)
-            .filter(|i| dims.contains(i))
+        return Ok(());
+    }
+
+    /// Construct the KDE of the components `dims` of the dataset, i.e. its marginal density. For Gaussian kernels, the
+    /// kernel covariance of the marginal is the sub-block of the kernel covariance $H$, such that only this small
+    /// matrix is decomposed again, while the bandwidth factor, selector, covariance mode and regularization are
+    /// forwarded. With [`BandwidthPolicy::Recompute`], the selected components are instead copied temporarily to fit
+    /// the marginal from scratch. In both cases, the resulting KDE shares the dataset and weights of `self`.
+    ///
+    /// Errors of the construction are reported with a message naming the kept components.
+    fn select_dims(&self, dims: &[usize], policy: BandwidthPolicy) -> Result<Self, KDEError>
+    where
+        B: Clone,
+    {
+        return self.try_select_dims(dims, policy).map_err(|e| {
+            KDEError::new(
+                e.kind,
+                format!(
+                    "marginalization to the dimensions {dims:?} failed: {}",
+                    e.message
+                ),
+            )
+        });
+    }
+
+    fn try_select_dims(&self, dims: &[usize], policy: BandwidthPolicy) -> Result<Self, KDEError>
+    where
+        B: Clone,
+    {
+        let data_dims = dims
+            .iter()
+            .map(|j| self.dims.as_ref().map_or(*j, |parent| parent[*j]))
             .collect::<Vec<_>>();
-        let marginalized = self.data.select(Axis(1), &indices);
-        return Ok(Self::with_bandwidth(marginalized, self.weights.clone()).unwrap());
+        let original_dims = Some(self.map_to_original(dims));
+        if policy == BandwidthPolicy::Recompute && !self.custom_bandwidth_matrix {
+            let mut res = Self::fit(
+                shared(self.dataset().select(Axis(1), dims)),
+                self.weights.clone(),
+                self.bandwidth.clone(),
+                self.covariance_mode,
+                self.regularization,
+            )?;
+            res.data = Arc::clone(&self.data);
+            res.dims = Some(data_dims);
+            res.original_dims = original_dims;
+            return Ok(res);
+        }
+        // Selecting all components in order is a no-op, which keeps the decomposition and the running moments
+        let identity = dims.iter().copied().eq(0..self.n_dims());
+        let (cholesky, inv_cholesky) = if identity {
+            (self.cholesky.clone(), self.inv_cholesky.clone())
+        } else {
+            let h = self
+                .covariance()
+                .select(Axis(0), dims)
+                .select(Axis(1), dims);
+            let cholesky = cholesky_decomposition(h.view())?;
+            let inv_cholesky = cholesky_inverse(cholesky.view());
+            (cholesky, inv_cholesky)
+        };
+        let mut res = Self {
+            data: Arc::clone(&self.data),
+            weights: self.weights.clone(),
+            dims: if identity {
+                self.dims.clone()
+            } else {
+                Some(data_dims)
+            },
+            selected_data: Default::default(),
+            original_dims,
+            bandwidth_factor: self.bandwidth_factor,
+            cholesky,
+            inv_cholesky,
+            normalization: F::zero(),
+            ln_normalization: F::zero(),
+            covariance_mode: self.covariance_mode,
+            custom_bandwidth_matrix: self.custom_bandwidth_matrix,
+            regularization: self.regularization,
+            bandwidth: self.bandwidth.clone(),
+            moments: if identity { self.moments.clone() } else { None },
+            cache_whitened: self.cache_whitened,
+            whitened: Default::default(),
+            tree: Default::default(),
+            #[cfg(feature = "sample")]
+            components: Default::default(),
+        };
+        res.update_normalization();
+        return Ok(res);
     }
+}
 
-    /// Marginalize the density by integrating out all components but the ones given in `dims`. For Gaussian kernels,
-    /// this is equivalent to simply remove the marginalized components from the dataset.
-    pub fn marginalize_to(&self, dims: &[usize]) -> Result<Self, KDEError> {
-        for i in dims {
-            if *i > self.data.dim().0 {
-                return Err(KDEError::new(
-                    ErrorKind::IndexError,
-                    format!(
-                        "index `{i}` out of bounds for data of dimension `{}`",
-                        self.data.dim().0
-                    ),
-                ));
+/// Cholesky factor $L$ of the kernel covariance $H = h^2 V$ and its inverse for the bandwidth factor `bw`, where
+/// $V$ is the dataset covariance matrix of the given mode with $\varepsilon \operatorname{tr} V / d$ added to the
+/// diagonal for the given regularization $\varepsilon$. If available, the running `moments` of the dataset replace
+/// the pass over the data for the modes derived from the sample covariance matrix.
+fn kernel_cholesky<F>(
+    data: ArrayView2<F>,
+    weights: Option<ArrayView1<F>>,
+    bw: F,
+    covariance_mode: CovarianceMode,
+    regularization: F,
+    moments: Option<&RunningMoments<F>>,
+) -> Result<(Array2<F>, Array2<F>), KDEError>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    if let CovarianceMode::Shrinkage(lambda) = covariance_mode {
+        if !(0.0..=1.0).contains(&lambda) {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!("the shrinkage intensity must lie in [0, 1], found {lambda}"),
+            ));
+        }
+    }
+    let sample_covariance =
+        || moments.map_or_else(|| covariance(data, weights), RunningMoments::covariance);
+    return match covariance_mode {
+        CovarianceMode::Full
+        | CovarianceMode::Robust
+        | CovarianceMode::Shrinkage(_)
+        | CovarianceMode::LedoitWolf => {
+            let cov = match covariance_mode {
+                CovarianceMode::Robust => robust_covariance(data, weights),
+                CovarianceMode::Shrinkage(lambda) => {
+                    shrunk_covariance(sample_covariance(), F::from(lambda).unwrap())
+                }
+                CovarianceMode::LedoitWolf => {
+                    shrunk_covariance(sample_covariance(), ledoit_wolf_shrinkage(data, weights))
+                }
+                _ => sample_covariance(),
+            };
+            let ridge = regularization * cov.diag().mean().unwrap_or(F::zero());
+            let cov = cov + Array2::from_diag_elem(data.dim().1, ridge);
+            check_variances(data, cov.diag())?;
+            let cov = cov.mapv(|c| c * bw * bw);
+            let cholesky = cholesky_decomposition(cov.view())?;
+            let inv_cholesky = cholesky_inverse(cholesky.view());
+            Ok((cholesky, inv_cholesky))
+        }
+        CovarianceMode::Diagonal => {
+            let var = moments.map_or_else(
+                || variance(data, weights),
+                |moments| moments.covariance().diag().to_owned(),
+            );
+            let ridge = regularization * var.mean().unwrap_or(F::zero());
+            let var = var.mapv(|v| v + ridge);
+            check_variances(data, var.view())?;
+            let sd = var.mapv(|v| v.sqrt() * bw.abs());
+            Ok((
+                Array2::from_diag(&sd),
+                Array2::from_diag(&sd.mapv(F::recip)),
+            ))
+        }
+    };
+}
+
+/// Return a `SingularityError` listing all dimensions along which the variance `var` of the dataset `data` vanishes,
+/// i.e. does not exceed the rounding errors expected for a constant column.
+fn check_variances<F: Float>(data: ArrayView2<F>, var: ArrayView1<F>) -> Result<(), KDEError> {
+    let degenerate = var
+        .indexed_iter()
+        .filter(|(j, v)| {
+            let scale = data.column(*j).fold(F::zero(), |acc, x| acc.max(x.abs()));
+            let tolerance = F::from(100).unwrap() * F::epsilon() * scale;
+            **v <= tolerance * tolerance || v.is_nan()
+        })
+        .map(|(j, _)| j)
+        .collect::<Vec<_>>();
+    if !degenerate.is_empty() {
+        return Err(KDEError::new(
+            ErrorKind::SingularityError,
+            format!(
+                "the dataset has (close to) zero variance along the dimensions {degenerate:?}, consider dropping these \
+                 dimensions or adding a small jitter to the data"
+            ),
+        ));
+    }
+    return Ok(());
+}
+
+/// Return a `DomainError` if the bandwidth factor `h` is not positive and finite.
+fn check_bandwidth_factor<F: Float>(h: F) -> Result<(), KDEError> {
+    if !(h > F::zero() && h.is_finite()) {
+        return Err(KDEError::new(
+            ErrorKind::DomainError,
+            format!(
+                "the bandwidth factor must be positive and finite, found {}",
+                h.to_f64().unwrap()
+            ),
+        ));
+    }
+    return Ok(());
+}
+
+/// Return a `DataError` naming the row and column of the first non-finite value in the dataset `data` of shape
+/// `(n_points, dim)`.
+fn check_finite<F: Float>(data: ArrayView2<F>) -> Result<(), KDEError> {
+    if let Some(((row, col), x)) = data.indexed_iter().find(|(_, x)| !x.is_finite()) {
+        return Err(KDEError::new(
+            ErrorKind::DataError,
+            format!(
+                "the dataset contains the non-finite value {} in row {row}, column {col}",
+                x.to_f64().unwrap()
+            ),
+        ));
+    }
+    return Ok(());
+}
+
+/// Return a `WeightError` naming the first offending index if any weight is negative or not finite.
+fn check_weight_values<F: Float>(weights: ArrayView1<F>) -> Result<(), KDEError> {
+    if let Some(i) = weights.iter().position(|w| !w.is_finite()) {
+        return Err(KDEError::new(
+            ErrorKind::WeightError,
+            format!("the weight with index {i} is not finite"),
+        ));
+    }
+    if let Some(i) = weights.iter().position(|w| *w < F::zero()) {
+        return Err(KDEError::new(
+            ErrorKind::WeightError,
+            format!("the weight with index {i} is negative"),
+        ));
+    }
+    return Ok(());
+}
+
+/// Return a `WeightError` if any weight is negative or not finite, see [`check_weight_values`], or the sum of weights
+/// is not positive and finite. Individual weights may be zero.
+fn check_weights<F: Float>(weights: ArrayView1<F>) -> Result<(), KDEError> {
+    check_weight_values(weights)?;
+    let sum = weights.fold(F::zero(), |acc, w| acc + *w);
+    if !(sum > F::zero() && sum.is_finite()) {
+        return Err(KDEError::new(
+            ErrorKind::WeightError,
+            format!(
+                "the sum of weights must be positive and finite, found {}",
+                sum.to_f64().unwrap()
+            ),
+        ));
+    }
+    return Ok(());
+}
+
+/// Dataset or weights of a [`GaussianKDE`], which are either owned or borrowed and shared between KDEs.
+type SharedArray<'a, F, D> = Arc<CowArray<'a, F, D>>;
+
+/// Wrap an owned or borrowed array into the shared storage of a [`GaussianKDE`].
+fn shared<'a, F, D>(array: impl Into<CowArray<'a, F, D>>) -> SharedArray<'a, F, D>
+where
+    D: Dimension,
+{
+    return Arc::new(array.into());
+}
+
+/// Deserialize an owned array into the shared storage of a [`GaussianKDE`].
+#[cfg(feature = "serde")]
+fn deserialize_shared<'de, 'a, F, D, De>(
+    deserializer: De,
+) -> Result<SharedArray<'a, F, D>, De::Error>
+where
+    Array<F, D>: serde::Deserialize<'de>,
+    D: Dimension,
+    De: serde::Deserializer<'de>,
+{
+    return <Array<F, D> as serde::Deserialize>::deserialize(deserializer).map(shared);
+}
+
+/// Deserialize an optional owned array into the shared storage of a [`GaussianKDE`].
+#[cfg(feature = "serde")]
+fn deserialize_shared_option<'de, 'a, F, D, De>(
+    deserializer: De,
+) -> Result<Option<SharedArray<'a, F, D>>, De::Error>
+where
+    Array<F, D>: serde::Deserialize<'de>,
+    D: Dimension,
+    De: serde::Deserializer<'de>,
+{
+    return <Option<Array<F, D>> as serde::Deserialize>::deserialize(deserializer)
+        .map(|array| array.map(shared));
+}
+
+/// Default of flags which are enabled unless disabled explicitly, for the deserialization of KDEs serialized before
+/// the flag was introduced.
+#[cfg(feature = "serde")]
+fn enabled() -> bool {
+    return true;
+}
+
+/// Collect the given points into an array of shape `(n_points, dim)`, where the dimension is inferred from the first
+/// point. Returns a `ShapeError` if there are no points or the points have different (or zero) lengths.
+fn collect_points<F, P>(points: impl IntoIterator<Item = P>) -> Result<Array2<F>, KDEError>
+where
+    F: Float,
+    P: AsRef<[F]>,
+{
+    let mut points = points.into_iter();
+    let Some(first) = points.next() else {
+        return Err(KDEError::new(
+            ErrorKind::ShapeError,
+            "cannot infer the dimension from an empty sequence of points",
+        ));
+    };
+    let dim = first.as_ref().len();
+    if dim == 0 {
+        return Err(KDEError::new(
+            ErrorKind::ShapeError,
+            "expected points of positive dimension, found an empty point",
+        ));
+    }
+    let mut data = Vec::with_capacity(dim * (points.size_hint().0 + 1));
+    data.extend_from_slice(first.as_ref());
+    for (i, point) in points.enumerate() {
+        let point = point.as_ref();
+        if point.len() != dim {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected points of dimension {dim}, found point {} of dimension {}",
+                    i + 1,
+                    point.len()
+                ),
+            ));
+        }
+        data.extend_from_slice(point);
+    }
+    return Ok(Array2::from_shape_vec((data.len() / dim, dim), data).unwrap());
+}
+
+#[cfg(test)]
+#[allow(clippy::excessive_precision)]
+mod tests {
+    use crate::covariance::covariance;
+    use crate::{
+        Bandwidth, BandwidthPolicy, CovarianceMode, ErrorKind, FixedBandwidth, GaussianKDE,
+        KDEError, ScottBandwidth,
+    };
+    use approx::{assert_abs_diff_eq, assert_relative_eq};
+    use ndarray::prelude::*;
+
+    #[test]
+    fn marginalize_index_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let kde = GaussianKDE::new(data, None).unwrap();
+        for dims in [&[3][..], &[0, 3], &[7]] {
+            assert!(matches!(
+                kde.marginalize(dims),
+                Err(e) if matches!(e.kind, ErrorKind::IndexError)
+            ));
+            assert!(matches!(
+                kde.marginalize_to(dims),
+                Err(e) if matches!(e.kind, ErrorKind::IndexError)
+            ));
+        }
+        assert_eq!(kde.marginalize(&[0]).unwrap().data().dim(), (10, 2));
+        assert_eq!(kde.marginalize(&[2]).unwrap().data().dim(), (10, 2));
+        assert_eq!(kde.marginalize(&[0, 2]).unwrap().data().dim(), (10, 1));
+        assert_eq!(
+            kde.marginalize(&[0, 2]).unwrap().data(),
+            kde.data().slice(s![.., 1..2])
+        );
+        assert_eq!(kde.marginalize_to(&[0]).unwrap().data().dim(), (10, 1));
+        assert_eq!(kde.marginalize_to(&[2]).unwrap().data().dim(), (10, 1));
+        assert_eq!(
+            kde.marginalize_to(&[0, 2]).unwrap().data(),
+            kde.data().select(Axis(1), &[0, 2])
+        );
+
+        // Repeated indices and marginalizations without remaining components
+        for dims in [&[1, 1][..], &[0, 2, 0]] {
+            assert!(matches!(
+                kde.marginalize(dims),
+                Err(e) if matches!(e.kind, ErrorKind::IndexError)
+            ));
+            assert!(matches!(
+                kde.marginalize_to(dims),
+                Err(e) if matches!(e.kind, ErrorKind::IndexError)
+            ));
+        }
+        assert!(matches!(
+            kde.marginalize(&[2, 0, 1]),
+            Err(e) if matches!(e.kind, ErrorKind::ShapeError)
+        ));
+        assert!(matches!(
+            kde.marginalize_to(&[]),
+            Err(e) if matches!(e.kind, ErrorKind::ShapeError)
+        ));
+
+        // Marginalizing nothing keeps the decomposition
+        let x = array![[0.3, 0.5, 0.7], [0.9, 0.1, 0.4]];
+        for same in [
+            kde.marginalize(&[]).unwrap(),
+            kde.marginalize_to(&[0, 1, 2]).unwrap(),
+        ] {
+            assert_eq!(same.data.as_ptr(), kde.data.as_ptr());
+            assert_eq!(same.cholesky(), kde.cholesky());
+            assert_eq!(same.eval_batch(x.view()), kde.eval_batch(x.view()));
+        }
+
+        // The components of the marginal follow the order of the given indices
+        let reversed = kde.marginalize_to(&[2, 0]).unwrap();
+        assert_eq!(reversed.data(), kde.data().select(Axis(1), &[2, 0]));
+        assert_relative_eq!(
+            reversed.eval_batch(x.select(Axis(1), &[2, 0]).view()),
+            kde.marginalize_to(&[0, 2])
+                .unwrap()
+                .eval_batch(x.select(Axis(1), &[0, 2]).view()),
+            max_relative = 1e-14
+        );
+        let permuted = kde.marginalize_to(&[1, 2, 0]).unwrap();
+        assert_relative_eq!(
+            permuted.eval_batch(x.select(Axis(1), &[1, 2, 0]).view()),
+            kde.eval_batch(x.view()),
+            max_relative = 1e-12
+        );
+    }
+
+    #[test]
+    fn bandwidth_policy_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let g = [0.2, 0.5, 0.8];
+        let x = Array2::from_shape_fn((9, 2), |(i, j)| if j == 0 { g[i / 3] } else { g[i % 3] });
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+
+        // Reference values from `scipy.stats.gaussian_kde(data.T).marginal([0, 2]).pdf(x.T)`
+        let keep = kde
+            .marginalize_to_with(&[0, 2], BandwidthPolicy::KeepParentFactor)
+            .unwrap();
+        let res = array![
+            0.11657927813422317,
+            0.7392231065680439,
+            1.0788155228009093,
+            0.12227902246280471,
+            0.6384125855744724,
+            1.5746238746596755,
+            0.24579840528702793,
+            0.4557739936169636,
+            1.204110850479925
+        ];
+        assert_eq!(keep.bandwidth_factor(), kde.bandwidth_factor());
+        assert_relative_eq!(keep.eval_batch(x.view()), res, max_relative = 1e-10);
+        assert_eq!(
+            kde.marginalize(&[1]).unwrap().eval_batch(x.view()),
+            keep.eval_batch(x.view())
+        );
+
+        // Reference values from `scipy.stats.gaussian_kde(data[:, [0, 2]].T).pdf(x.T)`
+        let recompute = kde
+            .marginalize_with(&[1], BandwidthPolicy::Recompute)
+            .unwrap();
+        let res = array![
+            0.10297001953883067,
+            0.7626656879935894,
+            1.1031195634054618,
+            0.10333772532993148,
+            0.6252723111250483,
+            1.6651654649634067,
+            0.247711876321937,
+            0.4276687551583312,
+            1.2740467686135588
+        ];
+        assert_relative_eq!(
+            recompute.bandwidth_factor(),
+            10f64.powf(-1.0 / 6.0),
+            max_relative = 1e-14
+        );
+        assert!(recompute.bandwidth_factor() < keep.bandwidth_factor());
+        assert_relative_eq!(recompute.eval_batch(x.view()), res, max_relative = 1e-10);
+        assert_eq!(recompute.data(), data.select(Axis(1), &[0, 2]));
+    }
+
+    #[test]
+    fn marginalize_order_test() {
+        use rand::prelude::*;
+        use rand_distr::StandardNormal;
+        use rand_pcg::Pcg64Mcg;
+
+        let mut rng = Pcg64Mcg::seed_from_u64(74);
+        let z = Array2::<f64>::from_shape_simple_fn((200, 4), || rng.sample(StandardNormal));
+        let data = z.dot(&array![
+            [1.0, 0.0, 0.0, 0.0],
+            [0.5, 1.2, 0.0, 0.0],
+            [0.0, 0.3, 0.4, 0.0],
+            [-0.8, 0.0, 0.6, 2.0]
+        ]);
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        assert_eq!(kde.original_dims(), vec![0, 1, 2, 3]);
+
+        // Grid points given in the order of the requested components, i.e. (x_3, x_1)
+        let marginal = kde.marginalize_to(&[3, 1]).unwrap();
+        assert_eq!(marginal.original_dims(), vec![3, 1]);
+        let g = Array1::linspace(-2.0, 2.0, 5);
+        let grid = Array2::from_shape_fn(
+            (25, 2),
+            |(i, j)| if j == 0 { 2.0 * g[i / 5] } else { g[i % 5] },
+        );
+        let reference = GaussianKDE::with_bandwidth_factor(
+            data.select(Axis(1), &[3, 1]),
+            None,
+            kde.bandwidth_factor(),
+        )
+        .unwrap();
+        assert_relative_eq!(
+            marginal.eval_batch(grid.view()),
+            reference.eval_batch(grid.view()),
+            max_relative = 1e-12
+        );
+        // ... which is not symmetric under exchange of the components
+        let swapped = grid.select(Axis(1), &[1, 0]);
+        assert!(
+            (marginal.eval_batch(swapped.view()) - reference.eval_batch(grid.view()))
+                .iter()
+                .any(|d| d.abs() > 1e-3)
+        );
+        assert_relative_eq!(
+            kde.marginalize_to(&[1, 3])
+                .unwrap()
+                .eval_batch(swapped.view()),
+            reference.eval_batch(grid.view()),
+            max_relative = 1e-12
+        );
+
+        // The mapping refers to the outermost KDE and survives copies of the dataset
+        let mut nested = marginal.marginalize(&[1]).unwrap();
+        assert_eq!(nested.original_dims(), vec![3]);
+        nested.push(array![0.5].view(), None).unwrap();
+        assert_eq!(nested.original_dims(), vec![3]);
+        let conditional = kde.condition(&[0, 2], array![0.1, -0.2].view()).unwrap();
+        assert_eq!(conditional.original_dims(), vec![1, 3]);
+        assert_eq!(
+            conditional.marginalize_to(&[1]).unwrap().original_dims(),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn marginalize_error_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [0.48, 0.69, 2.5], [0.81, 0.70, 2.5], [0.40, 0.67, 2.5], [0.62, 0.98, 2.5], [0.85, 0.81, 2.5],
+            [0.43, 0.81, 2.5], [0.34, 0.57, 2.5], [0.95, 0.70, 2.5], [0.03, 0.11, 2.5], [0.04, 0.21, 2.5]
+        ];
+        // The regularization makes the constant component well-defined for the parent, but not on its own
+        let kde = GaussianKDE::builder()
+            .regularization(1e-3)
+            .build(data)
+            .unwrap();
+        assert!(kde.marginalize_to(&[2]).is_ok());
+        let res = kde.marginalize_to_with(&[2], BandwidthPolicy::Recompute);
+        assert!(matches!(
+            res,
+            Err(e) if matches!(e.kind, ErrorKind::SingularityError) && e.message.contains("[2]")
+        ));
+        let res = kde.marginalize_with(&[0, 1], BandwidthPolicy::Recompute);
+        assert!(matches!(
+            res,
+            Err(e) if matches!(e.kind, ErrorKind::SingularityError) && e.message.contains("marginalization")
+        ));
+        assert!(
+            kde.marginalize_with(&[2], BandwidthPolicy::Recompute)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn bandwidth_factor_test() {
+        let data = array![
+            [0.5634880436705391],
+            [0.445981611845074],
+            [0.7438671296401687]
+        ];
+        let w_test = array![0.04622972052712859, 0.15162404870472723, 0.291517199926956];
+        let x_test = array![0.3];
+        // Reference values from `scipy.stats.gaussian_kde(data, bw_method=h, weights=w).pdf(x)`
+        for (h, res, res_w) in [
+            (0.3, 0.0153673585377815, 0.07145237528729906),
+            (1.7, 0.8630719844794014, 0.7162750872129447),
+        ] {
+            let kde = GaussianKDE::with_bandwidth_factor(data.clone(), None, h).unwrap();
+            assert_relative_eq!(kde.eval(x_test.view()), res, epsilon = 1E-10);
+            let kde =
+                GaussianKDE::with_bandwidth_factor(data.clone(), Some(w_test.clone()), h).unwrap();
+            assert_relative_eq!(kde.eval(x_test.view()), res_w, epsilon = 1E-10);
+        }
+
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let w_test = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        let x_test = array![
+            4.184559795606306309e-01,
+            1.755027879973122262e-01,
+            9.086181878876633533e-01,
+        ];
+        for (h, res, res_w) in [
+            (0.3, 2.6910107721803878e-11, 2.496386742545072e-15),
+            (1.7, 0.12525817076779625, 0.032023694846543514),
+        ] {
+            let kde = GaussianKDE::with_bandwidth_factor(data.clone(), None, h).unwrap();
+            assert_relative_eq!(kde.eval(x_test.view()), res, max_relative = 1E-10);
+            let kde =
+                GaussianKDE::with_bandwidth_factor(data.clone(), Some(w_test.clone()), h).unwrap();
+            assert_relative_eq!(kde.eval(x_test.view()), res_w, max_relative = 1E-10);
+        }
+    }
+
+    #[test]
+    fn bandwidth_factor_accessor_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let w_test = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        assert_eq!(
+            kde.bandwidth_factor(),
+            ScottBandwidth {}.bandwidth(data.view(), None)
+        );
+        let kde = GaussianKDE::new(data.clone(), Some(w_test.clone())).unwrap();
+        assert_eq!(
+            kde.bandwidth_factor(),
+            ScottBandwidth {}.bandwidth(data.view(), Some(w_test.view()))
+        );
+        let kde = GaussianKDE::with_bandwidth_factor(data.clone(), None, 0.3).unwrap();
+        assert_eq!(kde.bandwidth_factor(), 0.3);
+
+        // The scaled covariance is the bandwidth factor squared times the dataset covariance (`numpy.cov(data.T)`)
+        #[rustfmt::skip]
+        let cov = array![
+            [ 0.10070334244584332,  0.06501041624670263, -0.01984634470293988],
+            [ 0.06501041624670263,  0.07240620840899820,  0.01750309969125627],
+            [-0.01984634470293988,  0.01750309969125627,  0.07615402330810273]
+        ];
+        assert_abs_diff_eq!(kde.scaled_covariance(), cov * 0.09, epsilon = 1E-14);
+    }
+
+    #[test]
+    fn set_bandwidth_factor_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let w_test = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        #[rustfmt::skip]
+        let x_test = array![
+            [4.184559795606306309e-01, 1.755027879973122262e-01, 9.086181878876633533e-01],
+            [0.5, 0.6, 0.7],
+            [0.9, 0.1, 0.3]
+        ];
+        for weights in [None, Some(w_test)] {
+            let mut kde = GaussianKDE::new(data.clone(), weights.clone()).unwrap();
+            for h in [0.3, 1.7, 0.05] {
+                kde.set_bandwidth_factor(h).unwrap();
+                let reference =
+                    GaussianKDE::with_bandwidth_factor(data.clone(), weights.clone(), h).unwrap();
+                assert_eq!(kde.bandwidth_factor(), h);
+                assert_abs_diff_eq!(kde.cholesky(), reference.cholesky(), epsilon = 1E-14);
+                assert_relative_eq!(
+                    kde.eval_batch(x_test.view()),
+                    reference.eval_batch(x_test.view()),
+                    max_relative = 1E-12
+                );
+                assert_relative_eq!(
+                    kde.ln_eval_batch(x_test.view()),
+                    reference.ln_eval_batch(x_test.view()),
+                    max_relative = 1E-12
+                );
             }
+            let mut kde = kde.with_rescaled_bandwidth(0.8).unwrap();
+            let reference = GaussianKDE::with_bandwidth_factor(data.clone(), weights, 0.8).unwrap();
+            assert_relative_eq!(
+                kde.eval_batch(x_test.view()),
+                reference.eval_batch(x_test.view()),
+                max_relative = 1E-12
+            );
+
+            // Invalid factors are rejected without changing the KDE
+            let pdf = kde.eval_batch(x_test.view());
+            for h in [0.0, -0.5, f64::NAN, f64::INFINITY] {
+                let res = kde.set_bandwidth_factor(h);
+                assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+                assert_eq!(kde.bandwidth_factor(), 0.8);
+                assert_eq!(kde.eval_batch(x_test.view()), pdf);
+            }
+            let res = kde.with_rescaled_bandwidth(-1.0);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [0.6229016948897019, 0.7417869892607294, 0.7951935655656966, 0.9424502837770503, 0.7398985747399307],
+            [0.9223249966654170, 0.0290052282836147, 0.4656226543781053, 0.9433567169983137, 0.6489745531369242],
+            [0.9009004917506227, 0.1132059646531444, 0.4690690477821637, 0.2465728326198303, 0.5437608592359304],
+            [0.5739411879281008, 0.0131141895889022, 0.2167298004638482, 0.2794823660111103, 0.9163453718085519],
+            [0.7657254516291417, 0.1596042123580382, 0.7971469914312045, 0.1387674183989032, 0.6174525204661166],
+            [0.1266992325502697, 0.0017748622025346, 0.8714047447242821, 0.2094563824951179, 0.2154811692247323],
+            [0.9824211088259253, 0.8724077654368019, 0.2893051677469265, 0.9614779889500835, 0.5392234688708106],
+            [0.6778304772505923, 0.2047795145337928, 0.9409760010879991, 0.6906419411069082, 0.9665643123171954],
+            [0.8937416775764785, 0.2987888978538678, 0.3611899347223841, 0.1659560571297456, 0.1457019095406825],
+            [0.0651397133756763, 0.3013591007694625, 0.6031099974076544, 0.0033831193743568, 0.6779342495476912],
+            [0.3378968616278651, 0.3099579316031288, 0.8185180746470708, 0.4807451866300390, 0.3157931058464440],
+            [0.4812183862368639, 0.7046691341409093, 0.0570009295357899, 0.9750995631442353, 0.0228655632527207]
+        ];
+        #[rustfmt::skip]
+        let w_test = array![
+            0.7497950222912733, 0.8448808893881297, 0.0180675353785301, 0.7877383039804342, 0.3661844758418605,
+            0.5785188290568746, 0.0090783868195284, 0.0467271186989415, 0.1809194879510478, 0.9551798995911663,
+            0.1965216705130082, 0.7557364124513177
+        ];
+        #[rustfmt::skip]
+        let x_test = array![
+            [0.9296553195975211, 0.9420438294276994, 0.3443818130530120, 0.3547932050579122, 0.5247018206931200],
+            [0.7756030146989953, 0.1080528690648329, 0.7483980564846631, 0.7972266775913328, 0.8596943191875801],
+            [0.0366315799428275, 0.9458001850421838, 0.0911798641717686, 0.3407405355042222, 0.6108275380926242],
+            [0.9180871855333121, 0.3399595266258446, 0.9241976194277907, 0.5451440370982389, 0.3124503693429069]
+        ];
+        let kde = GaussianKDE::new(data, Some(w_test)).unwrap();
+        let json = serde_json::to_string(&kde).unwrap();
+        let kde_de: GaussianKDE<f64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(kde.data(), kde_de.data());
+        assert_eq!(kde.weights(), kde_de.weights());
+        assert_eq!(kde.cholesky(), kde_de.cholesky());
+        assert_eq!(
+            kde.eval_batch(x_test.view()),
+            kde_de.eval_batch(x_test.view())
+        );
+    }
+
+    #[test]
+    fn diagonal_covariance_identity_test() {
+        // Full factorial design, whose covariance matrix is exactly diagonal
+        let data = Array2::from_shape_fn((27, 3), |(i, j)| {
+            (i / 3usize.pow(j as u32) % 3) as f64 - 1.0
+        });
+        let full = GaussianKDE::new(data.clone(), None).unwrap();
+        let diagonal = GaussianKDE::with_diagonal_covariance(data, None).unwrap();
+        assert_eq!(diagonal.covariance_mode(), CovarianceMode::Diagonal);
+        assert_relative_eq!(
+            diagonal.scaled_covariance(),
+            full.scaled_covariance(),
+            epsilon = 1e-15
+        );
+        let x = array![[0.0, 0.0, 0.0], [0.3, -0.8, 1.1], [2.0, 1.5, -2.5]];
+        assert_relative_eq!(
+            diagonal.eval_batch(x.view()),
+            full.eval_batch(x.view()),
+            max_relative = 1e-12
+        );
+        assert_relative_eq!(
+            diagonal.ln_eval_batch(x.view()),
+            full.ln_eval_batch(x.view()),
+            max_relative = 1e-12
+        );
+        let marginal = diagonal.marginalize(&[1]).unwrap();
+        assert_eq!(marginal.covariance_mode(), CovarianceMode::Diagonal);
+        assert_relative_eq!(
+            marginal.eval(array![0.3, 1.1].view()),
+            full.marginalize(&[1])
+                .unwrap()
+                .eval(array![0.3, 1.1].view()),
+            max_relative = 1e-12
+        );
+    }
+
+    #[test]
+    fn diagonal_covariance_product_kernel_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [0.15, 0.40], [0.20, 0.30], [0.21, 0.29], [0.31, 0.44], [0.72, 0.90],
+            [0.74, 0.84], [0.60, 0.70], [0.45, 0.52], [0.90, 1.05], [0.05, 0.12]
+        ];
+        let weights = array![0.5, 1.0, 0.2, 2.0, 0.7, 0.3, 1.2, 0.9, 1.1, 0.4];
+        let kde = GaussianKDE::with_covariance_mode(
+            data.clone(),
+            Some(weights.clone()),
+            ScottBandwidth {},
+            CovarianceMode::Diagonal,
+        )
+        .unwrap();
+        let h = kde.bandwidth_factor();
+        let cov: Array2<f64> = crate::covariance::covariance(data.view(), Some(weights.view()));
+        assert!(cov[[0, 1]] > 0.9 * (cov[[0, 0]] * cov[[1, 1]]).sqrt());
+        assert_relative_eq!(
+            kde.scaled_covariance(),
+            Array2::from_diag(&cov.diag().mapv(|v| h * h * v)),
+            max_relative = 1e-14
+        );
+
+        // Weighted sum of products of one-dimensional normal densities
+        let product_kernel = |x: ArrayView1<f64>| {
+            let mut res = 0.0;
+            for (xi, w) in data.rows().into_iter().zip(&weights) {
+                let mut k = *w;
+                for j in 0..2 {
+                    let sigma = h * cov[[j, j]].sqrt();
+                    k *= (-0.5 * ((x[j] - xi[j]) / sigma).powi(2)).exp()
+                        / (sigma * (2.0 * std::f64::consts::PI).sqrt());
+                }
+                res += k;
+            }
+            return res / weights.sum();
+        };
+        let x = array![[0.3, 0.3], [0.5, 0.1], [0.7, 0.8], [1.5, -0.5]];
+        let res = kde.eval_batch(x.view());
+        for (j, xj) in x.rows().into_iter().enumerate() {
+            assert_relative_eq!(res[j], product_kernel(xj), max_relative = 1e-12);
+            assert_relative_eq!(
+                kde.ln_eval(xj),
+                product_kernel(xj).ln(),
+                max_relative = 1e-12
+            );
+        }
+
+        let constant = array![[0.1, 1.0], [0.2, 1.0], [0.3, 1.0]];
+        let res = GaussianKDE::with_diagonal_covariance(constant, None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+    }
+
+    #[test]
+    fn bandwidth_matrix_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let w_test = array![
+            4.545965000176888093e-01,
+            6.656013082343981146e-03,
+            4.089870211211721340e-01,
+            8.170516288204880961e-01,
+            7.990649716826044857e-01,
+            2.787743020513939740e-01,
+            7.606137013085603193e-01,
+            6.238816644921463261e-02,
+            8.113067447451907110e-01,
+            6.972756025050139694e-01,
+        ];
+        let h_matrix = array![
+            [0.04, 0.01, -0.005],
+            [0.01, 0.09, 0.02],
+            [-0.005, 0.02, 0.0625]
+        ];
+        let kde = GaussianKDE::with_bandwidth_matrix(
+            data.clone(),
+            Some(w_test.clone()),
+            h_matrix.clone(),
+        )
+        .unwrap();
+        assert_eq!(kde.bandwidth_factor(), 1.0);
+        assert_relative_eq!(kde.scaled_covariance(), h_matrix, max_relative = 1e-14);
+
+        // Reference values from `scipy.stats.gaussian_kde` with the covariance set manually to `h_matrix`
+        let x = array![
+            [
+                4.184559795606306309e-01,
+                1.755027879973122262e-01,
+                9.086181878876633533e-01
+            ],
+            [0.5, 0.5, 0.5],
+            [0.9, 0.1, 0.3]
+        ];
+        let res = array![
+            0.37014038152538453,
+            0.8690447077872645,
+            0.026906988993480305
+        ];
+        assert_relative_eq!(kde.eval_batch(x.view()), res, max_relative = 1e-10);
+
+        // The marginal uses the sub-block of the bandwidth matrix
+        let marginal = kde.marginalize(&[1]).unwrap();
+        assert_relative_eq!(
+            marginal.scaled_covariance(),
+            array![[0.04, -0.005], [-0.005, 0.0625]],
+            max_relative = 1e-14
+        );
+        assert_relative_eq!(
+            marginal.eval(array![4.184559795606306309e-01, 9.086181878876633533e-01].view()),
+            1.3869858177787646,
+            max_relative = 1e-10
+        );
+
+        let rescaled = kde.with_rescaled_bandwidth(2.0).unwrap();
+        assert_relative_eq!(
+            rescaled.scaled_covariance(),
+            &h_matrix * 4.0,
+            max_relative = 1e-14
+        );
+        assert_relative_eq!(
+            rescaled
+                .marginalize_to(&[0, 2])
+                .unwrap()
+                .scaled_covariance(),
+            array![[0.16, -0.02], [-0.02, 0.25]],
+            max_relative = 1e-14
+        );
+
+        // Invalid bandwidth matrices
+        let res = GaussianKDE::with_bandwidth_matrix(data.clone(), None, Array2::eye(2));
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let mut asymmetric = h_matrix.clone();
+        asymmetric[[0, 1]] = 0.02;
+        let res = GaussianKDE::with_bandwidth_matrix(data.clone(), None, asymmetric);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+        let res = GaussianKDE::with_bandwidth_matrix(data, None, -h_matrix);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+    }
+
+    #[test]
+    fn robust_covariance_test() {
+        use rand::prelude::*;
+        use rand_distr::StandardNormal;
+        use rand_pcg::Pcg64Mcg;
+
+        let mut rng = Pcg64Mcg::seed_from_u64(23);
+        let z = Array2::<f64>::from_shape_simple_fn((990, 2), || rng.sample(StandardNormal));
+        let clean = z.dot(&array![[1.0, 0.0], [0.5, 0.75_f64.sqrt()]].t());
+        let mut data = Array2::zeros((1000, 2));
+        data.slice_mut(s![..990, ..]).assign(&clean);
+        for (i, mut row) in data
+            .slice_mut(s![990.., ..])
+            .rows_mut()
+            .into_iter()
+            .enumerate()
+        {
+            let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+            row.assign(&array![100.0 * sign, -100.0 * sign]);
+        }
+        let mode = array![0.0, 0.0];
+
+        let reference = GaussianKDE::new(clean, None).unwrap().eval(mode.view());
+        let default = GaussianKDE::new(data.clone(), None)
+            .unwrap()
+            .eval(mode.view());
+        let robust = GaussianKDE::with_covariance_mode(
+            data,
+            None,
+            ScottBandwidth {},
+            CovarianceMode::Robust,
+        )
+        .unwrap();
+        assert_eq!(robust.covariance_mode(), CovarianceMode::Robust);
+        assert_relative_eq!(robust.eval(mode.view()), reference, max_relative = 5e-2);
+        assert!((default - reference).abs() > 0.5 * reference);
+        assert_eq!(
+            robust.marginalize(&[0]).unwrap().covariance_mode(),
+            CovarianceMode::Robust
+        );
+    }
+
+    #[test]
+    fn shrinkage_covariance_test() {
+        use rand::prelude::*;
+        use rand_distr::StandardNormal;
+        use rand_pcg::Pcg64Mcg;
+
+        // 20 samples in 15 dimensions with a (numerically) redundant component
+        let mut rng = Pcg64Mcg::seed_from_u64(31);
+        let mut data = Array2::<f64>::from_shape_simple_fn((20, 15), || rng.sample(StandardNormal));
+        let redundant = &data.column(0) * 0.3 + &data.column(1) * 0.7;
+        data.column_mut(14).assign(&redundant);
+        // The plain covariance matrix is (close to) singular, resulting in an error or a meaningless density
+        let full = GaussianKDE::new(data.clone(), None);
+        assert!(full.is_err() || full.unwrap().eval(data.row(0)) > 1e3);
+
+        for mode in [CovarianceMode::LedoitWolf, CovarianceMode::Shrinkage(0.5)] {
+            let kde =
+                GaussianKDE::with_covariance_mode(data.clone(), None, ScottBandwidth {}, mode)
+                    .unwrap();
+            let res = kde.eval_batch(data.view());
+            assert!(res.iter().all(|f| f.is_finite() && *f > 0.0 && *f < 1e-3));
+            assert!(kde.eval(Array1::zeros(15).view()) < res[0]);
+            assert!(kde.ln_eval(Array1::from_elem(15, 10.0).view()).is_finite());
+            assert_eq!(kde.marginalize(&[14]).unwrap().covariance_mode(), mode);
+        }
+
+        // Fewer samples than dimensions
+        let kde = GaussianKDE::with_covariance_mode(
+            data.slice(s![..12, ..]).to_owned(),
+            None,
+            ScottBandwidth {},
+            CovarianceMode::LedoitWolf,
+        )
+        .unwrap();
+        assert!(kde.eval(data.row(0)).is_finite());
+
+        // Intensities outside of [0, 1]
+        for lambda in [-0.1, 1.5, f64::NAN, f64::INFINITY] {
+            let mode = CovarianceMode::Shrinkage(lambda);
+            let res =
+                GaussianKDE::with_covariance_mode(data.clone(), None, ScottBandwidth {}, mode);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+            let res = GaussianKDE::builder()
+                .covariance_mode(mode)
+                .build(data.clone());
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+        for lambda in [0.0, 1.0] {
+            let mode = CovarianceMode::Shrinkage(lambda);
+            let kde = GaussianKDE::with_covariance_mode(
+                data.slice(s![.., ..3]).to_owned(),
+                None,
+                ScottBandwidth {},
+                mode,
+            );
+            assert!(kde.is_ok());
+        }
+    }
+
+    #[test]
+    fn covariance_accessor_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [4.778289487550605452e-01, 6.915810807566095120e-01, 8.973119595652500058e-01],
+            [8.092981665695588855e-01, 6.952206389245977336e-01, 8.867610462010474537e-01],
+            [4.016505747889576039e-01, 6.735560621931444558e-01, 6.015164821850446097e-01],
+            [6.183433169768373094e-01, 9.782506843349931813e-01, 8.643804075625444172e-01],
+            [8.470914298329793590e-01, 8.062118291413915561e-01, 7.143558061103683077e-01],
+            [4.336121335223386275e-01, 8.069600652351297532e-01, 9.589039393815833590e-01],
+            [3.374319617323934262e-01, 5.729598702618347028e-01, 8.259685606489839405e-01],
+            [9.510078434543683956e-01, 7.007529367689996347e-01, 1.796766943464989108e-02],
+            [2.938782386889049469e-02, 1.078441585862294216e-01, 5.370506790487759030e-01],
+            [4.110256667672318454e-02, 2.086942584603000972e-01, 6.946406596087403296e-01]
+        ];
+        let weights = array![0.45, 0.007, 0.41, 0.82, 0.8, 0.28, 0.76, 0.06, 0.81, 0.7];
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let h = kde.bandwidth_factor();
+        let cov =
+            crate::covariance::covariance(data.view(), Some(weights.view())).mapv(|c| c * h * h);
+        assert_relative_eq!(kde.covariance(), cov, max_relative = 1e-13);
+        assert_abs_diff_eq!(
+            kde.covariance().dot(&kde.inv_covariance()),
+            Array2::eye(3),
+            epsilon = 1e-12
+        );
+        assert_abs_diff_eq!(
+            kde.inv_cholesky().dot(&kde.cholesky()),
+            Array2::eye(3),
+            epsilon = 1e-12
+        );
+
+        let kde = kde.with_rescaled_bandwidth(0.3).unwrap();
+        assert_abs_diff_eq!(
+            kde.covariance().dot(&kde.inv_covariance()),
+            Array2::eye(3),
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn condition_test() {
+        use rand::prelude::*;
+        use rand_distr::StandardNormal;
+        use rand_pcg::Pcg64Mcg;
+
+        let mut rng = Pcg64Mcg::seed_from_u64(9);
+        // Bivariate normal distribution with means (1, -1), standard deviations (1, 2) and correlation 0.6
+        let (mu, sigma, rho): ([f64; 2], [f64; 2], f64) = ([1.0, -1.0], [1.0, 2.0], 0.6);
+        let data = Array2::<f64>::from_shape_fn((20000, 2), |_| rng.sample(StandardNormal));
+        let data = ndarray::stack![
+            Axis(1),
+            data.column(0).mapv(|z| mu[0] + sigma[0] * z),
+            (rho * &data.column(0) + (1.0 - rho * rho).sqrt() * &data.column(1))
+                .mapv(|z| mu[1] + sigma[1] * z)
+        ];
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let value = 0.5;
+        let conditional = kde.condition(&[1], array![value].view()).unwrap();
+        assert_eq!(conditional.data().dim(), (20000, 1));
+        // Analytic conditional distribution, whose variance is inflated by 1 + h^2 due to the smoothing
+        let mean = mu[0] + rho * sigma[0] / sigma[1] * (value - mu[1]);
+        let variance =
+            (1.0 + kde.bandwidth_factor().powi(2)) * sigma[0].powi(2) * (1.0 - rho * rho);
+        for x in Array1::linspace(-1.0, 4.0, 21) {
+            let exact = (-(x - mean).powi(2) / (2.0 * variance)).exp()
+                / (2.0 * std::f64::consts::PI * variance).sqrt();
+            assert_abs_diff_eq!(conditional.eval(array![x].view()), exact, epsilon = 2e-2);
+        }
+        // For Gaussian kernels, conditioning is exactly the ratio of joint and marginal density if the marginal
+        // keeps the bandwidth factor
+        let kde = GaussianKDE::with_bandwidth_factor(kde.data().to_owned(), None, 0.3).unwrap();
+        let conditional = kde.condition(&[1], array![value].view()).unwrap();
+        let marginal = kde.marginalize_to(&[1]).unwrap();
+        for x in Array1::linspace(-1.0, 4.0, 21) {
+            assert_relative_eq!(
+                conditional.eval(array![x].view()),
+                kde.eval(array![x, value].view()) / marginal.eval(array![value].view()),
+                max_relative = 1e-10
+            );
+        }
+
+        let res = kde.condition(&[2], array![0.0].view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+        let res = kde.condition(&[1, 1], array![0.0, 0.0].view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+        let res = kde.condition(&[1], array![0.0, 1.0].view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.condition(&[0, 1], array![0.0, 1.0].view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn from_flat_test() {
+        let flat = vec![0.1, 0.4, 0.3, -0.2, -0.5, 0.7, 0.9, 0.0, 0.2, 0.2];
+        let weights = vec![1.0, 0.5, 2.0, 0.3, 1.1];
+        let data = Array2::from_shape_vec((5, 2), flat.clone()).unwrap();
+        let kde = GaussianKDE::from_flat(flat.clone(), 2, Some(weights.clone())).unwrap();
+        let reference = GaussianKDE::new(data.clone(), Some(Array1::from(weights))).unwrap();
+        assert_eq!(kde.data(), data);
+        let x = array![[0.0, 0.0], [0.5, -0.3]];
+        assert_eq!(kde.eval_batch(x.view()), reference.eval_batch(x.view()));
+
+        let res = GaussianKDE::from_flat(flat.clone(), 3, None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = GaussianKDE::from_flat(flat.clone(), 0, None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = GaussianKDE::from_flat(flat, 2, Some(vec![1.0; 4]));
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn from_points_test() {
+        let data = array![[0.1, 0.4], [0.3, -0.2], [-0.5, 0.7], [0.9, 0.0], [0.2, 0.2]];
+        let weights = array![1.0, 0.5, 2.0, 0.3, 1.1];
+        let reference = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let x = array![[0.0, 0.0], [0.5, -0.3]];
+
+        let points: Vec<Vec<f64>> = data.rows().into_iter().map(|r| r.to_vec()).collect();
+        let kde = GaussianKDE::from_points(&points, Some(weights.clone())).unwrap();
+        assert_eq!(kde.data(), data);
+        assert_eq!(kde.eval_batch(x.view()), reference.eval_batch(x.view()));
+
+        let pairs = data
+            .rows()
+            .into_iter()
+            .zip(weights.iter())
+            .map(|(r, w)| ([r[0], r[1]], *w));
+        let kde = GaussianKDE::from_points_weighted(pairs).unwrap();
+        assert_eq!(kde.data(), data);
+        assert_eq!(kde.weights().unwrap(), weights);
+        assert_eq!(kde.eval_batch(x.view()), reference.eval_batch(x.view()));
+
+        let ragged = vec![vec![0.1, 0.4], vec![0.3], vec![-0.5, 0.7]];
+        let res = GaussianKDE::from_points(ragged, None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = GaussianKDE::from_points(Vec::<[f64; 2]>::new(), None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = GaussianKDE::<f64>::from_points_weighted(Vec::<([f64; 2], f64)>::new());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn from_view_test() {
+        let data = array![[0.1, 0.4], [0.3, -0.2], [-0.5, 0.7], [0.9, 0.0], [0.2, 0.2]];
+        let weights = array![1.0, 0.5, 2.0, 0.3, 1.1];
+        let x = array![[0.0, 0.0], [0.5, -0.3]];
+        let owned = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let borrowed = GaussianKDE::from_view(data.view(), Some(weights.view())).unwrap();
+        // The dataset remains accessible to other readers while the KDE borrows it
+        let mean = data.mean_axis(Axis(0)).unwrap();
+        assert_eq!(borrowed.data().as_ptr(), data.as_ptr());
+        assert_eq!(borrowed.weights().unwrap().as_ptr(), weights.as_ptr());
+        assert_eq!(borrowed.eval_batch(x.view()), owned.eval_batch(x.view()));
+        assert_eq!(borrowed.eval(mean.view()), owned.eval(mean.view()));
+        assert_eq!(borrowed.covariance(), owned.covariance());
+
+        let marginal = borrowed.marginalize(&[1]).unwrap();
+        assert_eq!(
+            marginal.eval_batch(x.slice(s![.., ..1])),
+            owned
+                .marginalize(&[1])
+                .unwrap()
+                .eval_batch(x.slice(s![.., ..1]))
+        );
+        drop(borrowed);
+        assert_eq!(marginal.data(), data.slice(s![.., ..1]));
+        // The remaining components are copied only once
+        assert_eq!(marginal.data().as_ptr(), marginal.data().as_ptr());
+    }
+
+    #[test]
+    fn shared_marginal_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [0.48, 0.69, 0.90], [0.81, 0.70, 0.89], [0.40, 0.67, 0.60], [0.62, 0.98, 0.86], [0.85, 0.81, 0.71],
+            [0.43, 0.81, 0.96], [0.34, 0.57, 0.83], [0.95, 0.70, 0.02], [0.03, 0.11, 0.54], [0.04, 0.21, 0.69]
+        ];
+        let weights = array![1.0, 0.5, 2.0, 0.3, 1.1, 0.7, 1.4, 0.9, 0.2, 1.6];
+        let x = array![[0.5, 0.6], [0.2, 0.8], [0.9, 0.1]];
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let marginal = kde.marginalize_to(&[2, 0]).unwrap();
+        assert_eq!(marginal.data.as_ptr(), kde.data.as_ptr());
+        assert_eq!(
+            marginal.weights().unwrap().as_ptr(),
+            kde.weights().unwrap().as_ptr()
+        );
+        assert_eq!(marginal.data(), data.select(Axis(1), &[2, 0]));
+
+        let h = kde.bandwidth_factor();
+        let copied = GaussianKDE::with_bandwidth_factor(
+            data.select(Axis(1), &[2, 0]),
+            Some(weights.clone()),
+            h,
+        )
+        .unwrap();
+        assert_relative_eq!(
+            marginal.eval_batch(x.view()),
+            copied.eval_batch(x.view()),
+            max_relative = 1e-12
+        );
+        assert_relative_eq!(
+            marginal.ln_eval(x.row(1)),
+            copied.ln_eval(x.row(1)),
+            max_relative = 1e-12
+        );
+
+        // Nested marginals keep sharing the dataset of the outermost KDE
+        let nested = marginal.marginalize(&[0]).unwrap();
+        assert_eq!(nested.data.as_ptr(), kde.data.as_ptr());
+        assert_eq!(nested.data(), data.slice(s![.., ..1]));
+        let copied = GaussianKDE::with_bandwidth_factor(
+            data.slice(s![.., ..1]).to_owned(),
+            Some(weights),
+            h,
+        )
+        .unwrap();
+        assert_relative_eq!(
+            nested.eval_batch(x.slice(s![.., ..1])),
+            copied.eval_batch(x.slice(s![.., ..1])),
+            max_relative = 1e-12
+        );
+    }
+
+    #[test]
+    fn fast_marginalization_test() {
+        use rand::prelude::*;
+        use rand_distr::StandardNormal;
+        use rand_pcg::Pcg64Mcg;
+
+        let mut rng = Pcg64Mcg::seed_from_u64(70);
+        let z = Array2::<f64>::from_shape_simple_fn((500, 4), || rng.sample(StandardNormal));
+        #[rustfmt::skip]
+        let mixing = array![
+            [1.0, 0.0, 0.0, 0.0],
+            [0.6, 0.8, 0.0, 0.0],
+            [-0.3, 0.2, 0.9, 0.0],
+            [0.1, -0.5, 0.4, 0.7]
+        ];
+        let data = z.dot(&mixing.t());
+        let weights = Array1::from_shape_fn(500, |i| 0.5 + (i % 5) as f64);
+        let x = array![[0.1, -0.4], [1.2, 0.3], [-2.0, 1.5]];
+
+        // The marginal matches a KDE fitted to the remaining components with the bandwidth factor of the parent
+        for mode in [
+            CovarianceMode::Full,
+            CovarianceMode::Diagonal,
+            CovarianceMode::Robust,
+        ] {
+            let kde = GaussianKDE::with_covariance_mode(
+                data.clone(),
+                Some(weights.clone()),
+                ScottBandwidth {},
+                mode,
+            )
+            .unwrap();
+            let marginal = kde.marginalize_to(&[3, 1]).unwrap();
+            assert_eq!(marginal.bandwidth_factor(), kde.bandwidth_factor());
+            let refitted = GaussianKDE::with_covariance_mode(
+                data.select(Axis(1), &[3, 1]),
+                Some(weights.clone()),
+                FixedBandwidth::new(kde.bandwidth_factor()).unwrap(),
+                mode,
+            )
+            .unwrap();
+            assert_relative_eq!(
+                marginal.covariance(),
+                refitted.covariance(),
+                max_relative = 1e-12
+            );
+            assert_relative_eq!(
+                marginal.eval_batch(x.view()),
+                refitted.eval_batch(x.view()),
+                max_relative = 1e-12
+            );
         }
-        let marginalized = self.data.select(Axis(1), dims);
-        return Ok(Self::with_bandwidth(marginalized, self.weights.clone()).unwrap());
+    }
+
+    #[test]
+    fn weight_validation_test() {
+        let data = array![[0.1, 0.4], [0.3, -0.2], [-0.5, 0.7], [0.9, 0.0], [0.2, 0.2]];
+        let is_weight_error = |res: Result<_, KDEError>, index: Option<usize>| {
+            matches!(res, Err(e) if matches!(e.kind, ErrorKind::WeightError)
+                && index.is_none_or(|i| e.message.contains(&format!("index {i}"))))
+        };
+        for (weights, index) in [
+            (array![1.0, f64::NAN, 1.0, 1.0, 1.0], Some(1)),
+            (array![1.0, 1.0, 1.0, f64::INFINITY, 1.0], Some(3)),
+            (array![1.0, 1.0, -0.5, 1.0, -1.0], Some(2)),
+            (array![0.0, 0.0, 0.0, 0.0, 0.0], None),
+            (array![f64::MAX, f64::MAX, 0.0, 0.0, 0.0], None),
+        ] {
+            let res = GaussianKDE::new(data.clone(), Some(weights.clone())).map(|_| ());
+            assert!(is_weight_error(res, index));
+            let res = GaussianKDE::with_bandwidth_matrix(
+                data.clone(),
+                Some(weights.clone()),
+                array![[0.1, 0.0], [0.0, 0.1]],
+            )
+            .map(|_| ());
+            assert!(is_weight_error(res, index));
+            let res = GaussianKDE::builder()
+                .weights(weights.clone())
+                .build(data.clone())
+                .map(|_| ());
+            assert!(is_weight_error(res, index));
+            let mut kde = GaussianKDE::new(data.clone(), None).unwrap();
+            assert!(is_weight_error(kde.set_weights(weights), index));
+            assert!(kde.weights().is_none());
+        }
+        let mut kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let res = kde.push(array![0.0, 0.0].view(), Some(-1.0));
+        assert!(is_weight_error(res, Some(0)));
+        assert_eq!(kde.data(), data);
+
+        // Zero weights are allowed as long as the sum is positive
+        let weights = array![1.0, 0.0, 2.0, 0.0, 1.5];
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let reference = GaussianKDE::new(
+            data.select(Axis(0), &[0, 2, 4]),
+            Some(array![1.0, 2.0, 1.5]),
+        )
+        .unwrap();
+        assert_relative_eq!(
+            kde.eval(array![0.1, 0.1].view()),
+            reference.eval(array![0.1, 0.1].view()),
+            max_relative = 1e-12
+        );
+    }
+
+    #[test]
+    fn non_finite_data_test() {
+        let data = array![
+            [0.1, 0.4, 1.2],
+            [0.3, -0.2, 0.5],
+            [-0.5, 0.7, 0.9],
+            [0.9, 0.0, -0.3],
+            [0.2, 0.2, 0.1]
+        ];
+        let is_data_error = |res: Result<_, KDEError>, row: usize, col: usize| {
+            matches!(res, Err(e) if matches!(e.kind, ErrorKind::DataError)
+                && e.message.contains(&format!("row {row}, column {col}")))
+        };
+        for (value, row, col) in [
+            (f64::NAN, 3, 1),
+            (f64::INFINITY, 0, 2),
+            (f64::NEG_INFINITY, 4, 0),
+        ] {
+            let mut data = data.clone();
+            data[[row, col]] = value;
+            // Only the first non-finite value is reported
+            data[[4, 2]] = f64::NAN;
+            let res = GaussianKDE::new(data.clone(), None).map(|_| ());
+            assert!(is_data_error(res, row, col));
+            let res = GaussianKDE::from_view(data.view(), None).map(|_| ());
+            assert!(is_data_error(res, row, col));
+            let res =
+                GaussianKDE::with_bandwidth_matrix(data.clone(), None, Array2::eye(3)).map(|_| ());
+            assert!(is_data_error(res, row, col));
+            let res = GaussianKDE::builder().build(data.clone()).map(|_| ());
+            assert!(is_data_error(res, row, col));
+            let res = GaussianKDE::builder()
+                .check_finite(false)
+                .build(data.clone());
+            assert!(!matches!(res, Err(e) if matches!(e.kind, ErrorKind::DataError)));
+        }
+        let mut kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let res = kde.extend(array![[0.0, 0.0, 0.0], [0.0, f64::NAN, 0.0]].view(), None);
+        assert!(is_data_error(res, 1, 1));
+        assert_eq!(kde.data(), data);
+    }
+
+    #[test]
+    fn singularity_diagnosis_test() {
+        let data = array![
+            [0.1, 0.4, 1.2],
+            [0.3, -0.2, 0.5],
+            [-0.5, 0.7, 0.9],
+            [0.9, 0.0, -0.3],
+            [0.2, 0.2, 0.1]
+        ];
+        let singularity_message = |res: Result<GaussianKDE<f64>, KDEError>| match res {
+            Err(e) if matches!(e.kind, ErrorKind::SingularityError) => e.message,
+            _ => panic!("expected a `SingularityError`"),
+        };
+
+        let mut constant = data.clone();
+        constant.column_mut(1).fill(0.3);
+        let message = singularity_message(GaussianKDE::new(constant.clone(), None));
+        assert!(message.contains("dimensions [1]"), "{message}");
+        assert!(message.contains("jitter"), "{message}");
+        let message = singularity_message(GaussianKDE::with_diagonal_covariance(
+            constant.clone(),
+            None,
+        ));
+        assert!(message.contains("dimensions [1]"), "{message}");
+        constant.column_mut(2).fill(-1.7);
+        let message = singularity_message(GaussianKDE::new(constant.clone(), None));
+        assert!(message.contains("dimensions [1, 2]"), "{message}");
+        // A column which is only constant among the points with positive weight
+        let weights = array![1.0, 1.0, 0.0, 1.0, 0.5];
+        let mut constant = data.clone();
+        constant
+            .column_mut(0)
+            .assign(&array![0.4, 0.4, -2.0, 0.4, 0.4]);
+        let message = singularity_message(GaussianKDE::new(constant, Some(weights)));
+        assert!(message.contains("dimensions [0]"), "{message}");
+
+        // Identical columns have a non-zero variance, but the Cholesky decomposition fails
+        let mut identical = data.clone();
+        identical.column_mut(2).assign(&data.column(0));
+        let message = singularity_message(GaussianKDE::new(identical, None));
+        assert!(message.contains("pivot 2"), "{message}");
+        let mut dependent = data.clone();
+        dependent
+            .column_mut(1)
+            .assign(&(&data.column(0) * 2.0 - 0.1));
+        let message = singularity_message(GaussianKDE::new(dependent, None));
+        assert!(message.contains("pivot 1"), "{message}");
+    }
+
+    #[test]
+    fn regularization_test() {
+        // Two columns with a correlation of 1 - 1e-14
+        let x = Array1::linspace(-1.0, 1.0, 40);
+        let z = Array1::from_shape_fn(40, |i| if i % 4 < 2 { 1.0 } else { -1.0 });
+        let y = &x + &(&z * (2e-14f64 * x.var(0.0) / z.var(0.0)).sqrt());
+        let data = ndarray::stack![Axis(1), x, y];
+        let cov = covariance(data.view(), None);
+        assert_relative_eq!(
+            1.0 - cov[[0, 1]] / (cov[[0, 0]] * cov[[1, 1]]).sqrt(),
+            1e-14,
+            max_relative = 0.1
+        );
+
+        let res = GaussianKDE::with_regularization(data.clone(), None, ScottBandwidth {}, 0.0);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+        let kde =
+            GaussianKDE::with_regularization(data.clone(), None, ScottBandwidth {}, 1e-9).unwrap();
+        assert_eq!(kde.regularization(), 1e-9);
+        let densities = kde.eval_batch(data.view());
+        assert!(densities.iter().all(|f| f.is_finite() && *f > 0.0));
+        let h = kde.bandwidth_factor();
+        let expected =
+            (&cov + Array2::from_diag_elem(2, 1e-9 * cov.diag().mean().unwrap())) * h * h;
+        assert_relative_eq!(kde.covariance(), expected, max_relative = 1e-12);
+        assert_eq!(
+            kde.eval_batch(data.view()),
+            GaussianKDE::builder()
+                .regularization(1e-9)
+                .build(data.clone())
+                .unwrap()
+                .eval_batch(data.view())
+        );
+
+        // Derived KDEs use the same regularization
+        let marginal = kde.marginalize(&[1]).unwrap();
+        assert_eq!(marginal.regularization(), 1e-9);
+        let h = marginal.bandwidth_factor();
+        assert_relative_eq!(
+            marginal.covariance()[[0, 0]],
+            cov[[0, 0]] * (1.0 + 1e-9) * h * h,
+            max_relative = 1e-12
+        );
+        let res = GaussianKDE::with_regularization(data, None, ScottBandwidth {}, -1e-9);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+    }
+
+    #[test]
+    fn clone_test() {
+        use rand::prelude::*;
+        use rand_distr::StandardNormal;
+        use rand_pcg::Pcg64Mcg;
+
+        let mut rng = Pcg64Mcg::seed_from_u64(100);
+        let data = Array2::<f64>::from_shape_simple_fn((500, 2), || rng.sample(StandardNormal));
+        let x = Array2::<f64>::from_shape_simple_fn((20, 2), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let expected = kde.eval_batch(x.view());
+        let copy = kde.clone();
+        assert_eq!(copy.eval_batch(x.view()), expected);
+        assert_eq!(copy.covariance(), kde.covariance());
+
+        // Each thread reweights its own copy without affecting the original
+        let results = std::thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|t| {
+                    let mut copy = kde.clone();
+                    let weights = Array1::from_shape_fn(500, |i| 1.0 + ((i + t) % 4) as f64);
+                    let (data, x) = (&data, &x);
+                    scope.spawn(move || {
+                        copy.set_weights(weights.clone()).unwrap();
+                        let reference = GaussianKDE::new(data.clone(), Some(weights)).unwrap();
+                        (copy.eval_batch(x.view()), reference.eval_batch(x.view()))
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        for (res, reference) in results {
+            assert_relative_eq!(res, reference, max_relative = 1e-12);
+        }
+        assert!(kde.weights().is_none());
+        assert_eq!(kde.eval_batch(x.view()), expected);
+
+        // The clone of a marginal remains a marginal
+        let marginal = kde.marginalize(&[0]).unwrap();
+        let copy = marginal.clone();
+        assert_eq!(copy.data(), marginal.data());
+        assert_eq!(copy.original_dims(), vec![1]);
     }
 }
@@ -0,0 +1,248 @@
+//! Combination of several fitted [`GaussianKDE`]s, e.g. estimated from parts of a dataset in parallel, into a single
+//! estimate of the union of their datasets.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::bandwidth::Bandwidth;
+use crate::{CowGaussianKDE, ErrorKind, KDEError};
+use alloc::{format, vec, vec::Vec};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+impl<'a, F, B> CowGaussianKDE<'a, F, B>
+where
+    B: Bandwidth<F> + Clone,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Merge the KDE with `other` into the KDE of the concatenated datasets and weights, see
+    /// [`GaussianKDE::merge_all`].
+    pub fn merge(&self, other: &CowGaussianKDE<'_, F, B>) -> Result<Self, KDEError> {
+        return Self::merge_parts(
+            self,
+            vec![
+                (self.dataset(), self.weights(), F::one()),
+                (other.dataset(), other.weights(), F::one()),
+            ],
+        );
+    }
+
+    /// Merge the given KDEs into the KDE of their concatenated datasets and weights, where the points of unweighted
+    /// KDEs are weighed with one. The result is the same as fitting the union of the datasets at once, i.e. each KDE
+    /// contributes proportionally to the sum of its weights (or its number of points). See
+    /// [`GaussianKDE::merge_scaled`] for a different relative normalization.
+    ///
+    /// The covariance matrix and the bandwidth factor are refitted on the union of the datasets with the bandwidth
+    /// selector, covariance mode and regularization of the first KDE. If the kernel covariance of the first KDE was
+    /// given by the user, it is used for the merged KDE as well.
+    ///
+    /// Returns a `ShapeError` if no KDEs are given or their dimensions do not match.
+    pub fn merge_all<'k, 'd: 'k>(
+        kdes: impl IntoIterator<Item = &'k CowGaussianKDE<'d, F, B>>,
+    ) -> Result<Self, KDEError>
+    where
+        B: 'k,
+    {
+        return Self::merge_scaled(kdes.into_iter().map(|kde| (kde, F::one())));
+    }
+
+    /// Merge the given KDEs into the KDE of their concatenated datasets, where the weights of each KDE (or ones for
+    /// unweighted KDEs) are multiplied by the given scale. For example, scaling each KDE by the inverse of the sum of
+    /// its weights gives all KDEs the same total probability mass, independent of the sizes of their datasets.
+    ///
+    /// The merged KDE is unweighted if all KDEs are unweighted and all scales are one. Otherwise, see
+    /// [`GaussianKDE::merge_all`].
+    ///
+    /// Returns a `ShapeError` if no KDEs are given or their dimensions do not match and a `DomainError` if any scale
+    /// is not positive and finite.
+    pub fn merge_scaled<'k, 'd: 'k>(
+        kdes: impl IntoIterator<Item = (&'k CowGaussianKDE<'d, F, B>, F)>,
+    ) -> Result<Self, KDEError>
+    where
+        B: 'k,
+    {
+        let kdes = kdes.into_iter().collect::<Vec<_>>();
+        let Some((first, _)) = kdes.first() else {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                "cannot merge an empty collection of KDEs",
+            ));
+        };
+        return Self::merge_parts(
+            first,
+            kdes.iter()
+                .map(|(kde, scale)| (kde.dataset(), kde.weights(), *scale))
+                .collect(),
+        );
+    }
+
+    /// Fit the KDE of the concatenated datasets of `parts`, given as dataset, optional weights and scale of the
+    /// weights, with the settings of `first`.
+    fn merge_parts(
+        first: &CowGaussianKDE<'_, F, B>,
+        parts: Vec<MergePart<F>>,
+    ) -> Result<Self, KDEError> {
+        let dim = first.n_dims();
+        for (data, _, scale) in parts.iter() {
+            if data.dim().1 != dim {
+                return Err(KDEError::new(
+                    ErrorKind::ShapeError,
+                    format!(
+                        "cannot merge KDEs of different dimensions {dim} and {}",
+                        data.dim().1
+                    ),
+                ));
+            }
+            if !(*scale > F::zero() && scale.is_finite()) {
+                return Err(KDEError::new(
+                    ErrorKind::DomainError,
+                    format!(
+                        "the scales of the merged KDEs must be positive and finite, found {}",
+                        scale.to_f64().unwrap()
+                    ),
+                ));
+            }
+        }
+        let data = ndarray::concatenate(
+            Axis(0),
+            &parts
+                .iter()
+                .map(|(data, _, _)| data.view())
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let weighted = parts
+            .iter()
+            .any(|(_, weights, scale)| weights.is_some() || *scale != F::one());
+        let weights = weighted.then(|| {
+            parts
+                .iter()
+                .flat_map(|(data, weights, scale)| {
+                    let weights = weights.map_or_else(
+                        || Array1::from_elem(data.dim().0, *scale),
+                        |w| w.mapv(|w| w * *scale),
+                    );
+                    weights.into_iter()
+                })
+                .collect::<Array1<F>>()
+        });
+        return first.refit(data, weights);
+    }
+}
+
+/// Dataset, optional weights and scale of the weights of a KDE to be merged.
+type MergePart<'p, F> = (CowArray<'p, F, Ix2>, Option<ArrayView1<'p, F>>, F);
+
+#[cfg(test)]
+mod tests {
+    use crate::{CowGaussianKDE, ErrorKind, GaussianKDE};
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+
+    fn assert_same_density(kde: &CowGaussianKDE<'_, f64>, reference: &CowGaussianKDE<'_, f64>) {
+        let x = array![[0.0, 0.0], [0.5, -0.3], [-1.0, 1.2]];
+        assert_eq!(kde.data(), reference.data());
+        assert_eq!(kde.weights(), reference.weights());
+        assert_relative_eq!(
+            kde.eval_batch(x.view()),
+            reference.eval_batch(x.view()),
+            max_relative = 1e-12
+        );
+    }
+
+    #[test]
+    fn merge_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3], [0.4, 1.1], [2.0, -0.5], [0.9, 0.8], [-0.3, -1.4],
+            [1.5, 2.2], [-2.1, 0.6], [0.1, 0.0], [1.1, -0.9], [-0.7, 1.7]
+        ];
+        let weights: Array1<f64> = array![0.5, 2.0, 1.0, 0.2, 1.5, 0.8, 0.3, 3.0, 1.2, 0.6];
+        let first = GaussianKDE::new(data.slice(s![..6, ..]).to_owned(), None).unwrap();
+        let second = GaussianKDE::new(data.slice(s![6.., ..]).to_owned(), None).unwrap();
+        let merged = first.merge(&second).unwrap();
+        assert!(merged.weights().is_none());
+        assert_same_density(&merged, &GaussianKDE::new(data.clone(), None).unwrap());
+
+        let first = GaussianKDE::new(
+            data.slice(s![..3, ..]).to_owned(),
+            Some(weights.slice(s![..3]).to_owned()),
+        )
+        .unwrap();
+        let second = GaussianKDE::new(
+            data.slice(s![3.., ..]).to_owned(),
+            Some(weights.slice(s![3..]).to_owned()),
+        )
+        .unwrap();
+        assert_same_density(
+            &first.merge(&second).unwrap(),
+            &GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap(),
+        );
+
+        // Unweighted points are weighed with one, also for borrowed datasets and marginals
+        // The merged KDEs share the lifetime of the borrowed dataset
+        let unweighted = GaussianKDE::from_view(data.slice(s![3..6, ..]), None).unwrap();
+        let full = CowGaussianKDE::new(
+            ndarray::concatenate(
+                Axis(1),
+                &[
+                    data.slice(s![6.., ..]),
+                    array![[0.3], [1.2], [-0.4], [0.8]].view(),
+                ],
+            )
+            .unwrap(),
+            Some(weights.slice(s![6..]).to_owned()),
+        )
+        .unwrap();
+        let marginal = full.marginalize(&[2]).unwrap();
+        let first = CowGaussianKDE::new(
+            data.slice(s![..3, ..]).to_owned(),
+            Some(weights.slice(s![..3]).to_owned()),
+        )
+        .unwrap();
+        let merged = GaussianKDE::merge_all([&first, &unweighted, &marginal]).unwrap();
+        let mut expected_weights = weights.clone();
+        expected_weights.slice_mut(s![3..6]).fill(1.0);
+        assert_same_density(
+            &merged,
+            &GaussianKDE::new(data.clone(), Some(expected_weights)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn merge_scaled_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3], [0.4, 1.1], [2.0, -0.5], [0.9, 0.8], [-0.3, -1.4],
+            [1.5, 2.2], [-2.1, 0.6], [0.1, 0.0], [1.1, -0.9], [-0.7, 1.7]
+        ];
+        let weights: Array1<f64> = array![0.5, 2.0, 1.0, 0.2, 1.5, 0.8, 0.3, 3.0, 1.2, 0.6];
+        let first = GaussianKDE::new(data.slice(s![..4, ..]).to_owned(), None).unwrap();
+        let second = GaussianKDE::new(
+            data.slice(s![4.., ..]).to_owned(),
+            Some(weights.slice(s![4..]).to_owned()),
+        )
+        .unwrap();
+        // Give both KDEs the same total probability mass
+        let scale = weights.slice(s![4..]).sum().recip();
+        let merged = GaussianKDE::merge_scaled([(&first, 0.25), (&second, scale)]).unwrap();
+        let mut expected_weights = weights.mapv(|w| w * scale);
+        expected_weights.slice_mut(s![..4]).fill(0.25);
+        assert_same_density(
+            &merged,
+            &GaussianKDE::new(data.clone(), Some(expected_weights)).unwrap(),
+        );
+        assert_relative_eq!(
+            merged.weights().unwrap().slice(s![4..]).sum(),
+            1.0,
+            max_relative = 1e-12
+        );
+
+        let res = GaussianKDE::merge_scaled([(&first, 1.0), (&second, 0.0)]);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        let res = GaussianKDE::<f64>::merge_all([]);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let other = GaussianKDE::new(data.slice(s![.., ..1]).to_owned(), None).unwrap();
+        let res = first.merge(&other);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+}
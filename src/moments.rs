@@ -0,0 +1,211 @@
+//! Moments of the probability distribution estimated by KDEs, which are the moments of the (weighted) mixture of the
+//! kernels centered at the data points.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::summation::compensated_sum;
+use crate::{Bandwidth, CowGaussianKDE};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Compute the mean $\mu = \sum_i w_i x_i / \sum_i w_i$ of the distribution estimated by the KDE, which is the
+    /// (weighted) mean of the data points since the kernels are centered at them.
+    pub fn distribution_mean(&self) -> Array1<F> {
+        let data = self.dataset();
+        let sum_weights = self.sum_weights();
+        return Array1::from_shape_fn(self.n_dims(), |k| match self.weights() {
+            Some(w) => {
+                compensated_sum(data.column(k).iter().zip(w).map(|(x, w)| *w * *x)) / sum_weights
+            }
+            None => compensated_sum(data.column(k).iter().copied()) / sum_weights,
+        });
+    }
+
+    /// Compute the covariance matrix of the distribution estimated by the KDE,
+    /// \\[ \mathrm{Cov}(X) = \frac{1}{\sum_i w_i} \sum_i w_i (x_i - \mu)(x_i - \mu)^\top + H, \\]
+    /// i.e. the (weighted) covariance of the data points around the mean $\mu$ plus the kernel covariance $H$.
+    ///
+    /// This is **not** the kernel covariance $H = h^2 V$ returned by [`GaussianKDE::covariance`], which describes the
+    /// width of a single kernel, and neither the covariance $V$ of the dataset from which the bandwidth is derived.
+    /// Since the KDE is a mixture of the kernels with the probabilities $w_i / \sum_j w_j$, the scatter of the data
+    /// points is normalized by the total weight instead of the unbiased normalization of $V$, and the kernels add the
+    /// smearing $H$. For a Gaussian dataset with covariance $\Sigma$, the result is about $(1 + h^2) \Sigma$.
+    pub fn distribution_covariance(&self) -> Array2<F> {
+        let data = self.dataset();
+        let mean = self.distribution_mean();
+        let sum_weights = self.sum_weights();
+        let weight = |i: usize| self.weights().map_or(F::one(), |w| w[i]);
+        let scatter = Array2::from_shape_fn((self.n_dims(), self.n_dims()), |(j, k)| {
+            compensated_sum(
+                data.column(j)
+                    .iter()
+                    .zip(data.column(k))
+                    .enumerate()
+                    .map(|(i, (x, y))| weight(i) * (*x - mean[j]) * (*y - mean[k])),
+            ) / sum_weights
+        });
+        return scatter + self.covariance();
+    }
+
+    /// Compute the skewness $\mu_3 / \mu_2^{3/2}$ of each marginal of the distribution estimated by the KDE. Since the
+    /// Gaussian kernels are symmetric, the third central moment $\mu_3 = m_3$ is the (weighted) third central moment of
+    /// the data points, while the variance $\mu_2 = m_2 + H_{kk}$ includes the kernel variance $H_{kk}$ of the
+    /// component. The moments of the data points are normalized by the total weight, as in
+    /// [`GaussianKDE::distribution_covariance`].
+    pub fn marginal_skewness(&self) -> Array1<F> {
+        let cov = self.covariance();
+        return Array1::from_shape_fn(self.n_dims(), |k| {
+            let (m2, m3, _) = self.central_data_moments(k);
+            let var = m2 + cov[[k, k]];
+            m3 / (var * var.sqrt())
+        });
+    }
+
+    /// Compute the excess kurtosis $\mu_4 / \mu_2^2 - 3$ of each marginal of the distribution estimated by the KDE. For
+    /// the kernel variance $s^2 = H_{kk}$ of the component and the (weighted) central moments $m_2$ and $m_4$ of the
+    /// data points, the moments of the marginal are $\mu_2 = m_2 + s^2$ and $\mu_4 = m_4 + 6 m_2 s^2 + 3 s^4$, the
+    /// latter from the moments of the convolution with the Gaussian kernel. The excess kurtosis therefore tends to zero
+    /// for wide kernels.
+    pub fn marginal_kurtosis(&self) -> Array1<F> {
+        let cov = self.covariance();
+        return Array1::from_shape_fn(self.n_dims(), |k| {
+            let (m2, _, m4) = self.central_data_moments(k);
+            let s2 = cov[[k, k]];
+            let var = m2 + s2;
+            let mu4 = m4 + F::from(6).unwrap() * m2 * s2 + F::from(3).unwrap() * s2 * s2;
+            mu4 / (var * var) - F::from(3).unwrap()
+        });
+    }
+
+    /// Second, third and fourth central moments of the component `k` of the data points, normalized by the total
+    /// weight.
+    fn central_data_moments(&self, k: usize) -> (F, F, F) {
+        let data = self.dataset();
+        let x = data.column(k);
+        let sum_weights = self.sum_weights();
+        let mean = self.distribution_mean()[k];
+        let weight = |i: usize| self.weights().map_or(F::one(), |w| w[i]);
+        let moment = |p: i32| {
+            compensated_sum(
+                x.iter()
+                    .enumerate()
+                    .map(|(i, x)| weight(i) * (*x - mean).powi(p)),
+            ) / sum_weights
+        };
+        return (moment(2), moment(3), moment(4));
+    }
+
+    /// Total weight of the data points, or their number for unweighted data.
+    fn sum_weights(&self) -> F {
+        return self
+            .weights()
+            .map_or(F::from(self.n_points()).unwrap(), |w| {
+                compensated_sum(w.iter().copied())
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GaussianKDE;
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn distribution_covariance_test() {
+        let data = array![[0.0, 1.0], [1.0, 3.0], [-2.0, 0.5], [0.5, -1.0]];
+        let weights = array![1.0, 0.5, 2.0, 1.5];
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let mean = data.t().dot(&weights) / 5.0;
+        assert_relative_eq!(kde.distribution_mean(), mean, max_relative = 1e-12);
+        let centered = &data - &mean;
+        let scatter = centered
+            .t()
+            .dot(&(&centered * &weights.insert_axis(Axis(1))))
+            / 5.0;
+        assert_relative_eq!(
+            kde.distribution_covariance(),
+            &scatter + &kde.covariance(),
+            max_relative = 1e-12
+        );
+
+        // Gaussian dataset, whose distribution covariance is inflated by the kernels
+        let mut rng = Pcg64Mcg::seed_from_u64(97);
+        let cov = array![[2.0, 0.6], [0.6, 0.5]];
+        let data = Array2::<f64>::from_shape_simple_fn((20_000, 2), || rng.sample(StandardNormal));
+        // Correlate the standard normal components with the Cholesky factor of the covariance
+        let chol = array![[2f64.sqrt(), 0.0], [0.6 / 2f64.sqrt(), 0.32f64.sqrt()]];
+        let data = data.dot(&chol.t());
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let h2 = kde.bandwidth_factor().powi(2);
+        let res = kde.distribution_covariance();
+        assert_relative_eq!(res, &cov * (1.0 + h2), max_relative = 0.03);
+        assert_relative_eq!(kde.distribution_mean(), array![0.0, 0.0], epsilon = 0.03);
+
+        // The covariance of a large sample drawn from the KDE matches
+        #[cfg(feature = "sample")]
+        {
+            let sample = kde.sample_batch_with_rng(200_000, &mut rng);
+            let centered = &sample - &sample.mean_axis(Axis(0)).unwrap();
+            let empirical = centered.t().dot(&centered) / 200_000.0;
+            assert_relative_eq!(empirical, res, max_relative = 0.01);
+        }
+    }
+
+    #[test]
+    fn marginal_moments_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(98);
+        // Skewed first component and Gaussian second component
+        let z = Array2::<f64>::from_shape_simple_fn((2000, 2), || rng.sample(StandardNormal));
+        let mut data = z.clone();
+        data.column_mut(0).mapv_inplace(|x| x + 0.3 * x * x);
+        let weights = Array1::from_shape_simple_fn(2000, || rng.random_range(0.5..1.5));
+
+        // Explicit moments of the (weighted) mixture
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let x = data.column(0);
+        let w = weights.sum();
+        let mean = x.dot(&weights) / w;
+        let m = |p: i32| x.mapv(|x| (x - mean).powi(p)).dot(&weights) / w;
+        let s2 = kde.covariance()[[0, 0]];
+        let var = m(2) + s2;
+        assert_relative_eq!(
+            kde.marginal_skewness()[0],
+            m(3) / var.powf(1.5),
+            max_relative = 1e-10
+        );
+        assert_relative_eq!(
+            kde.marginal_kurtosis()[0],
+            (m(4) + 6.0 * m(2) * s2 + 3.0 * s2 * s2) / (var * var) - 3.0,
+            max_relative = 1e-10
+        );
+
+        // A symmetric dataset has no skewness
+        let symmetric = ndarray::concatenate![Axis(0), z, -&z];
+        let kde = GaussianKDE::new(symmetric, None).unwrap();
+        assert_relative_eq!(kde.marginal_skewness(), array![0.0, 0.0], epsilon = 1e-12);
+        assert_relative_eq!(kde.marginal_kurtosis(), array![0.0, 0.0], epsilon = 0.2);
+
+        // The moments of a large sample drawn from the KDE match
+        #[cfg(feature = "sample")]
+        {
+            let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+            let n = 400_000;
+            let sample = kde.sample_batch_with_rng(n, &mut rng);
+            let centered = &sample - &sample.mean_axis(Axis(0)).unwrap();
+            let moment = |p: i32| centered.mapv(|x| x.powi(p)).sum_axis(Axis(0)) / n as f64;
+            let skewness = moment(3) / moment(2).mapv(|v| v.powf(1.5));
+            let kurtosis = moment(4) / moment(2).mapv(|v| v * v) - 3.0;
+            assert_relative_eq!(kde.marginal_skewness(), skewness, epsilon = 0.03);
+            assert_relative_eq!(kde.marginal_kurtosis(), kurtosis, epsilon = 0.1);
+        }
+    }
+}
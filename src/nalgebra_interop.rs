@@ -0,0 +1,192 @@
+//! Construction, evaluation and sampling of KDEs with the dynamically sized matrices and vectors of
+//! [`nalgebra`](https://docs.rs/nalgebra).
+//!
+//! Matrices of points follow the convention of the ndarray API, i.e. each row of a `DMatrix` of shape
+//! `(n_points, dim)` is a point, unless the method name contains `columns`. Since `DMatrix` stores its elements in
+//! column-major order, the matrices are viewed as ndarray arrays in Fortran order instead of being copied.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::{Bandwidth, CowGaussianKDE, KDEError};
+use nalgebra::{DMatrix, DVector, Scalar};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// View the matrix as an array of the same shape, without copying.
+fn view<F>(matrix: &DMatrix<F>) -> ArrayView2<'_, F> {
+    return ArrayView2::from_shape((matrix.nrows(), matrix.ncols()).f(), matrix.as_slice())
+        .unwrap();
+}
+
+impl<F> CowGaussianKDE<'_, F>
+where
+    F: Float + FloatConst + FromPrimitive + Scalar,
+{
+    /// Create a new kernel density estimator from the rows of `data` and (optionally) the weights using the default
+    /// bandwidth choice [`ScottBandwidth`](crate::ScottBandwidth), borrowing the matrices as for
+    /// [`GaussianKDE::from_view`]. The matrix is expected to have the shape `(n_points, dim)`, see
+    /// [`GaussianKDE::from_nalgebra_columns`] for points stored as columns.
+    ///
+    /// Returns the errors of [`GaussianKDE::new`].
+    pub fn from_nalgebra<'v>(
+        data: &'v DMatrix<F>,
+        weights: Option<&'v DVector<F>>,
+    ) -> Result<CowGaussianKDE<'v, F>, KDEError> {
+        return CowGaussianKDE::from_view(view(data), weights.map(|w| aview1(w.as_slice())));
+    }
+
+    /// Create a new kernel density estimator from the columns of `data` and (optionally) the weights as for
+    /// [`GaussianKDE::from_nalgebra`]. The matrix is expected to have the shape `(dim, n_points)`, which is the memory
+    /// layout of the ndarray API, such that the data points are contiguous.
+    pub fn from_nalgebra_columns<'v>(
+        data: &'v DMatrix<F>,
+        weights: Option<&'v DVector<F>>,
+    ) -> Result<CowGaussianKDE<'v, F>, KDEError> {
+        return CowGaussianKDE::from_view(
+            view(data).reversed_axes(),
+            weights.map(|w| aview1(w.as_slice())),
+        );
+    }
+}
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + Scalar,
+{
+    /// Evaluate the probability density estimated by the KDE at the point `x` as for [`GaussianKDE::eval`].
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn eval_na(&self, x: &DVector<F>) -> F {
+        return self.eval(aview1(x.as_slice()));
+    }
+
+    /// Evaluate the probability density estimated by the KDE at the rows of `x`, a matrix of shape
+    /// `(n_points, dim)`, as for [`GaussianKDE::eval_batch`].
+    ///
+    /// *Panics* if the number of columns of `x` does not match the dimension of the KDE dataset.
+    pub fn eval_batch_na(&self, x: &DMatrix<F>) -> DVector<F> {
+        return self.eval_batch_view(view(x));
+    }
+
+    /// Evaluate the probability density estimated by the KDE at the columns of `x`, a matrix of shape
+    /// `(dim, n_points)`, as for [`GaussianKDE::eval_batch`].
+    ///
+    /// *Panics* if the number of rows of `x` does not match the dimension of the KDE dataset.
+    pub fn eval_batch_columns_na(&self, x: &DMatrix<F>) -> DVector<F> {
+        return self.eval_batch_view(view(x).reversed_axes());
+    }
+
+    /// Evaluate the points along `Axis(1)` of `x` into a new vector.
+    fn eval_batch_view(&self, x: ArrayView2<F>) -> DVector<F> {
+        assert_eq!(x.dim().1, self.n_dims());
+        let mut res = DVector::zeros(x.dim().0);
+        self.eval_batch_into(x, aview_mut1(res.as_mut_slice()))
+            .unwrap();
+        return res;
+    }
+}
+
+#[cfg(feature = "sample")]
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float
+        + FloatConst
+        + FromPrimitive
+        + Scalar
+        + rand_distr::uniform::SampleUniform
+        + rand_distr::weighted::Weight,
+    rand_distr::StandardNormal: rand_distr::Distribution<F>,
+{
+    /// Sample a random point from the probability density estimated by the KDE using a given RNG as for
+    /// [`GaussianKDE::sample_with_rng`].
+    pub fn sample_na(&self, rng: &mut impl rand::Rng) -> DVector<F> {
+        return DVector::from_vec(self.sample_with_rng(rng).into_raw_vec_and_offset().0);
+    }
+
+    /// Sample `n` random points from the probability density estimated by the KDE using a given RNG as for
+    /// [`GaussianKDE::sample_batch_with_rng`] and return them as the rows of a matrix of shape `(n, dim)`. For the same
+    /// RNG state, the points are identical.
+    pub fn sample_batch_na(&self, n: usize, rng: &mut impl rand::Rng) -> DMatrix<F> {
+        let mut res = DMatrix::zeros(n, self.n_dims());
+        let out = ArrayViewMut2::from_shape((n, self.n_dims()).f(), res.as_mut_slice()).unwrap();
+        self.sample_batch_into(out, rng).unwrap();
+        return res;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CowGaussianKDE, GaussianKDE};
+    use approx::assert_relative_eq;
+    use nalgebra::{DMatrix, DVector};
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn nalgebra_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(105);
+        let data = Array2::<f64>::from_shape_simple_fn((300, 3), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(300, || rng.random_range(0.5..1.5));
+        let x = Array2::<f64>::from_shape_simple_fn((40, 3), || rng.sample(StandardNormal));
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let expected = kde.eval_batch(x.view());
+
+        // Points as rows and as columns of the nalgebra matrices
+        let rows = DMatrix::from_fn(300, 3, |i, j| data[[i, j]]);
+        let columns = rows.transpose();
+        let na_weights = DVector::from_iterator(300, weights.iter().copied());
+        let na_x = DMatrix::from_fn(40, 3, |i, j| x[[i, j]]);
+        let from_rows = CowGaussianKDE::from_nalgebra(&rows, Some(&na_weights)).unwrap();
+        let from_columns =
+            CowGaussianKDE::from_nalgebra_columns(&columns, Some(&na_weights)).unwrap();
+        assert_eq!(from_rows.dataset(), data);
+        assert_eq!(from_columns.dataset(), data);
+        assert_eq!(from_columns.dataset().as_ptr(), columns.as_ptr());
+        assert!(from_rows == from_columns);
+        let point = DVector::from_iterator(3, x.row(7).iter().copied());
+        for res in [
+            kde.eval_batch_na(&na_x),
+            kde.eval_batch_columns_na(&na_x.transpose()),
+            from_rows.eval_batch_na(&na_x),
+            from_columns.eval_batch_columns_na(&na_x.transpose()),
+        ] {
+            assert_relative_eq!(
+                res.as_slice(),
+                expected.as_slice().unwrap(),
+                max_relative = 1e-13
+            );
+        }
+        assert_relative_eq!(kde.eval_na(&point), expected[7], max_relative = 1e-13);
+        assert_relative_eq!(from_rows.eval_na(&point), expected[7], max_relative = 1e-13);
+        let unweighted = CowGaussianKDE::from_nalgebra(&rows, None).unwrap();
+        assert!(unweighted == CowGaussianKDE::from_view(data.view(), None).unwrap());
+
+        // Sampling draws the same points as the ndarray API
+        #[cfg(feature = "sample")]
+        {
+            let samples = kde.sample_batch_na(50, &mut Pcg64Mcg::seed_from_u64(105));
+            let expected = kde.sample_batch_with_rng(50, &mut Pcg64Mcg::seed_from_u64(105));
+            assert_eq!((samples.nrows(), samples.ncols()), (50, 3));
+            assert!(
+                expected
+                    .indexed_iter()
+                    .all(|((i, j), &e)| samples[(i, j)] == e)
+            );
+            let sample = kde.sample_na(&mut Pcg64Mcg::seed_from_u64(105));
+            let expected = kde.sample_with_rng(&mut Pcg64Mcg::seed_from_u64(105));
+            assert_eq!(sample.as_slice(), expected.as_slice().unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn nalgebra_shape_test() {
+        let data = DMatrix::from_fn(20, 2, |i, j| (i * (j + 1)) as f64 % 7.0);
+        let kde = CowGaussianKDE::from_nalgebra(&data, None).unwrap();
+        kde.eval_batch_columns_na(&data);
+    }
+}
@@ -0,0 +1,326 @@
+//! Storage of fitted KDEs in NumPy archives (`.npz`), e.g. for the exchange with Python.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::cholesky::cholesky_inverse;
+use crate::{
+    Bandwidth, CovarianceMode, CowGaussianKDE, ErrorKind, KDEError, ScottBandwidth, check_finite,
+    check_weights, shared,
+};
+use ndarray::prelude::*;
+use ndarray_npy::{NpzReader, NpzWriter, ReadableElement, WritableElement};
+use num_traits::{Float, FloatConst, FromPrimitive};
+use std::fs::File;
+use std::path::Path;
+
+/// Version of the layout of the archives written by [`GaussianKDE::write_npz`].
+const NPZ_VERSION: f64 = 1.0;
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + WritableElement + 'static,
+{
+    /// Write the KDE to the NumPy archive at `path`, which is created or truncated, such that it can be restored by
+    /// [`GaussianKDE::read_npz`] without fitting or loaded in Python with `numpy.load`. The bandwidth selector is not
+    /// stored.
+    ///
+    /// Version 1 of the layout contains the arrays
+    /// - `data` of shape `(n_points, dim)` with the dataset,
+    /// - `weights` of shape `(n_points,)` with the weights of the data points, only for weighted KDEs,
+    /// - `cholesky` of shape `(dim, dim)` with the lower-triangular Cholesky factor $L$ of the kernel covariance
+    ///   $H = LL^\top$,
+    /// - `bandwidth_factor` of shape `()` with the bandwidth factor $h$ and
+    /// - `metadata` of shape `(5,)` in double precision with the layout version, the covariance mode (0: full,
+    ///   1: diagonal, 2: robust, 3: shrinkage, 4: Ledoit-Wolf), the shrinkage intensity, whether the kernel
+    ///   covariance was given by the user (0 or 1) and the regularization.
+    ///
+    /// The dataset, weights, Cholesky factor and bandwidth factor are stored with the element type of the KDE.
+    ///
+    /// Returns an `IoError` if the file cannot be written.
+    pub fn write_npz(&self, path: impl AsRef<Path>) -> Result<(), KDEError> {
+        let path = path.as_ref();
+        let io_error = |e: &dyn std::fmt::Display| {
+            KDEError::new(
+                ErrorKind::IoError,
+                format!("failed to write `{}`: {e}", path.display()),
+            )
+        };
+        let (mode, intensity) = match self.covariance_mode {
+            CovarianceMode::Full => (0.0, 0.0),
+            CovarianceMode::Diagonal => (1.0, 0.0),
+            CovarianceMode::Robust => (2.0, 0.0),
+            CovarianceMode::Shrinkage(lambda) => (3.0, lambda),
+            CovarianceMode::LedoitWolf => (4.0, 0.0),
+        };
+        let metadata = array![
+            NPZ_VERSION,
+            mode,
+            intensity,
+            if self.custom_bandwidth_matrix {
+                1.0
+            } else {
+                0.0
+            },
+            self.regularization.to_f64().unwrap()
+        ];
+        let mut npz = NpzWriter::new(File::create(path).map_err(|e| io_error(&e))?);
+        npz.add_array("data", &self.dataset())
+            .map_err(|e| io_error(&e))?;
+        if let Some(weights) = self.weights() {
+            npz.add_array("weights", &weights)
+                .map_err(|e| io_error(&e))?;
+        }
+        npz.add_array("cholesky", &self.cholesky)
+            .map_err(|e| io_error(&e))?;
+        npz.add_array("bandwidth_factor", &arr0(self.bandwidth_factor))
+            .map_err(|e| io_error(&e))?;
+        npz.add_array("metadata", &metadata)
+            .map_err(|e| io_error(&e))?;
+        npz.finish().map_err(|e| io_error(&e))?;
+        return Ok(());
+    }
+}
+
+impl<F> CowGaussianKDE<'static, F>
+where
+    F: Float + FloatConst + FromPrimitive + ReadableElement + 'static,
+{
+    /// Read a KDE from the NumPy archive at `path` written by [`GaussianKDE::write_npz`], restoring the kernel
+    /// covariance without fitting. Archives containing only the arrays `data` and (optionally) `weights`, e.g. written
+    /// in Python by `numpy.savez(path, data=data, weights=weights)`, are fitted with the default bandwidth choice
+    /// [`ScottBandwidth`] as by [`GaussianKDE::new`]. The arrays must have the element type `F`.
+    ///
+    /// Since the bandwidth selector is not stored, the restored KDE uses [`ScottBandwidth`] when it is fitted again,
+    /// e.g. after adding points with [`GaussianKDE::push`].
+    ///
+    /// Returns an `IoError` if the file cannot be read, is not a NumPy archive, misses an array or has an unknown
+    /// layout version, a `ShapeError` if the shapes of the arrays do not match, a `DataError` or `WeightError` for
+    /// invalid data or weights as during construction and a `SingularityError` if the stored Cholesky factor has a
+    /// non-positive diagonal.
+    pub fn read_npz(path: impl AsRef<Path>) -> Result<Self, KDEError> {
+        let path = path.as_ref();
+        let io_error = |e: &dyn std::fmt::Display| {
+            KDEError::new(
+                ErrorKind::IoError,
+                format!("failed to read `{}`: {e}", path.display()),
+            )
+        };
+        let mut npz = NpzReader::new(File::open(path).map_err(|e| io_error(&e))?)
+            .map_err(|e| io_error(&e))?;
+        let names = npz.names().map_err(|e| io_error(&e))?;
+        let data: Array2<F> = npz.by_name("data").map_err(|e| io_error(&e))?;
+        let weights: Option<Array1<F>> = if names.iter().any(|name| name == "weights") {
+            Some(npz.by_name("weights").map_err(|e| io_error(&e))?)
+        } else {
+            None
+        };
+        if !names.iter().any(|name| name == "cholesky") {
+            return CowGaussianKDE::new(data, weights);
+        }
+
+        let metadata: Array1<f64> = npz.by_name("metadata").map_err(|e| io_error(&e))?;
+        if metadata.first() != Some(&NPZ_VERSION) {
+            return Err(io_error(&format!(
+                "unsupported layout version {:?}, expected {NPZ_VERSION}",
+                metadata.first()
+            )));
+        }
+        if metadata.len() != 5 {
+            return Err(io_error(&format!(
+                "expected 5 metadata entries, found {}",
+                metadata.len()
+            )));
+        }
+        let covariance_mode = match metadata[1] {
+            0.0 => CovarianceMode::Full,
+            1.0 => CovarianceMode::Diagonal,
+            2.0 => CovarianceMode::Robust,
+            3.0 => CovarianceMode::Shrinkage(metadata[2]),
+            4.0 => CovarianceMode::LedoitWolf,
+            mode => return Err(io_error(&format!("unknown covariance mode {mode}"))),
+        };
+        let cholesky: Array2<F> = npz.by_name("cholesky").map_err(|e| io_error(&e))?;
+        let bandwidth_factor: Array0<F> =
+            npz.by_name("bandwidth_factor").map_err(|e| io_error(&e))?;
+
+        let (n_points, dim) = data.dim();
+        if cholesky.dim() != (dim, dim) || weights.as_ref().is_some_and(|w| w.dim() != n_points) {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected a Cholesky factor of shape `({dim}, {dim})` and {n_points} weights for data array with \
+                     shape `{:?}`, found `{:?}` and {:?}",
+                    data.dim(),
+                    cholesky.dim(),
+                    weights.as_ref().map(|w| w.dim())
+                ),
+            ));
+        }
+        check_finite(data.view())?;
+        if let Some(ref w) = weights {
+            check_weights(w.view())?;
+        }
+        if !cholesky
+            .diag()
+            .iter()
+            .all(|l| *l > F::zero() && l.is_finite())
+        {
+            return Err(KDEError::new(
+                ErrorKind::SingularityError,
+                "the stored Cholesky factor has a non-positive or non-finite diagonal",
+            ));
+        }
+        let inv_cholesky = cholesky_inverse(cholesky.view());
+        let mut kde = Self {
+            data: shared(data),
+            weights: weights.map(shared),
+            dims: None,
+            selected_data: Default::default(),
+            original_dims: None,
+            bandwidth_factor: bandwidth_factor.into_scalar(),
+            cholesky,
+            inv_cholesky,
+            normalization: F::zero(),
+            ln_normalization: F::zero(),
+            covariance_mode,
+            custom_bandwidth_matrix: metadata[3] != 0.0,
+            regularization: F::from(metadata[4]).unwrap(),
+            bandwidth: ScottBandwidth {},
+            moments: None,
+            cache_whitened: true,
+            whitened: Default::default(),
+            tree: Default::default(),
+            #[cfg(feature = "sample")]
+            components: Default::default(),
+        };
+        kde.update_normalization();
+        return Ok(kde);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CovarianceMode, ErrorKind, GaussianKDE};
+    use ndarray::prelude::*;
+    use ndarray_npy::NpzWriter;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    /// Path of a temporary archive, unique for each test.
+    fn temp_path(name: &str) -> PathBuf {
+        return std::env::temp_dir()
+            .join(format!("gaussian_kde_{}_{name}.npz", std::process::id()));
+    }
+
+    #[test]
+    fn npz_roundtrip_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(102);
+        let data = Array2::<f64>::from_shape_simple_fn((300, 3), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(300, || rng.random_range(0.5..1.5));
+        let x = Array2::<f64>::from_shape_simple_fn((20, 3), || rng.sample(StandardNormal));
+        let path = temp_path("roundtrip");
+
+        let kdes = [
+            GaussianKDE::new(data.clone(), None).unwrap(),
+            GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap(),
+            GaussianKDE::builder()
+                .weights(weights.clone())
+                .covariance_mode(CovarianceMode::Shrinkage(0.3))
+                .regularization(1e-6)
+                .build(data.clone())
+                .unwrap(),
+            GaussianKDE::new(data.clone(), None)
+                .unwrap()
+                .marginalize(&[1])
+                .unwrap(),
+        ];
+        for kde in kdes {
+            kde.write_npz(&path).unwrap();
+            let loaded = GaussianKDE::<f64>::read_npz(&path).unwrap();
+            assert!(loaded == kde);
+            assert_eq!(loaded.bandwidth_factor(), kde.bandwidth_factor());
+            assert_eq!(loaded.covariance_mode(), kde.covariance_mode());
+            assert_eq!(loaded.regularization(), kde.regularization());
+            let x = x.slice(s![.., ..kde.data().dim().1]);
+            assert_eq!(loaded.eval_batch(x), kde.eval_batch(x));
+        }
+
+        // The user-given kernel covariance is kept on refits
+        let kde =
+            GaussianKDE::with_bandwidth_matrix(data.clone(), None, Array2::eye(3) * 0.1).unwrap();
+        kde.write_npz(&path).unwrap();
+        let mut loaded = GaussianKDE::<f64>::read_npz(&path).unwrap();
+        assert_eq!(loaded.covariance(), kde.covariance());
+        loaded.push(x.row(0), None).unwrap();
+        assert_eq!(loaded.covariance(), kde.covariance());
+
+        // Single precision
+        let kde = GaussianKDE::new(data.mapv(|x| x as f32), None).unwrap();
+        kde.write_npz(&path).unwrap();
+        let loaded = GaussianKDE::<f32>::read_npz(&path).unwrap();
+        assert!(loaded == kde);
+        let res = GaussianKDE::<f64>::read_npz(&path);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IoError)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn npz_python_layout_test() {
+        // Archive with the layout of `numpy.savez(path, data=data, weights=weights)`
+        let mut rng = Pcg64Mcg::seed_from_u64(102);
+        let data = Array2::<f64>::from_shape_simple_fn((100, 2), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(100, || rng.random_range(0.5..1.5));
+        let path = temp_path("python");
+        let mut npz = NpzWriter::new(File::create(&path).unwrap());
+        npz.add_array("data", &data).unwrap();
+        npz.add_array("weights", &weights).unwrap();
+        npz.finish().unwrap();
+        let loaded = GaussianKDE::<f64>::read_npz(&path).unwrap();
+        assert!(loaded == GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap());
+
+        // Compressed archive with the dataset only, as written by `numpy.savez_compressed(path, data=data)`
+        let mut npz = NpzWriter::new_compressed(File::create(&path).unwrap());
+        npz.add_array("data", &data).unwrap();
+        npz.finish().unwrap();
+        let loaded = GaussianKDE::<f64>::read_npz(&path).unwrap();
+        assert!(loaded == GaussianKDE::new(data.clone(), None).unwrap());
+
+        // Unknown layout versions, missing arrays and mismatching shapes are reported
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let mut npz = NpzWriter::new(File::create(&path).unwrap());
+        npz.add_array("data", &data).unwrap();
+        npz.add_array("cholesky", &kde.cholesky()).unwrap();
+        npz.add_array("bandwidth_factor", &arr0(kde.bandwidth_factor()))
+            .unwrap();
+        npz.add_array("metadata", &array![2.0, 0.0, 0.0, 0.0, 0.0])
+            .unwrap();
+        npz.finish().unwrap();
+        let res = GaussianKDE::<f64>::read_npz(&path);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IoError)));
+
+        let mut npz = NpzWriter::new(File::create(&path).unwrap());
+        npz.add_array("data", &data.slice(s![.., ..1])).unwrap();
+        npz.add_array("cholesky", &kde.cholesky()).unwrap();
+        npz.add_array("bandwidth_factor", &arr0(kde.bandwidth_factor()))
+            .unwrap();
+        npz.add_array("metadata", &array![1.0, 0.0, 0.0, 0.0, 0.0])
+            .unwrap();
+        npz.finish().unwrap();
+        let res = GaussianKDE::<f64>::read_npz(&path);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+
+        let mut npz = NpzWriter::new(File::create(&path).unwrap());
+        npz.add_array("weights", &weights).unwrap();
+        npz.finish().unwrap();
+        let res = GaussianKDE::<f64>::read_npz(&path);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IoError)));
+        std::fs::remove_file(&path).unwrap();
+
+        let res = GaussianKDE::<f64>::read_npz(&path);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IoError)));
+    }
+}
@@ -0,0 +1,24 @@
+//! Lazily initialized values for `no_std` builds, replacing `std::sync::OnceLock` for the caches of the KDE.
+
+use alloc::boxed::Box;
+use once_cell::race::OnceBox;
+
+/// Subset of the interface of `std::sync::OnceLock` used by the crate, backed by a [`OnceBox`]. If several threads
+/// initialize the value concurrently, all of them compute it, but only one result is kept.
+pub(crate) struct OnceLock<T>(OnceBox<T>);
+
+impl<T> OnceLock<T> {
+    pub(crate) fn get(&self) -> Option<&T> {
+        return self.0.get();
+    }
+
+    pub(crate) fn get_or_init(&self, f: impl FnOnce() -> T) -> &T {
+        return self.0.get_or_init(|| Box::new(f()));
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        return Self(OnceBox::new());
+    }
+}
@@ -0,0 +1,407 @@
+//! Python bindings of the KDE via [`pyo3`](https://docs.rs/pyo3) and [`numpy`](https://docs.rs/numpy), mirroring
+//! the interface of `scipy.stats.gaussian_kde`, see [`PyGaussianKDE`].
+
+use crate::covariance::effective_sample_size;
+use crate::{Bandwidth, ErrorKind, GaussianKDE, KDEError, ScottBandwidth, SilvermanBandwidth};
+use ndarray::prelude::*;
+use numpy::{
+    AllowTypeChange, IntoPyArray, PyArray1, PyArray2, PyArrayLikeDyn, PyUntypedArrayMethods,
+};
+use pyo3::exceptions::{PyIOError, PyIndexError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+pyo3::import_exception!(numpy.linalg, LinAlgError);
+
+/// Errors are raised as `IndexError` for invalid indices, `numpy.linalg.LinAlgError` for singular covariance
+/// matrices (as by `scipy.stats.gaussian_kde`), `IOError` for failed file operations and `ValueError` otherwise, with
+/// the message of the [`KDEError`].
+impl From<KDEError> for PyErr {
+    fn from(e: KDEError) -> Self {
+        return match e.kind {
+            ErrorKind::IndexError => PyIndexError::new_err(e.message),
+            ErrorKind::SingularityError => LinAlgError::new_err(e.message),
+            ErrorKind::IoError => PyIOError::new_err(e.message),
+            _ => PyValueError::new_err(e.message),
+        };
+    }
+}
+
+/// Bandwidth selector given by the `bw_method` argument of `scipy.stats.gaussian_kde`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BwMethod {
+    Scott,
+    Silverman,
+    Factor(f64),
+}
+
+impl BwMethod {
+    /// Parse the name of a bandwidth rule, `"scott"` or `"silverman"`.
+    fn from_name(name: &str) -> Result<Self, KDEError> {
+        return match name {
+            "scott" => Ok(BwMethod::Scott),
+            "silverman" => Ok(BwMethod::Silverman),
+            _ => Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!("`bw_method` should be 'scott', 'silverman' or a scalar, found '{name}'"),
+            )),
+        };
+    }
+
+    /// Extract the bandwidth selector from `None`, a rule name or a scalar factor.
+    fn extract(bw_method: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        let Some(bw_method) = bw_method.filter(|b| !b.is_none()) else {
+            return Ok(BwMethod::Scott);
+        };
+        if let Ok(name) = bw_method.extract::<String>() {
+            return Ok(Self::from_name(&name)?);
+        }
+        if let Ok(factor) = bw_method.extract::<f64>() {
+            return Ok(BwMethod::Factor(factor));
+        }
+        return Err(PyTypeError::new_err(
+            "`bw_method` should be 'scott', 'silverman' or a scalar, callables are not supported",
+        ));
+    }
+}
+
+impl Bandwidth<f64> for BwMethod {
+    fn bandwidth(&self, data: ArrayView2<f64>, weights: Option<ArrayView1<f64>>) -> f64 {
+        return match self {
+            BwMethod::Scott => ScottBandwidth {}.bandwidth(data, weights),
+            BwMethod::Silverman => SilvermanBandwidth {}.bandwidth(data, weights),
+            BwMethod::Factor(factor) => *factor,
+        };
+    }
+}
+
+/// View a dataset in the layout `(dim, n_points)` of `scipy.stats.gaussian_kde`, or a one-dimensional dataset of
+/// shape `(n_points,)`, as array of shape `(n_points, dim)`.
+fn dataset_view(dataset: ArrayViewD<'_, f64>) -> Result<ArrayView2<'_, f64>, KDEError> {
+    return match dataset.ndim() {
+        1 => Ok(dataset
+            .into_dimensionality::<Ix1>()
+            .unwrap()
+            .insert_axis(Axis(1))),
+        2 => Ok(dataset
+            .into_dimensionality::<Ix2>()
+            .unwrap()
+            .reversed_axes()),
+        n => Err(KDEError::new(
+            ErrorKind::ShapeError,
+            format!("expected a dataset with one or two dimensions, found {n}"),
+        )),
+    };
+}
+
+/// View the points in the layout `(dim, n_points)` as array of shape `(n_points, dim)`, following the conventions of
+/// `scipy.stats.gaussian_kde.evaluate`: scalars and one-dimensional arrays are promoted to a single row, and a single
+/// row of length `dim` is a single point.
+fn points_view(points: ArrayViewD<'_, f64>, dim: usize) -> Result<ArrayView2<'_, f64>, KDEError> {
+    let points = match points.ndim() {
+        0 | 1 => {
+            let n = points.len();
+            points.into_shape_with_order((1, n)).unwrap()
+        }
+        2 => points.into_dimensionality::<Ix2>().unwrap(),
+        n => {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!("expected points with at most two dimensions, found {n}"),
+            ));
+        }
+    };
+    if points.nrows() == dim {
+        return Ok(points.reversed_axes());
+    }
+    if points.nrows() == 1 && points.ncols() == dim {
+        return Ok(points);
+    }
+    return Err(KDEError::new(
+        ErrorKind::ShapeError,
+        format!(
+            "points have dimension {}, dataset has dimension {dim}",
+            points.nrows()
+        ),
+    ));
+}
+
+/// Resolve the possibly negative indices of the components kept by `scipy.stats.gaussian_kde.marginalize`.
+fn marginal_dims(dimensions: &[isize], dim: usize) -> Result<Vec<usize>, KDEError> {
+    return dimensions
+        .iter()
+        .map(|&i| {
+            let j = if i < 0 { i + dim as isize } else { i };
+            if j < 0 || j >= dim as isize {
+                return Err(KDEError::new(
+                    ErrorKind::IndexError,
+                    format!("index `{i}` out of bounds for data of dimension `{dim}`"),
+                ));
+            }
+            return Ok(j as usize);
+        })
+        .collect();
+}
+
+/// Gaussian KDE in double precision for Python, a drop-in for `scipy.stats.gaussian_kde` exposed as
+/// `gaussian_kde`. Datasets and points follow the layout `(dim, n_points)` of scipy, i.e. each column is a point.
+///
+/// NumPy arrays of `float64` are read in place, other array-likes are converted by NumPy. The dataset is copied once
+/// into the KDE, such that later changes of the array do not affect it, while the points are evaluated without
+/// copies and with the GIL released. The results are handed to NumPy without copies.
+///
+/// The class is not registered in a Python module by the crate. An extension module adds it with
+/// `module.add_class::<PyGaussianKDE>()`, see `examples/python_module.rs`. Python extensions should enable the
+/// `extension-module` feature of `pyo3` when they are built.
+#[pyclass(name = "gaussian_kde", module = "gaussian_kde", frozen)]
+pub struct PyGaussianKDE {
+    kde: GaussianKDE<f64, BwMethod>,
+}
+
+#[pymethods]
+impl PyGaussianKDE {
+    /// Fit the KDE to the columns of `dataset` with the bandwidth factor given by `bw_method`, which is `'scott'`
+    /// (the default), `'silverman'` or a scalar, and the optional `weights`.
+    #[new]
+    #[pyo3(signature = (dataset, bw_method=None, weights=None))]
+    fn new(
+        dataset: PyArrayLikeDyn<'_, f64, AllowTypeChange>,
+        bw_method: Option<&Bound<'_, PyAny>>,
+        weights: Option<PyArrayLikeDyn<'_, f64, AllowTypeChange>>,
+    ) -> PyResult<Self> {
+        let bandwidth = BwMethod::extract(bw_method)?;
+        let data = dataset_view(dataset.as_array())?
+            .as_standard_layout()
+            .into_owned();
+        let weights = match weights {
+            Some(ref w) if w.ndim() != 1 => {
+                return Err(PyValueError::new_err("`weights` should be one-dimensional"));
+            }
+            Some(ref w) => Some(
+                w.as_array()
+                    .into_dimensionality::<Ix1>()
+                    .unwrap()
+                    .to_owned(),
+            ),
+            None => None,
+        };
+        let kde = GaussianKDE::with_bandwidth_selector(data, weights, bandwidth)?;
+        return Ok(Self { kde });
+    }
+
+    /// Evaluate the estimated density at the columns of `points`.
+    fn evaluate<'py>(
+        &self,
+        py: Python<'py>,
+        points: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        let x = points_view(points.as_array(), self.kde.n_dims())?;
+        let res = py.detach(|| self.kde.try_eval_batch(x))?;
+        return Ok(res.into_pyarray(py));
+    }
+
+    /// Evaluate the estimated density at the columns of `points`, same as `evaluate`.
+    fn pdf<'py>(
+        &self,
+        py: Python<'py>,
+        points: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        return self.evaluate(py, points);
+    }
+
+    /// Evaluate the estimated density at the columns of `points`, same as `evaluate`.
+    fn __call__<'py>(
+        &self,
+        py: Python<'py>,
+        points: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        return self.evaluate(py, points);
+    }
+
+    /// Evaluate the logarithm of the estimated density at the columns of `points`, which is accurate far in the
+    /// tails.
+    fn logpdf<'py>(
+        &self,
+        py: Python<'py>,
+        points: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        let x = points_view(points.as_array(), self.kde.n_dims())?;
+        let res = py.detach(|| self.kde.ln_eval_batch(x));
+        return Ok(res.into_pyarray(py));
+    }
+
+    /// Draw `size` points (by default the number of data points) from the estimated density as the columns of an
+    /// array of shape `(dim, size)`. With an integer `seed`, the result is reproducible.
+    #[pyo3(signature = (size=None, seed=None))]
+    fn resample<'py>(
+        &self,
+        py: Python<'py>,
+        size: Option<usize>,
+        seed: Option<u64>,
+    ) -> Bound<'py, PyArray2<f64>> {
+        let size = size.unwrap_or(self.kde.n_points());
+        let res = py.detach(|| match seed {
+            Some(seed) => self.kde.sample_batch_seeded(size, seed),
+            None => self.kde.sample_batch(size),
+        });
+        return res.reversed_axes().into_pyarray(py);
+    }
+
+    /// Integrate the estimated density of a one-dimensional KDE over the interval `[low, high]`.
+    fn integrate_box_1d(&self, low: f64, high: f64) -> PyResult<f64> {
+        return Ok(self.kde.integrate_box_1d(low, high)?);
+    }
+
+    /// Marginal KDE of the components given by the (possibly negative) indices in `dimensions`, an integer or a
+    /// sequence of integers.
+    fn marginalize(&self, dimensions: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let dimensions = match dimensions.extract::<isize>() {
+            Ok(i) => vec![i],
+            Err(_) => dimensions.extract::<Vec<isize>>()?,
+        };
+        let dims = marginal_dims(&dimensions, self.kde.n_dims())?;
+        return Ok(Self {
+            kde: self.kde.marginalize_to(&dims)?,
+        });
+    }
+
+    /// Dataset of shape `(d, n)`.
+    #[getter]
+    fn dataset<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        return self.kde.data().t().to_owned().into_pyarray(py);
+    }
+
+    /// Weights of the data points normalized to sum to one.
+    #[getter]
+    fn weights<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        let n = self.kde.n_points();
+        let weights = match self.kde.weights() {
+            Some(w) => &w / w.sum(),
+            None => Array1::from_elem(n, 1.0 / n as f64),
+        };
+        return weights.into_pyarray(py);
+    }
+
+    /// Dimension of the dataset.
+    #[getter]
+    fn d(&self) -> usize {
+        return self.kde.n_dims();
+    }
+
+    /// Number of data points.
+    #[getter]
+    fn n(&self) -> usize {
+        return self.kde.n_points();
+    }
+
+    /// Effective number of data points.
+    #[getter]
+    fn neff(&self) -> f64 {
+        return match self.kde.weights() {
+            Some(w) => effective_sample_size(w),
+            None => self.kde.n_points() as f64,
+        };
+    }
+
+    /// Bandwidth factor by which the covariance matrix of the dataset is scaled.
+    #[getter]
+    fn factor(&self) -> f64 {
+        return self.kde.bandwidth_factor();
+    }
+
+    /// Bandwidth factor by which the covariance matrix of the dataset is scaled, same as `factor`.
+    fn covariance_factor(&self) -> f64 {
+        return self.kde.bandwidth_factor();
+    }
+
+    /// Covariance matrix of the kernels of shape `(d, d)`.
+    #[getter]
+    fn covariance<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        return self.kde.covariance().into_pyarray(py);
+    }
+
+    fn __repr__(&self) -> String {
+        return format!("{}", self.kde);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn python_conversion_test() {
+        // Datasets in the layout of scipy
+        let dataset = array![[0.1, 0.4, 0.2, 0.9], [1.0, -1.0, 0.5, 0.0]];
+        let data = dataset_view(dataset.view().into_dyn()).unwrap();
+        assert_eq!(data, dataset.t());
+        let flat = array![0.1, 0.4, 0.2, 0.9];
+        let data = dataset_view(flat.view().into_dyn()).unwrap();
+        assert_eq!(data.dim(), (4, 1));
+        let cube = Array3::<f64>::zeros((2, 2, 2));
+        let res = dataset_view(cube.view().into_dyn());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+
+        // Points in the layout of scipy, including the special cases of a single point
+        let points = array![[0.0, 1.0, 2.0], [3.0, 4.0, 5.0]];
+        assert_eq!(
+            points_view(points.view().into_dyn(), 2).unwrap(),
+            points.t()
+        );
+        let point = array![0.5, 1.5];
+        let x = points_view(point.view().into_dyn(), 2).unwrap();
+        assert_eq!(x, array![[0.5, 1.5]]);
+        let x = points_view(point.view().insert_axis(Axis(0)).into_dyn(), 2).unwrap();
+        assert_eq!(x, array![[0.5, 1.5]]);
+        let x = points_view(point.view().into_dyn(), 1).unwrap();
+        assert_eq!(x, array![[0.5], [1.5]]);
+        let scalar = arr0(0.5);
+        let x = points_view(scalar.view().into_dyn(), 1).unwrap();
+        assert_eq!(x, array![[0.5]]);
+        let res = points_view(points.view().into_dyn(), 3);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = points_view(point.view().into_dyn(), 3);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+
+        // Indices of the marginal components
+        assert_eq!(marginal_dims(&[0, -1], 3).unwrap(), vec![0, 2]);
+        let res = marginal_dims(&[3], 3);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+        let res = marginal_dims(&[-4], 3);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+    }
+
+    #[test]
+    fn python_bandwidth_test() {
+        assert_eq!(BwMethod::from_name("scott").unwrap(), BwMethod::Scott);
+        assert_eq!(
+            BwMethod::from_name("silverman").unwrap(),
+            BwMethod::Silverman
+        );
+        let res = BwMethod::from_name("isj");
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+
+        // The selectors agree with the ones of the crate, as in scipy
+        let dataset = array![[0.1, 0.4, 0.2, 0.9, 0.3], [1.0, -1.0, 0.5, 0.0, 0.2]];
+        let data = dataset_view(dataset.view().into_dyn()).unwrap().to_owned();
+        let weights = array![1.0, 2.0, 0.5, 1.0, 1.5];
+        for (method, expected) in [
+            (
+                BwMethod::Scott,
+                ScottBandwidth {}.bandwidth(data.view(), Some(weights.view())),
+            ),
+            (
+                BwMethod::Silverman,
+                SilvermanBandwidth {}.bandwidth(data.view(), Some(weights.view())),
+            ),
+            (BwMethod::Factor(0.3), 0.3),
+        ] {
+            let kde =
+                GaussianKDE::with_bandwidth_selector(data.clone(), Some(weights.clone()), method)
+                    .unwrap();
+            assert_eq!(kde.bandwidth_factor(), expected);
+        }
+        assert!(
+            (BwMethod::Scott.bandwidth(data.view(), None) - 5f64.powf(-1.0 / 6.0)).abs() < 1e-15
+        );
+    }
+}
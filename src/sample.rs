@@ -1,98 +1,488 @@
+#[cfg(doc)]
 use crate::GaussianKDE;
-use ndarray::prelude::*;
+use crate::{Bandwidth, CovarianceMode, CowGaussianKDE, ErrorKind, KDEError};
+use ndarray::{Zip, prelude::*};
 use num_traits::{Float, FloatConst, FromPrimitive};
 use rand::prelude::*;
 use rand_distr::{
     StandardNormal, Uniform,
     uniform::SampleUniform,
-    weighted::{Weight, WeightedIndex},
+    weighted::{Weight, WeightedAliasIndex},
 };
 use rand_pcg::Pcg64Mcg;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
-impl<F> GaussianKDE<F>
+/// Selection of the kernel to draw from when sampling. For weighted data, an alias table over the data points with
+/// non-zero weight is used, which requires $\mathcal{O}(n)$ setup and $\mathcal{O}(1)$ operations per draw. Points
+/// with zero weight are excluded from the table, such that they are never selected.
+pub(crate) enum ComponentSampler {
+    Uniform(Uniform<usize>),
+    Alias {
+        table: WeightedAliasIndex<f64>,
+        indices: Vec<usize>,
+    },
+}
+
+impl Distribution<usize> for ComponentSampler {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        return match self {
+            ComponentSampler::Uniform(uniform) => uniform.sample(rng),
+            ComponentSampler::Alias { table, indices } => indices[table.sample(rng)],
+        };
+    }
+}
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Get the sampler choosing the kernel (i.e. data point) to draw from according to the weights, which is
+    /// constructed on first use and cached afterwards. The weights are validated during construction, such that at
+    /// least one of them is positive.
+    pub(crate) fn component_sampler(&self) -> &ComponentSampler {
+        return self.components.get_or_init(|| {
+            let Some(ref w) = self.weights else {
+                return ComponentSampler::Uniform(Uniform::new(0, self.n_points()).unwrap());
+            };
+            // Rescale by the largest weight, such that the sum of weights in the alias table cannot overflow
+            let max = w.fold(F::zero(), |acc, w| acc.max(*w));
+            let (indices, weights): (Vec<usize>, Vec<f64>) = w
+                .indexed_iter()
+                .filter(|(_, w)| **w > F::zero())
+                .map(|(i, w)| (i, (*w / max).to_f64().unwrap()))
+                .unzip();
+            let table = WeightedAliasIndex::new(weights).expect("the sum of weights is positive");
+            return ComponentSampler::Alias { table, indices };
+        });
+    }
+
+    /// Add the kernel offset $Lz$, i.e. the standard normal vector `z` transformed to a draw from a kernel centered at
+    /// the origin, to `out` in place, exploiting the lower-triangular (or diagonal) structure of $L$ to avoid any
+    /// allocation.
+    pub(crate) fn add_kernel_offset(&self, z: ArrayView1<F>, mut out: ArrayViewMut1<F>) {
+        if self.covariance_mode == CovarianceMode::Diagonal {
+            for i in 0..z.dim() {
+                out[i] = out[i] + self.cholesky[[i, i]] * z[i];
+            }
+            return;
+        }
+        for i in 0..z.dim() {
+            let mut offset = F::zero();
+            for k in 0..=i {
+                offset = offset + self.cholesky[[i, k]] * z[k];
+            }
+            out[i] = out[i] + offset;
+        }
+    }
+}
+
+impl<'a, F, B> CowGaussianKDE<'a, F, B>
 where
+    B: Bandwidth<F>,
     F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + 'static,
     StandardNormal: Distribution<F>,
 {
     /// Sample a random point from the probability density estimated by the KDE.
     ///
-    /// This function uses an operating system source to seed the RNG.
+    /// This function uses an operating system source to seed the RNG, which requires the `os-rng` feature.
+    #[cfg(feature = "os-rng")]
     pub fn sample(&self) -> Array1<F> {
-        let mut rng = Pcg64Mcg::from_os_rng();
-        let i = if let Some(ref w) = self.weights {
-            let choice = WeightedIndex::new(w.iter()).unwrap();
-            choice.sample(&mut rng)
-        } else {
-            Uniform::new(0, self.data.dim().0).unwrap().sample(&mut rng)
-        };
-        let tmp = Array1::from_shape_simple_fn(self.data.dim().1, || rng.sample(StandardNormal));
-        return &self.data.index_axis(Axis(0), i) + &self.cholesky.dot(&tmp);
+        return self.sample_with_rng(&mut Pcg64Mcg::from_os_rng());
     }
 
     /// Sample a random point from the probability density estimated by the KDE using a given RNG.
     pub fn sample_with_rng(&self, rng: &mut impl Rng) -> Array1<F> {
-        let i = if let Some(ref w) = self.weights {
-            let choice = WeightedIndex::new(w.iter()).unwrap();
-            choice.sample(rng)
-        } else {
-            Uniform::new(0, self.data.dim().0).unwrap().sample(rng)
-        };
-        let tmp = Array1::from_shape_simple_fn(self.data.dim().1, || rng.sample(StandardNormal));
-        return &self.data.index_axis(Axis(0), i) + &self.cholesky.dot(&tmp);
+        return Distribution::sample(self, rng);
+    }
+
+    /// Sample a random point from the probability density estimated by the KDE, using an RNG seeded with `seed`.
+    ///
+    /// The RNG is a [`Pcg64Mcg`] constructed by [`SeedableRng::seed_from_u64`], which expands the seed into the
+    /// 128-bit state with a PCG32 generator. The result is identical to the one of
+    /// [`GaussianKDE::sample_with_rng`] with this RNG and only depends on `seed` and the fitted KDE, independent of
+    /// the platform. Changes of the sampling algorithm (and hence of the output for a given seed) are considered
+    /// breaking.
+    pub fn sample_seeded(&self, seed: u64) -> Array1<F> {
+        return self.sample_with_rng(&mut Pcg64Mcg::seed_from_u64(seed));
+    }
+
+    /// Sample `n` random points from the probability density estimated by the KDE, using an RNG seeded with `seed`.
+    /// See [`GaussianKDE::sample_seeded`] for the construction of the RNG.
+    pub fn sample_batch_seeded(&self, n: usize, seed: u64) -> Array2<F> {
+        return self.sample_batch_with_rng(n, &mut Pcg64Mcg::seed_from_u64(seed));
     }
 
     /// Sample `n` random point from the probability density estimated by the KDE.
     ///
-    /// This function uses an operating system source to seed the RNG.
+    /// This function uses an operating system source to seed the RNG, which requires the `os-rng` feature.
+    #[cfg(feature = "os-rng")]
     pub fn sample_batch(&self, n: usize) -> Array2<F> {
-        let mut rng = Pcg64Mcg::from_os_rng();
-        let mut res =
-            Array2::from_shape_simple_fn((n, self.data.dim().1), || rng.sample(StandardNormal));
-        if let Some(ref w) = self.weights {
-            let choice = WeightedIndex::new(w.iter()).unwrap();
-            let mut tmp;
-            for i in 0..n {
-                let k = choice.sample(&mut rng);
-                tmp = &self.data.index_axis(Axis(0), k)
-                    + &self.cholesky.dot(&res.index_axis(Axis(0), i));
-                res.index_axis_mut(Axis(0), i).assign(&tmp);
-            }
-        } else {
-            let uniform = Uniform::new(0, self.data.dim().0).unwrap();
-            let mut tmp;
-            for i in 0..n {
-                let k = uniform.sample(&mut rng);
-                tmp = &self.data.index_axis(Axis(0), k)
-                    + &self.cholesky.dot(&res.index_axis(Axis(0), i));
-                res.index_axis_mut(Axis(0), i).assign(&tmp);
+        return self.sample_batch_with_rng(n, &mut Pcg64Mcg::from_os_rng());
+    }
+
+    /// Sample `n` random point from the probability density estimated by the KDE using a given RNG.
+    pub fn sample_batch_with_rng(&self, n: usize, rng: &mut impl Rng) -> Array2<F> {
+        let mut res = Array2::zeros((n, self.n_dims()));
+        self.sample_batch_into(res.view_mut(), rng).unwrap();
+        return res;
+    }
+
+    /// Fill the rows of `out` with random points from the probability density estimated by the KDE using a given
+    /// RNG, e.g. to reuse a buffer when sampling repeatedly. For the same RNG state, the result is identical to the
+    /// one of [`GaussianKDE::sample_batch_with_rng`].
+    ///
+    /// Returns a `ShapeError` if the second dimension of `out` does not match the dimension of the KDE dataset.
+    pub fn sample_batch_into(
+        &self,
+        mut out: ArrayViewMut2<F>,
+        rng: &mut impl Rng,
+    ) -> Result<(), KDEError> {
+        let dim = self.n_dims();
+        if out.dim().1 != dim {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected output buffer with {dim} columns, found shape `{:?}`",
+                    out.dim()
+                ),
+            ));
+        }
+        // The standard normal vector of each point is generated into a scratch buffer, such that each row of `out`
+        // is written only once
+        let mut samples = self.samples(rng);
+        for row in out.rows_mut() {
+            samples.fill(row);
+        }
+        return Ok(());
+    }
+
+    /// Sample a random point from the probability density estimated by the KDE using a given RNG and return it
+    /// together with the index of the data point whose kernel it was drawn from. For the same RNG state, the point is
+    /// identical to the one of [`GaussianKDE::sample_with_rng`].
+    pub fn sample_indexed(&self, rng: &mut impl Rng) -> (Array1<F>, usize) {
+        let mut res = Array1::zeros(self.n_dims());
+        let k = self.samples(rng).fill(res.view_mut());
+        return (res, k);
+    }
+
+    /// Sample `n` random points from the probability density estimated by the KDE using a given RNG and return them
+    /// together with the index of the data point whose kernel each row was drawn from. For the same RNG state, the
+    /// points are identical to the ones of [`GaussianKDE::sample_batch_with_rng`].
+    pub fn sample_batch_indexed(&self, n: usize, rng: &mut impl Rng) -> (Array2<F>, Vec<usize>) {
+        let mut res = Array2::zeros((n, self.n_dims()));
+        let mut samples = self.samples(rng);
+        let indices = res
+            .rows_mut()
+            .into_iter()
+            .map(|row| samples.fill(row))
+            .collect();
+        return (res, indices);
+    }
+
+    /// Sample `n` random points from the probability density estimated by the KDE using a given RNG, selecting the
+    /// kernels by systematic resampling instead of independent draws.
+    ///
+    /// Systematic (low-variance) resampling places `n` equidistant thresholds with a common random offset on the
+    /// cumulative distribution of the normalized weights, such that each data point $x_i$ is selected
+    /// $\lfloor n w_i / \sum_j w_j \rfloor$ or $\lceil n w_i / \sum_j w_j \rceil$ times. For unweighted data, all points are
+    /// thus repeated equally often up to one. The selected kernels are shuffled before the Gaussian offsets are
+    /// added, such that the rows of the result are exchangeable as for [`GaussianKDE::sample_batch_with_rng`], but
+    /// not independent.
+    pub fn sample_batch_systematic(&self, n: usize, rng: &mut impl Rng) -> Array2<F> {
+        let dim = self.n_dims();
+        let mut res = Array2::zeros((n, dim));
+        let mut z = Array1::zeros(dim);
+        for (k, mut row) in self
+            .systematic_components(n, rng)
+            .into_iter()
+            .zip(res.rows_mut())
+        {
+            z.mapv_inplace(|_| rng.sample(StandardNormal));
+            self.assign_data_point(k, row.view_mut());
+            self.add_kernel_offset(z.view(), row);
+        }
+        return res;
+    }
+
+    /// Select `n` kernels by systematic resampling over the cumulative distribution of the weights and return their
+    /// indices in random order.
+    fn systematic_components(&self, n: usize, rng: &mut impl Rng) -> Vec<usize> {
+        let n_data = self.n_points();
+        let total = self
+            .weights
+            .as_ref()
+            .map_or(F::from_usize(n_data).unwrap(), |w| w.sum());
+        let step = F::one() / F::from_usize(n).unwrap();
+        let mut threshold = step * rng.random_range(F::zero()..F::one());
+        let mut cumulative = F::zero();
+        let mut components = Vec::with_capacity(n);
+        for i in 0..n_data {
+            let w = self.weights.as_ref().map_or(F::one(), |w| w[i]);
+            cumulative = cumulative + w / total;
+            while components.len() < n && threshold < cumulative {
+                components.push(i);
+                threshold = threshold + step;
             }
         }
+        // Thresholds missed due to rounding of the cumulative weights belong to the last point with non-zero weight
+        let last = components.last().copied().unwrap_or(n_data - 1);
+        components.resize(n, last);
+        components.shuffle(rng);
+        return components;
+    }
+
+    /// Sample `n_pairs` antithetic pairs of random points from the probability density estimated by the KDE using a
+    /// given RNG, e.g. to reduce the variance of Monte Carlo estimates of expectations of monotone functions.
+    ///
+    /// Returns an array of shape `(2 * n_pairs, dim)`, where the rows `2 * i` and `2 * i + 1` form a pair: both are
+    /// drawn from the same kernel $i_k$, with the offsets $Lz$ and $-Lz$ from its center $x_{i_k}$ for a common
+    /// standard normal vector $z$. Each row on its own is distributed according to the estimated density, but the
+    /// two points of a pair are (negatively) correlated. Estimates should therefore average over each pair first,
+    /// and their variance must be estimated from the `n_pairs` pair averages rather than from the individual rows.
+    pub fn sample_batch_antithetic(&self, n_pairs: usize, rng: &mut impl Rng) -> Array2<F> {
+        let dim = self.n_dims();
+        let components = self.component_sampler();
+        let mut res = Array2::zeros((2 * n_pairs, dim));
+        let mut z = Array1::zeros(dim);
+        let mut offset = Array1::zeros(dim);
+        let mut center = Array1::zeros(dim);
+        for mut pair in res.axis_chunks_iter_mut(Axis(0), 2) {
+            let k = components.sample(rng);
+            z.mapv_inplace(|_| rng.sample(StandardNormal));
+            offset.fill(F::zero());
+            self.add_kernel_offset(z.view(), offset.view_mut());
+            self.assign_data_point(k, center.view_mut());
+            pair.row_mut(0).assign(&(&center + &offset));
+            pair.row_mut(1).assign(&(&center - &offset));
+        }
         return res;
     }
 
-    /// Sample `n` random point from the probability density estimated by the KDE using a given RNG.
-    pub fn sample_batch_with_rng(&self, n: usize, rng: &mut impl Rng) -> Array2<F> {
-        let mut res =
-            Array2::from_shape_simple_fn((n, self.data.dim().1), || rng.sample(StandardNormal));
-        if let Some(ref w) = self.weights {
-            let choice = WeightedIndex::new(w.iter()).unwrap();
-            let mut tmp;
-            for i in 0..n {
-                let k = choice.sample(rng);
-                tmp = &self.data.index_axis(Axis(0), k)
-                    + &self.cholesky.dot(&res.index_axis(Axis(0), i));
-                res.index_axis_mut(Axis(0), i).assign(&tmp);
+    /// Sample `n` random points from the probability density estimated by the KDE truncated to the box
+    /// $[\mathrm{low}_1, \mathrm{high}_1] \times \dots \times [\mathrm{low}_d, \mathrm{high}_d]$ using a given
+    /// RNG, i.e. from the density renormalized to the box. Infinite bounds are allowed.
+    ///
+    /// The points are obtained by rejection sampling, drawing at most `max_attempts` points in total. Returns a
+    /// `ShapeError` if the lengths of `low` or `high` do not match the dimension of the KDE dataset, a `DomainError`
+    /// if `low` exceeds `high` in any dimension and a `SamplingError` (mentioning the observed acceptance rate) if
+    /// fewer than `n` points inside the box were drawn within `max_attempts` attempts.
+    pub fn sample_batch_truncated(
+        &self,
+        n: usize,
+        low: ArrayView1<F>,
+        high: ArrayView1<F>,
+        max_attempts: usize,
+        rng: &mut impl Rng,
+    ) -> Result<Array2<F>, KDEError> {
+        let dim = self.n_dims();
+        if low.dim() != dim || high.dim() != dim {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected bounds of shape `({dim},)`, found `({},)` and `({},)`",
+                    low.dim(),
+                    high.dim()
+                ),
+            ));
+        }
+        if Zip::from(&low)
+            .and(&high)
+            .any(|l, h| l.partial_cmp(h).is_none_or(|o| o.is_gt()))
+        {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                "the lower bounds of the box must not exceed the upper bounds",
+            ));
+        }
+        let mut res = Array2::zeros((n, dim));
+        let mut samples = self.samples(rng);
+        let mut accepted = 0;
+        let mut attempts = 0;
+        while accepted < n {
+            if attempts == max_attempts {
+                return Err(KDEError::new(
+                    ErrorKind::SamplingError,
+                    format!(
+                        "only {accepted} of {attempts} draws lie inside the box (acceptance rate {:.3e}), \
+                        {n} points were requested",
+                        accepted as f64 / attempts as f64
+                    ),
+                ));
             }
-        } else {
-            let uniform = Uniform::new(0, self.data.dim().0).unwrap();
-            let mut tmp;
-            for i in 0..n {
-                let k = uniform.sample(rng);
-                tmp = &self.data.index_axis(Axis(0), k)
-                    + &self.cholesky.dot(&res.index_axis(Axis(0), i));
-                res.index_axis_mut(Axis(0), i).assign(&tmp);
+            let mut row = res.index_axis_mut(Axis(0), accepted);
+            samples.fill(row.view_mut());
+            attempts += 1;
+            if Zip::from(&row)
+                .and(&low)
+                .and(&high)
+                .all(|x, l, h| *x >= *l && *x <= *h)
+            {
+                accepted += 1;
             }
         }
+        return Ok(res);
+    }
+
+    /// Convert the KDE into an (approximately) equivalent unweighted KDE by fitting a new KDE with equal weights to
+    /// `n` points drawn from the estimated density using a given RNG.
+    ///
+    /// The new KDE uses the same bandwidth selector and covariance mode, or the same bandwidth matrix if the kernel
+    /// covariance was given explicitly. Note that the drawn points include the kernel smearing, such that their
+    /// covariance is increased by the kernel covariance $H$; see [`GaussianKDE::resample_points`] for a variant without.
+    pub fn resample_unweighted(&self, n: usize, rng: &mut impl Rng) -> Result<Self, KDEError>
+    where
+        B: Clone,
+    {
+        return self.refit(self.sample_batch_with_rng(n, rng), None);
+    }
+
+    /// Convert the KDE into an (approximately) equivalent unweighted KDE by fitting a new KDE with equal weights to
+    /// `n` data points drawn with replacement proportionally to their weights using a given RNG.
+    ///
+    /// In contrast to [`GaussianKDE::resample_unweighted`], the data points are not smeared by the kernels, which is
+    /// cheaper and preserves the moments of the weighted dataset. The new KDE uses the same bandwidth selector and
+    /// covariance mode, or the same bandwidth matrix if the kernel covariance was given explicitly.
+    pub fn resample_points(&self, n: usize, rng: &mut impl Rng) -> Result<Self, KDEError>
+    where
+        B: Clone,
+    {
+        let components = self.component_sampler();
+        let indices = (0..n).map(|_| components.sample(rng)).collect::<Vec<_>>();
+        return self.refit(self.dataset().select(Axis(0), &indices), None);
+    }
+
+    /// Create an iterator over random points drawn from the probability density estimated by the KDE using the
+    /// given RNG. In contrast to `sample_iter` of the [`Distribution`] implementation, the standard normal vectors
+    /// are generated into a scratch buffer held by the iterator, and [`Samples::next_into`] writes the points into
+    /// a given buffer without any allocation.
+    pub fn samples<'s, R>(&'s self, rng: &'s mut R) -> Samples<'s, 'a, F, B, R>
+    where
+        R: Rng + ?Sized,
+    {
+        return Samples {
+            kde: self,
+            rng,
+            z: Array1::zeros(self.n_dims()),
+        };
+    }
+}
+
+/// Infinite iterator over random points drawn from the probability density estimated by a [`GaussianKDE`], see
+/// [`GaussianKDE::samples`].
+pub struct Samples<'s, 'a, F, B, R>
+where
+    F: Float + FloatConst + FromPrimitive,
+    B: Bandwidth<F>,
+    R: Rng + ?Sized,
+{
+    kde: &'s CowGaussianKDE<'a, F, B>,
+    rng: &'s mut R,
+    z: Array1<F>,
+}
+
+impl<F, B, R> Samples<'_, '_, F, B, R>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + 'static,
+    StandardNormal: Distribution<F>,
+    R: Rng + ?Sized,
+{
+    /// Draw the next point and write it into `out`.
+    ///
+    /// Returns a `ShapeError` if the length of `out` does not match the dimension of the KDE dataset.
+    pub fn next_into(&mut self, out: ArrayViewMut1<F>) -> Result<(), KDEError> {
+        if out.dim() != self.z.dim() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected output buffer of length {}, found {}",
+                    self.z.dim(),
+                    out.dim()
+                ),
+            ));
+        }
+        self.fill(out);
+        return Ok(());
+    }
+
+    /// Draw the next point into `out`, whose length must match the dimension of the KDE dataset, and return the index
+    /// of the kernel it was drawn from.
+    fn fill(&mut self, mut out: ArrayViewMut1<F>) -> usize {
+        let k = self.kde.component_sampler().sample(self.rng);
+        self.z.mapv_inplace(|_| self.rng.sample(StandardNormal));
+        self.kde.assign_data_point(k, out.view_mut());
+        self.kde.add_kernel_offset(self.z.view(), out);
+        return k;
+    }
+}
+
+impl<F, B, R> Iterator for Samples<'_, '_, F, B, R>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + 'static,
+    StandardNormal: Distribution<F>,
+    R: Rng + ?Sized,
+{
+    type Item = Array1<F>;
+
+    fn next(&mut self) -> Option<Array1<F>> {
+        let mut res = Array1::zeros(self.z.dim());
+        self.fill(res.view_mut());
+        return Some(res);
+    }
+}
+
+/// Draws from the probability density estimated by the KDE, such that a fitted KDE can be used wherever a
+/// distribution is expected, e.g. `rng.sample(&kde)` or `kde.sample_iter(rng)`. Each draw chooses a kernel according
+/// to the weights of the data points and transforms a standard normal vector with the Cholesky factor of the kernel
+/// covariance.
+impl<F, B> Distribution<Array1<F>> for CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + 'static,
+    StandardNormal: Distribution<F>,
+{
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Array1<F> {
+        let i = self.component_sampler().sample(rng);
+        let mut res = Array1::zeros(self.n_dims());
+        self.assign_data_point(i, res.view_mut());
+        let z = Array1::from_shape_simple_fn(self.n_dims(), || rng.sample(StandardNormal));
+        self.add_kernel_offset(z.view(), res.view_mut());
+        return res;
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F> + Sync,
+    F: Float + FloatConst + FromPrimitive + SampleUniform + Weight + Send + Sync + 'static,
+    StandardNormal: Distribution<F>,
+{
+    /// Sample `n` random points from the probability density estimated by the KDE, distributing the work over the
+    /// threads of the global `rayon` thread pool.
+    ///
+    /// The points are generated in chunks of fixed size, each using its own RNG seeded deterministically from `seed`
+    /// and the chunk index. Hence, the output only depends on `seed` and not on the number of threads.
+    pub fn sample_batch_par(&self, n: usize, seed: u64) -> Array2<F> {
+        const CHUNK_SIZE: usize = 1024;
+        let dim = self.n_dims();
+        let mut res = Array2::zeros((n, dim));
+        // Construct the cached component sampler before distributing the work
+        self.component_sampler();
+        res.axis_chunks_iter_mut(Axis(0), CHUNK_SIZE)
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(i, mut chunk)| {
+                let mut rng =
+                    Pcg64Mcg::seed_from_u64(seed ^ (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+                let mut samples = self.samples(&mut rng);
+                for row in chunk.rows_mut() {
+                    samples.fill(row);
+                }
+            });
         return res;
     }
 }
@@ -103,6 +493,7 @@ mod tests {
     use ndarray::prelude::*;
 
     #[test]
+    #[cfg(feature = "os-rng")]
     fn sample_test_1d() {
         let data = array![[0.15], [0.2], [0.21], [0.5], [0.72], [0.74], [0.8]];
         let kde = GaussianKDE::new(data.clone(), None).unwrap();
@@ -110,6 +501,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "os-rng")]
     fn sample_test_2d() {
         let data = array![
             [0.15, 0.4],
@@ -123,4 +515,563 @@ mod tests {
         let kde = GaussianKDE::new(data.clone(), None).unwrap();
         let _sample = kde.sample_batch(100_000);
     }
+
+    #[test]
+    fn sample_diagonal_test() {
+        use approx::assert_abs_diff_eq;
+        use rand::SeedableRng;
+        use rand_pcg::Pcg64Mcg;
+
+        let data = array![
+            [0.15, 0.4],
+            [0.2, 0.3],
+            [0.21, 0.29],
+            [0.31, 0.74],
+            [0.72, 0.9],
+            [0.74, 0.84],
+            [0.6, 0.3]
+        ];
+        let kde = GaussianKDE::with_diagonal_covariance(data.clone(), None).unwrap();
+        let sample = kde.sample_batch_with_rng(200_000, &mut Pcg64Mcg::seed_from_u64(1));
+        // Covariance of the mixture: covariance of the data points (biased) plus the diagonal kernel covariance
+        let centered = &data - &data.mean_axis(Axis(0)).unwrap();
+        let cov = centered.t().dot(&centered) / 7.0 + kde.scaled_covariance();
+        let centered = &sample - &sample.mean_axis(Axis(0)).unwrap();
+        assert_abs_diff_eq!(centered.t().dot(&centered) / 200_000.0, cov, epsilon = 2e-3);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn sample_batch_par_test() {
+        use approx::assert_abs_diff_eq;
+        use rand::SeedableRng;
+        use rand_pcg::Pcg64Mcg;
+
+        let data = array![
+            [0.15, 0.4],
+            [0.2, 0.3],
+            [0.21, 0.29],
+            [0.31, 0.74],
+            [0.72, 0.9],
+            [0.74, 0.84],
+            [0.6, 0.3]
+        ];
+        let weights = array![0.5, 1.0, 0.2, 2.0, 0.7, 0.3, 1.2];
+        let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+        let sample = kde.sample_batch_par(100_000, 42);
+        assert_eq!(sample.dim(), (100_000, 2));
+        assert_eq!(sample, kde.sample_batch_par(100_000, 42));
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        assert_eq!(sample, pool.install(|| kde.sample_batch_par(100_000, 42)));
+        assert_ne!(sample, kde.sample_batch_par(100_000, 43));
+
+        let reference = kde.sample_batch_with_rng(100_000, &mut Pcg64Mcg::seed_from_u64(42));
+        let mean = sample.mean_axis(Axis(0)).unwrap();
+        let mean_ref = reference.mean_axis(Axis(0)).unwrap();
+        assert_abs_diff_eq!(mean, mean_ref, epsilon = 5E-3);
+        let cov = sample.t().dot(&sample) / 100_000.
+            - mean
+                .view()
+                .insert_axis(Axis(1))
+                .dot(&mean.view().insert_axis(Axis(0)));
+        let cov_ref = reference.t().dot(&reference) / 100_000.
+            - mean_ref
+                .view()
+                .insert_axis(Axis(1))
+                .dot(&mean_ref.view().insert_axis(Axis(0)));
+        assert_abs_diff_eq!(cov, cov_ref, epsilon = 2E-3);
+    }
+
+    #[test]
+    fn sample_batch_into_test() {
+        use crate::ErrorKind;
+        use approx::assert_abs_diff_eq;
+        use rand::SeedableRng;
+        use rand_pcg::Pcg64Mcg;
+
+        let data = array![
+            [0.15, 0.4],
+            [0.2, 0.3],
+            [0.21, 0.29],
+            [0.31, 0.74],
+            [0.72, 0.9],
+            [0.74, 0.84],
+            [0.6, 0.3]
+        ];
+        let weights = array![0.5, 1.0, 0.2, 2.0, 0.7, 0.3, 1.2];
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let mut out = Array2::from_elem((100_000, 2), f64::NAN);
+        kde.sample_batch_into(out.view_mut(), &mut Pcg64Mcg::seed_from_u64(3))
+            .unwrap();
+        assert_eq!(
+            out,
+            kde.sample_batch_with_rng(100_000, &mut Pcg64Mcg::seed_from_u64(3))
+        );
+        // Mean and covariance of the mixture: weighted moments of the data points plus the kernel covariance
+        let w = &weights / weights.sum();
+        let mean = w.dot(&data);
+        let centered = &data - &mean;
+        let cov = centered
+            .t()
+            .dot(&(&centered * &w.view().insert_axis(Axis(1))))
+            + kde.covariance();
+        let sample_mean = out.mean_axis(Axis(0)).unwrap();
+        let centered = &out - &sample_mean;
+        assert_abs_diff_eq!(sample_mean, mean, epsilon = 3e-3);
+        assert_abs_diff_eq!(centered.t().dot(&centered) / 100_000.0, cov, epsilon = 2e-3);
+
+        let mut out = Array2::zeros((10, 3));
+        let res = kde.sample_batch_into(out.view_mut(), &mut Pcg64Mcg::seed_from_u64(3));
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn component_sampler_test() {
+        use rand::prelude::*;
+        use rand_pcg::Pcg64Mcg;
+
+        let data = array![[0.15], [0.2], [0.21], [0.5], [0.72], [0.74], [0.8]];
+        let weights = array![0.5, 1.0, 0.0, 2.0, 0.7, 0.3, 1.2];
+        let n = 100_000;
+        // Huge weights whose sum is not representable must not change the selection probabilities
+        for scale in [1.0, 1e306] {
+            let kde = GaussianKDE::new(data.clone(), Some(weights.mapv(|w| w * scale))).unwrap();
+            let components = kde.component_sampler();
+            let mut rng = Pcg64Mcg::seed_from_u64(1);
+            let mut counts = [0usize; 7];
+            for _ in 0..n {
+                counts[components.sample(&mut rng)] += 1;
+            }
+            assert_eq!(counts[2], 0);
+            // Pearson's chi-square statistic with 5 degrees of freedom, whose 99.9% quantile is 20.515
+            let chi2 = counts
+                .iter()
+                .zip(weights.iter())
+                .filter(|(_, w)| **w > 0.0)
+                .map(|(c, w)| {
+                    let expected = n as f64 * w / weights.sum();
+                    (*c as f64 - expected).powi(2) / expected
+                })
+                .sum::<f64>();
+            assert!(chi2 < 20.515, "chi2 = {chi2}");
+        }
+    }
+
+    #[test]
+    fn zero_weights_test() {
+        use crate::ErrorKind;
+        use rand::prelude::*;
+        use rand_pcg::Pcg64Mcg;
+
+        // All-zero weights previously panicked when constructing the kernel selection for sampling, they are now
+        // rejected when constructing or reweighting the KDE
+        let data = array![[0.15], [0.2], [0.21], [0.5], [0.72]];
+        let res = GaussianKDE::new(data.clone(), Some(Array1::zeros(5)));
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::WeightError)));
+        let mut kde = GaussianKDE::new(data, Some(array![0.0, 1.0, 0.0, 2.0, 0.0])).unwrap();
+        let res = kde.set_weights(Array1::zeros(5));
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::WeightError)));
+        let mut rng = Pcg64Mcg::seed_from_u64(1);
+        let (_, indices) = kde.sample_batch_indexed(100, &mut rng);
+        assert!(indices.iter().all(|i| *i == 1 || *i == 3));
+    }
+
+    #[test]
+    fn distribution_test() {
+        use approx::assert_abs_diff_eq;
+        use rand::prelude::*;
+        use rand_pcg::Pcg64Mcg;
+
+        let data = array![
+            [0.15, 0.4],
+            [0.2, 0.3],
+            [0.21, 0.29],
+            [0.31, 0.74],
+            [0.72, 0.9],
+            [0.74, 0.84],
+            [0.6, 0.3]
+        ];
+        let weights = array![0.5, 1.0, 0.2, 2.0, 0.7, 0.3, 1.2];
+        let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+        let mut rng = Pcg64Mcg::seed_from_u64(4);
+        let point: Array1<f64> = rng.sample(&kde);
+        assert_eq!(point.dim(), 2);
+
+        let n = 100_000;
+        let points = (&kde).sample_iter(&mut rng).take(n).collect::<Vec<_>>();
+        let sample = ndarray::stack(
+            Axis(0),
+            &points.iter().map(|p| p.view()).collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let reference = kde.sample_batch_seeded(n, 40);
+        let moments = |x: &Array2<f64>| {
+            let mean = x.mean_axis(Axis(0)).unwrap();
+            let centered = x - &mean;
+            (mean, centered.t().dot(&centered) / n as f64)
+        };
+        let (mean, cov) = moments(&sample);
+        let (mean_ref, cov_ref) = moments(&reference);
+        assert_abs_diff_eq!(mean, mean_ref, epsilon = 5e-3);
+        assert_abs_diff_eq!(cov, cov_ref, epsilon = 2e-3);
+    }
+
+    #[test]
+    fn samples_test() {
+        use crate::ErrorKind;
+        use approx::assert_abs_diff_eq;
+        use rand::prelude::*;
+        use rand_pcg::Pcg64Mcg;
+
+        let data = array![
+            [0.15, 0.4],
+            [0.2, 0.3],
+            [0.21, 0.29],
+            [0.31, 0.74],
+            [0.72, 0.9],
+            [0.74, 0.84],
+            [0.6, 0.3]
+        ];
+        let weights = array![0.5, 1.0, 0.2, 2.0, 0.7, 0.3, 1.2];
+        let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+        let n = 100_000;
+        let mut rng = Pcg64Mcg::seed_from_u64(5);
+        let mut samples = kde.samples(&mut rng);
+        let mut sample = Array2::zeros((n, 2));
+        for row in sample.rows_mut() {
+            samples.next_into(row).unwrap();
+        }
+        let moments = |x: &Array2<f64>| {
+            let mean = x.mean_axis(Axis(0)).unwrap();
+            let centered = x - &mean;
+            (mean, centered.t().dot(&centered) / n as f64)
+        };
+        let (mean, cov) = moments(&sample);
+        let (mean_ref, cov_ref) = moments(&kde.sample_batch_seeded(n, 50));
+        assert_abs_diff_eq!(mean, mean_ref, epsilon = 5e-3);
+        assert_abs_diff_eq!(cov, cov_ref, epsilon = 2e-3);
+        // Same draws as the batch function and the iterator for the same RNG state
+        let reference = kde.sample_batch_with_rng(10, &mut Pcg64Mcg::seed_from_u64(6));
+        let mut rng = Pcg64Mcg::seed_from_u64(6);
+        for (point, row) in kde.samples(&mut rng).zip(reference.rows()) {
+            assert_eq!(point, row);
+        }
+
+        let mut out = Array1::zeros(3);
+        let res = kde.samples(&mut rng).next_into(out.view_mut());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn sample_seeded_test() {
+        let data = array![
+            [0.15, 0.4],
+            [0.2, 0.3],
+            [0.21, 0.29],
+            [0.31, 0.74],
+            [0.72, 0.9],
+            [0.74, 0.84],
+            [0.6, 0.3]
+        ];
+        let weights = array![0.5, 1.0, 0.2, 2.0, 0.7, 0.3, 1.2];
+        // A fixed bandwidth factor avoids platform-dependent rounding of `powf` in the fit itself
+        let kde =
+            GaussianKDE::with_bandwidth_factor(data.clone(), Some(weights.clone()), 0.5).unwrap();
+        let sample = kde.sample_batch_seeded(3, 42);
+        // Bit patterns of the output at the time of writing, which must remain stable
+        #[rustfmt::skip]
+        let reference: Array2<u64> = array![
+            [4593531955712079692, 4595980123578456902],
+            [4589700060037023165, 4603427054218789710],
+            [4602658762008590944, 4605184714087103190]
+        ];
+        assert_eq!(sample.mapv(f64::to_bits), reference);
+        assert_eq!(kde.sample_seeded(42), sample.row(0));
+
+        let kde = GaussianKDE::with_bandwidth_factor(
+            data.mapv(|x| x as f32),
+            Some(weights.mapv(|x| x as f32)),
+            0.5,
+        )
+        .unwrap();
+        let sample = kde.sample_batch_seeded(3, 42);
+        #[rustfmt::skip]
+        let reference: Array2<u32> = array![
+            [1039927247, 1044487316],
+            [1032789786, 1058358304],
+            [1056927249, 1061632201]
+        ];
+        assert_eq!(sample.mapv(f32::to_bits), reference);
+        assert_eq!(kde.sample_seeded(42), sample.row(0));
+    }
+
+    #[test]
+    fn sample_batch_truncated_test() {
+        use crate::ErrorKind;
+        use approx::assert_abs_diff_eq;
+        use rand::prelude::*;
+        use rand_pcg::Pcg64Mcg;
+
+        let data = array![
+            [0.05, 0.4],
+            [0.2, 0.03],
+            [0.21, 0.29],
+            [0.31, 0.74],
+            [0.72, 0.97],
+            [0.94, 0.84],
+            [0.6, 0.3]
+        ];
+        let weights = array![0.5, 1.0, 0.2, 2.0, 0.7, 0.3, 1.2];
+        let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+        let mut rng = Pcg64Mcg::seed_from_u64(7);
+        let n = 50_000;
+        let (low, high) = (array![0.0, 0.0], array![1.0, 1.0]);
+        let sample = kde
+            .sample_batch_truncated(n, low.view(), high.view(), 10 * n, &mut rng)
+            .unwrap();
+        assert_eq!(sample.dim(), (n, 2));
+        assert!(sample.iter().all(|x| (0.0..=1.0).contains(x)));
+
+        // Fractions of the points in the cells of a 4x4 grid compared to the renormalized integrals of the density
+        // over the cells, computed with the midpoint rule
+        let (n_cells, n_sub) = (4, 25);
+        let step = 1.0 / (n_cells * n_sub) as f64;
+        let mut integrals = Array2::<f64>::zeros((n_cells, n_cells));
+        for i in 0..n_cells * n_sub {
+            for j in 0..n_cells * n_sub {
+                let x = array![(i as f64 + 0.5) * step, (j as f64 + 0.5) * step];
+                integrals[[i / n_sub, j / n_sub]] += kde.eval(x.view()) * step * step;
+            }
+        }
+        integrals /= integrals.sum();
+        let mut fractions = Array2::<f64>::zeros((n_cells, n_cells));
+        for x in sample.rows() {
+            let cell = x.mapv(|x| ((x * n_cells as f64) as usize).min(n_cells - 1));
+            fractions[[cell[0], cell[1]]] += 1.0 / n as f64;
+        }
+        assert_abs_diff_eq!(fractions, integrals, epsilon = 5e-3);
+
+        // Infinite bounds
+        let low = array![f64::NEG_INFINITY, 0.5];
+        let high = array![0.5, f64::INFINITY];
+        let sample = kde
+            .sample_batch_truncated(1000, low.view(), high.view(), 100_000, &mut rng)
+            .unwrap();
+        assert!(
+            sample
+                .rows()
+                .into_iter()
+                .all(|x| x[0] <= 0.5 && x[1] >= 0.5)
+        );
+
+        let (low, high) = (array![5.0, 5.0], array![5.1, 5.1]);
+        let res = kde.sample_batch_truncated(10, low.view(), high.view(), 1000, &mut rng);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SamplingError)));
+        let res = kde.sample_batch_truncated(10, high.view(), low.view(), 1000, &mut rng);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        let res = kde.sample_batch_truncated(10, array![0.0].view(), high.view(), 1000, &mut rng);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn resample_test() {
+        use crate::covariance::covariance;
+        use approx::assert_abs_diff_eq;
+        use rand::prelude::*;
+        use rand_pcg::Pcg64Mcg;
+
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3], [0.4, 1.1], [2.0, -0.5], [0.9, 0.8], [-0.3, -1.4],
+            [1.5, 2.2], [-2.1, 0.6], [0.1, 0.0], [1.1, -0.9], [-0.7, 1.7]
+        ];
+        let weights = array![0.5, 2.0, 1.0, 0.2, 1.5, 0.8, 0.3, 3.0, 1.2, 0.6];
+        let kde =
+            GaussianKDE::with_bandwidth_factor(data.clone(), Some(weights.clone()), 0.4).unwrap();
+        let mean = data.t().dot(&weights) / weights.sum();
+        // Population covariance of the weighted dataset, without correction for the effective number of points
+        let centered = &data - &mean;
+        let cov = (&centered * &weights.view().insert_axis(Axis(1)))
+            .t()
+            .dot(&centered)
+            / weights.sum();
+        let n = 50_000;
+        let mut rng = Pcg64Mcg::seed_from_u64(3);
+
+        let points = kde.resample_points(n, &mut rng).unwrap();
+        assert!(points.weights().is_none());
+        assert_eq!(points.bandwidth_factor(), 0.4);
+        assert_abs_diff_eq!(
+            points.data().mean_axis(Axis(0)).unwrap(),
+            mean,
+            epsilon = 1e-2
+        );
+        assert_abs_diff_eq!(covariance(points.data().view(), None), cov, epsilon = 2e-2);
+
+        let unweighted = kde.resample_unweighted(n, &mut rng).unwrap();
+        assert!(unweighted.weights().is_none());
+        assert_eq!(unweighted.bandwidth_factor(), 0.4);
+        assert_abs_diff_eq!(
+            unweighted.data().mean_axis(Axis(0)).unwrap(),
+            mean,
+            epsilon = 1e-2
+        );
+        // The kernel smearing adds the kernel covariance $H$
+        assert_abs_diff_eq!(
+            covariance(unweighted.data().view(), None),
+            &cov + &kde.covariance(),
+            epsilon = 2e-2
+        );
+    }
+
+    #[test]
+    fn sample_batch_antithetic_test() {
+        use approx::{assert_abs_diff_eq, assert_relative_eq};
+        use rand::prelude::*;
+        use rand_pcg::Pcg64Mcg;
+
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3], [0.4, 1.1], [2.0, -0.5], [0.9, 0.8], [-0.3, -1.4],
+            [1.5, 2.2], [-2.1, 0.6], [0.1, 0.0], [1.1, -0.9], [-0.7, 1.7]
+        ];
+        let weights = array![0.5, 2.0, 1.0, 0.2, 1.5, 0.8, 0.3, 3.0, 1.2, 0.6];
+        let kde = GaussianKDE::new(data.clone(), Some(weights)).unwrap();
+        let mut rng = Pcg64Mcg::seed_from_u64(9);
+        let n_pairs = 100_000;
+        let sample = kde.sample_batch_antithetic(n_pairs, &mut rng);
+        assert_eq!(sample.dim(), (2 * n_pairs, 2));
+
+        // The offsets of each pair cancel, i.e. the pair average is the center of the shared kernel
+        for pair in sample.axis_chunks_iter(Axis(0), 2).take(1000) {
+            let mean = pair.mean_axis(Axis(0)).unwrap();
+            let center = data
+                .rows()
+                .into_iter()
+                .min_by(|a, b| {
+                    let da = (&mean - a).mapv(|x| x * x).sum();
+                    let db = (&mean - b).mapv(|x| x * x).sum();
+                    da.partial_cmp(&db).unwrap()
+                })
+                .unwrap();
+            assert_relative_eq!(mean, center, epsilon = 1e-12);
+        }
+
+        let reference = kde.sample_batch_with_rng(2 * n_pairs, &mut rng);
+        let mean = sample.mean_axis(Axis(0)).unwrap();
+        assert_abs_diff_eq!(mean, reference.mean_axis(Axis(0)).unwrap(), epsilon = 1e-2);
+        let cov = |x: &Array2<f64>| {
+            let centered = x - &x.mean_axis(Axis(0)).unwrap();
+            centered.t().dot(&centered) / x.dim().0 as f64
+        };
+        assert_abs_diff_eq!(cov(&sample), cov(&reference), epsilon = 2e-2);
+    }
+
+    #[test]
+    fn sample_batch_systematic_test() {
+        use approx::assert_abs_diff_eq;
+        use rand::prelude::*;
+        use rand_pcg::Pcg64Mcg;
+
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3], [0.4, 1.1], [2.0, -0.5], [0.9, 0.8], [-0.3, -1.4],
+            [1.5, 2.2], [-2.1, 0.6], [0.1, 0.0], [1.1, -0.9], [-0.7, 1.7]
+        ];
+        let weights = array![0.5, 2.0, 1.0, 0.2, 1.5, 0.0, 0.3, 3.0, 1.2, 0.6];
+        let mut rng = Pcg64Mcg::seed_from_u64(4);
+
+        let weighted = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let unweighted = GaussianKDE::new(data.clone(), None).unwrap();
+        for (kde, weights) in [
+            (&weighted, weights.clone()),
+            (&unweighted, Array1::ones(10)),
+        ] {
+            for n in [1, 7, 10, 33, 1000] {
+                let mut counts = Array1::<f64>::zeros(10);
+                for k in kde.systematic_components(n, &mut rng) {
+                    counts[k] += 1.0;
+                }
+                let expected = &weights * n as f64 / weights.sum();
+                assert_abs_diff_eq!(counts, expected, epsilon = 1.0);
+            }
+        }
+
+        let n = 50_000;
+        let sample = weighted.sample_batch_systematic(n, &mut rng);
+        assert_eq!(sample.dim(), (n, 2));
+        let reference = weighted.sample_batch_with_rng(n, &mut rng);
+        assert_abs_diff_eq!(
+            sample.mean_axis(Axis(0)).unwrap(),
+            reference.mean_axis(Axis(0)).unwrap(),
+            epsilon = 1e-2
+        );
+        let cov = |x: &Array2<f64>| {
+            let centered = x - &x.mean_axis(Axis(0)).unwrap();
+            centered.t().dot(&centered) / x.dim().0 as f64
+        };
+        assert_abs_diff_eq!(cov(&sample), cov(&reference), epsilon = 2e-2);
+    }
+
+    #[test]
+    fn sample_batch_indexed_test() {
+        use approx::assert_abs_diff_eq;
+        use ndarray::Zip;
+        use rand::prelude::*;
+        use rand_pcg::Pcg64Mcg;
+
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3], [0.4, 1.1], [2.0, -0.5], [0.9, 0.8], [-0.3, -1.4],
+            [1.5, 2.2], [-2.1, 0.6], [0.1, 0.0], [1.1, -0.9], [-0.7, 1.7]
+        ];
+        let weights = array![0.5, 2.0, 1.0, 0.2, 1.5, 0.0, 0.3, 3.0, 1.2, 0.6];
+        let kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let n = 100_000;
+        let (sample, indices) = kde.sample_batch_indexed(n, &mut Pcg64Mcg::seed_from_u64(8));
+        assert_eq!(sample.dim(), (n, 2));
+        assert_eq!(indices.len(), n);
+        assert_eq!(
+            sample,
+            kde.sample_batch_with_rng(n, &mut Pcg64Mcg::seed_from_u64(8))
+        );
+
+        // Whitening the offset from the indexed data point recovers the standard normal vector
+        let mut squared_norm = 0.0;
+        for (x, k) in sample.rows().into_iter().zip(&indices) {
+            let z: Array1<f64> = kde.inv_cholesky().dot(&(&x - &data.row(*k)));
+            assert!(z.iter().all(|z| z.abs() < 6.0));
+            squared_norm += z.dot(&z) / n as f64;
+        }
+        assert_abs_diff_eq!(squared_norm, 2.0, epsilon = 2e-2);
+
+        let mut counts = Array1::<f64>::zeros(10);
+        for k in &indices {
+            counts[*k] += 1.0;
+        }
+        let expected = &weights * n as f64 / weights.sum();
+        assert_eq!(counts[5], 0.0);
+        let chi2 = Zip::from(&counts).and(&expected).fold(0.0, |acc, c, e| {
+            if *e > 0.0 {
+                acc + (c - e).powi(2) / e
+            } else {
+                acc
+            }
+        });
+        // 99th percentile of the chi-squared distribution with 8 degrees of freedom
+        assert!(chi2 < 20.09);
+
+        let mut rng = Pcg64Mcg::seed_from_u64(2);
+        let (x, k): (Array1<f64>, _) = kde.sample_indexed(&mut rng);
+        assert_eq!(x, kde.sample_with_rng(&mut Pcg64Mcg::seed_from_u64(2)));
+        assert!(
+            kde.inv_cholesky()
+                .dot(&(&x - &data.row(k)))
+                .iter()
+                .all(|z| z.abs() < 6.0)
+        );
+    }
 }
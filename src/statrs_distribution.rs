@@ -0,0 +1,178 @@
+//! Implementations of the distribution traits of [`statrs`](https://docs.rs/statrs), such that KDEs can be passed to
+//! generic statistical code, e.g. goodness-of-fit tests.
+//!
+//! [`Continuous`] is implemented for points given as `Array1<F>` in any dimension and as scalars for one-dimensional
+//! KDEs. [`ContinuousCDF`] (with [`Min`] and [`Max`], the bounds of the support) is implemented for one-dimensional
+//! KDEs only. Since the traits cannot return errors, their methods *panic* with the message of the [`KDEError`] of
+//! the corresponding method of the KDE, e.g. for multivariate KDEs.
+
+use crate::{Bandwidth, CowGaussianKDE};
+#[cfg(doc)]
+use crate::{GaussianKDE, KDEError};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+use statrs::distribution::{Continuous, ContinuousCDF};
+use statrs::statistics::{Max, Min};
+
+/// The density at a point given as array, see [`GaussianKDE::eval`] and [`GaussianKDE::ln_eval`].
+///
+/// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+impl<F, B> Continuous<Array1<F>, F> for CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    fn pdf(&self, x: Array1<F>) -> F {
+        return self.eval(x.view());
+    }
+
+    fn ln_pdf(&self, x: Array1<F>) -> F {
+        return self.ln_eval(x.view());
+    }
+}
+
+/// The density of a one-dimensional KDE at a scalar point, as for the univariate distributions of `statrs`.
+///
+/// *Panics* if the KDE is multivariate.
+impl<F, B> Continuous<F, F> for CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    fn pdf(&self, x: F) -> F {
+        self.check_univariate("pdf")
+            .unwrap_or_else(|e| panic!("{e}"));
+        return self.eval(aview1(&[x]));
+    }
+
+    fn ln_pdf(&self, x: F) -> F {
+        self.check_univariate("ln_pdf")
+            .unwrap_or_else(|e| panic!("{e}"));
+        return self.ln_eval(aview1(&[x]));
+    }
+}
+
+/// The distribution functions of a one-dimensional KDE, see [`GaussianKDE::cdf`], [`GaussianKDE::sf`] and
+/// [`GaussianKDE::quantile`]. The inverse is $-\infty$ for `p = 0` and $+\infty$ for `p = 1`, the bounds of the
+/// support.
+///
+/// *Panics* if the KDE is multivariate or `p` does not lie in $[0, 1]$.
+impl<F, B> ContinuousCDF<F, F> for CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    fn cdf(&self, x: F) -> F {
+        return CowGaussianKDE::cdf(self, x).unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    fn sf(&self, x: F) -> F {
+        return CowGaussianKDE::sf(self, x).unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    fn inverse_cdf(&self, p: F) -> F {
+        if p == F::zero() || p == F::one() {
+            self.check_univariate("inverse_cdf")
+                .unwrap_or_else(|e| panic!("{e}"));
+            return if p == F::zero() {
+                F::neg_infinity()
+            } else {
+                F::infinity()
+            };
+        }
+        return self.quantile(p).unwrap_or_else(|e| panic!("{e}"));
+    }
+}
+
+/// The lower bound $-\infty$ of the support of the (Gaussian) kernels.
+impl<F, B> Min<F> for CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive,
+{
+    fn min(&self) -> F {
+        return F::neg_infinity();
+    }
+}
+
+/// The upper bound $+\infty$ of the support of the (Gaussian) kernels.
+impl<F, B> Max<F> for CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive,
+{
+    fn max(&self) -> F {
+        return F::infinity();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GaussianKDE;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+    use statrs::distribution::{Continuous, ContinuousCDF};
+
+    /// Kolmogorov-Smirnov statistic of the sample `x` with respect to the distribution `dist`.
+    #[cfg(feature = "sample")]
+    fn ks_statistic(dist: &impl ContinuousCDF<f64, f64>, mut x: Vec<f64>) -> f64 {
+        x.sort_by(f64::total_cmp);
+        let n = x.len() as f64;
+        return x.iter().enumerate().fold(0.0, |acc, (i, xi)| {
+            let cdf = dist.cdf(*xi);
+            acc.max(cdf - i as f64 / n).max((i + 1) as f64 / n - cdf)
+        });
+    }
+
+    #[test]
+    fn statrs_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(109);
+        let data = Array2::<f64>::from_shape_simple_fn((200, 1), || rng.sample(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(200, || rng.random_range(0.5..1.5));
+        let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+
+        // The trait methods agree with the ones of the KDE
+        for x in [-3.0, -0.4, 0.0, 1.3, 8.0] {
+            assert_eq!(Continuous::<f64, f64>::pdf(&kde, x), kde.eval(aview1(&[x])));
+            assert_eq!(
+                Continuous::<f64, f64>::ln_pdf(&kde, x),
+                kde.ln_eval(aview1(&[x]))
+            );
+            assert_eq!(Continuous::pdf(&kde, array![x]), kde.eval(aview1(&[x])));
+            assert_eq!(ContinuousCDF::cdf(&kde, x), kde.cdf(x).unwrap());
+            assert_eq!(ContinuousCDF::sf(&kde, x), kde.sf(x).unwrap());
+        }
+        for p in [0.01, 0.3, 0.5, 0.99] {
+            assert_eq!(kde.inverse_cdf(p), kde.quantile(p).unwrap());
+        }
+        assert_eq!(kde.inverse_cdf(0.0), f64::NEG_INFINITY);
+        assert_eq!(kde.inverse_cdf(1.0), f64::INFINITY);
+
+        // Kolmogorov-Smirnov test of samples from the KDE at the 1% level, and of a shifted sample, which is rejected
+        #[cfg(feature = "sample")]
+        {
+            let n = 2000;
+            let critical = 1.63 / (n as f64).sqrt();
+            let sample = kde.sample_batch_seeded(n, 109).into_raw_vec_and_offset().0;
+            let shifted = sample.iter().map(|x| x + 0.5).collect::<Vec<_>>();
+            assert!(ks_statistic(&kde, sample.clone()) < critical);
+            assert!(ks_statistic(&kde, shifted) > critical);
+            // The KDE is close to the normal distribution the data are drawn from
+            let normal = statrs::distribution::Normal::new(0.0, 1.0).unwrap();
+            assert!(ks_statistic(&normal, sample) < 0.1);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "requires a one-dimensional KDE")]
+    fn statrs_multivariate_test() {
+        let data = array![[0.1, 1.0], [0.4, -1.0], [0.2, 0.5], [0.9, 0.0]];
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let x = array![0.3, 0.2];
+        assert_eq!(Continuous::pdf(&kde, x.clone()), kde.eval(x.view()));
+        assert_eq!(Continuous::ln_pdf(&kde, x.clone()), kde.ln_eval(x.view()));
+        ContinuousCDF::cdf(&kde, 0.3);
+    }
+}
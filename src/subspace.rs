@@ -0,0 +1,214 @@
+//! Kernel density estimation for datasets confined to an affine subspace of lower dimension, e.g. due to a linear
+//! constraint between the variables, whose covariance matrix is singular.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::covariance::covariance;
+use crate::eigen::symmetric_eigen;
+use crate::{CowGaussianKDE, ErrorKind, KDEError, check_finite, check_weights};
+use alloc::{format, string::ToString};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// Orthogonal projection from the original $d$-dimensional space onto the $r$-dimensional affine subspace
+/// $\\{\bm{\mu} + B \bm{z}\\}$ spanned by the dataset, as returned by [`GaussianKDE::with_rank_reduction`].
+///
+/// The columns of the basis $B$ of shape `(d, r)` are orthonormal, such that the reduced coordinates
+/// $\bm{z} = B^\top (\bm{x} - \bm{\mu})$ preserve distances within the subspace and densities in reduced coordinates
+/// are densities with respect to the $r$-dimensional volume of the subspace.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubspaceProjection<F> {
+    basis: Array2<F>,
+    offset: Array1<F>,
+}
+
+impl<F> SubspaceProjection<F>
+where
+    F: Float + 'static,
+{
+    /// Orthonormal basis $B$ of the subspace of shape `(d, r)`, ordered by decreasing variance of the dataset along
+    /// the basis vectors.
+    pub fn basis(&self) -> ArrayView2<'_, F> {
+        return self.basis.view();
+    }
+
+    /// Origin $\bm{\mu}$ of the reduced coordinates, i.e. the (weighted) mean of the dataset.
+    pub fn offset(&self) -> ArrayView1<'_, F> {
+        return self.offset.view();
+    }
+
+    /// Dimension $r$ of the subspace.
+    pub fn rank(&self) -> usize {
+        return self.basis.dim().1;
+    }
+
+    /// Reduced coordinates $\bm{z} = B^\top (\bm{x} - \bm{\mu})$ of the points `x` of shape `(n_points, d)`, as
+    /// expected by the evaluation of the reduced KDE. Points outside of the subspace are projected orthogonally.
+    pub fn project(&self, x: ArrayView2<F>) -> Array2<F> {
+        return (&x - &self.offset).dot(&self.basis);
+    }
+
+    /// Points $\bm{x} = \bm{\mu} + B \bm{z}$ in the original space for the reduced coordinates `z` of shape
+    /// `(n_points, r)`, e.g. to map samples of the reduced KDE back.
+    pub fn embed(&self, z: ArrayView2<F>) -> Array2<F> {
+        return z.dot(&self.basis.t()) + &self.offset;
+    }
+}
+
+impl<'a, F> CowGaussianKDE<'a, F>
+where
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Create a kernel density estimator for a dataset of shape `(n_points, d)` with a singular covariance matrix,
+    /// e.g. since the variables satisfy a linear constraint like $\sum_j x_j = 1$.
+    ///
+    /// The dataset is projected onto the principal axes of its covariance matrix whose variance exceeds `tol` times
+    /// the largest variance, and the KDE with [`ScottBandwidth`](crate::ScottBandwidth) is fitted in these $r$
+    /// reduced coordinates. The returned [`SubspaceProjection`] maps points between the original and the reduced
+    /// coordinates, i.e. the density at $\bm{x}$ within the subspace is `kde.eval_batch(projection.project(x))`. Since
+    /// the basis is orthonormal, this is the density with respect to the volume of the subspace, while the density in
+    /// the original space is not defined.
+    ///
+    /// Returns a `DomainError` if `tol` is not in $[0, 1)$ and a `SingularityError` if all points coincide, besides the
+    /// errors of [`GaussianKDE::new`].
+    pub fn with_rank_reduction(
+        data: Array2<F>,
+        weights: Option<Array1<F>>,
+        tol: F,
+    ) -> Result<(CowGaussianKDE<'a, F>, SubspaceProjection<F>), KDEError> {
+        if !(tol >= F::zero() && tol < F::one()) {
+            return Err(KDEError::new(
+                ErrorKind::DomainError,
+                format!(
+                    "the tolerance of the rank reduction must be in [0, 1), found {}",
+                    tol.to_f64().unwrap()
+                ),
+            ));
+        }
+        check_finite(data.view())?;
+        let n = data.dim().0;
+        let w = weights.as_ref().map(|w| w.view());
+        if let Some(w) = w {
+            if w.len() != n {
+                return Err(KDEError::new(
+                    ErrorKind::ShapeError,
+                    format!(
+                        "the dataset contains {n} points, but {} weights were given",
+                        w.len()
+                    ),
+                ));
+            }
+            check_weights(w)?;
+        }
+        if n < 2 {
+            return Err(KDEError::new(
+                ErrorKind::SingularityError,
+                "the rank reduction requires at least two data points".to_string(),
+            ));
+        }
+        let (variances, axes) = symmetric_eigen(covariance(data.view(), w).view());
+        let threshold = tol * variances[0];
+        let rank = variances.iter().take_while(|v| **v > threshold).count();
+        if rank == 0 {
+            return Err(KDEError::new(
+                ErrorKind::SingularityError,
+                "all data points coincide, such that the dataset does not span a subspace"
+                    .to_string(),
+            ));
+        }
+        let offset = match w {
+            Some(w) => {
+                let sum_weights = w.sum();
+                w.dot(&data).mapv(|x| x / sum_weights)
+            }
+            None => data.mean_axis(Axis(0)).unwrap(),
+        };
+        let projection = SubspaceProjection {
+            basis: axes.slice(s![.., ..rank]).to_owned(),
+            offset,
+        };
+        let reduced = projection.project(data.view());
+        return Ok((CowGaussianKDE::new(reduced, weights)?, projection));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ErrorKind, GaussianKDE};
+    use approx::{assert_abs_diff_eq, assert_relative_eq};
+    use ndarray::prelude::*;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, StandardNormal};
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn rank_reduction_test() {
+        // Points on the plane x + y + z = 1 with the orthonormal in-plane coordinates (u, v)
+        let e = array![
+            [1.0 / 2f64.sqrt(), -1.0 / 2f64.sqrt(), 0.0],
+            [1.0 / 6f64.sqrt(), 1.0 / 6f64.sqrt(), -2.0 / 6f64.sqrt()]
+        ];
+        let origin = Array1::from_elem(3, 1.0 / 3.0);
+        let mut rng = Pcg64Mcg::seed_from_u64(68);
+        let mut uv = Array2::<f64>::zeros((300, 2));
+        for mut row in uv.rows_mut() {
+            let (a, b): (f64, f64) = (
+                StandardNormal.sample(&mut rng),
+                StandardNormal.sample(&mut rng),
+            );
+            row.assign(&array![1.5 * a + 0.3, 0.8 * a + 0.5 * b - 0.2]);
+        }
+        let data = uv.dot(&e) + &origin;
+
+        let res = GaussianKDE::new(data.clone(), None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+
+        let (kde, projection) =
+            GaussianKDE::with_rank_reduction(data.clone(), None, 1e-10).unwrap();
+        assert_eq!(projection.rank(), 2);
+        assert_abs_diff_eq!(
+            projection.basis().sum_axis(Axis(0)),
+            array![0.0, 0.0],
+            epsilon = 1e-12
+        );
+        assert_abs_diff_eq!(
+            projection.embed(projection.project(data.view()).view()),
+            data,
+            epsilon = 1e-12
+        );
+
+        // The reduced coordinates are a rotation of (u, v), which leaves the density invariant
+        let reference = GaussianKDE::new(uv, None).unwrap();
+        let uv_x = array![[0.0, 0.0], [0.3, -0.2], [-1.5, 0.4], [2.0, 1.0]];
+        let x = uv_x.dot(&e) + &origin;
+        assert_relative_eq!(
+            kde.eval_batch(projection.project(x.view()).view()),
+            reference.eval_batch(uv_x.view()),
+            max_relative = 1e-10
+        );
+
+        // Weighted data is centered at the weighted mean
+        let weights = Array1::from_shape_fn(300, |i| 1.0 + (i % 7) as f64);
+        let (_, projection) =
+            GaussianKDE::with_rank_reduction(data.clone(), Some(weights.clone()), 1e-10).unwrap();
+        assert_abs_diff_eq!(
+            projection.offset(),
+            weights.dot(&data) / weights.sum(),
+            epsilon = 1e-14
+        );
+    }
+
+    #[test]
+    fn rank_reduction_error_test() {
+        let data = array![[1.0, 2.0], [3.0, 4.0], [5.0, 6.5]];
+        let res = GaussianKDE::with_rank_reduction(data.clone(), None, 1.0);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        let res = GaussianKDE::with_rank_reduction(data.clone(), None, f64::NAN);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        let res = GaussianKDE::with_rank_reduction(Array2::from_elem((4, 2), 1.5), None, 1e-10);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+        let res = GaussianKDE::with_rank_reduction(data, Some(array![1.0, 1.0]), 1e-10);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+}
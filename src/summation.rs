@@ -0,0 +1,166 @@
+//! Compensated summation for the sums over all data points, e.g. in the covariance matrix and the kernel sum, whose
+//! rounding error then does not grow with the number of points. Without compensation, summing $10^6$ terms in single
+//! precision loses about three significant digits.
+
+use num_traits::Float;
+
+/// Accumulator for the sum of a sequence of floating point numbers with the second-order variant of Kahan-Babuška
+/// summation by Klein, which carries the rounding error of each addition in a compensation term and the rounding
+/// error of the compensation in a second one. Unlike the first-order variant, this remains accurate for $10^6$ and
+/// more terms in single precision, whose rounding errors accumulate systematically if the terms are of similar size.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CompensatedSum<F> {
+    sum: F,
+    compensation: F,
+    second_compensation: F,
+}
+
+/// Sum `a + b` and its rounding error, which is recovered from the larger of both operands.
+fn two_sum<F: Float>(a: F, b: F) -> (F, F) {
+    let t = a + b;
+    let error = if a.abs() >= b.abs() {
+        (a - t) + b
+    } else {
+        (b - t) + a
+    };
+    return (t, error);
+}
+
+impl<F: Float> CompensatedSum<F> {
+    pub(crate) fn new() -> Self {
+        return Self {
+            sum: F::zero(),
+            compensation: F::zero(),
+            second_compensation: F::zero(),
+        };
+    }
+
+    pub(crate) fn add(&mut self, x: F) {
+        let (sum, error) = two_sum(self.sum, x);
+        let (compensation, second_error) = two_sum(self.compensation, error);
+        self.sum = sum;
+        self.compensation = compensation;
+        self.second_compensation = self.second_compensation + second_error;
+    }
+
+    /// Multiply the accumulated sum by `factor`, e.g. to rescale a sum of exponentials to a new maximum.
+    pub(crate) fn scale(&mut self, factor: F) {
+        self.sum = self.sum * factor;
+        self.compensation = self.compensation * factor;
+        self.second_compensation = self.second_compensation * factor;
+    }
+
+    /// Compensated value of the sum. Infinite sums are returned as is, since their compensation is undefined.
+    pub(crate) fn value(&self) -> F {
+        return if self.sum.is_finite() {
+            self.sum + (self.compensation + self.second_compensation)
+        } else {
+            self.sum
+        };
+    }
+}
+
+/// Compensated sum of the given values, see [`CompensatedSum`].
+pub(crate) fn compensated_sum<F: Float>(values: impl IntoIterator<Item = F>) -> F {
+    let mut acc = CompensatedSum::new();
+    for x in values {
+        acc.add(x);
+    }
+    return acc.value();
+}
+
+/// Accumulator for the logarithm $\ln \sum_i \exp(a_i)$ of a sum of exponentials in a single pass, which keeps the
+/// running maximum $m$ of the arguments and the compensated sum of the rescaled terms $\exp(a_i - m)$, such that
+/// neither of them under- or overflows.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LnSumExp<F> {
+    max: F,
+    sum: CompensatedSum<F>,
+}
+
+impl<F: Float> LnSumExp<F> {
+    pub(crate) fn new() -> Self {
+        return Self {
+            max: F::neg_infinity(),
+            sum: CompensatedSum::new(),
+        };
+    }
+
+    /// Add the term $\exp(a)$ for the argument `arg`. Returns the factor by which the previously added terms were
+    /// rescaled to the new maximum and the rescaled term itself, such that sums weighted with the same terms can be
+    /// maintained alongside. Arguments of $-\infty$ do not contribute.
+    pub(crate) fn add(&mut self, arg: F) -> (F, F) {
+        if arg == F::neg_infinity() {
+            return (F::one(), F::zero());
+        }
+        if arg > self.max {
+            let rescale = F::exp(self.max - arg);
+            self.sum.scale(rescale);
+            self.sum.add(F::one());
+            self.max = arg;
+            return (rescale, F::one());
+        }
+        let term = F::exp(arg - self.max);
+        self.sum.add(term);
+        return (F::one(), term);
+    }
+
+    /// Sum of the rescaled terms $\sum_i \exp(a_i - m)$.
+    pub(crate) fn rescaled_sum(&self) -> F {
+        return self.sum.value();
+    }
+
+    /// Logarithm of the sum of all terms, which is $-\infty$ if no term contributed.
+    pub(crate) fn value(&self) -> F {
+        return self.max + self.sum.value().ln();
+    }
+}
+
+/// Logarithm of the sum of the exponentials of the given arguments, see [`LnSumExp`].
+pub(crate) fn ln_sum_exp<F: Float>(args: impl IntoIterator<Item = F>) -> F {
+    let mut acc = LnSumExp::new();
+    for arg in args {
+        acc.add(arg);
+    }
+    return acc.value();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompensatedSum, compensated_sum, ln_sum_exp};
+
+    #[test]
+    fn compensated_sum_test() {
+        // The naive summation accumulates the rounding errors of each addition to a relative error of about 1e-2
+        let values = std::iter::repeat_n(0.1f32, 1_000_000);
+        let naive = values.clone().fold(0.0, |acc, x| acc + x);
+        assert!((naive - 1e5).abs() > 100.0);
+        approx::assert_relative_eq!(compensated_sum(values), 1e5, max_relative = 1e-7);
+        assert_eq!(compensated_sum([1e30f64, 1.0, -1e30]), 1.0);
+        assert_eq!(compensated_sum([1.0, f64::INFINITY, 2.0]), f64::INFINITY);
+
+        let mut acc = CompensatedSum::new();
+        acc.add(3.0f64);
+        acc.scale(0.5);
+        acc.add(0.25);
+        assert_eq!(acc.value(), 1.75);
+    }
+
+    #[test]
+    fn ln_sum_exp_test() {
+        approx::assert_relative_eq!(
+            ln_sum_exp([0.5f64, -1.0, 2.0]),
+            (0.5f64.exp() + (-1.0f64).exp() + 2.0f64.exp()).ln(),
+            max_relative = 1e-15
+        );
+        // The exponentials of the arguments are not representable
+        approx::assert_relative_eq!(
+            ln_sum_exp([-1000.0f64, f64::NEG_INFINITY, -1000.0]),
+            -1000.0 + 2.0f64.ln(),
+            max_relative = 1e-15
+        );
+        assert_eq!(ln_sum_exp([1000.0f64, 1000.0]), 1000.0 + 2.0f64.ln());
+        assert_eq!(ln_sum_exp([f64::NEG_INFINITY]), f64::NEG_INFINITY);
+        assert_eq!(ln_sum_exp(std::iter::empty::<f64>()), f64::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,416 @@
+//! Distribution functions of one-dimensional KDEs, which are weighted averages of the corresponding functions of the
+//! normal distribution and can therefore be computed exactly.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::special::std_normal_interval;
+use crate::summation::compensated_sum;
+use crate::{Bandwidth, CowGaussianKDE, ErrorKind, KDEError};
+use alloc::format;
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// Maximum number of iterations when inverting the cumulative distribution function. Newton's method converges in a
+/// handful of iterations, and even pure bisection reaches double precision well within this limit. The same limit
+/// applies to the doubling steps bracketing the quantile, which then span $2^{200}$ kernel widths.
+const MAX_ITERATIONS: usize = 200;
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Evaluate the cumulative distribution function
+    /// \\[ F_\mathrm{KDE}(x) = \int_{-\infty}^x f_\mathrm{KDE}(t) \\, \mathrm{d}t = \frac{1}{\sum_i w_i} \sum_{i=1}^n w_i \\, \Phi\left(\frac{x - x_i}{\sigma}\right) \\]
+    /// of a one-dimensional KDE at `x`, where $\Phi$ is the cumulative distribution function of the standard normal
+    /// distribution and $\sigma^2 = H$ is the kernel variance.
+    ///
+    /// Returns a `ShapeError` if the KDE is multivariate.
+    pub fn cdf(&self, x: F) -> Result<F, KDEError> {
+        self.check_univariate("cdf")?;
+        return Ok(self.univariate_kernel_average(|xi| {
+            std_normal_interval(F::neg_infinity(), (x - xi) / self.cholesky[[0, 0]])
+        }));
+    }
+
+    /// Evaluate the cumulative distribution function of a one-dimensional KDE at all points in `x`, see
+    /// [`GaussianKDE::cdf`].
+    ///
+    /// Returns a `ShapeError` if the KDE is multivariate.
+    pub fn cdf_batch(&self, x: ArrayView1<F>) -> Result<Array1<F>, KDEError> {
+        self.check_univariate("cdf_batch")?;
+        return Ok(x.mapv(|x| self.cdf(x).unwrap()));
+    }
+
+    /// Evaluate the survival function
+    /// \\[ 1 - F_\mathrm{KDE}(x) = \frac{1}{\sum_i w_i} \sum_{i=1}^n w_i \\, \Phi\left(\frac{x_i - x}{\sigma}\right) \\]
+    /// of a one-dimensional KDE at `x`. In contrast to `1 - cdf(x)`, each term is computed from the complementary
+    /// error function, such that the relative precision is retained far in the upper tail.
+    ///
+    /// Returns a `ShapeError` if the KDE is multivariate.
+    pub fn sf(&self, x: F) -> Result<F, KDEError> {
+        self.check_univariate("sf")?;
+        return Ok(self.univariate_kernel_average(|xi| {
+            std_normal_interval((x - xi) / self.cholesky[[0, 0]], F::infinity())
+        }));
+    }
+
+    /// Return the equal-tailed interval $[a, b]$ containing the probability `mass` of a one-dimensional KDE, i.e.
+    /// $F_\mathrm{KDE}(a) = 1 - F_\mathrm{KDE}(b) = (1 - \mathrm{mass}) / 2$.
+    ///
+    /// Returns a `ShapeError` if the KDE is multivariate and a `DomainError` if `mass` does not lie in the open
+    /// interval $(0, 1)$.
+    pub fn central_interval(&self, mass: F) -> Result<(F, F), KDEError> {
+        self.check_univariate("central_interval")?;
+        check_probability(mass)?;
+        let half = F::from(0.5).unwrap();
+        let low = self.invert_cdf(half - half * mass, None);
+        let high = self.invert_cdf(half + half * mass, Some(low));
+        return Ok((low, high));
+    }
+
+    /// Evaluate the quantile function, i.e. the inverse of the cumulative distribution function
+    /// [`GaussianKDE::cdf`], of a one-dimensional KDE at the probability `p`. The quantile is found by bracketing
+    /// the root of $F_\mathrm{KDE}(x) - p$ and refining it by Newton's method, falling back to bisection whenever a
+    /// Newton step leaves the bracket.
+    ///
+    /// Returns a `ShapeError` if the KDE is multivariate and a `DomainError` if `p` does not lie in the open
+    /// interval $(0, 1)$.
+    pub fn quantile(&self, p: F) -> Result<F, KDEError> {
+        self.check_univariate("quantile")?;
+        check_probability(p)?;
+        return Ok(self.invert_cdf(p, None));
+    }
+
+    /// Evaluate the quantile function of a one-dimensional KDE at all probabilities in `p`, see
+    /// [`GaussianKDE::quantile`]. If `p` is sorted in ascending order, each quantile serves as lower bound of the
+    /// bracket for the next one.
+    ///
+    /// Returns a `ShapeError` if the KDE is multivariate and a `DomainError` if any element of `p` does not lie in
+    /// the open interval $(0, 1)$.
+    pub fn quantile_batch(&self, p: ArrayView1<F>) -> Result<Array1<F>, KDEError> {
+        self.check_univariate("quantile_batch")?;
+        for p in p.iter() {
+            check_probability(*p)?;
+        }
+        let mut previous: Option<(F, F)> = None;
+        return Ok(p.mapv(|p| {
+            let lower = previous.filter(|(p_prev, _)| *p_prev <= p).map(|(_, x)| x);
+            let x = self.invert_cdf(p, lower);
+            previous = Some((p, x));
+            x
+        }));
+    }
+
+    /// Solve $F_\mathrm{KDE}(x) = p$ for a one-dimensional KDE, optionally starting from a known `lower` bound of
+    /// the solution.
+    fn invert_cdf(&self, p: F, lower: Option<F>) -> F {
+        let sigma = self.cholesky[[0, 0]];
+        let data = self.data_column(0);
+        let cdf = |x: F| {
+            self.univariate_kernel_average(|xi| {
+                std_normal_interval(F::neg_infinity(), (x - xi) / sigma)
+            })
+        };
+        let pdf = |x: F| {
+            let norm = F::one() / (sigma * F::TAU().sqrt());
+            self.univariate_kernel_average(|xi| {
+                let z = (x - xi) / sigma;
+                norm * (-z * z / F::from(2).unwrap()).exp()
+            })
+        };
+
+        // Bracket the quantile, starting from the range of the data widened by one kernel width
+        let (mut lo, mut step) = match lower {
+            Some(lower) => (lower, sigma),
+            None => (
+                data.fold(F::infinity(), |acc, x| acc.min(*x)) - sigma,
+                sigma,
+            ),
+        };
+        // The number of steps is limited and the bounds are kept finite in case the distribution function does not
+        // reach p due to rounding
+        for _ in 0..MAX_ITERATIONS {
+            if !(cdf(lo) > p && (lo - step).is_finite()) {
+                break;
+            }
+            lo = lo - step;
+            step = step + step;
+        }
+        let mut hi = data.fold(F::neg_infinity(), |acc, x| acc.max(*x)) + sigma;
+        step = sigma;
+        for _ in 0..MAX_ITERATIONS {
+            if !(cdf(hi) < p && (hi + step).is_finite()) {
+                break;
+            }
+            hi = hi + step;
+            step = step + step;
+        }
+        hi = hi.max(lo);
+
+        // Safeguarded Newton iteration
+        let tolerance = F::from(4).unwrap() * F::epsilon();
+        let mut x = (lo + hi) / F::from(2).unwrap();
+        for _ in 0..MAX_ITERATIONS {
+            let residual = cdf(x) - p;
+            if residual == F::zero() {
+                break;
+            }
+            if residual < F::zero() {
+                lo = x;
+            } else {
+                hi = x;
+            }
+            let newton = x - residual / pdf(x);
+            let next = if newton > lo && newton < hi {
+                newton
+            } else {
+                (lo + hi) / F::from(2).unwrap()
+            };
+            let converged = (next - x).abs() <= tolerance * (x.abs() + sigma);
+            x = next;
+            if converged || hi - lo <= tolerance * (x.abs() + sigma) {
+                break;
+            }
+        }
+        return x;
+    }
+
+    /// Return a `ShapeError` mentioning `method` if the KDE is not one-dimensional.
+    pub(crate) fn check_univariate(&self, method: &str) -> Result<(), KDEError> {
+        if self.n_dims() != 1 {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "`{method}` requires a one-dimensional KDE, found dimension {}",
+                    self.n_dims()
+                ),
+            ));
+        }
+        return Ok(());
+    }
+
+    /// Weighted average of `f(x_i)` over the data points of a one-dimensional KDE.
+    ///
+    /// The weighted sum and the sum of weights are both compensated, such that the average of a constant `f` is exact,
+    /// e.g. the distribution function is exactly one far in the upper tail.
+    pub(crate) fn univariate_kernel_average(&self, f: impl Fn(F) -> F) -> F {
+        let data = self.data_column(0);
+        return if let Some(ref w) = self.weights {
+            compensated_sum(data.iter().zip(w.iter()).map(|(xi, w)| *w * f(*xi)))
+                / compensated_sum(w.iter().copied())
+        } else {
+            compensated_sum(data.iter().map(|xi| f(*xi))) / F::from(data.len()).unwrap()
+        };
+    }
+}
+
+/// Return a `DomainError` if `p` does not lie in the open interval $(0, 1)$.
+fn check_probability<F: Float>(p: F) -> Result<(), KDEError> {
+    if !(p > F::zero() && p < F::one()) {
+        return Err(KDEError::new(
+            ErrorKind::DomainError,
+            format!(
+                "probability must lie in the open interval (0, 1), found {}",
+                p.to_f64().unwrap()
+            ),
+        ));
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ErrorKind, GaussianKDE};
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn cdf_test() {
+        let data = array![
+            [0.5634880436705391],
+            [0.445981611845074],
+            [0.7438671296401687]
+        ];
+        let w_test = array![0.04622972052712859, 0.15162404870472723, 0.291517199926956];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let kde_w = GaussianKDE::new(data, Some(w_test)).unwrap();
+        // Cumulative Simpson integration of the density on a fine grid starting far in the left tail
+        let step = 1e-3;
+        let grid = Array1::range(-1.0, 2.5 + step / 2.0, step);
+        for kde in [kde, kde_w] {
+            let pdf = kde.eval_batch(grid.view().insert_axis(Axis(1)));
+            let cdf = kde.cdf_batch(grid.view()).unwrap();
+            let mut integral = 0.0;
+            for k in (2..grid.len()).step_by(2) {
+                integral += step / 3.0 * (pdf[k - 2] + 4.0 * pdf[k - 1] + pdf[k]);
+                assert_relative_eq!(cdf[k], integral, epsilon = 1e-9);
+            }
+            assert!(cdf.windows(2).into_iter().all(|w| w[0] <= w[1]));
+            assert_eq!(kde.cdf(f64::NEG_INFINITY).unwrap(), 0.0);
+            assert_eq!(kde.cdf(f64::INFINITY).unwrap(), 1.0);
+            assert_relative_eq!(
+                kde.cdf(0.6).unwrap() - kde.cdf(0.3).unwrap(),
+                kde.integrate_box_1d(0.3, 0.6).unwrap(),
+                max_relative = 1e-12
+            );
+        }
+
+        let kde_2d = GaussianKDE::new(array![[0.0, 1.0], [1.0, 0.5], [2.0, 3.0]], None).unwrap();
+        let res = kde_2d.cdf(0.5);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde_2d.cdf_batch(array![0.5].view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn quantile_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(3);
+        // Second component is standard-normal, the first one is uniform
+        let data = Array2::from_shape_fn((5000, 2), |(_, j)| {
+            if j == 1 {
+                rng.sample(StandardNormal)
+            } else {
+                rng.random::<f64>()
+            }
+        });
+        let kde = GaussianKDE::new(data, None)
+            .unwrap()
+            .marginalize_to(&[1])
+            .unwrap();
+        let p = array![0.025, 0.16, 0.5, 0.9, 0.975];
+        // Quantiles of the standard normal distribution from Python's `statistics.NormalDist().inv_cdf(p)`
+        let normal = array![
+            -1.959963984540054,
+            -0.994457883209753,
+            0.0,
+            1.2815515655446004,
+            1.959963984540054
+        ];
+        // The smoothing with the kernels increases the variance by a factor of 1 + h^2
+        let scale = (1.0 + kde.bandwidth_factor().powi(2)).sqrt();
+        let res = kde.quantile_batch(p.view()).unwrap();
+        for (j, p_j) in p.iter().enumerate() {
+            assert_relative_eq!(res[j], scale * normal[j], epsilon = 3e-2);
+            assert_relative_eq!(kde.quantile(*p_j).unwrap(), res[j], epsilon = 1e-12);
+            assert_relative_eq!(kde.cdf(res[j]).unwrap(), *p_j, epsilon = 1e-10);
+        }
+        // Unsorted probabilities and extreme tails
+        let p = array![0.7, 1e-12, 0.3, 1.0 - 1e-12];
+        let res = kde.quantile_batch(p.view()).unwrap();
+        for (j, p_j) in p.iter().enumerate() {
+            assert_relative_eq!(kde.cdf(res[j]).unwrap(), *p_j, epsilon = 1e-10);
+        }
+
+        for p in [0.0, 1.0, -0.5, f64::NAN] {
+            let res = kde.quantile(p);
+            assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        }
+        let res = kde.quantile_batch(array![0.5, 1.5].view());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+        let kde_2d = GaussianKDE::new(array![[0.0, 1.0], [1.0, 0.5], [2.0, 3.0]], None).unwrap();
+        let res = kde_2d.quantile(0.5);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn quantile_tail_test() {
+        // The weighted average must reach exactly one in the upper tail, otherwise the bracketing of probabilities
+        // closer to one than its rounding error does not terminate
+        let mut rng = Pcg64Mcg::seed_from_u64(30);
+        let data = Array2::from_shape_simple_fn((1000, 1), || rng.sample::<f64, _>(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(1000, || rng.random_range(0.0..1.0));
+        let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+        assert_eq!(kde.cdf(1e6).unwrap(), 1.0);
+        assert_eq!(kde.cdf(-1e6).unwrap(), 0.0);
+        for p in [
+            1.0 - f64::EPSILON / 2.0,
+            1.0 - f64::EPSILON,
+            f64::EPSILON / 2.0,
+            1e-300,
+        ] {
+            let x = kde.quantile(p).unwrap();
+            assert!(x.is_finite());
+            assert_relative_eq!(kde.cdf(x).unwrap(), p, epsilon = 4.0 * f64::EPSILON);
+        }
+    }
+
+    #[test]
+    fn sf_test() {
+        let data = array![
+            [0.5634880436705391],
+            [0.445981611845074],
+            [0.7438671296401687]
+        ];
+        let w_test = array![0.04622972052712859, 0.15162404870472723, 0.291517199926956];
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let kde_w = GaussianKDE::new(data, Some(w_test)).unwrap();
+        for kde in [kde, kde_w] {
+            let sigma = f64::sqrt(kde.covariance()[[0, 0]]);
+            for x in [-0.5, 0.3, 0.6, 1.2] {
+                assert_relative_eq!(
+                    kde.sf(x).unwrap(),
+                    1.0 - kde.cdf(x).unwrap(),
+                    max_relative = 1e-12
+                );
+            }
+            // Simpson integration of the density over the upper tail 8 kernel widths above the largest data point,
+            // where `1 - cdf` has lost all precision
+            let x = 0.7438671296401687 + 8.0 * sigma;
+            let step = sigma / 1000.0;
+            let grid = Array1::range(x, x + 10.0 * sigma + step / 2.0, step);
+            let pdf = kde.eval_batch(grid.view().insert_axis(Axis(1)));
+            let integral = (2..grid.len())
+                .step_by(2)
+                .map(|k| step / 3.0 * (pdf[k - 2] + 4.0 * pdf[k - 1] + pdf[k]))
+                .sum::<f64>();
+            assert_relative_eq!(kde.sf(x).unwrap(), integral, max_relative = 1e-8);
+            assert!(((1.0 - kde.cdf(x).unwrap()) / integral - 1.0).abs() > 0.1);
+            assert_eq!(kde.sf(f64::NEG_INFINITY).unwrap(), 1.0);
+            assert_eq!(kde.sf(f64::INFINITY).unwrap(), 0.0);
+        }
+    }
+
+    #[test]
+    fn central_interval_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(8);
+        let data =
+            Array2::from_shape_simple_fn((5000, 1), || 3.0 * rng.sample::<f64, _>(StandardNormal));
+        let kde = GaussianKDE::new(data, None).unwrap();
+        let (low, high) = kde.central_interval(0.68).unwrap();
+        assert_relative_eq!(low, -3.0, max_relative = 5e-2);
+        assert_relative_eq!(high, 3.0, max_relative = 5e-2);
+        assert_relative_eq!(kde.cdf(low).unwrap(), 0.16, epsilon = 1e-10);
+        assert_relative_eq!(kde.sf(high).unwrap(), 0.16, epsilon = 1e-10);
+
+        let res = kde.central_interval(1.0);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::DomainError)));
+
+        // Both tails of a mass close to one, which require a distribution function reaching exactly one
+        let data = Array2::from_shape_simple_fn((1000, 1), || rng.sample::<f64, _>(StandardNormal));
+        let weights = Array1::from_shape_simple_fn(1000, || rng.random_range(0.0..1.0));
+        let kde = GaussianKDE::new(data, Some(weights)).unwrap();
+        for mass in [1.0 - f64::EPSILON, 1.0 - 1e-12] {
+            let (low, high) = kde.central_interval(mass).unwrap();
+            assert!(low.is_finite() && high.is_finite() && low < high);
+            assert_relative_eq!(
+                kde.cdf(low).unwrap(),
+                (1.0 - mass) / 2.0,
+                max_relative = 1e-6
+            );
+            assert_relative_eq!(
+                kde.cdf(high).unwrap(),
+                (1.0 + mass) / 2.0,
+                epsilon = 4.0 * f64::EPSILON
+            );
+        }
+        let kde_2d = GaussianKDE::new(array![[0.0, 1.0], [1.0, 0.5], [2.0, 3.0]], None).unwrap();
+        let res = kde_2d.sf(0.5);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde_2d.central_interval(0.5);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+}
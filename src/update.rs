@@ -0,0 +1,721 @@
+//! Incremental updates of a [`GaussianKDE`] with additional or removed data points or new weights, avoiding a refit
+//! from scratch for data arriving in batches, sliding windows over a stream of data or iterative reweighting.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::bandwidth::Bandwidth;
+use crate::covariance::RunningMoments;
+use crate::{
+    CowGaussianKDE, ErrorKind, KDEError, check_finite, check_weight_values, check_weights,
+    kernel_cholesky, shared,
+};
+use alloc::format;
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Append the data point `point` with the given weight to the dataset, see [`GaussianKDE::extend`]. Without a
+    /// weight, the point is weighed like the points of an unweighted KDE, i.e. with one.
+    pub fn push(&mut self, point: ArrayView1<F>, weight: Option<F>) -> Result<(), KDEError> {
+        let weight = weight.map(|w| [w]);
+        return self.extend(
+            point.insert_axis(Axis(0)),
+            weight.as_ref().map(|w| aview1(w)),
+        );
+    }
+
+    /// Append the points given by the array `points` of shape `(n_points, dim)` with the given weights to the
+    /// dataset. Without weights, the points are weighed like the points of an unweighted KDE, i.e. with one.
+    ///
+    /// The result is the KDE constructed from the concatenated dataset, but the weighted mean and covariance matrix
+    /// of the dataset are updated with the new points only, such that the kernel covariance is obtained without
+    /// another pass over the stored points. The bandwidth factor is recomputed by the bandwidth selector. For
+    /// [`CovarianceMode::Robust`](crate::CovarianceMode::Robust) and
+    /// [`CovarianceMode::LedoitWolf`](crate::CovarianceMode::LedoitWolf), the covariance matrix (or the shrinkage
+    /// intensity) still requires a pass over the whole dataset. A user-given bandwidth matrix is kept unchanged.
+    ///
+    /// The updated dataset is built next to the stored one, such that a borrowed dataset, or one shared with other
+    /// KDEs (e.g. marginals), is left untouched, and so is the KDE if the update fails.
+    ///
+    /// Returns a `ShapeError` if the dimension of the points does not match the dimension of the KDE or the number of
+    /// weights does not match the number of points, a `DataError` if any point is not finite and a `WeightError` if
+    /// any weight is negative or not finite.
+    pub fn extend(
+        &mut self,
+        points: ArrayView2<F>,
+        weights: Option<ArrayView1<F>>,
+    ) -> Result<(), KDEError> {
+        if points.dim().1 != self.n_dims() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected points of dimension {}, found {}",
+                    self.n_dims(),
+                    points.dim().1
+                ),
+            ));
+        }
+        if let Some(w) = weights {
+            if w.dim() != points.dim().0 {
+                return Err(KDEError::new(
+                    ErrorKind::ShapeError,
+                    format!(
+                        "expected {} weights for {} points, found {}",
+                        points.dim().0,
+                        points.dim().0,
+                        w.dim()
+                    ),
+                ));
+            }
+            check_weight_values(w)?;
+        }
+        check_finite(points)?;
+        let mut moments = self.current_moments();
+        if let Some(ref mut moments) = moments {
+            for (i, x) in points.rows().into_iter().enumerate() {
+                moments.push(x, weights.map_or(F::one(), |w| w[i]));
+            }
+        }
+        let data = ndarray::concatenate(Axis(0), &[self.dataset().view(), points]).unwrap();
+        let new_weights = if self.weights.is_none() && weights.is_none() {
+            None
+        } else {
+            let or_ones =
+                |w: Option<ArrayView1<F>>, n| w.map_or_else(|| Array1::ones(n), |w| w.to_owned());
+            Some(
+                ndarray::concatenate(
+                    Axis(0),
+                    &[
+                        or_ones(self.weights(), self.n_points()).view(),
+                        or_ones(weights, points.dim().0).view(),
+                    ],
+                )
+                .unwrap(),
+            )
+        };
+        return self.replace_dataset(data, new_weights, moments);
+    }
+
+    /// Remove the data point with the given index from the dataset, see [`GaussianKDE::truncate_front`].
+    ///
+    /// Returns an `IndexError` if `index` is out of bounds and a `SingularityError` if too few points would remain
+    /// for a non-singular covariance matrix.
+    pub fn remove(&mut self, index: usize) -> Result<(), KDEError> {
+        if index >= self.n_points() {
+            return Err(KDEError::new(
+                ErrorKind::IndexError,
+                format!(
+                    "index {index} is out of bounds for a dataset of {} points",
+                    self.n_points()
+                ),
+            ));
+        }
+        self.check_remaining(self.n_points() - 1)?;
+        let mut moments = self.current_moments();
+        let (data, weights) = {
+            let data = self.dataset();
+            if let Some(ref mut moments) = moments {
+                let w = self.weights().map_or(F::one(), |w| w[index]);
+                moments.remove(data.row(index), w);
+            }
+            let weights = self.weights().map(|w| {
+                ndarray::concatenate(Axis(0), &[w.slice(s![..index]), w.slice(s![index + 1..])])
+                    .unwrap()
+            });
+            let data = ndarray::concatenate(
+                Axis(0),
+                &[data.slice(s![..index, ..]), data.slice(s![index + 1.., ..])],
+            )
+            .unwrap();
+            (data, weights)
+        };
+        return self.replace_dataset(data, weights, moments);
+    }
+
+    /// Remove the first `k` data points from the dataset, e.g. to maintain a KDE over a sliding window of the most
+    /// recent points together with [`GaussianKDE::extend`].
+    ///
+    /// As for additional points, the weighted mean and covariance matrix of the dataset are downdated with the
+    /// removed points only and the bandwidth factor is recomputed by the bandwidth selector.
+    ///
+    /// Returns an `IndexError` if `k` exceeds the number of points and a `SingularityError` if too few points would
+    /// remain for a non-singular covariance matrix.
+    pub fn truncate_front(&mut self, k: usize) -> Result<(), KDEError> {
+        if k > self.n_points() {
+            return Err(KDEError::new(
+                ErrorKind::IndexError,
+                format!(
+                    "cannot remove {k} points from a dataset of {} points",
+                    self.n_points()
+                ),
+            ));
+        }
+        self.check_remaining(self.n_points() - k)?;
+        let mut moments = self.current_moments();
+        let (data, weights) = {
+            let data = self.dataset();
+            if let Some(ref mut moments) = moments {
+                for (i, x) in data.slice(s![..k, ..]).rows().into_iter().enumerate() {
+                    moments.remove(x, self.weights().map_or(F::one(), |w| w[i]));
+                }
+            }
+            let weights = self.weights().map(|w| w.slice(s![k..]).to_owned());
+            (data.slice(s![k.., ..]).to_owned(), weights)
+        };
+        return self.replace_dataset(data, weights, moments);
+    }
+
+    /// Replace the weights of the data points, e.g. during an iterative reweighting procedure. The dataset itself is
+    /// neither copied nor modified, but the weighted covariance matrix, the bandwidth factor and the normalization are
+    /// recomputed as for a new KDE with the given weights. A user-given bandwidth matrix is kept unchanged.
+    ///
+    /// Returns a `ShapeError` if the number of weights does not match the number of data points, a `WeightError` if
+    /// any weight is negative or not finite or all weights are zero and a `SingularityError` if the weighted
+    /// covariance matrix is singular, in which case the previous weights are kept.
+    pub fn set_weights(&mut self, weights: Array1<F>) -> Result<(), KDEError> {
+        if weights.dim() != self.n_points() {
+            return Err(KDEError::new(
+                ErrorKind::ShapeError,
+                format!(
+                    "expected {} weights for a dataset of {} points, found {}",
+                    self.n_points(),
+                    self.n_points(),
+                    weights.dim()
+                ),
+            ));
+        }
+        check_weights(weights.view())?;
+        let kernel = self.fit_kernel(self.dataset().view(), Some(weights.view()), None)?;
+        self.weights = Some(shared(weights));
+        self.set_kernel(kernel, None);
+        return Ok(());
+    }
+
+    /// Drop the weights of the data points, such that all points are weighed equally, see
+    /// [`GaussianKDE::set_weights`].
+    pub fn clear_weights(&mut self) -> Result<(), KDEError> {
+        let kernel = self.fit_kernel(self.dataset().view(), None, None)?;
+        self.weights = None;
+        self.set_kernel(kernel, None);
+        return Ok(());
+    }
+
+    /// Return a `SingularityError` if a dataset of `n_points` points would result in a singular covariance matrix,
+    /// analogous to the checks during construction.
+    fn check_remaining(&self, n_points: usize) -> Result<(), KDEError> {
+        if n_points == 0
+            || (n_points < self.n_dims()
+                && !self.covariance_mode.allows_few_points()
+                && !self.custom_bandwidth_matrix)
+        {
+            return Err(KDEError::new(
+                ErrorKind::SingularityError,
+                format!(
+                    "the dataset would have fewer entries ({}) than dimensions ({}), resulting in a singular covariance matrix",
+                    n_points,
+                    self.n_dims()
+                ),
+            ));
+        }
+        return Ok(());
+    }
+
+    /// Copy of the running moments of the dataset to be updated, computing them if this is the first update. Returns
+    /// `None` for a user-given bandwidth matrix, which does not depend on the dataset.
+    fn current_moments(&self) -> Option<RunningMoments<F>> {
+        if self.custom_bandwidth_matrix {
+            return None;
+        }
+        return Some(
+            self.moments
+                .clone()
+                .unwrap_or_else(|| RunningMoments::new(self.dataset().view(), self.weights())),
+        );
+    }
+
+    /// Recompute the bandwidth factor and the kernel covariance for the updated dataset and weights, using their
+    /// running `moments` if available, and store all of them. The KDE is left unchanged if this fails, e.g. because
+    /// the covariance matrix of the updated dataset is singular.
+    fn replace_dataset(
+        &mut self,
+        data: Array2<F>,
+        weights: Option<Array1<F>>,
+        moments: Option<RunningMoments<F>>,
+    ) -> Result<(), KDEError> {
+        let kernel = self.fit_kernel(
+            data.view(),
+            weights.as_ref().map(|w| w.view()),
+            moments.as_ref(),
+        )?;
+        self.data = shared(data);
+        self.weights = weights.map(shared);
+        self.dims = None;
+        self.selected_data = Default::default();
+        self.set_kernel(kernel, moments);
+        return Ok(());
+    }
+
+    /// Compute the bandwidth factor and the Cholesky factor of the kernel covariance together with its inverse for
+    /// the given dataset and weights, see [`kernel_cholesky`]. Returns `None` for a user-given bandwidth matrix, which
+    /// is kept unchanged.
+    fn fit_kernel(
+        &self,
+        data: ArrayView2<F>,
+        weights: Option<ArrayView1<F>>,
+        moments: Option<&RunningMoments<F>>,
+    ) -> Result<Option<Kernel<F>>, KDEError> {
+        if self.custom_bandwidth_matrix {
+            return Ok(None);
+        }
+        let bw = self.bandwidth.bandwidth(data, weights);
+        let (cholesky, inv_cholesky) = kernel_cholesky(
+            data,
+            weights,
+            bw,
+            self.covariance_mode,
+            self.regularization,
+            moments,
+        )?;
+        return Ok(Some(Kernel {
+            bandwidth_factor: bw,
+            cholesky,
+            inv_cholesky,
+        }));
+    }
+
+    /// Store the `kernel` recomputed after the dataset or the weights changed together with the running `moments`
+    /// of the dataset, update the normalization and reset the cached kernel selection, whitened dataset and kd-tree.
+    fn set_kernel(&mut self, kernel: Option<Kernel<F>>, moments: Option<RunningMoments<F>>) {
+        self.whitened = Default::default();
+        self.tree = Default::default();
+        #[cfg(feature = "sample")]
+        {
+            self.components = Default::default();
+        }
+        if let Some(kernel) = kernel {
+            self.bandwidth_factor = kernel.bandwidth_factor;
+            self.cholesky = kernel.cholesky;
+            self.inv_cholesky = kernel.inv_cholesky;
+            self.moments = moments;
+        }
+        self.update_normalization();
+    }
+}
+
+/// Bandwidth factor and Cholesky factor of the kernel covariance with its inverse, recomputed after an update.
+struct Kernel<F> {
+    bandwidth_factor: F,
+    cholesky: Array2<F>,
+    inv_cholesky: Array2<F>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Bandwidth, CovarianceMode, CowGaussianKDE, ErrorKind, GaussianKDE, ScottBandwidth,
+    };
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::{Rng, SeedableRng};
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    fn assert_same_kde<B: Bandwidth<f64>>(
+        kde: &CowGaussianKDE<'_, f64, B>,
+        reference: &CowGaussianKDE<'_, f64, B>,
+    ) {
+        let x = array![[0.0, 0.0, 0.0], [0.5, -0.3, 1.0], [-1.0, 1.2, -0.4]];
+        assert_eq!(kde.data(), reference.data());
+        assert_eq!(kde.weights(), reference.weights());
+        assert_relative_eq!(
+            kde.bandwidth_factor(),
+            reference.bandwidth_factor(),
+            max_relative = 1e-12
+        );
+        assert_relative_eq!(
+            kde.covariance(),
+            reference.covariance(),
+            max_relative = 1e-12
+        );
+        assert_relative_eq!(
+            kde.eval_batch(x.view()),
+            reference.eval_batch(x.view()),
+            max_relative = 1e-12
+        );
+    }
+
+    #[test]
+    fn push_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3, 0.5], [0.4, 1.1, -0.2], [2.0, -0.5, 0.9], [0.9, 0.8, 1.4], [-0.3, -1.4, 0.1],
+            [1.5, 2.2, -0.7], [-2.1, 0.6, 0.3], [0.1, 0.0, -1.1], [1.1, -0.9, 0.6], [-0.7, 1.7, 0.2],
+            [0.6, -0.2, -0.4], [-1.6, -0.8, 1.0], [0.3, 1.4, 0.7], [1.8, 0.5, -0.9], [-0.4, -0.1, 0.0]
+        ];
+        let weights = array![
+            0.5, 2.0, 1.0, 0.2, 1.5, 0.8, 0.3, 3.0, 1.2, 0.6, 0.9, 1.1, 0.4, 2.2, 0.7
+        ];
+
+        let mut kde = GaussianKDE::new(data.slice(s![..5, ..]).to_owned(), None).unwrap();
+        for x in data.slice(s![5.., ..]).rows() {
+            kde.push(x, None).unwrap();
+        }
+        assert_same_kde(&kde, &GaussianKDE::new(data.clone(), None).unwrap());
+
+        let mut kde = GaussianKDE::new(
+            data.slice(s![..4, ..]).to_owned(),
+            Some(weights.slice(s![..4]).to_owned()),
+        )
+        .unwrap();
+        for (x, w) in data
+            .slice(s![4.., ..])
+            .rows()
+            .into_iter()
+            .zip(weights.slice(s![4..]))
+        {
+            kde.push(x, Some(*w)).unwrap();
+        }
+        assert_same_kde(
+            &kde,
+            &GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap(),
+        );
+
+        // Unweighted points mixed with weighted ones are weighed with one
+        let mut kde = GaussianKDE::new(data.slice(s![..8, ..]).to_owned(), None).unwrap();
+        kde.extend(data.slice(s![8..12, ..]), Some(weights.slice(s![8..12])))
+            .unwrap();
+        kde.extend(data.slice(s![12.., ..]), None).unwrap();
+        let mut expected_weights = Array1::ones(15);
+        expected_weights
+            .slice_mut(s![8..12])
+            .assign(&weights.slice(s![8..12]));
+        assert_same_kde(
+            &kde,
+            &GaussianKDE::new(data.clone(), Some(expected_weights)).unwrap(),
+        );
+
+        for mode in [
+            CovarianceMode::Diagonal,
+            CovarianceMode::Shrinkage(0.2),
+            CovarianceMode::LedoitWolf,
+            CovarianceMode::Robust,
+        ] {
+            let mut kde = GaussianKDE::with_covariance_mode(
+                data.slice(s![..6, ..]).to_owned(),
+                Some(weights.slice(s![..6]).to_owned()),
+                ScottBandwidth {},
+                mode,
+            )
+            .unwrap();
+            kde.extend(data.slice(s![6.., ..]), Some(weights.slice(s![6..])))
+                .unwrap();
+            let reference = GaussianKDE::with_covariance_mode(
+                data.clone(),
+                Some(weights.clone()),
+                ScottBandwidth {},
+                mode,
+            )
+            .unwrap();
+            assert_same_kde(&kde, &reference);
+        }
+    }
+
+    #[test]
+    fn push_shared_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3, 0.5], [0.4, 1.1, -0.2], [2.0, -0.5, 0.9], [0.9, 0.8, 1.4], [-0.3, -1.4, 0.1],
+            [1.5, 2.2, -0.7], [-2.1, 0.6, 0.3], [0.1, 0.0, -1.1], [1.1, -0.9, 0.6], [-0.7, 1.7, 0.2],
+            [0.6, -0.2, -0.4], [-1.6, -0.8, 1.0], [0.3, 1.4, 0.7], [1.8, 0.5, -0.9], [-0.4, -0.1, 0.0]
+        ];
+        // Borrowed data is copied and left untouched
+        let mut kde = GaussianKDE::from_view(data.slice(s![..10, ..]), None).unwrap();
+        kde.extend(data.slice(s![10.., ..]), None).unwrap();
+        assert_same_kde(&kde, &GaussianKDE::new(data.clone(), None).unwrap());
+
+        // Updating a marginal leaves the dataset of its parent untouched
+        let parent = GaussianKDE::new(data.slice(s![..10, ..]).to_owned(), None).unwrap();
+        let mut marginal = parent.marginalize_to(&[2, 0]).unwrap();
+        marginal.extend(data.slice(s![10.., ..;-2]), None).unwrap();
+        assert_eq!(parent.data(), data.slice(s![..10, ..]));
+        let reference = GaussianKDE::new(data.select(Axis(1), &[2, 0]), None).unwrap();
+        let x = array![[0.0, 0.0], [0.5, -0.3]];
+        assert_relative_eq!(
+            marginal.eval_batch(x.view()),
+            reference.eval_batch(x.view()),
+            max_relative = 1e-12
+        );
+
+        // A user-given bandwidth matrix is kept
+        let h_matrix = array![[0.3, 0.1, 0.0], [0.1, 0.2, 0.0], [0.0, 0.0, 0.4]];
+        let mut kde = GaussianKDE::with_bandwidth_matrix(
+            data.slice(s![..10, ..]).to_owned(),
+            None,
+            h_matrix.clone(),
+        )
+        .unwrap();
+        kde.extend(data.slice(s![10.., ..]), None).unwrap();
+        assert_same_kde(
+            &kde,
+            &GaussianKDE::with_bandwidth_matrix(data.clone(), None, h_matrix).unwrap(),
+        );
+    }
+
+    #[test]
+    fn sliding_window_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(7);
+        let points =
+            Array2::from_shape_simple_fn((1000, 2), || rng.sample::<f64, _>(StandardNormal));
+        let points = &points.dot(&array![[1.0, 0.6], [0.0, 0.8]]) + &array![3.0, -1.0];
+        let weights = Array1::from_shape_simple_fn(1000, || rng.random_range(0.5..2.0));
+        let x = array![[3.0, -1.0], [2.0, 0.5], [4.5, -2.0]];
+
+        let mut kde = GaussianKDE::new(points.slice(s![..3, ..]).to_owned(), None).unwrap();
+        for p in points.slice(s![3.., ..]).rows() {
+            kde.push(p, None).unwrap();
+        }
+        kde.truncate_front(500).unwrap();
+        let reference = GaussianKDE::new(points.slice(s![500.., ..]).to_owned(), None).unwrap();
+        assert_eq!(kde.data(), reference.data());
+        assert_relative_eq!(
+            kde.covariance(),
+            reference.covariance(),
+            max_relative = 1e-10
+        );
+        assert_relative_eq!(
+            kde.eval_batch(x.view()),
+            reference.eval_batch(x.view()),
+            max_relative = 1e-10
+        );
+
+        // Sliding window over weighted points, with single removals
+        let mut kde = GaussianKDE::new(
+            points.slice(s![..100, ..]).to_owned(),
+            Some(weights.slice(s![..100]).to_owned()),
+        )
+        .unwrap();
+        for start in (0..900).step_by(100) {
+            let end = start + 200;
+            kde.extend(
+                points.slice(s![start + 100..end, ..]),
+                Some(weights.slice(s![start + 100..end])),
+            )
+            .unwrap();
+            kde.truncate_front(100).unwrap();
+        }
+        kde.remove(17).unwrap();
+        kde.remove(0).unwrap();
+        let indices = (901..1000).filter(|i| *i != 917).collect::<Vec<_>>();
+        let reference = GaussianKDE::new(
+            points.select(Axis(0), &indices),
+            Some(weights.select(Axis(0), &indices)),
+        )
+        .unwrap();
+        assert_eq!(kde.data(), reference.data());
+        assert_relative_eq!(
+            kde.covariance(),
+            reference.covariance(),
+            max_relative = 1e-10
+        );
+        assert_relative_eq!(
+            kde.eval_batch(x.view()),
+            reference.eval_batch(x.view()),
+            max_relative = 1e-10
+        );
+    }
+
+    #[test]
+    fn remove_error_test() {
+        let data = array![
+            [-1.2, 0.3, 0.5],
+            [0.4, 1.1, -0.2],
+            [2.0, -0.5, 0.9],
+            [0.9, 0.8, 1.4]
+        ];
+        let mut kde = GaussianKDE::new(data.clone(), None).unwrap();
+        assert!(matches!(kde.remove(4), Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+        assert!(matches!(kde.truncate_front(5), Err(e) if matches!(e.kind, ErrorKind::IndexError)));
+        assert!(matches!(
+            kde.truncate_front(2),
+            Err(e) if matches!(e.kind, ErrorKind::SingularityError)
+        ));
+        assert_eq!(kde.data(), data);
+
+        // The first component of the remaining points is constant, which leaves the KDE unchanged
+        let data = array![[1.0, 2.0], [0.0, 0.0], [0.0, 1.0], [2.0, -1.0], [0.0, 3.0]];
+        let weights = array![0.5, 1.0, 2.0, 1.5, 0.8];
+        let mut kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        kde.truncate_front(1).unwrap();
+        let res = kde.remove(2);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+        let reference = GaussianKDE::new(
+            data.slice(s![1.., ..]).to_owned(),
+            Some(weights.slice(s![1..]).to_owned()),
+        )
+        .unwrap();
+        assert_eq!(kde.data(), reference.data());
+        assert_eq!(kde.weights(), reference.weights());
+        assert_relative_eq!(
+            kde.covariance(),
+            reference.covariance(),
+            max_relative = 1e-12
+        );
+        let res = kde.truncate_front(3);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+        assert_eq!(kde.data(), reference.data());
+        kde.remove(0).unwrap();
+        let reference = GaussianKDE::new(
+            data.slice(s![2.., ..]).to_owned(),
+            Some(weights.slice(s![2..]).to_owned()),
+        )
+        .unwrap();
+        assert_relative_eq!(
+            kde.covariance(),
+            reference.covariance(),
+            max_relative = 1e-12
+        );
+    }
+
+    #[test]
+    fn set_weights_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3, 0.5], [0.4, 1.1, -0.2], [2.0, -0.5, 0.9], [0.9, 0.8, 1.4], [-0.3, -1.4, 0.1],
+            [1.5, 2.2, -0.7], [-2.1, 0.6, 0.3], [0.1, 0.0, -1.1], [1.1, -0.9, 0.6], [-0.7, 1.7, 0.2],
+            [0.6, -0.2, -0.4], [-1.6, -0.8, 1.0], [0.3, 1.4, 0.7], [1.8, 0.5, -0.9], [-0.4, -0.1, 0.0]
+        ];
+        let weights = array![
+            0.5, 2.0, 1.0, 0.2, 1.5, 0.8, 0.3, 3.0, 1.2, 0.6, 0.9, 1.1, 0.4, 2.2, 0.7
+        ];
+        let mut kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let ptr = kde.data().as_ptr();
+        kde.set_weights(weights.clone()).unwrap();
+        assert_eq!(kde.data().as_ptr(), ptr);
+        assert_same_kde(
+            &kde,
+            &GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap(),
+        );
+        let new_weights = weights.mapv(|w| 1.0 / (1.0 + w));
+        kde.set_weights(new_weights.clone()).unwrap();
+        assert_same_kde(
+            &kde,
+            &GaussianKDE::new(data.clone(), Some(new_weights.clone())).unwrap(),
+        );
+        kde.clear_weights().unwrap();
+        assert_eq!(kde.data().as_ptr(), ptr);
+        assert_same_kde(&kde, &GaussianKDE::new(data.clone(), None).unwrap());
+
+        // Reweighting after adding points does not reuse the running moments of the previous weights
+        kde.set_weights(weights.clone()).unwrap();
+        kde.push(array![0.2, -0.4, 0.8].view(), Some(1.3)).unwrap();
+        let reweighted = ndarray::concatenate![Axis(0), new_weights.slice(s![..;-1]), array![0.4]];
+        kde.set_weights(reweighted.clone()).unwrap();
+        let reference = GaussianKDE::new(
+            ndarray::concatenate![Axis(0), data, array![[0.2, -0.4, 0.8]]],
+            Some(reweighted),
+        )
+        .unwrap();
+        assert_same_kde(&kde, &reference);
+
+        let res = kde.set_weights(weights.clone());
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+    }
+
+    #[test]
+    fn set_weights_error_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3, 0.5], [0.4, 1.1, -0.2], [2.0, -0.5, 0.9], [0.9, 0.8, 1.4], [-0.3, -1.4, 0.1]
+        ];
+        let weights = array![0.5, 2.0, 1.0, 0.2, 1.5];
+        // A single point with a non-zero weight has a singular covariance matrix, which leaves the KDE unchanged
+        let mut kde = GaussianKDE::new(data.clone(), Some(weights.clone())).unwrap();
+        let res = kde.set_weights(array![1.0, 0.0, 0.0, 0.0, 0.0]);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+        assert_same_kde(
+            &kde,
+            &GaussianKDE::new(data.clone(), Some(weights)).unwrap(),
+        );
+    }
+
+    #[cfg(feature = "sample")]
+    #[test]
+    fn set_weights_sampling_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(3);
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3, 0.5], [0.4, 1.1, -0.2], [2.0, -0.5, 0.9], [0.9, 0.8, 1.4], [-0.3, -1.4, 0.1],
+            [1.5, 2.2, -0.7], [-2.1, 0.6, 0.3], [0.1, 0.0, -1.1], [1.1, -0.9, 0.6], [-0.7, 1.7, 0.2],
+            [0.6, -0.2, -0.4], [-1.6, -0.8, 1.0], [0.3, 1.4, 0.7], [1.8, 0.5, -0.9], [-0.4, -0.1, 0.0]
+        ];
+        let mut kde = GaussianKDE::new(data, None).unwrap();
+        let (_, indices) = kde.sample_batch_indexed(100, &mut rng);
+        assert!(indices.iter().any(|i| *i < 5));
+        let mut weights = array![
+            0.5, 2.0, 1.0, 0.2, 1.5, 0.8, 0.3, 3.0, 1.2, 0.6, 0.9, 1.1, 0.4, 2.2, 0.7
+        ];
+        weights.slice_mut(s![..5]).fill(0.0);
+        kde.set_weights(weights).unwrap();
+        let (_, indices) = kde.sample_batch_indexed(100, &mut rng);
+        assert!(indices.iter().all(|i| *i >= 5));
+    }
+
+    #[test]
+    fn push_shape_test() {
+        let data = array![
+            [-1.2, 0.3, 0.5],
+            [0.4, 1.1, -0.2],
+            [2.0, -0.5, 0.9],
+            [0.9, 0.8, 1.4]
+        ];
+        let mut kde = GaussianKDE::new(data.clone(), None).unwrap();
+        let res = kde.push(array![0.0, 1.0].view(), None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        let res = kde.extend(
+            array![[0.0, 1.0, 2.0]].view(),
+            Some(array![1.0, 2.0].view()),
+        );
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::ShapeError)));
+        assert_eq!(kde.data(), data);
+    }
+
+    #[test]
+    fn push_error_test() {
+        #[rustfmt::skip]
+        let data = array![
+            [-1.2, 0.3, 0.5], [0.4, 1.1, -0.2], [2.0, -0.5, 0.9], [0.9, 0.8, 1.4], [-0.3, -1.4, 0.1],
+            [1.5, 2.2, -0.7], [-2.1, 0.6, 0.3], [0.1, 0.0, -1.1], [1.1, -0.9, 0.6], [-0.7, 1.7, 0.2],
+            [0.6, -0.2, -0.4], [-1.6, -0.8, 1.0], [0.3, 1.4, 0.7], [1.8, 0.5, -0.9], [-0.4, -0.1, 0.0]
+        ];
+        let weights = array![
+            0.5, 2.0, 1.0, 0.2, 1.5, 0.8, 0.3, 3.0, 1.2, 0.6, 0.9, 1.1, 0.4, 2.2, 0.7
+        ];
+        // The variance of the updated dataset overflows, which leaves the KDE unchanged
+        let mut kde = GaussianKDE::new(data.slice(s![..10, ..]).to_owned(), None).unwrap();
+        let res = kde.push(array![1e300, 0.0, 0.0].view(), None);
+        assert!(matches!(res, Err(e) if matches!(e.kind, ErrorKind::SingularityError)));
+        assert_same_kde(
+            &kde,
+            &GaussianKDE::new(data.slice(s![..10, ..]).to_owned(), None).unwrap(),
+        );
+        kde.extend(data.slice(s![10.., ..]), None).unwrap();
+        assert_same_kde(&kde, &GaussianKDE::new(data.clone(), None).unwrap());
+
+        let parent = GaussianKDE::new(data.clone(), Some(weights)).unwrap();
+        let mut marginal = parent.marginalize_to(&[2, 0]).unwrap();
+        let res = marginal.push(array![0.0, 1e300].view(), Some(1.0));
+        assert!(res.is_err());
+        let reference = parent.marginalize_to(&[2, 0]).unwrap();
+        assert_eq!(marginal.data(), reference.data());
+        assert_eq!(marginal.covariance(), reference.covariance());
+        let x = array![[0.0, 0.0], [0.5, -0.3]];
+        assert_eq!(
+            marginal.eval_batch(x.view()),
+            reference.eval_batch(x.view())
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Whitening transform of KDEs, which maps the kernels to standard normal distributions, and distances in the metric
+//! of the kernel covariance.
+
+#[cfg(doc)]
+use crate::GaussianKDE;
+use crate::{Bandwidth, CowGaussianKDE};
+use ndarray::prelude::*;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+impl<F, B> CowGaussianKDE<'_, F, B>
+where
+    B: Bandwidth<F>,
+    F: Float + FloatConst + FromPrimitive + 'static,
+{
+    /// Map the points `x` of shape `(n_points, dim)` to the whitened coordinates $z = L^{-1} x$ in which the kernels
+    /// are standard normal, where $H = LL^\top$ is the Cholesky decomposition of the kernel covariance. For points as
+    /// rows of `x`, this is the product $X L^{-\top}$ with the transposed [`GaussianKDE::inv_cholesky`].
+    ///
+    /// The density estimated by the KDE is $f(x) = \frac{1}{\det L} \, g(L^{-1} x)$ with the KDE $g$ of the whitened
+    /// data points with standard normal kernels, and Euclidean distances between whitened points are the Mahalanobis
+    /// distances of the original points, see [`GaussianKDE::mahalanobis`]. The evaluation additionally subtracts the
+    /// mean of the dataset before whitening for numerical reasons, which does not change the distances.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn whiten(&self, x: ArrayView2<F>) -> Array2<F> {
+        assert_eq!(x.dim().1, self.n_dims());
+        return x.dot(&self.inv_cholesky.t());
+    }
+
+    /// Map the whitened points `z` of shape `(n_points, dim)` back to the original coordinates $x = L z$, i.e. compute
+    /// the product $Z L^\top$ with the transposed [`GaussianKDE::cholesky`]. This is the inverse of
+    /// [`GaussianKDE::whiten`].
+    ///
+    /// *Panics* if the dimension of `z` does not match the dimension of the KDE dataset.
+    pub fn unwhiten(&self, z: ArrayView2<F>) -> Array2<F> {
+        assert_eq!(z.dim().1, self.n_dims());
+        return z.dot(&self.cholesky.t());
+    }
+
+    /// Compute the Mahalanobis distance $\sqrt{(x - y)^\top H^{-1} (x - y)} = \lVert L^{-1}(x - y) \rVert$ between the
+    /// points `x` and `y` in the metric of the kernel covariance $H = LL^\top$, i.e. in units of the kernel width.
+    ///
+    /// *Panics* if the dimension of `x` or `y` does not match the dimension of the KDE dataset.
+    pub fn mahalanobis(&self, x: ArrayView1<F>, y: ArrayView1<F>) -> F {
+        assert_eq!(x.dim(), self.n_dims());
+        assert_eq!(y.dim(), self.n_dims());
+        let z = self.inv_cholesky.dot(&(&x - &y));
+        return z.dot(&z).sqrt();
+    }
+
+    /// Compute the Mahalanobis distances $\lVert L^{-1}(x - x_i) \rVert$ between the point `x` and all data points
+    /// $x_i$ in the metric of the kernel covariance, in the order of the dataset, see [`GaussianKDE::mahalanobis`].
+    /// The distances are computed from the cached whitened dataset if available.
+    ///
+    /// *Panics* if the dimension of `x` does not match the dimension of the KDE dataset.
+    pub fn mahalanobis_to_data(&self, x: ArrayView1<F>) -> Array1<F> {
+        assert_eq!(x.dim(), self.n_dims());
+        return self.sq_distances_to_data(x).mapv(F::sqrt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GaussianKDE;
+    use approx::assert_relative_eq;
+    use ndarray::prelude::*;
+    use rand::prelude::*;
+    use rand_distr::StandardNormal;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn mahalanobis_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(94);
+        let mut data = Array2::<f64>::from_shape_simple_fn((200, 3), || rng.sample(StandardNormal));
+        let x0 = data.column(0).to_owned();
+        data.column_mut(2).scaled_add(0.8, &x0);
+        data.column_mut(1).mapv_inplace(|x| 3.0 * x);
+        let mut kde = GaussianKDE::new(data.clone(), None).unwrap();
+
+        let inv_cov = kde.inv_covariance();
+        let x = array![0.5, -1.0, 2.0];
+        let y = array![-0.3, 0.4, 1.1];
+        let d = &x - &y;
+        let expected = d.dot(&inv_cov.dot(&d)).sqrt();
+        assert_relative_eq!(
+            kde.mahalanobis(x.view(), y.view()),
+            expected,
+            max_relative = 1e-12
+        );
+        assert_eq!(
+            kde.mahalanobis(x.view(), y.view()),
+            kde.mahalanobis(y.view(), x.view())
+        );
+        assert_eq!(kde.mahalanobis(x.view(), x.view()), 0.0);
+
+        // Solving $H v = x - y$ with the covariance from its accessor by Cramer's rule gives the same distance
+        let cov = kde.covariance();
+        let det = |m: &Array2<f64>| {
+            m[[0, 0]] * (m[[1, 1]] * m[[2, 2]] - m[[1, 2]] * m[[2, 1]])
+                - m[[0, 1]] * (m[[1, 0]] * m[[2, 2]] - m[[1, 2]] * m[[2, 0]])
+                + m[[0, 2]] * (m[[1, 0]] * m[[2, 1]] - m[[1, 1]] * m[[2, 0]])
+        };
+        let v = Array1::from_shape_fn(3, |k| {
+            let mut m = cov.clone();
+            m.column_mut(k).assign(&d);
+            det(&m) / det(&cov)
+        });
+        assert_relative_eq!(d.dot(&v).sqrt(), expected, max_relative = 1e-10);
+
+        for cache in [true, false] {
+            kde.set_whitened_data_cache(cache);
+            let res = kde.mahalanobis_to_data(x.view());
+            let expected = data.map_axis(Axis(1), |xi| kde.mahalanobis(x.view(), xi));
+            assert_relative_eq!(res, expected, max_relative = 1e-12);
+            assert_relative_eq!(
+                kde.mahalanobis_to_data(data.row(7))[7],
+                0.0,
+                epsilon = 1e-12
+            );
+        }
+
+        // Marginals measure the distances in their components only
+        let marginal = kde.marginalize(&[1]).unwrap();
+        let res = marginal.mahalanobis_to_data(array![0.5, 2.0].view());
+        let expected = data.select(Axis(1), &[0, 2]).map_axis(Axis(1), |xi| {
+            marginal.mahalanobis(array![0.5, 2.0].view(), xi)
+        });
+        assert_relative_eq!(res, expected, max_relative = 1e-12);
+    }
+
+    #[test]
+    fn whiten_test() {
+        let mut rng = Pcg64Mcg::seed_from_u64(95);
+        let mut data =
+            Array2::<f64>::from_shape_simple_fn((20_000, 3), || rng.sample(StandardNormal));
+        let x0 = data.column(0).to_owned();
+        data.column_mut(2).scaled_add(-0.5, &x0);
+        data.column_mut(1).mapv_inplace(|x| 0.1 * x + 4.0);
+        let kde = GaussianKDE::new(data.clone(), None).unwrap();
+
+        let z = kde.whiten(data.view());
+        assert_relative_eq!(kde.unwhiten(z.view()), data, max_relative = 1e-12);
+        assert_relative_eq!(
+            z.row(3).to_owned() - z.row(8),
+            kde.inv_cholesky().dot(&(&data.row(3) - &data.row(8))),
+            max_relative = 1e-12
+        );
+        let d = &z.row(3) - &z.row(8);
+        assert_relative_eq!(
+            d.dot(&d).sqrt(),
+            kde.mahalanobis(data.row(3), data.row(8)),
+            max_relative = 1e-12
+        );
+
+        // The whitened data points have the covariance $L^{-1} V L^{-\top} = I / h^2$ for $H = h^2 V$
+        let h = kde.bandwidth_factor();
+        let centered = &z - &z.mean_axis(Axis(0)).unwrap();
+        let cov = centered.t().dot(&centered) / 19_999.0;
+        assert_relative_eq!(cov * h * h, Array2::eye(3), epsilon = 1e-10);
+
+        // The density is the one of the whitened KDE with standard normal kernels
+        let whitened = GaussianKDE::with_bandwidth_matrix(z.clone(), None, Array2::eye(3)).unwrap();
+        let x = array![[0.3, 4.1, -0.2], [-1.0, 3.9, 0.7]];
+        let det = kde.cholesky().diag().product();
+        assert_relative_eq!(
+            kde.eval_batch(x.view()),
+            whitened.eval_batch(kde.whiten(x.view()).view()) / det,
+            max_relative = 1e-10
+        );
+    }
+}
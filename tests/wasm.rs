@@ -0,0 +1,57 @@
+//! Evaluation and sampling on `wasm32-unknown-unknown`, where no operating system source of entropy is available
+//! unless it is wired up by the application. The crate compiles for this target with
+//! ```sh
+//! cargo build --target wasm32-unknown-unknown --no-default-features --features sample
+//! ```
+//! and these tests run in Node.js with [`wasm-bindgen-test`](https://docs.rs/wasm-bindgen-test), e.g. with
+//! ```sh
+//! wasm-pack test --node -- --no-default-features --features sample
+//! ```
+#![cfg(target_arch = "wasm32")]
+
+use gaussian_kde::GaussianKDE;
+use ndarray::prelude::*;
+use rand::SeedableRng;
+use rand_pcg::Pcg64Mcg;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn kde() -> GaussianKDE<f64> {
+    let data = array![
+        [0.15, 0.4],
+        [0.2, 0.3],
+        [0.21, 0.29],
+        [0.31, 0.74],
+        [0.72, 0.9],
+        [0.74, 0.84],
+        [0.6, 0.3]
+    ];
+    let weights = array![0.5, 1.0, 0.2, 2.0, 0.7, 0.3, 1.2];
+    return GaussianKDE::new(data, Some(weights)).unwrap();
+}
+
+#[wasm_bindgen_test]
+fn wasm_eval_test() {
+    let kde = kde();
+    let x = array![[0.3, 0.5], [0.7, 0.8], [2.0, -1.0]];
+    let res = kde.eval_batch(x.view());
+    for (xi, r) in x.rows().into_iter().zip(res.iter()) {
+        assert_eq!(kde.eval(xi), *r);
+    }
+    assert!(res[1] > res[2]);
+}
+
+#[cfg(feature = "sample")]
+#[wasm_bindgen_test]
+fn wasm_sample_test() {
+    let kde = kde();
+    let sample = kde.sample_batch_seeded(1000, 107);
+    assert_eq!(sample.dim(), (1000, 2));
+    assert!(sample.iter().all(|x| x.is_finite()));
+    // The seeded variants draw the same points as the RNG based ones, independent of the platform
+    let reference = kde.sample_batch_with_rng(1000, &mut Pcg64Mcg::seed_from_u64(107));
+    assert_eq!(sample, reference);
+    assert_eq!(
+        kde.sample_seeded(3),
+        kde.sample_with_rng(&mut Pcg64Mcg::seed_from_u64(3))
+    );
+}